@@ -0,0 +1,227 @@
+//! Build script: embeds build metadata and the licenses of the compiled-in
+//! dependencies.
+//!
+//! The commit, build date, target triple, and enabled features are passed to
+//! the compiler as `UNVENV_BUILD_*` variables for `unvenv version --json`.
+//! The build date honors `SOURCE_DATE_EPOCH` for reproducible builds.
+//!
+//! Like `cargo about`, this asks `cargo metadata` for the resolved dependency
+//! graph of the target platform, follows the normal (non-dev, non-build)
+//! edges from unvenv, and collects each crate's SPDX expression and license
+//! files. The result is written to `$OUT_DIR/third_party_licenses.json` for
+//! `unvenv license --third-party`. If the metadata cannot be read, an empty
+//! list is embedded and the build warns rather than fails.
+
+use serde_json::{Value, json};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// File name prefixes that hold license texts
+const LICENSE_PREFIXES: [&str; 4] = ["LICENSE", "LICENCE", "COPYING", "NOTICE"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    emit_build_metadata();
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    let dependencies = collect().unwrap_or_else(|error| {
+        println!("cargo:warning=third-party license data unavailable: {error}");
+        Vec::new()
+    });
+    fs::write(
+        out_dir.join("third_party_licenses.json"),
+        Value::Array(dependencies).to_string(),
+    )
+    .expect("OUT_DIR is writable");
+}
+
+/// Pass the commit, date, target, and features of this build to the compiler
+fn emit_build_metadata() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default());
+    // Rebuild when the checked-out commit changes
+    for path in [".git/HEAD", ".git/refs", ".git/packed-refs"] {
+        if manifest_dir.join(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&manifest_dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    let epoch = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        });
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            let feature = key.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=UNVENV_BUILD_COMMIT={commit}");
+    println!("cargo:rustc-env=UNVENV_BUILD_DATE={}", rfc3339(epoch));
+    println!(
+        "cargo:rustc-env=UNVENV_BUILD_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=UNVENV_BUILD_FEATURES={}",
+        features.join(",")
+    );
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 UTC timestamp
+fn rfc3339(secs: u64) -> String {
+    // Days to civil date, after Howard Hinnant's `civil_from_days`
+    let days = secs / 86_400;
+    let secs_of_day = secs % 86_400;
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// The normal dependencies of unvenv, with their licenses, sorted by name
+fn collect() -> Result<Vec<Value>, String> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    let target = env::var("TARGET").map_err(|e| e.to_string())?;
+
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--offline"])
+        .args(["--filter-platform", &target, "--manifest-path"])
+        .arg(Path::new(&manifest_dir).join("Cargo.toml"))
+        .output()
+        .map_err(|e| format!("cannot run cargo metadata: {e}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+
+    let packages: BTreeMap<&str, &Value> = metadata["packages"]
+        .as_array()
+        .ok_or("metadata has no packages")?
+        .iter()
+        .filter_map(|package| Some((package["id"].as_str()?, package)))
+        .collect();
+    let nodes: BTreeMap<&str, &Value> = metadata["resolve"]["nodes"]
+        .as_array()
+        .ok_or("metadata has no resolve graph")?
+        .iter()
+        .filter_map(|node| Some((node["id"].as_str()?, node)))
+        .collect();
+    let root = metadata["resolve"]["root"]
+        .as_str()
+        .ok_or("metadata has no root package")?;
+
+    // Walk the normal dependency edges from the root
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![root];
+    while let Some(id) = pending.pop() {
+        let Some(node) = nodes.get(id) else {
+            continue;
+        };
+        for dep in node["deps"].as_array().into_iter().flatten() {
+            let normal = dep["dep_kinds"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|kind| kind["kind"].is_null());
+            if let Some(dep_id) = dep["pkg"].as_str() {
+                if normal && seen.insert(dep_id) {
+                    pending.push(dep_id);
+                }
+            }
+        }
+    }
+
+    let mut dependencies: Vec<Value> = seen
+        .into_iter()
+        .filter_map(|id| packages.get(id).copied())
+        .map(describe)
+        .collect();
+    dependencies.sort_by(|a, b| {
+        (a["name"].as_str(), a["version"].as_str())
+            .cmp(&(b["name"].as_str(), b["version"].as_str()))
+    });
+    Ok(dependencies)
+}
+
+/// Name, version, license expression, and license texts of one package
+fn describe(package: &Value) -> Value {
+    let dir = package["manifest_path"]
+        .as_str()
+        .and_then(|path| Path::new(path).parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut files: Vec<PathBuf> = package["license_file"]
+        .as_str()
+        .map(|file| dir.join(file))
+        .into_iter()
+        .collect();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path.file_name().is_some_and(|name| {
+                        let name = name.to_string_lossy().to_ascii_uppercase();
+                        LICENSE_PREFIXES
+                            .iter()
+                            .any(|prefix| name.starts_with(prefix))
+                    })
+            })
+            .filter(|path| !files.contains(path))
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+
+    let texts: Vec<Value> = files
+        .iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(path).ok()?;
+            let file = path.file_name()?.to_string_lossy().into_owned();
+            Some(json!({ "file": file, "text": text }))
+        })
+        .collect();
+
+    json!({
+        "name": package["name"],
+        "version": package["version"],
+        "license": package["license"],
+        "texts": texts,
+    })
+}
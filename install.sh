@@ -5,14 +5,45 @@ set -euo pipefail
 # Usage: curl -fsSL https://raw.githubusercontent.com/tftio/unvenv/main/install.sh | sh
 # Or with custom install directory: INSTALL_DIR=/usr/local/bin curl ... | sh
 # Or to force installation over same/newer version: FORCE_INSTALL=1 curl ... | sh
+# Release archives are checked against their minisign signature, with the
+# key pinned below, when the release has one and minisign is installed;
+# otherwise the checksum alone is checked. REQUIRE_SIGNATURE=1 makes a
+# missing signature or minisign an error
+# Or to avoid GitHub API rate limits (e.g. on shared CI runners):
+#   GITHUB_TOKEN=... curl ... | sh    (GH_TOKEN and --token <token> also work)
+# Behind a proxy, HTTP_PROXY/HTTPS_PROXY/NO_PROXY are honored, or pass --proxy <url>
+# To install from a mirror of the GitHub releases (e.g. Artifactory):
+#   UNVENV_API_URL=https://mirror/api GITHUB_DOWNLOAD_URL=https://mirror curl ... | sh
+#   (the token is only ever sent to https://api.github.com, never to a mirror)
+#   or RELEASES_URL=https://mirror/unvenv/releases when the layout differs;
+#   assets are fetched from $RELEASES_URL/<version>/<asset>
+# To install the newest prerelease instead of the latest stable release:
+#   CHANNEL=beta curl ... | sh    (or --channel beta)
+# To install a specific release: curl ... | sh -s -- --version v1.2.3
 
 TOOL_NAME="unvenv"
 REPO_OWNER="${REPO_OWNER:-tftio}"
 REPO_NAME="${REPO_NAME:-$TOOL_NAME}"
 INSTALL_DIR="${INSTALL_DIR:-$HOME/.local/bin}"
 FORCE_INSTALL="${FORCE_INSTALL:-0}"
-GITHUB_API_URL="https://api.github.com"
-GITHUB_DOWNLOAD_URL="https://github.com"
+REQUIRE_SIGNATURE="${REQUIRE_SIGNATURE:-0}"
+# Release signing key, also in minisign.pub; see "Verifying Releases" in
+# the README for where it comes from
+MINISIGN_PUBLIC_KEY="RWTdcuMEsMqFe8lOoRbndChonGOpbpnsTkoF+PWXgXeCwT4SGvVu0Hiu"
+GITHUB_TOKEN="${GITHUB_TOKEN:-${GH_TOKEN:-}}"
+PROXY="${PROXY:-}"
+DOWNLOAD_ATTEMPTS="${DOWNLOAD_ATTEMPTS:-5}"
+CHANNEL="${CHANNEL:-stable}"
+# Release tag to install; empty for the latest on the channel
+REQUESTED_VERSION=""
+# Not GITHUB_API_URL: GitHub Enterprise runners set that to their own server
+UNVENV_API_URL="${UNVENV_API_URL:-https://api.github.com}"
+UNVENV_API_URL="${UNVENV_API_URL%/}"
+GITHUB_DOWNLOAD_URL="${GITHUB_DOWNLOAD_URL:-https://github.com}"
+GITHUB_DOWNLOAD_URL="${GITHUB_DOWNLOAD_URL%/}"
+RELEASES_URL="${RELEASES_URL:-$GITHUB_DOWNLOAD_URL/$REPO_OWNER/$REPO_NAME/releases/download}"
+RELEASES_URL="${RELEASES_URL%/}"
+RAW_CONTENT_URL="${RAW_CONTENT_URL:-https://raw.githubusercontent.com}"
 
 # Colors for output
 RED='\033[0;31m'
@@ -37,6 +68,21 @@ log_error() {
     echo -e "${RED}[ERROR]${NC} $1" >&2
 }
 
+# Whether we are running under a Windows shell (Git Bash, MSYS2, Cygwin)
+is_windows() {
+    case "$(uname -s)" in
+    MINGW* | MSYS* | CYGWIN*) return 0 ;;
+    *) return 1 ;;
+    esac
+}
+
+# Whether someone can answer a prompt
+# Under `curl ... | sh` stdin is the script itself, so ask the terminal
+# directly; opening /dev/tty fails when there is no controlling terminal
+has_tty() {
+    (: </dev/tty) 2>/dev/null
+}
+
 # Detect OS and architecture
 detect_platform() {
     local os arch target
@@ -172,21 +218,126 @@ get_installed_version() {
 }
 
 # Get latest release version from GitHub API
+# The stable channel follows the latest release; the beta channel follows
+# the newest release of any kind, prereleases included
 get_latest_version() {
-    local api_url="$GITHUB_API_URL/repos/$REPO_OWNER/$REPO_NAME/releases/latest"
+    local api_url="$UNVENV_API_URL/repos/$REPO_OWNER/$REPO_NAME/releases/latest"
+    if [ "$CHANNEL" = "beta" ]; then
+        # Releases are listed newest first
+        api_url="$UNVENV_API_URL/repos/$REPO_OWNER/$REPO_NAME/releases?per_page=10"
+    fi
 
-    log_info "Fetching latest release information..."
+    log_info "Fetching latest $CHANNEL release information..."
 
+    fetch_api "$api_url" | grep '"tag_name":' | sed -E 's/.*"tag_name": *"([^"]+)".*/\1/' | awk 'NR == 1'
+}
+
+# Print a GitHub API response to stdout
+# Authenticated requests get a far higher rate limit than anonymous ones,
+# which are shared by everything behind the same egress IP. The token is
+# only sent to api.github.com itself, so a mirror never sees it
+fetch_api() {
+    local url="$1"
+    local token=""
+    case "$url" in
+    https://api.github.com/*)
+        token="$GITHUB_TOKEN"
+        ;;
+    esac
+
+    # The header is passed as configuration rather than on the command line,
+    # where any local user could read the token in the process list
     if command -v curl >/dev/null 2>&1; then
-        curl -fsSL "$api_url" | grep '"tag_name":' | sed -E 's/.*"tag_name": *"([^"]+)".*/\1/'
+        if [ -n "$token" ]; then
+            printf 'header = "Authorization: Bearer %s"\n' "$token" | curl -fsSL --config - "$url"
+        else
+            curl -fsSL "$url"
+        fi
     elif command -v wget >/dev/null 2>&1; then
-        wget -qO- "$api_url" | grep '"tag_name":' | sed -E 's/.*"tag_name": *"([^"]+)".*/\1/'
+        if [ -n "$token" ]; then
+            # mktemp creates the file readable by its owner only
+            local config status=0
+            config=$(mktemp)
+            printf 'header = Authorization: Bearer %s\n' "$token" >"$config"
+            wget -qO- --config="$config" "$url" || status=$?
+            rm -f "$config"
+            return "$status"
+        else
+            wget -qO- "$url"
+        fi
     else
         log_error "Neither curl nor wget is available. Please install one of them."
         exit 1
     fi
 }
 
+# Download a file to disk, resuming partial downloads. Failures that may
+# pass (network errors, timeouts, HTTP 5xx and 429) are retried with
+# exponential backoff, up to the optional third argument or
+# DOWNLOAD_ATTEMPTS times; any other HTTP error fails at once
+download_file() {
+    local url="$1"
+    local dest="$2"
+    local attempts="${3:-$DOWNLOAD_ATTEMPTS}"
+    local attempt=1
+    local delay=2
+    local resume=1
+    local status code transient
+
+    while true; do
+        status=0
+        code=""
+        transient=0
+        if command -v curl >/dev/null 2>&1; then
+            if [ "$resume" = "1" ]; then
+                code=$(curl -fsSL -w '%{http_code}' -C - "$url" -o "$dest") || status=$?
+            else
+                code=$(curl -fsSL -w '%{http_code}' "$url" -o "$dest") || status=$?
+            fi
+            # 33: the server does not support ranges, and curl refuses to
+            # resume; download whole files from here on, starting right away
+            if [ "$status" -eq 33 ]; then
+                log_warn "The server cannot resume downloads of $(basename "$dest"), downloading it whole..."
+                rm -f "$dest"
+                resume=0
+                continue
+            fi
+            # Resolving, connecting, timeouts, TLS handshakes, and dropped
+            # connections; 22 is an HTTP error, whose code says which
+            case "$status" in
+            6 | 7 | 28 | 35 | 52 | 56) transient=1 ;;
+            22)
+                case "$code" in
+                5?? | 429) transient=1 ;;
+                esac
+                ;;
+            esac
+        elif command -v wget >/dev/null 2>&1; then
+            wget -q -c "$url" -O "$dest" || status=$?
+            # wget reports every HTTP error alike (8), so only its network
+            # failures are retried
+            if [ "$status" -eq 4 ]; then
+                transient=1
+            fi
+        else
+            log_error "Neither curl nor wget is available."
+            exit 1
+        fi
+
+        if [ "$status" -eq 0 ]; then
+            return 0
+        fi
+        if [ "$transient" = "0" ] || [ "$attempt" -ge "$attempts" ]; then
+            return "$status"
+        fi
+
+        log_warn "Download of $(basename "$dest") failed (attempt $attempt/$attempts), retrying in ${delay}s..."
+        sleep "$delay"
+        attempt=$((attempt + 1))
+        delay=$((delay * 2))
+    done
+}
+
 # Download and verify checksum (mandatory)
 download_and_verify() {
     local download_url="$1"
@@ -196,12 +347,8 @@ download_and_verify() {
 
     log_info "Downloading $filename..."
 
-    if command -v curl >/dev/null 2>&1; then
-        curl -fsSL "$download_url" -o "$temp_dir/$filename"
-    elif command -v wget >/dev/null 2>&1; then
-        wget -q "$download_url" -O "$temp_dir/$filename"
-    else
-        log_error "Neither curl nor wget is available."
+    if ! download_file "$download_url" "$temp_dir/$filename"; then
+        log_error "Failed to download $download_url"
         exit 1
     fi
 
@@ -209,25 +356,35 @@ download_and_verify() {
     # Checksum file is named without the archive extension (e.g., $TOOL_NAME-aarch64-apple-darwin.sha256)
     local base_filename="${filename%.tar.gz}"
     base_filename="${base_filename%.zip}"
-    local checksum_url="${GITHUB_DOWNLOAD_URL}/${REPO_OWNER}/${REPO_NAME}/releases/download/${version}/${base_filename}.sha256"
+    local checksum_url="${RELEASES_URL}/${version}/${base_filename}.sha256"
     local checksum_file="$temp_dir/${base_filename}.sha256"
 
+    local expected_hash
     log_info "Downloading checksum file..."
-    if command -v curl >/dev/null 2>&1; then
-        if ! curl -fsSL "$checksum_url" -o "$checksum_file" 2>/dev/null; then
-            log_error "Checksum file not available at: $checksum_url"
+    # Only a probe: mirrors without per-asset checksums fall back to the
+    # manifest below, so a failure is not worth retrying
+    if download_file "$checksum_url" "$checksum_file" 1 2>/dev/null; then
+        expected_hash=$(cut -d' ' -f1 "$checksum_file")
+    else
+        # Some mirrors publish a single SHA256SUMS manifest instead, with
+        # lines of the form "<hash>  <filename>" (or "<hash> *<filename>")
+        local manifest_url="${RELEASES_URL}/${version}/SHA256SUMS"
+        local manifest_file="$temp_dir/SHA256SUMS"
+        log_info "No per-asset checksum, trying SHA256SUMS manifest..."
+        if ! download_file "$manifest_url" "$manifest_file" 2>/dev/null; then
+            log_error "Checksum file not available at: $checksum_url or $manifest_url"
+            log_error "Checksum verification is mandatory for security."
+            exit 1
+        fi
+        expected_hash=$(awk -v name="$filename" '$2 == name || $2 == "*" name { print $1; exit }' "$manifest_file")
+        if [ -z "$expected_hash" ]; then
+            log_error "SHA256SUMS has no entry for $filename"
             log_error "Checksum verification is mandatory for security."
             exit 1
         fi
-    else
-        log_error "curl is required for checksum download."
-        exit 1
     fi
 
     log_info "Verifying checksum..."
-    # Extract expected hash and verify directly
-    local expected_hash
-    expected_hash=$(cut -d' ' -f1 "$checksum_file")
     local actual_hash
 
     if command -v sha256sum >/dev/null 2>&1; then
@@ -250,6 +407,49 @@ download_and_verify() {
     fi
 }
 
+# Report a release whose signature cannot be checked: an error with
+# REQUIRE_SIGNATURE=1, otherwise a warning
+missing_signature() {
+    if [ "$REQUIRE_SIGNATURE" = "1" ]; then
+        log_error "$1"
+        log_error "Signature verification is required (REQUIRE_SIGNATURE=1)."
+        exit 1
+    fi
+    log_warn "$1; relying on the checksum alone"
+}
+
+# Verify the minisign signature of a downloaded archive
+# The checksum only proves the download matches what the release serves;
+# the signature proves the release was built by the pipeline holding the
+# key. Releases from before signing started have no signature
+verify_signature() {
+    local download_url="$1"
+    local filename="$2"
+    local temp_dir="$3"
+    local signature_url="${download_url}.minisig"
+    local signature_file="$temp_dir/${filename}.minisig"
+
+    if ! command -v minisign >/dev/null 2>&1; then
+        missing_signature "minisign is not installed (https://jedisct1.github.io/minisign/)"
+        return 0
+    fi
+
+    log_info "Downloading signature..."
+    if ! download_file "$signature_url" "$signature_file" 2>/dev/null; then
+        missing_signature "No signature at $signature_url"
+        return 0
+    fi
+
+    log_info "Verifying signature..."
+    if minisign -Vq -P "$MINISIGN_PUBLIC_KEY" -m "$temp_dir/$filename" -x "$signature_file"; then
+        log_success "Signature verification passed"
+    else
+        log_error "Signature verification failed!"
+        log_error "$filename was not signed with the unvenv release key."
+        exit 1
+    fi
+}
+
 # Extract archive based on file extension
 extract_archive() {
     local archive_file="$1"
@@ -276,14 +476,44 @@ extract_archive() {
     esac
 }
 
+# Print the CHANGELOG.md section for a release, if there is one
+show_release_notes() {
+    local version="$1"
+    local temp_dir="$2"
+    local changelog="$temp_dir/CHANGELOG.md"
+    local url="$RAW_CONTENT_URL/$REPO_OWNER/$REPO_NAME/$version/CHANGELOG.md"
+
+    if ! download_file "$url" "$changelog" 2>/dev/null; then
+        log_warn "Release notes for $version are not available"
+        return 0
+    fi
+
+    # Sections start with "## [1.2.3]"; print up to the next section
+    local notes
+    notes=$(awk -v version="${version#v}" '
+        /^## \[/ { in_section = index($0, "## [" version "]") == 1; next }
+        in_section { print }
+    ' "$changelog")
+
+    if [ -z "$(echo "$notes" | tr -d '[:space:]')" ]; then
+        log_warn "No release notes found for $version"
+        return 0
+    fi
+
+    echo "" >&2
+    echo "Release notes for $version:" >&2
+    echo "$notes" >&2
+    echo "" >&2
+}
+
 # Check if binary needs to be replaced
 check_existing_installation() {
     local install_path="$1"
 
     if [ -f "$install_path" ]; then
-        if [ -t 0 ]; then # Check if we have a TTY (interactive)
-            echo -n "$(basename "$install_path") is already installed at $install_path. Replace it? [y/N]: "
-            read -r response
+        if has_tty; then
+            printf "%s is already installed at %s. Replace it? [y/N]: " "$(basename "$install_path")" "$install_path" >/dev/tty
+            read -r response </dev/tty
             case "$response" in
             [yY] | [yY][eE][sS])
                 return 0
@@ -300,7 +530,96 @@ check_existing_installation() {
     fi
 }
 
+# Parse command-line options
+parse_args() {
+    while [ $# -gt 0 ]; do
+        case "$1" in
+        --token)
+            if [ $# -lt 2 ]; then
+                log_error "--token requires a value"
+                exit 1
+            fi
+            GITHUB_TOKEN="$2"
+            shift 2
+            ;;
+        --token=*)
+            GITHUB_TOKEN="${1#--token=}"
+            shift
+            ;;
+        --proxy)
+            if [ $# -lt 2 ]; then
+                log_error "--proxy requires a value"
+                exit 1
+            fi
+            PROXY="$2"
+            shift 2
+            ;;
+        --proxy=*)
+            PROXY="${1#--proxy=}"
+            shift
+            ;;
+        --channel)
+            if [ $# -lt 2 ]; then
+                log_error "--channel requires a value"
+                exit 1
+            fi
+            CHANNEL="$2"
+            shift 2
+            ;;
+        --channel=*)
+            CHANNEL="${1#--channel=}"
+            shift
+            ;;
+        --version)
+            if [ $# -lt 2 ]; then
+                log_error "--version requires a value"
+                exit 1
+            fi
+            REQUESTED_VERSION="$2"
+            shift 2
+            ;;
+        --version=*)
+            REQUESTED_VERSION="${1#--version=}"
+            shift
+            ;;
+        *)
+            log_error "Unknown option: $1"
+            exit 1
+            ;;
+        esac
+    done
+}
+
+# Point curl and wget at the proxy
+configure_proxy() {
+    if [ -n "$PROXY" ]; then
+        export http_proxy="$PROXY" https_proxy="$PROXY"
+    fi
+
+    # curl ignores the uppercase HTTP_PROXY and wget ignores the uppercase
+    # spellings altogether, so mirror them into the lowercase variables
+    if [ -z "${http_proxy:-}" ] && [ -n "${HTTP_PROXY:-}" ]; then
+        export http_proxy="$HTTP_PROXY"
+    fi
+    if [ -z "${https_proxy:-}" ] && [ -n "${HTTPS_PROXY:-}" ]; then
+        export https_proxy="$HTTPS_PROXY"
+    fi
+    if [ -z "${no_proxy:-}" ] && [ -n "${NO_PROXY:-}" ]; then
+        export no_proxy="$NO_PROXY"
+    fi
+}
+
 main() {
+    parse_args "$@"
+    case "$CHANNEL" in
+    stable | beta) ;;
+    *)
+        log_error "Unknown channel: $CHANNEL (expected stable or beta)"
+        exit 1
+        ;;
+    esac
+    configure_proxy
+
     log_info "Installing $TOOL_NAME..."
 
     # Detect platform
@@ -308,21 +627,27 @@ main() {
     target=$(detect_platform)
     log_info "Detected platform: $target"
 
-    # Get latest version
+    # Get latest version, unless a specific one was asked for
     local version
-    version=$(get_latest_version)
-    if [ -z "$version" ]; then
-        log_error "Failed to get latest version"
-        exit 1
+    if [ -n "$REQUESTED_VERSION" ]; then
+        # Release tags carry a leading v
+        version="v${REQUESTED_VERSION#v}"
+        log_info "Requested version: $version"
+    else
+        version=$(get_latest_version)
+        if [ -z "$version" ]; then
+            log_error "Failed to get latest version"
+            exit 1
+        fi
+        log_info "Latest version: $version"
     fi
-    log_info "Latest version: $version"
 
     # Create install directory if it doesn't exist
     mkdir -p "$INSTALL_DIR"
 
     # Determine install path
     local install_path="$INSTALL_DIR/$TOOL_NAME"
-    if [ "$(uname -s)" = "MINGW*" ] || [ "$(uname -s)" = "MSYS*" ] || [ "$(uname -s)" = "CYGWIN*" ]; then
+    if is_windows; then
         install_path="${install_path}.exe"
     fi
 
@@ -374,9 +699,12 @@ main() {
         esac
     fi
 
-    # Construct download URL
+    # Construct download URL (Windows releases are published as zip archives)
     local filename="${TOOL_NAME}-${target}.tar.gz"
-    local download_url="$GITHUB_DOWNLOAD_URL/$REPO_OWNER/$REPO_NAME/releases/download/$version/$filename"
+    if is_windows; then
+        filename="${TOOL_NAME}-${target}.zip"
+    fi
+    local download_url="$RELEASES_URL/$version/$filename"
 
     # Create temporary directory
     local temp_dir
@@ -386,13 +714,14 @@ main() {
 
     # Download and verify
     download_and_verify "$download_url" "$filename" "$temp_dir" "$version"
+    verify_signature "$download_url" "$filename" "$temp_dir"
 
     # Extract archive
     extract_archive "$filename" "$temp_dir"
 
     # Find the binary (handle potential directory structure)
     local binary_name="$TOOL_NAME"
-    if [ "$(uname -s)" = "MINGW*" ] || [ "$(uname -s)" = "MSYS*" ] || [ "$(uname -s)" = "CYGWIN*" ]; then
+    if is_windows; then
         binary_name="${TOOL_NAME}.exe"
     fi
 
@@ -408,11 +737,22 @@ main() {
         fi
     fi
 
+    # Show what is about to be installed before asking for confirmation
+    if [ -f "$install_path" ] && has_tty; then
+        show_release_notes "$version" "$temp_dir"
+    fi
+
     # Check for existing installation and prompt if needed
     check_existing_installation "$install_path"
 
     # Install binary
     log_info "Installing to $install_path..."
+    if is_windows && [ -f "$install_path" ]; then
+        # Windows refuses to overwrite a running executable but allows
+        # renaming it, so move the old binary aside before copying
+        rm -f "${install_path}.old" 2>/dev/null || true
+        mv "$install_path" "${install_path}.old"
+    fi
     cp "$binary_path" "$install_path"
     chmod +x "$install_path"
 
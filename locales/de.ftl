@@ -0,0 +1,11 @@
+# Deutsche Meldungen von `unvenv hook run`
+
+hook-blocked = Commit abgelehnt: { $count } virtuelle Python-Umgebung(en) werden von Git nicht ignoriert
+hook-more = ...und { $count } weitere
+hook-fix = Behebung: ignorieren und aus dem Index entfernen, dann erneut committen:
+hook-details = Details mit `unvenv scan`; einmalig umgehen mit `git commit --no-verify`.
+hook-partial = unvenv hat nach { $millis } ms abgebrochen; den Rest mit `unvenv scan` prüfen
+hook-partial-blocked = Commit abgelehnt: unvenv hat nach { $millis } ms abgebrochen, bevor alle Dateien im Index geprüft waren; `unvenv scan` ausführen oder unter [hook] in .unvenv.toml `time-budget` (ms) erhöhen bzw. `fail-open = true` setzen, um solche Commits durchzulassen
+tracking-untracked = nicht versioniert
+tracking-staged = vorgemerkt
+tracking-committed = committet
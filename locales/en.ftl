@@ -0,0 +1,13 @@
+# Messages of `unvenv hook run`, in Fluent syntax (simple messages and
+# { $variable } placeholders only). Every other locale falls back to these
+# for missing messages.
+
+hook-blocked = Commit blocked: { $count } Python virtual environment(s) are not ignored by Git
+hook-more = ...and { $count } more
+hook-fix = Fix: ignore and unstage them, then commit again:
+hook-details = Run `unvenv scan` for details, or `git commit --no-verify` to bypass once.
+hook-partial = unvenv stopped after { $millis } ms; run `unvenv scan` to check the rest
+hook-partial-blocked = Commit blocked: unvenv stopped after { $millis } ms, before checking every staged file; run `unvenv scan`, or under [hook] in .unvenv.toml raise `time-budget` (ms) or set `fail-open = true` to let such commits through
+tracking-untracked = untracked
+tracking-staged = staged
+tracking-committed = committed
@@ -0,0 +1,11 @@
+# `unvenv hook run` の日本語メッセージ
+
+hook-blocked = コミットを中止しました: Git で無視されていない Python 仮想環境が { $count } 個あります
+hook-more = ...ほか { $count } 個
+hook-fix = 対処: 無視設定に追加してインデックスから外し、もう一度コミットしてください:
+hook-details = 詳細は `unvenv scan` で確認できます。一度だけ回避するには `git commit --no-verify` を使ってください。
+hook-partial = unvenv は { $millis } ms で停止しました。残りは `unvenv scan` で確認してください
+hook-partial-blocked = コミットを中止しました: unvenv はステージされたファイルをすべて確認する前に { $millis } ms で停止しました。`unvenv scan` を実行するか、.unvenv.toml の [hook] で `time-budget` (ms) を増やすか、このようなコミットを通すには `fail-open = true` を設定してください
+tracking-untracked = 未追跡
+tracking-staged = ステージ済み
+tracking-committed = コミット済み
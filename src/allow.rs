@@ -0,0 +1,266 @@
+//! `.unvenv-allow` markers: documented exceptions for a subtree.
+//!
+//! Some directories legitimately contain venvs Git must see, such as
+//! fixtures for tools that inspect virtual environments. A `.unvenv-allow`
+//! file in such a directory suppresses the findings in it and everything
+//! below it:
+//!
+//! ```text
+//! # tests/fixtures/.unvenv-allow
+//! Broken venvs the doctor tests load; see tests/doctor.rs.
+//! ```
+//!
+//! The file has to say why. A marker without a justification (only blank
+//! lines and `#` comments) is ignored with a warning. Suppressed findings do
+//! not fail the scan, but the report still lists them with the
+//! justification, so an audit sees every exception.
+
+use crate::VenvInfo;
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// File name of the marker
+pub const MARKER: &str = ".unvenv-allow";
+
+/// A marker with its justification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allowance {
+    /// Path of the marker file, relative to the scan root
+    pub marker: PathBuf,
+    /// Why the subtree may contain venvs
+    pub justification: String,
+}
+
+/// A finding an allowance suppressed
+#[derive(Debug)]
+pub struct Suppressed {
+    /// The venv
+    pub venv: VenvInfo,
+    /// The marker covering it
+    pub allowance: Allowance,
+}
+
+/// The justification in the contents of a marker, if it has one
+fn justification(content: &str) -> Option<String> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Read the marker in `dir`, relative to `workdir`, if there is a valid one
+fn read_allowance(workdir: &Path, dir: &Path) -> Option<Allowance> {
+    let marker = dir.join(MARKER);
+    let content = match fs::read_to_string(workdir.join(&marker)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            eprintln!(
+                "{} Could not read {}: {e}",
+                "Warning:".yellow().bold(),
+                marker.display()
+            );
+            return None;
+        }
+    };
+    let Some(justification) = justification(&content) else {
+        eprintln!(
+            "{} Ignoring {}: it must say why venvs are allowed there",
+            "Warning:".yellow().bold(),
+            marker.display()
+        );
+        return None;
+    };
+    Some(Allowance {
+        marker,
+        justification,
+    })
+}
+
+/// Finds the marker covering each venv, reading each directory once
+#[derive(Debug)]
+pub struct Allowances<'a> {
+    workdir: &'a Path,
+    by_dir: HashMap<PathBuf, Option<Allowance>>,
+}
+
+impl<'a> Allowances<'a> {
+    /// Marker lookup for venvs found under `workdir`
+    pub fn new(workdir: &'a Path) -> Self {
+        Self {
+            workdir,
+            by_dir: HashMap::new(),
+        }
+    }
+
+    /// The nearest marker in the venv directory or above it, for the venv
+    /// whose `pyvenv.cfg` is at `cfg_path`, relative to the scan root
+    pub fn allowance_of(&mut self, cfg_path: &Path) -> Option<&Allowance> {
+        let venv_dir = cfg_path.parent()?;
+        let workdir = self.workdir;
+        let mut found = None;
+        for rel_dir in venv_dir.ancestors() {
+            let allowance = self
+                .by_dir
+                .entry(rel_dir.to_path_buf())
+                .or_insert_with(|| read_allowance(workdir, rel_dir));
+            if allowance.is_some() {
+                found = Some(rel_dir.to_path_buf());
+                break;
+            }
+        }
+        self.by_dir.get(&found?)?.as_ref()
+    }
+
+    /// Split `venvs` into the ones still reported and the ones a marker
+    /// suppresses
+    pub fn apply(&mut self, venvs: Vec<VenvInfo>) -> (Vec<VenvInfo>, Vec<Suppressed>) {
+        let mut reported = Vec::new();
+        let mut suppressed = Vec::new();
+        for venv in venvs {
+            match self.allowance_of(&venv.path).cloned() {
+                Some(allowance) => suppressed.push(Suppressed { venv, allowance }),
+                None => reported.push(venv),
+            }
+        }
+        (reported, suppressed)
+    }
+}
+
+/// Print the suppressed findings with their justification
+pub fn print_suppressed(suppressed: &[Suppressed], is_tty: bool) {
+    let headline = format!(
+        "{} virtual environment(s) suppressed by {MARKER} markers:",
+        suppressed.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "Note:".yellow().bold());
+    } else {
+        println!("Note: {headline}");
+    }
+    for finding in suppressed {
+        let path = finding.venv.path.to_string_lossy().replace('\\', "/");
+        let marker = finding
+            .allowance
+            .marker
+            .to_string_lossy()
+            .replace('\\', "/");
+        if is_tty {
+            println!("     📁 {} (suppressed)", path.cyan());
+            println!("        {marker}: {}", finding.allowance.justification);
+        } else {
+            println!("    {path} (suppressed)");
+            println!("      {marker}: {}", finding.allowance.justification);
+        }
+    }
+    println!();
+}
+
+/// Markdown section listing the suppressed findings, empty when there are none
+pub fn markdown_section(suppressed: &[Suppressed]) -> String {
+    let mut markdown = String::new();
+    if suppressed.is_empty() {
+        return markdown;
+    }
+    let _ = writeln!(
+        markdown,
+        "\n### Suppressed\n\n{} Python virtual environment(s) are allowed by `{MARKER}` markers.\n",
+        suppressed.len()
+    );
+    markdown.push_str("| Path | Marker | Justification |\n");
+    markdown.push_str("| --- | --- | --- |\n");
+    for finding in suppressed {
+        let _ = writeln!(
+            markdown,
+            "| `{}` | `{}` | {} |",
+            finding.venv.path.to_string_lossy().replace('\\', "/"),
+            finding
+                .allowance
+                .marker
+                .to_string_lossy()
+                .replace('\\', "/"),
+            finding.allowance.justification.replace('|', "\\|")
+        );
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn venv(path: &str) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking: None,
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    #[test]
+    fn test_justification() {
+        assert_eq!(
+            justification("# why\n\n  Fixtures for the doctor tests.\n  Keep them.\n"),
+            Some("Fixtures for the doctor tests. Keep them.".to_string())
+        );
+        assert_eq!(justification("# TODO\n\n"), None);
+        assert_eq!(justification(""), None);
+    }
+
+    #[test]
+    fn test_apply_allowances() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("tests/fixtures/broken"))?;
+        fs::write(
+            root.join("tests/fixtures/.unvenv-allow"),
+            "Venvs the doctor tests load\n",
+        )?;
+        fs::create_dir_all(root.join("tools/venv"))?;
+        fs::write(root.join("tools/.unvenv-allow"), "# no reason given\n")?;
+
+        let mut allowances = Allowances::new(root);
+        let (reported, suppressed) = allowances.apply(vec![
+            venv("tests/fixtures/broken/pyvenv.cfg"),
+            venv("tools/venv/pyvenv.cfg"),
+            venv("venv/pyvenv.cfg"),
+        ]);
+        let reported: Vec<_> = reported.iter().map(|venv| venv.path.clone()).collect();
+        assert_eq!(
+            reported,
+            [
+                PathBuf::from("tools/venv/pyvenv.cfg"),
+                PathBuf::from("venv/pyvenv.cfg")
+            ]
+        );
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(
+            suppressed[0].allowance.marker,
+            Path::new("tests/fixtures/.unvenv-allow")
+        );
+        assert_eq!(
+            suppressed[0].allowance.justification,
+            "Venvs the doctor tests load"
+        );
+
+        assert!(markdown_section(&suppressed).contains(
+            "| `tests/fixtures/broken/pyvenv.cfg` | `tests/fixtures/.unvenv-allow` | Venvs the doctor tests load |"
+        ));
+        assert_eq!(markdown_section(&[]), "");
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! Python tooling directories that carry the same problems as venvs.
+//!
+//! A venv is not the only directory full of machine-specific paths and large
+//! binaries that tools drop into a checkout. The scan also reports these
+//! when Git does not ignore them:
+//!
+//! - `.direnv/` (UV009 `unignored-direnv`): direnv's per-directory state,
+//!   with layouts, caches, and profiles. A `.direnv/` holding a reported
+//!   venv is left to the venv's finding, which already suggests ignoring it.
+//! - `.pyenv/` with a `versions/` directory (UV010 `pyenv-install`): a pyenv
+//!   root inside the repository, with whole Python builds below it. Its
+//!   contents are not walked.
+//! - pip's build metadata and download caches (UV011 `pip-cache`):
+//!   `pip-wheel-metadata/`, `[.]pip[-download]-cache/`, and `.cache/pip/`,
+//!   left behind by tooling that points `PIP_CACHE_DIR` or
+//!   `--download-cache` at the working directory. Not walked either.
+
+use crate::{
+    direnv,
+    rules::{PIP_CACHE, PYENV_INSTALL, Rule, UNIGNORED_DIRENV},
+};
+use colored::Colorize;
+use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// Directory pyenv keeps its root in when `PYENV_ROOT` points into a checkout
+const PYENV_DIR: &str = ".pyenv";
+
+/// Names of pip's metadata and cache directories
+const PIP_DIRS: [&str; 5] = [
+    "pip-wheel-metadata",
+    ".pip-cache",
+    "pip-cache",
+    "pip-download-cache",
+    ".pip-download-cache",
+];
+
+/// Which kind of tooling directory was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// direnv state
+    Direnv,
+    /// A pyenv root with installed versions
+    Pyenv,
+    /// pip build metadata or a pip download cache
+    PipCache,
+}
+
+impl ArtifactKind {
+    /// What the directory at `dir` is, judging by its name and contents
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let name = dir.file_name()?;
+        if name == direnv::LAYOUT_DIR {
+            Some(Self::Direnv)
+        } else if name == PYENV_DIR && dir.join("versions").is_dir() {
+            Some(Self::Pyenv)
+        } else if PIP_DIRS.iter().any(|pip_dir| name == *pip_dir)
+            || (name == "pip"
+                && dir
+                    .parent()
+                    .and_then(Path::file_name)
+                    .is_some_and(|parent| parent == ".cache"))
+        {
+            Some(Self::PipCache)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the walk skips the directory's contents
+    ///
+    /// `.direnv/` is walked so the venvs of `layout python` are still found.
+    pub const fn prunes(self) -> bool {
+        match self {
+            Self::Direnv => false,
+            Self::Pyenv | Self::PipCache => true,
+        }
+    }
+
+    /// The rule the directory violates
+    pub const fn rule(self) -> Rule {
+        match self {
+            Self::Direnv => UNIGNORED_DIRENV,
+            Self::Pyenv => PYENV_INSTALL,
+            Self::PipCache => PIP_CACHE,
+        }
+    }
+
+    /// Human description for the report
+    const fn describe(self) -> &'static str {
+        match self {
+            Self::Direnv => "direnv state",
+            Self::Pyenv => "pyenv Python installs",
+            Self::PipCache => "pip build metadata or downloads",
+        }
+    }
+}
+
+/// A tooling directory Git does not ignore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    /// The directory, relative to the scanned directory
+    pub path: PathBuf,
+    /// What it is
+    pub kind: ArtifactKind,
+}
+
+impl Artifact {
+    /// This directory in the public report model
+    pub fn to_report(&self) -> unvenv::Artifact {
+        let rule = self.kind.rule();
+        let mut artifact = unvenv::Artifact::new(self.path.clone(), rule.id.to_string());
+        artifact.help_uri = Some(rule.doc_url());
+        artifact.actions.push(unvenv::Action::AddGitignoreEntry {
+            file: PathBuf::from(".gitignore"),
+            entry: ignore_entry(self),
+        });
+        artifact
+    }
+}
+
+/// `.gitignore` entry that covers `artifact`
+fn ignore_entry(artifact: &Artifact) -> String {
+    match artifact.kind {
+        // direnv creates `.direnv/` next to every `.envrc`
+        ArtifactKind::Direnv => format!("{}/", direnv::LAYOUT_DIR),
+        ArtifactKind::Pyenv | ArtifactKind::PipCache => {
+            format!("/{}/", artifact.path.to_string_lossy().replace('\\', "/"))
+        }
+    }
+}
+
+/// Print the artifacts after the venv report
+pub fn print_artifacts(artifacts: &[Artifact], is_tty: bool) {
+    let headline = "Found Python tooling directories that are not ignored by Git:";
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+    println!();
+    for artifact in artifacts {
+        let path = format!("{}/", artifact.path.to_string_lossy().replace('\\', "/"));
+        let rule = artifact.kind.rule();
+        if is_tty {
+            println!("  📁 {}", path.cyan());
+            println!("     Contains: {}", artifact.kind.describe());
+            println!("     Rule: {}", rule.describe());
+            println!("     Docs: {}", rule.doc_url());
+        } else {
+            println!("  {path}");
+            println!("    Contains: {}", artifact.kind.describe());
+            println!("    Rule: {}", rule.describe());
+            println!("    Docs: {}", rule.doc_url());
+        }
+    }
+    println!();
+
+    let entries: BTreeSet<String> = artifacts.iter().map(ignore_entry).collect();
+    if is_tty {
+        println!("{}", "Suggested .gitignore entries:".bold());
+    } else {
+        println!("Suggested .gitignore entries:");
+    }
+    println!();
+    for entry in entries {
+        if is_tty {
+            println!("  {}", entry.green());
+        } else {
+            println!("  {entry}");
+        }
+    }
+    println!();
+}
+
+/// Markdown section on `artifacts` for the job summary and PR comment
+pub fn markdown_section(artifacts: &[Artifact]) -> String {
+    let mut markdown = String::new();
+    if artifacts.is_empty() {
+        return markdown;
+    }
+    let _ = writeln!(
+        markdown,
+        "\n### Tooling directories\n\nFound {} Python tooling director(ies) that Git does not ignore.\n",
+        artifacts.len()
+    );
+    markdown.push_str("| Path | Contains | Rule |\n");
+    markdown.push_str("| --- | --- | --- |\n");
+    for artifact in artifacts {
+        let rule = artifact.kind.rule();
+        let _ = writeln!(
+            markdown,
+            "| `{}/` | {} | [{}]({}) |",
+            artifact.path.to_string_lossy().replace('\\', "/"),
+            artifact.kind.describe(),
+            rule.id,
+            rule.doc_url()
+        );
+    }
+
+    markdown.push_str("\nAdd these lines to `.gitignore`:\n\n```gitignore\n");
+    let entries: BTreeSet<String> = artifacts.iter().map(ignore_entry).collect();
+    for entry in entries {
+        let _ = writeln!(markdown, "{entry}");
+    }
+    markdown.push_str("```\n");
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join(".direnv"))?;
+        fs::create_dir_all(root.join("tools/.pyenv/versions/3.12.1"))?;
+        fs::create_dir_all(root.join("docs/.pyenv"))?;
+        fs::create_dir_all(root.join("build/pip-wheel-metadata"))?;
+        fs::create_dir_all(root.join(".cache/pip"))?;
+        fs::create_dir_all(root.join("vendor/pip"))?;
+
+        assert_eq!(
+            ArtifactKind::detect(&root.join(".direnv")),
+            Some(ArtifactKind::Direnv)
+        );
+        assert_eq!(
+            ArtifactKind::detect(&root.join("tools/.pyenv")),
+            Some(ArtifactKind::Pyenv)
+        );
+        // Without installed versions it is only a directory with that name
+        assert_eq!(ArtifactKind::detect(&root.join("docs/.pyenv")), None);
+        assert_eq!(ArtifactKind::detect(&root.join("tools")), None);
+        for dir in ["build/pip-wheel-metadata", ".cache/pip"] {
+            assert_eq!(
+                ArtifactKind::detect(&root.join(dir)),
+                Some(ArtifactKind::PipCache),
+                "{dir}"
+            );
+        }
+        // A vendored copy of pip is not a cache
+        assert_eq!(ArtifactKind::detect(&root.join("vendor/pip")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_entry() {
+        let artifact = |path: &str, kind| Artifact {
+            path: PathBuf::from(path),
+            kind,
+        };
+        assert_eq!(
+            ignore_entry(&artifact("app/.direnv", ArtifactKind::Direnv)),
+            ".direnv/"
+        );
+        assert_eq!(
+            ignore_entry(&artifact("tools/.pyenv", ArtifactKind::Pyenv)),
+            "/tools/.pyenv/"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! History audit for commits that added Python virtual environments.
+//!
+//! The scan only sees the current working tree, so a venv committed with
+//! `git commit --no-verify` is invisible once it has been ignored or removed.
+//! The audit walks the commit history instead and reports every commit that
+//! added a `pyvenv.cfg`, grouped by author.
+
+use crate::config::Outcome;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::{Delta, Oid, Repository, Sort};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// A commit that added one or more `pyvenv.cfg` files
+#[derive(Debug)]
+pub struct AuditFinding {
+    /// Abbreviated commit hash
+    pub commit: String,
+    /// Commit author as `Name <email>`
+    pub author: String,
+    /// First line of the commit message
+    pub summary: String,
+    /// Repository-relative paths of the added `pyvenv.cfg` files
+    pub paths: Vec<PathBuf>,
+}
+
+/// Run the audit from `workdir` and print the report, returning the exit code
+pub fn run_audit(workdir: &Path, since: &str, is_tty: bool) -> Result<i32> {
+    let findings = audit_since(workdir, since)?;
+
+    if findings.is_empty() {
+        if is_tty {
+            println!(
+                "{} No commits since {} added Python virtual environments",
+                "OK:".green().bold(),
+                since.cyan()
+            );
+        } else {
+            println!("OK: No commits since {since} added Python virtual environments");
+        }
+        Ok(0)
+    } else {
+        print_audit_report(&findings, since, is_tty);
+        Ok(Outcome::Violations.default_code())
+    }
+}
+
+/// Find commits reachable from HEAD but not from `since` that added `pyvenv.cfg`
+///
+/// Merge commits are skipped: the changes they bring in are attributed to the
+/// commits that originally introduced them.
+pub fn audit_since(workdir: &Path, since: &str) -> Result<Vec<AuditFinding>> {
+    let repo = Repository::discover(workdir)
+        .context("Not in a Git repository; audit requires commit history")?;
+
+    let since_commit = repo
+        .revparse_single(since)
+        .with_context(|| format!("Unknown revision '{since}'"))?
+        .peel_to_commit()
+        .with_context(|| format!("Revision '{since}' does not point to a commit"))?;
+
+    let (findings, _) = walk_history(&repo, Some(since_commit.id()), None)
+        .with_context(|| format!("Failed to audit history since '{since}'"))?;
+    Ok(findings)
+}
+
+/// Find commits reachable from HEAD but not from `hide` that added
+/// `pyvenv.cfg`, newest first, looking at no more than `max_commits`
+///
+/// Also returns whether the limit cut the walk short.
+pub fn walk_history(
+    repo: &Repository,
+    hide: Option<Oid>,
+    max_commits: Option<usize>,
+) -> Result<(Vec<AuditFinding>, bool)> {
+    let mut revwalk = repo.revwalk().context("Failed to start revision walk")?;
+    // Sorting must be configured before pushing, as it resets the walker
+    revwalk
+        .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .context("Failed to configure revision walk")?;
+    revwalk.push_head().context("Failed to resolve HEAD")?;
+    if let Some(hide) = hide {
+        revwalk
+            .hide(hide)
+            .context("Failed to exclude the audited revision's history")?;
+    }
+
+    let mut findings = Vec::new();
+
+    for (walked, oid) in revwalk.enumerate() {
+        if max_commits.is_some_and(|max| walked >= max) {
+            return Ok((findings, true));
+        }
+        let oid = oid.context("Failed to walk commit history")?;
+        let commit = repo
+            .find_commit(oid)
+            .with_context(|| format!("Failed to read commit {oid}"))?;
+
+        if commit.parent_count() > 1 {
+            continue;
+        }
+
+        let tree = commit.tree().context("Failed to read commit tree")?;
+        let parent_tree = if commit.parent_count() == 1 {
+            Some(
+                commit
+                    .parent(0)
+                    .and_then(|parent| parent.tree())
+                    .context("Failed to read parent commit tree")?,
+            )
+        } else {
+            None
+        };
+
+        let diff = repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            .with_context(|| format!("Failed to diff commit {oid}"))?;
+
+        let paths: Vec<PathBuf> = diff
+            .deltas()
+            .filter(|delta| delta.status() == Delta::Added)
+            .filter_map(|delta| delta.new_file().path().map(Path::to_path_buf))
+            .filter(|path| path.file_name().is_some_and(|name| name == "pyvenv.cfg"))
+            .collect();
+
+        if paths.is_empty() {
+            continue;
+        }
+
+        let author = commit.author();
+        let id = oid.to_string();
+        findings.push(AuditFinding {
+            commit: id[..7].to_string(),
+            author: format!(
+                "{} <{}>",
+                author.name().unwrap_or("unknown"),
+                author.email().unwrap_or("unknown")
+            ),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            paths,
+        });
+    }
+
+    Ok((findings, false))
+}
+
+/// Count offending commits per author, most frequent first
+pub fn author_counts(findings: &[AuditFinding]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for finding in findings {
+        *counts.entry(finding.author.as_str()).or_default() += 1;
+    }
+
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(author, count)| (author.to_string(), count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Print the commits that bypassed the policy and the per-author summary
+fn print_audit_report(findings: &[AuditFinding], since: &str, is_tty: bool) {
+    if is_tty {
+        println!(
+            "{} Found {} commit(s) since {} that added Python virtual environments!",
+            "WARNING:".yellow().bold(),
+            findings.len(),
+            since.cyan()
+        );
+        println!();
+        println!("These commits bypassed the unvenv check (for example via --no-verify).");
+        println!();
+
+        for finding in findings {
+            println!(
+                "  {} {} {}",
+                finding.commit.yellow(),
+                finding.summary,
+                format!("({})", finding.author).dimmed()
+            );
+            for path in &finding.paths {
+                let normalized_path = path.to_string_lossy().replace('\\', "/");
+                println!("     📁 {}", normalized_path.cyan());
+            }
+        }
+        println!();
+
+        println!("{}", "Commits per author:".bold());
+        println!();
+    } else {
+        println!(
+            "WARNING: Found {} commit(s) since {since} that added Python virtual environments!",
+            findings.len()
+        );
+        println!();
+
+        for finding in findings {
+            println!(
+                "  {} {} ({})",
+                finding.commit, finding.summary, finding.author
+            );
+            for path in &finding.paths {
+                let normalized_path = path.to_string_lossy().replace('\\', "/");
+                println!("    {normalized_path}");
+            }
+        }
+        println!();
+
+        println!("Commits per author:");
+    }
+    for (author, count) in author_counts(findings) {
+        println!("  {count:>4}  {author}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn finding(author: &str) -> AuditFinding {
+        AuditFinding {
+            commit: "abcdef1".to_string(),
+            author: author.to_string(),
+            summary: "Add venv".to_string(),
+            paths: vec![PathBuf::from("venv/pyvenv.cfg")],
+        }
+    }
+
+    #[test]
+    fn test_author_counts_sorted_by_frequency() {
+        let findings = vec![finding("b <b@x>"), finding("a <a@x>"), finding("b <b@x>")];
+
+        let counts = author_counts(&findings);
+
+        assert_eq!(
+            counts,
+            vec![("b <b@x>".to_string(), 2), ("a <a@x>".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_audit_since_finds_added_venv() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+        git(dir, &["tag", "base"]);
+
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "--no-verify", "-m", "Add venv"]);
+
+        let findings = audit_since(dir, "base")?;
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].summary, "Add venv");
+        assert_eq!(findings[0].author, "Test <test@example.com>");
+        assert_eq!(findings[0].paths, vec![PathBuf::from("venv/pyvenv.cfg")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_since_ignores_history_before_ref() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+
+        git(dir, &["init"]);
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Add venv"]);
+
+        let findings = audit_since(dir, "HEAD")?;
+        assert!(findings.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_since_unknown_revision() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        git(temp_dir.path(), &["init"]);
+
+        assert!(audit_since(temp_dir.path(), "no-such-ref").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Self-benchmark of the scan phases.
+//!
+//! `unvenv bench` runs each phase of a scan on its own over the current
+//! directory: walking the tree, asking Git about ignore rules, and parsing
+//! `pyvenv.cfg` files. The timings show which phase dominates, so users can
+//! tell whether a slow hook is down to the size of the tree, to expensive
+//! ignore rules, or to the venvs themselves.
+
+use crate::{ScanArgs, find_unignored_venvs, parse_pyvenv_cfg};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use unvenv::ignore::IgnoreMatcher;
+use walkdir::WalkDir;
+
+/// Timings and counts for each scan phase
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    /// Time to enumerate the whole tree, without any pruning
+    pub walk: Duration,
+    /// Directories found by the walk
+    pub dirs: usize,
+    /// Files found by the walk
+    pub files: usize,
+    /// Time spent answering Git ignore queries, or `None` outside a repository
+    pub ignore: Option<Duration>,
+    /// Ignore queries a scan would make
+    pub ignore_checks: usize,
+    /// Directories a scan never visits because they are ignored or inside a venv
+    pub pruned: usize,
+    /// Time to parse the `pyvenv.cfg` files a scan would report
+    pub parse: Duration,
+    /// `pyvenv.cfg` files parsed
+    pub parsed: usize,
+    /// Time for a complete scan, as run by `unvenv scan`
+    pub scan: Duration,
+}
+
+/// Run the benchmark from `workdir` and print the breakdown
+pub fn run_bench(workdir: &Path, is_tty: bool) -> Result<i32> {
+    let report = bench(workdir)?;
+    print_bench_report(&report, is_tty);
+    Ok(0)
+}
+
+/// Time each scan phase over `workdir`
+pub fn bench(workdir: &Path) -> Result<BenchReport> {
+    let mut report = BenchReport::default();
+
+    // Walk: enumerate every directory and file, as if nothing were pruned
+    let started = Instant::now();
+    let mut dirs = Vec::new();
+    for entry in WalkDir::new(workdir)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| e.file_name().to_str() != Some(".git"))
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        if entry.file_type().is_dir() {
+            dirs.push(entry.into_path());
+        } else {
+            report.files += 1;
+        }
+    }
+    report.walk = started.elapsed();
+    report.dirs = dirs.len();
+
+    // Ignore: the queries a scan makes, skipping whatever it would prune.
+    // The walk yields parents before their children, so a directory is
+    // pruned exactly when its parent was.
+    let started = Instant::now();
+    let mut matcher = IgnoreMatcher::discover(workdir);
+    let mut pruned: HashSet<&Path> = HashSet::new();
+    let mut venvs: Vec<&PathBuf> = Vec::new();
+    for dir in &dirs {
+        if dir.parent().is_some_and(|parent| pruned.contains(parent)) {
+            pruned.insert(dir);
+            report.pruned += 1;
+            continue;
+        }
+
+        let is_venv = dir.join("pyvenv.cfg").is_file();
+        let ignored = if let Some(matcher) = matcher.as_mut() {
+            report.ignore_checks += 1;
+            let mut ignored = matcher.is_dir_ignored(dir)?;
+            if is_venv && !ignored {
+                report.ignore_checks += 1;
+                ignored = matcher.is_file_ignored(&dir.join("pyvenv.cfg"))?;
+            }
+            ignored
+        } else {
+            false
+        };
+
+        if ignored || is_venv {
+            pruned.insert(dir);
+        }
+        if is_venv && !ignored {
+            venvs.push(dir);
+        }
+    }
+    if matcher.is_some() {
+        report.ignore = Some(started.elapsed());
+    }
+
+    // Parse: read the configuration of every venv a scan would report
+    let started = Instant::now();
+    for dir in venvs {
+        let cfg_path = dir.join("pyvenv.cfg");
+        let rel_path = cfg_path.strip_prefix(workdir).unwrap_or(&cfg_path);
+        parse_pyvenv_cfg(&cfg_path, rel_path)?;
+        report.parsed += 1;
+    }
+    report.parse = started.elapsed();
+
+    // Scan: everything together, with pruning, for comparison
+    let started = Instant::now();
+    find_unignored_venvs(workdir, &ScanArgs::default(), false)?;
+    report.scan = started.elapsed();
+
+    Ok(report)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:>9.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Print the per-phase breakdown
+fn print_bench_report(report: &BenchReport, is_tty: bool) {
+    let ignore = report.ignore.map_or_else(
+        || format!("{:>12}   not in a Git repository", "-"),
+        |duration| {
+            format!(
+                "{}   {} queries, {} directories pruned",
+                format_duration(duration),
+                report.ignore_checks,
+                report.pruned
+            )
+        },
+    );
+    let rows = [
+        (
+            "walk",
+            format!(
+                "{}   {} directories, {} files",
+                format_duration(report.walk),
+                report.dirs,
+                report.files
+            ),
+        ),
+        ("ignore", ignore),
+        (
+            "parse",
+            format!(
+                "{}   {} pyvenv.cfg file(s)",
+                format_duration(report.parse),
+                report.parsed
+            ),
+        ),
+        (
+            "scan",
+            format!(
+                "{}   complete scan with pruning",
+                format_duration(report.scan)
+            ),
+        ),
+    ];
+
+    if is_tty {
+        println!("{}", "Scan benchmark".bold());
+        println!();
+        for (phase, details) in rows {
+            println!("  {:<8}{details}", phase.cyan());
+        }
+    } else {
+        println!("Scan benchmark");
+        println!();
+        for (phase, details) in rows {
+            println!("  {phase:<8}{details}");
+        }
+    }
+
+    // The walk above visits everything; the real scan skips pruned directories
+    if report.pruned > 0 {
+        println!();
+        println!(
+            "The scan skips {} of {} directories because they are ignored or inside a venv.",
+            report.pruned, report.dirs
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bench_counts_phases() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+
+        fs::write(dir.join(".gitignore"), "build/\n")?;
+        fs::create_dir_all(dir.join("build").join("out"))?;
+        fs::create_dir_all(dir.join("venv").join("lib"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let report = bench(dir)?;
+
+        // root, build, build/out, venv, venv/lib
+        assert_eq!(report.dirs, 5);
+        assert_eq!(report.files, 2);
+        assert!(report.ignore.is_some());
+        assert_eq!(report.pruned, 2);
+        assert_eq!(report.parsed, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bench_outside_repo() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let report = bench(temp_dir.path())?;
+
+        assert_eq!(report.dirs, 1);
+        assert_eq!(report.ignore, None);
+        assert_eq!(report.ignore_checks, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! `version --json`: which build of unvenv this is.
+//!
+//! Fleet inventory tooling records the output to know exactly what is
+//! deployed on each machine. Everything but the version comes from
+//! `build.rs`; the commit is `null` for builds outside a Git checkout, such
+//! as `cargo install` from crates.io.
+
+use serde::Serialize;
+
+/// Build metadata of the running binary
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    /// Semantic version from Cargo.toml
+    pub version: &'static str,
+    /// Commit the binary was built from
+    pub commit: Option<&'static str>,
+    /// When the build script ran, as an RFC 3339 UTC timestamp
+    pub build_date: &'static str,
+    /// Target triple
+    pub target: &'static str,
+    /// Enabled Cargo features
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// Metadata of this build
+    pub fn current() -> Self {
+        let commit = env!("UNVENV_BUILD_COMMIT");
+        Self {
+            version: crate::VERSION,
+            commit: (!commit.is_empty()).then_some(commit),
+            build_date: env!("UNVENV_BUILD_DATE"),
+            target: env!("UNVENV_BUILD_TARGET"),
+            features: env!("UNVENV_BUILD_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Print the build metadata as JSON
+pub fn print_json() -> anyhow::Result<i32> {
+    println!("{}", serde_json::to_string_pretty(&BuildInfo::current())?);
+    Ok(0)
+}
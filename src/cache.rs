@@ -0,0 +1,618 @@
+//! Persistent scan cache.
+//!
+//! Evaluating `.gitignore` rules is the most expensive part of a scan. With
+//! `scan --cache`, each directory's verdict is stored on disk together with
+//! the directory's mtime and a fingerprint of the ignore files that apply to
+//! it, so repeated scans (e.g. on every commit) only re-evaluate directories
+//! whose contents or ignore rules changed.
+//!
+//! The cache lives in `.git/unvenv/` inside repositories and under the XDG
+//! cache directory otherwise.
+//!
+//! Editor watchers and Git hooks can run scans at the same time, so every
+//! cache file under `.git/unvenv/` is written through [`write_atomic`] while
+//! holding a [`FileLock`]: readers see either the old or the new file, never
+//! a mix. Writers that merge their entries into the file re-read it under the
+//! lock, so they do not drop each other's entries. A lock left behind by a
+//! crashed process is taken over after a minute; if two processes take
+//! it over at once, one of their updates may be lost, which for a cache only
+//! costs a re-check.
+
+use anyhow::{Context, Result, bail};
+use git2::Repository;
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Write as _,
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use unvenv::ignore::IgnoreMatcher;
+
+/// First line of every cache file; bump the version when the format changes
+const CACHE_HEADER: &str = "unvenv-scan-cache 1";
+
+/// Directories modified this recently are not cached, because a change made
+/// within the filesystem's timestamp granularity would go unnoticed
+const RACY_WINDOW_NS: u128 = 2_000_000_000;
+
+/// How long to wait for another process to release a cache lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lock files older than this were left behind by a process that crashed
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+/// What a scan concluded about a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirVerdict {
+    /// Ignored by Git; the whole subtree is skipped
+    Ignored,
+    /// A venv root (contains `pyvenv.cfg`)
+    Venv,
+    /// Neither; the walk descends into it
+    Plain,
+}
+
+impl DirVerdict {
+    const fn code(self) -> char {
+        match self {
+            Self::Ignored => 'I',
+            Self::Venv => 'V',
+            Self::Plain => 'P',
+        }
+    }
+
+    const fn from_code(code: char) -> Option<Self> {
+        match code {
+            'I' => Some(Self::Ignored),
+            'V' => Some(Self::Venv),
+            'P' => Some(Self::Plain),
+            _ => None,
+        }
+    }
+}
+
+/// State a cached verdict was computed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirStamp {
+    mtime_ns: u128,
+    rules: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheEntry {
+    stamp: DirStamp,
+    verdict: DirVerdict,
+}
+
+/// On-disk cache of per-directory scan verdicts
+pub struct ScanCache {
+    path: PathBuf,
+    started_ns: u128,
+    previous: HashMap<PathBuf, CacheEntry>,
+    current: HashMap<PathBuf, CacheEntry>,
+    /// Ignore-rule fingerprints by walk depth
+    rules: Vec<Fingerprint>,
+}
+
+impl ScanCache {
+    /// Open the cache for a scan of `workdir`, starting cold if it is missing
+    /// or unreadable
+    ///
+    /// Verdicts computed with case-insensitive matching are kept apart from
+    /// the others, since they can differ for the same directory.
+    pub fn open(
+        workdir: &Path,
+        matcher: Option<&IgnoreMatcher>,
+        ignore_case: bool,
+    ) -> Option<Self> {
+        let path = cache_path(workdir, matcher.map(IgnoreMatcher::repo))?;
+        let mut seed = ignore_rules_seed(workdir, matcher);
+        if ignore_case {
+            seed = seed.add_bytes(b"ignore-case");
+        }
+        Some(Self::load(path, seed, now_ns()))
+    }
+
+    fn load(path: PathBuf, seed: Fingerprint, started_ns: u128) -> Self {
+        let previous = fs::read_to_string(&path)
+            .map(|content| parse_entries(&content))
+            .unwrap_or_default();
+
+        Self {
+            path,
+            started_ns,
+            previous,
+            current: HashMap::new(),
+            rules: vec![seed],
+        }
+    }
+
+    /// Stamp describing the directory with `metadata`, found at walk `depth`
+    ///
+    /// Must be called for every directory in walk order, since it tracks the
+    /// ignore rules inherited from the directories above.
+    pub fn stamp(&mut self, depth: usize, metadata: &Metadata) -> Option<DirStamp> {
+        self.rules.truncate(depth + 1);
+        let rules = self.rules.get(depth)?.0;
+        let mtime_ns = mtime_ns(metadata)?;
+        Some(DirStamp { mtime_ns, rules })
+    }
+
+    /// Cached verdict for `dir`, if it was computed from the same state
+    pub fn get(&self, dir: &Path, stamp: DirStamp) -> Option<DirVerdict> {
+        self.previous
+            .get(dir)
+            .filter(|entry| entry.stamp == stamp)
+            .map(|entry| entry.verdict)
+    }
+
+    /// Record the verdict for `dir`
+    ///
+    /// For directories the walk descends into, the `.gitignore` inside them
+    /// becomes part of the rules applying to their children.
+    pub fn insert(&mut self, dir: &Path, stamp: DirStamp, verdict: DirVerdict) {
+        if verdict == DirVerdict::Plain {
+            self.rules
+                .push(Fingerprint(stamp.rules).add_file(&dir.join(".gitignore")));
+        }
+
+        let racy = stamp.mtime_ns + RACY_WINDOW_NS > self.started_ns;
+        let storable = dir.to_str().is_some_and(|path| !path.contains('\n'));
+        if !racy && storable {
+            self.current
+                .insert(dir.to_path_buf(), CacheEntry { stamp, verdict });
+        }
+    }
+
+    /// Write the verdicts recorded during this scan back to disk
+    ///
+    /// Entries another scan wrote since this one loaded the file are kept;
+    /// entries this scan loaded and did not record again are dropped, so
+    /// directories that are gone do not pile up.
+    pub fn save(&self) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut entries = fs::read_to_string(&self.path)
+            .map(|content| parse_entries(&content))
+            .unwrap_or_default();
+        entries.retain(|dir, entry| self.previous.get(dir) != Some(entry));
+        entries.extend(
+            self.current
+                .iter()
+                .map(|(dir, entry)| (dir.clone(), *entry)),
+        );
+
+        let mut content = String::from(CACHE_HEADER);
+        content.push('\n');
+        for (dir, entry) in &entries {
+            if let Some(dir) = dir.to_str() {
+                let _ = writeln!(
+                    content,
+                    "{} {:016x} {} {dir}",
+                    entry.stamp.mtime_ns,
+                    entry.stamp.rules,
+                    entry.verdict.code()
+                );
+            }
+        }
+        write_atomic(&self.path, &content)
+    }
+}
+
+/// Exclusive lock on a cache file, held until dropped
+///
+/// The lock is a `<file>.lock` sibling created with `O_EXCL`, which works on
+/// every platform and filesystem Git itself supports.
+#[derive(Debug)]
+pub struct FileLock(PathBuf);
+
+impl FileLock {
+    /// Lock `path`, waiting up to [`LOCK_TIMEOUT`] for another process to
+    /// finish with it
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let lock_path = sibling(path, "lock");
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let started = SystemTime::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_path)
+            {
+                Ok(_) => return Ok(Self(lock_path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to create {}", lock_path.display()));
+                }
+            }
+
+            let age = fs::metadata(&lock_path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age > STALE_LOCK) {
+                // Whoever held it is gone; the next attempt takes it over
+                let _ = fs::remove_file(&lock_path);
+                continue;
+            }
+            if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                bail!(
+                    "{} is locked by another unvenv process; remove it if none is running",
+                    lock_path.display()
+                );
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Replace the file at `path` with `content` in one step
+///
+/// The content goes to a temporary file unique to this process and write,
+/// which is then renamed over `path`, so an interrupted or concurrent write
+/// never leaves a truncated or interleaved file behind.
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp_path = sibling(
+        path,
+        &format!(
+            "{}.{}.tmp",
+            process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+    }
+    Ok(())
+}
+
+/// `<path>.<suffix>`, keeping any extension `path` already has
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn parse_entries(content: &str) -> HashMap<PathBuf, CacheEntry> {
+    let mut lines = content.lines();
+    if lines.next() != Some(CACHE_HEADER) {
+        return HashMap::new();
+    }
+
+    lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, ' ');
+            let mtime_ns = fields.next()?.parse().ok()?;
+            let rules = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let mut code = fields.next()?.chars();
+            let verdict = DirVerdict::from_code(code.next()?)?;
+            let dir = PathBuf::from(fields.next()?);
+            Some((
+                dir,
+                CacheEntry {
+                    stamp: DirStamp { mtime_ns, rules },
+                    verdict,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Where the cache for a scan of `workdir` lives
+fn cache_path(workdir: &Path, repo: Option<&Repository>) -> Option<PathBuf> {
+    if let Some(repo) = repo {
+        return Some(repo.path().join("unvenv").join("scan-cache"));
+    }
+
+    let key = Fingerprint::EMPTY.add_bytes(workdir.to_string_lossy().as_bytes());
+    Some(user_cache_dir()?.join(format!("scan-{:016x}", key.0)))
+}
+
+/// Per-user cache directory for unvenv (`$XDG_CACHE_HOME/unvenv` and friends)
+pub fn user_cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("unvenv"))
+}
+
+/// Fingerprint of the ignore sources that apply above the scan root
+fn ignore_rules_seed(workdir: &Path, matcher: Option<&IgnoreMatcher>) -> Fingerprint {
+    let Some(matcher) = matcher else {
+        return Fingerprint::EMPTY;
+    };
+    let repo = matcher.repo();
+
+    let mut seed = Fingerprint::EMPTY.add_file(&repo.path().join("info").join("exclude"));
+
+    if let Some(excludes_file) = repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_path("core.excludesFile").ok())
+    {
+        seed = seed.add_file(&excludes_file);
+    }
+    let xdg_config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(xdg_config) = xdg_config {
+        seed = seed.add_file(&xdg_config.join("git").join("ignore"));
+    }
+
+    // .gitignore files between the repository root and the scan root; the
+    // scan root's own file is picked up when the walk enters it
+    if let Some(rel) = matcher.git_path(workdir) {
+        for ancestor in rel.ancestors().skip(1) {
+            seed = seed.add_file(&matcher.root().join(ancestor).join(".gitignore"));
+        }
+    }
+
+    seed
+}
+
+fn mtime_ns(metadata: &Metadata) -> Option<u128> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos())
+}
+
+/// Stable 64-bit FNV-1a hash, used to fingerprint ignore files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint(pub u64);
+
+impl Fingerprint {
+    /// Fingerprint of nothing
+    pub const EMPTY: Self = Self(0xcbf2_9ce4_8422_2325);
+
+    /// Mix in `bytes`
+    pub fn add_bytes(self, bytes: &[u8]) -> Self {
+        let hash = bytes.iter().fold(self.0, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        Self(hash)
+    }
+
+    /// Mix in the identity and state of the file at `path`
+    fn add_file(self, path: &Path) -> Self {
+        let hash = self.add_bytes(path.to_string_lossy().as_bytes());
+        fs::metadata(path).map_or_else(
+            |_| hash.add_bytes(b"missing"),
+            |metadata| {
+                hash.add_bytes(&mtime_ns(&metadata).unwrap_or(0).to_le_bytes())
+                    .add_bytes(&metadata.len().to_le_bytes())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const STAMP: DirStamp = DirStamp {
+        mtime_ns: 1_000_000_000_000,
+        rules: 42,
+    };
+
+    fn cache_in(dir: &TempDir) -> ScanCache {
+        ScanCache::load(
+            dir.path().join("scan-cache"),
+            Fingerprint::EMPTY,
+            STAMP.mtime_ns + 10 * RACY_WINDOW_NS,
+        )
+    }
+
+    #[test]
+    fn test_cache_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let mut cache = cache_in(&temp_dir);
+        cache.insert(Path::new("/repo/build"), STAMP, DirVerdict::Ignored);
+        cache.insert(Path::new("/repo/venv"), STAMP, DirVerdict::Venv);
+        cache.insert(Path::new("/repo/src dir"), STAMP, DirVerdict::Plain);
+        cache.save()?;
+
+        let cache = cache_in(&temp_dir);
+        assert_eq!(
+            cache.get(Path::new("/repo/build"), STAMP),
+            Some(DirVerdict::Ignored)
+        );
+        assert_eq!(
+            cache.get(Path::new("/repo/venv"), STAMP),
+            Some(DirVerdict::Venv)
+        );
+        assert_eq!(
+            cache.get(Path::new("/repo/src dir"), STAMP),
+            Some(DirVerdict::Plain)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_miss_when_stamp_changes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let mut cache = cache_in(&temp_dir);
+        cache.insert(Path::new("/repo/build"), STAMP, DirVerdict::Ignored);
+        cache.save()?;
+
+        let cache = cache_in(&temp_dir);
+        let touched = DirStamp {
+            mtime_ns: STAMP.mtime_ns + 1,
+            ..STAMP
+        };
+        let new_rules = DirStamp { rules: 7, ..STAMP };
+        assert_eq!(cache.get(Path::new("/repo/build"), touched), None);
+        assert_eq!(cache.get(Path::new("/repo/build"), new_rules), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_merges_with_concurrent_writers() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut seed = cache_in(&temp_dir);
+        seed.insert(Path::new("/repo/gone"), STAMP, DirVerdict::Plain);
+        seed.save()?;
+
+        // Two scans start from the same file
+        let mut first = cache_in(&temp_dir);
+        let mut second = cache_in(&temp_dir);
+        first.insert(Path::new("/repo/build"), STAMP, DirVerdict::Ignored);
+        second.insert(Path::new("/repo/venv"), STAMP, DirVerdict::Venv);
+        first.save()?;
+        second.save()?;
+
+        let cache = cache_in(&temp_dir);
+        assert_eq!(
+            cache.get(Path::new("/repo/build"), STAMP),
+            Some(DirVerdict::Ignored)
+        );
+        assert_eq!(
+            cache.get(Path::new("/repo/venv"), STAMP),
+            Some(DirVerdict::Venv)
+        );
+        // Neither scan found it again
+        assert_eq!(cache.get(Path::new("/repo/gone"), STAMP), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recently_modified_directories_are_not_cached() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let mut cache = ScanCache::load(
+            temp_dir.path().join("scan-cache"),
+            Fingerprint::EMPTY,
+            STAMP.mtime_ns + 1,
+        );
+        cache.insert(Path::new("/repo/build"), STAMP, DirVerdict::Ignored);
+        cache.save()?;
+
+        let cache = cache_in(&temp_dir);
+        assert_eq!(cache.get(Path::new("/repo/build"), STAMP), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_cache_starts_cold() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("scan-cache"), "garbage\n1 2 3\n")?;
+
+        let cache = cache_in(&temp_dir);
+        assert!(cache.previous.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stamp_tracks_gitignore_of_parent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let metadata = fs::metadata(temp_dir.path())?;
+        let mut cache = cache_in(&temp_dir);
+
+        let root = cache.stamp(0, &metadata).expect("stamp");
+        cache.insert(temp_dir.path(), root, DirVerdict::Plain);
+        let before = cache.stamp(1, &metadata).expect("stamp");
+
+        fs::write(temp_dir.path().join(".gitignore"), "venv/\n")?;
+        let root = cache.stamp(0, &metadata).expect("stamp");
+        cache.insert(temp_dir.path(), root, DirVerdict::Plain);
+        let after = cache.stamp(1, &metadata).expect("stamp");
+
+        assert_ne!(before, after);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_writers_leave_a_whole_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("unvenv").join("last-scan");
+
+        thread::scope(|scope| {
+            for writer in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    let content = format!("{writer}\n").repeat(10_000);
+                    for _ in 0..5 {
+                        let _lock = FileLock::acquire(path).expect("lock");
+                        write_atomic(path, &content).expect("write");
+                    }
+                });
+            }
+        });
+
+        let content = fs::read_to_string(&path)?;
+        let mut lines = content.lines();
+        let first = lines.next().expect("content");
+        assert!(lines.all(|line| line == first));
+        assert_eq!(content.len(), (first.len() + 1) * 10_000);
+        // No lock or temporary file is left behind
+        assert_eq!(fs::read_dir(path.parent().expect("parent"))?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("scan-cache");
+        let lock_path = sibling(&path, "lock");
+        fs::write(&lock_path, "")?;
+        let crashed = SystemTime::now() - STALE_LOCK - Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&lock_path)?
+            .set_modified(crashed)?;
+
+        let lock = FileLock::acquire(&path)?;
+        drop(lock);
+        assert!(!lock_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        assert_eq!(Fingerprint::EMPTY.add_bytes(b"a").0, 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,383 @@
+//! `scan --ignore-engine git`: ignore verdicts straight from Git.
+//!
+//! The scanner normally asks libgit2 whether a path is ignored. libgit2
+//! reimplements Git's rules (`.gitignore` files at every level with their
+//! negations, `.git/info/exclude`, `core.excludesFile`, `core.ignoreCase`),
+//! and wherever the two disagree a user gets a finding `git status` cannot
+//! explain. With `--ignore-engine git` every verdict comes from one
+//! long-running `git check-ignore --stdin -z --verbose --non-matching
+//! --no-index` process instead, so the scan ignores exactly what Git
+//! ignores, at the cost of a round trip per directory.
+//!
+//! `--no-index` keeps the semantics of the libgit2 engine: whether a path is
+//! ignored does not depend on whether it is already tracked.
+//!
+//! One divergence is known: libgit2 lets a `core.excludesFile` pattern win
+//! over a `.gitignore` negation of the same path, while Git gives `.gitignore`
+//! precedence. Repositories that re-include a globally excluded directory
+//! should scan with `--ignore-engine git`.
+//!
+//! The tests double as a differential harness: they build repositories that
+//! exercise each ignore source and compare both engines path by path.
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+/// Where ignore verdicts come from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum IgnoreEngine {
+    /// libgit2, in process (fast)
+    #[default]
+    Libgit2,
+    /// The `git` CLI's `check-ignore`, for exact parity with Git
+    Git,
+}
+
+/// The pattern that decided a path, as `git check-ignore --verbose` reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// File the pattern is in, e.g. `.gitignore` or `.git/info/exclude`
+    pub source: String,
+    /// Line of the pattern in `source`
+    pub line: usize,
+    /// The pattern; starts with `!` when it re-includes the path
+    pub pattern: String,
+}
+
+impl Match {
+    /// Whether the pattern ignores the path rather than re-including it
+    pub fn ignores(&self) -> bool {
+        !self.pattern.starts_with('!')
+    }
+}
+
+impl fmt::Display for Match {
+    /// `source:line:pattern`, as in `git check-ignore -v`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.pattern)
+    }
+}
+
+/// A running `git check-ignore --stdin`
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// Ignore verdicts from `git check-ignore`, one process per repository
+pub struct CheckIgnore {
+    root: PathBuf,
+    process: Option<Process>,
+    /// Paths already checked, with the pattern that decided them
+    matches: HashMap<PathBuf, Option<Match>>,
+}
+
+impl CheckIgnore {
+    /// Verdicts for the working tree at `root`; Git is started on first use
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            process: None,
+            matches: HashMap::new(),
+        }
+    }
+
+    fn process(&mut self) -> Result<&mut Process> {
+        if self.process.is_none() {
+            let mut child = Command::new("git")
+                .args([
+                    "check-ignore",
+                    "--stdin",
+                    "-z",
+                    "--verbose",
+                    "--non-matching",
+                    "--no-index",
+                ])
+                // Answer each path as soon as it is read
+                .env("GIT_FLUSH", "1")
+                .current_dir(&self.root)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::inherit())
+                .spawn()
+                .context("--ignore-engine git needs the git CLI")?;
+            let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+                bail!("Failed to connect to git check-ignore");
+            };
+            self.process = Some(Process {
+                child,
+                stdin,
+                stdout: BufReader::new(stdout),
+            });
+        }
+        self.process
+            .as_mut()
+            .context("Failed to start git check-ignore")
+    }
+
+    /// The pattern deciding `git_path` (relative to the root), if any
+    pub fn query(&mut self, git_path: &Path) -> Result<Option<Match>> {
+        let path = git_path.to_string_lossy().replace('\\', "/");
+        let process = self.process()?;
+        process
+            .stdin
+            .write_all(format!("{path}\0").as_bytes())
+            .and_then(|()| process.stdin.flush())
+            .context("Failed to query git check-ignore")?;
+
+        // <source> NUL <line> NUL <pattern> NUL <path> NUL, all but the path
+        // empty when nothing matches
+        let mut fields = Vec::with_capacity(4);
+        for _ in 0..4 {
+            let mut field = Vec::new();
+            let read = process
+                .stdout
+                .read_until(b'\0', &mut field)
+                .context("Failed to read from git check-ignore")?;
+            if read == 0 || field.pop() != Some(b'\0') {
+                bail!("git check-ignore exited while checking {path}");
+            }
+            fields.push(String::from_utf8_lossy(&field).into_owned());
+        }
+        Ok(parse_record(&fields))
+    }
+
+    /// Whether `git_path` (relative to the root) is ignored
+    pub fn is_ignored(&mut self, git_path: &Path) -> Result<bool> {
+        // The repository root itself can never be ignored
+        if git_path.as_os_str().is_empty() {
+            return Ok(false);
+        }
+        if !self.matches.contains_key(git_path) {
+            let found = self.query(git_path)?;
+            self.matches.insert(git_path.to_path_buf(), found);
+        }
+        Ok(self.checked(git_path).is_some_and(Match::ignores))
+    }
+
+    /// The pattern that decided an already checked path, if any
+    pub fn checked(&self, git_path: &Path) -> Option<&Match> {
+        self.matches.get(git_path)?.as_ref()
+    }
+}
+
+impl Drop for CheckIgnore {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.take() {
+            let Process {
+                mut child, stdin, ..
+            } = process;
+            // Closing stdin ends the process
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
+
+/// The match in one `-z --verbose --non-matching` record
+fn parse_record(fields: &[String]) -> Option<Match> {
+    let [source, line, pattern, _path] = fields else {
+        return None;
+    };
+    if source.is_empty() {
+        return None;
+    }
+    Some(Match {
+        source: source.clone(),
+        line: line.parse().ok()?,
+        pattern: pattern.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+    use unvenv::ignore::IgnoreMatcher;
+
+    /// Repository with `files` (path, contents), directories created as needed
+    fn repo(files: &[(&str, &str)]) -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        git(temp_dir.path(), &["init", "-q"]);
+        git(temp_dir.path(), &["config", "core.ignoreCase", "false"]);
+        for (path, content) in files {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().expect("files are below the root"))
+                .expect("Failed to create directory");
+            fs::write(path, content).expect("Failed to write");
+        }
+        temp_dir
+    }
+
+    /// Paths below `root` on which the engines disagree, with the libgit2 and
+    /// the Git verdict
+    fn differences(root: &Path) -> Vec<(String, bool, bool)> {
+        let mut libgit2 = IgnoreMatcher::discover(root).expect("repo");
+        let mut git = CheckIgnore::new(root);
+        let mut differences = Vec::new();
+        for entry in walkdir::WalkDir::new(root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+        {
+            let entry = entry.expect("readable");
+            let git_path = entry.path().strip_prefix(root).expect("below the root");
+            let expected = git.is_ignored(git_path).expect("git check-ignore runs");
+            let actual = if entry.file_type().is_dir() {
+                libgit2.is_dir_ignored(entry.path())
+            } else {
+                libgit2.is_file_ignored(entry.path())
+            }
+            .expect("libgit2 answers");
+            if actual != expected {
+                differences.push((git_path.display().to_string(), actual, expected));
+            }
+        }
+        differences
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let fields = |fields: [&str; 4]| fields.map(String::from);
+        assert_eq!(
+            parse_record(&fields([".gitignore", "5", "!lib/keep/", "lib/keep"])),
+            Some(Match {
+                source: ".gitignore".to_string(),
+                line: 5,
+                pattern: "!lib/keep/".to_string(),
+            })
+        );
+        assert_eq!(parse_record(&fields(["", "", "", "src"])), None);
+        assert_eq!(
+            parse_record(&fields([
+                "/home/me/.config/git/ignore",
+                "2",
+                "*.log",
+                "a.log"
+            ]))
+            .map(|found| found.to_string())
+            .as_deref(),
+            Some("/home/me/.config/git/ignore:2:*.log")
+        );
+    }
+
+    #[test]
+    fn test_check_ignore_verdicts() {
+        let temp_dir = repo(&[
+            (".gitignore", "build/\nlib/*\n!lib/keep/\n"),
+            ("build/sub/pyvenv.cfg", ""),
+            ("lib/keep/venv/pyvenv.cfg", ""),
+            ("lib/drop/venv/pyvenv.cfg", ""),
+        ]);
+        let mut git = CheckIgnore::new(temp_dir.path());
+
+        assert!(git.is_ignored(Path::new("build")).expect("git runs"));
+        // Everything below an ignored directory is ignored too
+        assert!(
+            git.is_ignored(Path::new("build/sub/pyvenv.cfg"))
+                .expect("git runs")
+        );
+        assert!(git.is_ignored(Path::new("lib/drop")).expect("git runs"));
+        assert!(
+            !git.is_ignored(Path::new("lib/keep/venv"))
+                .expect("git runs")
+        );
+        assert!(!git.is_ignored(Path::new("")).expect("git runs"));
+        assert_eq!(
+            git.query(Path::new("lib/keep")).expect("git runs"),
+            Some(Match {
+                source: ".gitignore".to_string(),
+                line: 3,
+                pattern: "!lib/keep/".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parity_nested_negations() {
+        let temp_dir = repo(&[
+            (".gitignore", "/*\n!/src/\n!.gitignore\n"),
+            (
+                "src/.gitignore",
+                "*env*/\n!keep-env/\ncache/\n!cache/pyvenv.cfg\n",
+            ),
+            ("src/app/venv/pyvenv.cfg", ""),
+            ("src/app/.venv/pyvenv.cfg", ""),
+            ("src/keep-env/pyvenv.cfg", ""),
+            ("src/cache/pyvenv.cfg", ""),
+            ("src/app/main.py", ""),
+            ("tools/venv/pyvenv.cfg", ""),
+        ]);
+        assert_eq!(differences(temp_dir.path()), []);
+    }
+
+    #[test]
+    fn test_parity_info_exclude_and_excludes_file() {
+        let temp_dir = repo(&[
+            (".gitignore", "!global-env/\n"),
+            ("local/venv/pyvenv.cfg", ""),
+            ("global-env/pyvenv.cfg", ""),
+            ("other-env/pyvenv.cfg", ""),
+            ("src/main.py", ""),
+        ]);
+        let root = temp_dir.path();
+        fs::write(root.join(".git/info/exclude"), "# local only\nlocal/\n")
+            .expect("Failed to write");
+        let excludes = TempDir::new().expect("Failed to create temp directory");
+        let excludes_file = excludes.path().join("ignore");
+        fs::write(&excludes_file, "*-env/\n").expect("Failed to write");
+        git(
+            root,
+            &[
+                "config",
+                "core.excludesFile",
+                &excludes_file.to_string_lossy(),
+            ],
+        );
+
+        // libgit2 ranks core.excludesFile above the .gitignore negation
+        assert_eq!(
+            differences(root),
+            [
+                ("global-env".to_string(), true, false),
+                ("global-env/pyvenv.cfg".to_string(), true, false),
+            ]
+        );
+        let mut git = CheckIgnore::new(root);
+        assert!(git.is_ignored(Path::new("local/venv")).expect("git runs"));
+        assert!(git.is_ignored(Path::new("other-env")).expect("git runs"));
+        // .gitignore takes precedence over core.excludesFile
+        assert!(!git.is_ignored(Path::new("global-env")).expect("git runs"));
+    }
+
+    #[test]
+    fn test_parity_pattern_syntax() {
+        let temp_dir = repo(&[
+            (
+                ".gitignore",
+                "**/build/\ndocs/**/venv\n/anchored/\n\\#literal/\n\\!bang/\nVENV/\n*.cfg\n!/keep/*.cfg\n",
+            ),
+            ("a/b/build/pyvenv.cfg", ""),
+            ("docs/venv/pyvenv.cfg", ""),
+            ("docs/x/y/venv/pyvenv.cfg", ""),
+            ("anchored/pyvenv.cfg", ""),
+            ("nested/anchored/pyvenv.cfg", ""),
+            ("#literal/pyvenv.cfg", ""),
+            ("!bang/pyvenv.cfg", ""),
+            ("venv/pyvenv.cfg", ""),
+            ("keep/pyvenv.cfg", ""),
+        ]);
+        assert_eq!(differences(temp_dir.path()), []);
+    }
+}
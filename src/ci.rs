@@ -0,0 +1,701 @@
+//! CI system integrations for `scan --ci` and `unvenv ci`.
+//!
+//! The regular report is written for people reading a terminal. CI systems
+//! surface problems through their own conventions instead (workflow
+//! annotations, job summaries, Code Quality reports), so `--ci` additionally
+//! emits the violations in the form the selected system understands.
+//!
+//! `unvenv ci` is the one command for every pipeline: it detects the system
+//! from its environment variables, runs the scan with its output and
+//! `--require-git`, and when the job builds a pull or merge request whose
+//! target branch was fetched, also audits the commits since that branch.
+
+use crate::{ScanArgs, TrackingStatus, VenvInfo, audit, rules::Rule, scan_for_venvs};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use git2::{ObjectType, Oid, Repository};
+use serde::Serialize;
+use std::{
+    env,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write as _,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Where GitLab jobs conventionally leave the Code Quality report
+pub const GITLAB_CODE_QUALITY_REPORT: &str = "gl-code-quality-report.json";
+/// Body for the Bitbucket Code Insights "create report" request
+pub const BITBUCKET_REPORT: &str = "unvenv-insights-report.json";
+/// Body for the Bitbucket Code Insights "add annotations" request
+pub const BITBUCKET_ANNOTATIONS: &str = "unvenv-insights-annotations.json";
+/// Where CircleCI's `store_test_results` step picks up the JUnit report
+pub const CIRCLECI_JUNIT_REPORT: &str = "test-results/unvenv/results.xml";
+
+/// CI systems `--ci` can report to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CiProvider {
+    /// GitHub Actions: error annotations and a job summary
+    Github,
+    /// GitLab CI: a Code Quality report and a collapsible log section
+    Gitlab,
+    /// TeamCity: inspection and build problem service messages
+    Teamcity,
+    /// Jenkins: GCC-style `file:line: error:` lines for the Warnings plugin
+    Jenkins,
+    /// Azure Pipelines: `task.logissue` logging commands
+    Azure,
+    /// Bitbucket Data Center: Code Insights report and annotations, written
+    /// to files for the pipeline to upload
+    Bitbucket,
+    /// CircleCI: a JUnit report for `store_test_results`
+    Circleci,
+}
+
+impl CiProvider {
+    /// Name of the CI system
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Github => "GitHub Actions",
+            Self::Gitlab => "GitLab CI",
+            Self::Teamcity => "TeamCity",
+            Self::Jenkins => "Jenkins",
+            Self::Azure => "Azure Pipelines",
+            Self::Bitbucket => "Bitbucket",
+            Self::Circleci => "CircleCI",
+        }
+    }
+
+    /// The CI system whose variables `var` sees, if any
+    fn detect(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let set = |name| var(name).is_some_and(|value| !value.is_empty());
+        [
+            ("GITHUB_ACTIONS", Self::Github),
+            ("GITLAB_CI", Self::Gitlab),
+            ("TF_BUILD", Self::Azure),
+            ("CIRCLECI", Self::Circleci),
+            ("BITBUCKET_BUILD_NUMBER", Self::Bitbucket),
+            ("TEAMCITY_VERSION", Self::Teamcity),
+            // Last: other systems can run inside a Jenkins agent
+            ("JENKINS_URL", Self::Jenkins),
+        ]
+        .into_iter()
+        .find_map(|(name, provider)| set(name).then_some(provider))
+    }
+
+    /// Revision a pull or merge request is merged into, from the job's
+    /// variables
+    fn base_ref(self, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+        let branch = |name| {
+            var(name).filter(|value| !value.is_empty()).map(|branch| {
+                format!(
+                    "origin/{}",
+                    branch.strip_prefix("refs/heads/").unwrap_or(&branch)
+                )
+            })
+        };
+        match self {
+            Self::Github => branch("GITHUB_BASE_REF"),
+            // The merge base itself, when GitLab computed it
+            Self::Gitlab => var("CI_MERGE_REQUEST_DIFF_BASE_SHA")
+                .filter(|sha| !sha.is_empty())
+                .or_else(|| branch("CI_MERGE_REQUEST_TARGET_BRANCH_NAME")),
+            Self::Azure => branch("SYSTEM_PULLREQUEST_TARGETBRANCH"),
+            Self::Jenkins => branch("CHANGE_TARGET"),
+            Self::Bitbucket => branch("BITBUCKET_PR_DESTINATION_BRANCH"),
+            // Neither exposes the target branch of a pull request
+            Self::Circleci | Self::Teamcity => None,
+        }
+    }
+}
+
+/// Run the scan the way the detected (or given) CI system wants it, then
+/// audit the commits since the pull request's target branch, returning the
+/// exit code
+pub fn run(workdir: &Path, provider: Option<CiProvider>, base: Option<String>) -> Result<i32> {
+    let var = |name: &str| env::var(name).ok();
+    let Some(provider) = provider.or_else(|| CiProvider::detect(var)) else {
+        bail!(
+            "No supported CI system detected (GitHub Actions, GitLab CI, Jenkins, Azure Pipelines, CircleCI, TeamCity, Bitbucket); pass --provider"
+        );
+    };
+    let base = base.or_else(|| provider.base_ref(var));
+    eprintln!(
+        "unvenv ci: {}, base {}",
+        provider.name(),
+        base.as_deref().unwrap_or("none (not a pull request build)")
+    );
+
+    // A checkout without its repository must not pass
+    let args = ScanArgs {
+        ci: Some(provider),
+        require_git: true,
+        ..ScanArgs::default()
+    };
+    let code = scan_for_venvs(&args, false)?;
+    if code != 0 {
+        return Ok(code);
+    }
+
+    let Some(base) = base else {
+        return Ok(code);
+    };
+    // Shallow clones often lack the target branch
+    let resolves =
+        Repository::discover(workdir).is_ok_and(|repo| repo.revparse_single(&base).is_ok());
+    if !resolves {
+        eprintln!(
+            "unvenv ci: {base} is not in this clone; fetch it (or deepen the clone) to audit the branch's commits"
+        );
+        return Ok(code);
+    }
+    audit::run_audit(workdir, &base, false)
+}
+
+/// Emit the violations for `provider`
+pub fn report(provider: CiProvider, venvs: &[VenvInfo]) -> Result<()> {
+    match provider {
+        CiProvider::Github => report_github(venvs),
+        CiProvider::Gitlab => report_gitlab(venvs, Path::new(GITLAB_CODE_QUALITY_REPORT)),
+        CiProvider::Teamcity => {
+            print!("{}", teamcity_messages(venvs));
+            Ok(())
+        }
+        CiProvider::Azure => {
+            for venv in venvs {
+                println!(
+                    "##vso[task.logissue type=error;sourcepath={};linenumber=1;code=unignored-venv]{}",
+                    escape_azure_property(&cfg_path(venv)),
+                    escape_azure_data(&violation_message(venv))
+                );
+            }
+            Ok(())
+        }
+        CiProvider::Bitbucket => report_bitbucket(venvs, Path::new(".")),
+        CiProvider::Jenkins => {
+            for venv in venvs {
+                println!("{}:1: error: {}", cfg_path(venv), violation_message(venv));
+            }
+            Ok(())
+        }
+        CiProvider::Circleci => report_junit(venvs, Path::new(CIRCLECI_JUNIT_REPORT)),
+    }
+}
+
+/// Forward-slash path of the venv directory, as CI UIs expect
+fn venv_dir(venv: &VenvInfo) -> String {
+    let dir = venv.path.parent().unwrap_or_else(|| Path::new(""));
+    let dir = dir.to_string_lossy().replace('\\', "/");
+    if dir.is_empty() { ".".to_string() } else { dir }
+}
+
+fn cfg_path(venv: &VenvInfo) -> String {
+    venv.path.to_string_lossy().replace('\\', "/")
+}
+
+fn tracking_label(venv: &VenvInfo) -> &'static str {
+    venv.tracking.map_or("untracked", TrackingStatus::label)
+}
+
+/// One-line description of a violation, ending with the rule's page
+fn violation_message(venv: &VenvInfo) -> String {
+    format!(
+        "Python virtual environment is not ignored by Git ({}); add `{}/` to .gitignore (see {})",
+        tracking_label(venv),
+        venv_dir(venv),
+        Rule::for_venv(venv).doc_url()
+    )
+}
+
+fn report_github(venvs: &[VenvInfo]) -> Result<()> {
+    for venv in venvs {
+        println!(
+            "::error file={},title=Unignored Python virtual environment::{}",
+            escape_github_property(&cfg_path(venv)),
+            escape_github_data(&violation_message(venv))
+        );
+    }
+
+    // Outside Actions (or in a step without a summary) there is nowhere to write
+    let Some(summary_path) = env::var_os("GITHUB_STEP_SUMMARY").filter(|path| !path.is_empty())
+    else {
+        return Ok(());
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&summary_path)
+        .with_context(|| format!("Failed to open {}", Path::new(&summary_path).display()))?;
+    file.write_all(markdown_summary(venvs).as_bytes())
+        .context("Failed to write the job summary")
+}
+
+/// One entry of a GitLab Code Quality report
+#[derive(Debug, Serialize)]
+struct CodeQualityIssue {
+    description: String,
+    check_name: &'static str,
+    fingerprint: String,
+    severity: &'static str,
+    location: CodeQualityLocation,
+}
+
+#[derive(Debug, Serialize)]
+struct CodeQualityLocation {
+    path: String,
+    lines: CodeQualityLines,
+}
+
+#[derive(Debug, Serialize)]
+struct CodeQualityLines {
+    begin: u32,
+}
+
+fn report_gitlab(venvs: &[VenvInfo], report_path: &Path) -> Result<()> {
+    let issues: Vec<CodeQualityIssue> = venvs
+        .iter()
+        .map(|venv| {
+            let path = cfg_path(venv);
+            // Stable across runs, so GitLab can tell new issues from old ones
+            let fingerprint = Oid::hash_object(ObjectType::Blob, path.as_bytes())
+                .map_or_else(|_| path.clone(), |oid| oid.to_string());
+            CodeQualityIssue {
+                description: violation_message(venv),
+                check_name: "unignored-venv",
+                fingerprint,
+                severity: "major",
+                location: CodeQualityLocation {
+                    path,
+                    lines: CodeQualityLines { begin: 1 },
+                },
+            }
+        })
+        .collect();
+
+    // An empty report tells GitLab that earlier issues were fixed
+    fs::write(report_path, serde_json::to_string_pretty(&issues)?)
+        .with_context(|| format!("Failed to write {}", report_path.display()))?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    println!(
+        "\x1b[0Ksection_start:{timestamp}:unvenv[collapsed=true]\r\x1b[0Kunvenv: {} violation(s), Code Quality report in {}",
+        venvs.len(),
+        report_path.display()
+    );
+    for venv in venvs {
+        println!("{}: {}", cfg_path(venv), violation_message(venv));
+    }
+    println!("\x1b[0Ksection_end:{timestamp}:unvenv\r\x1b[0K");
+    Ok(())
+}
+
+/// TeamCity service messages: one inspection per venv and a build problem
+fn teamcity_messages(venvs: &[VenvInfo]) -> String {
+    let mut messages = String::new();
+    if venvs.is_empty() {
+        return messages;
+    }
+
+    messages.push_str(
+        "##teamcity[inspectionType id='unignored-venv' name='Unignored Python virtual environment' category='unvenv' description='Python virtual environments must be ignored by Git']\n",
+    );
+    for venv in venvs {
+        let _ = writeln!(
+            messages,
+            "##teamcity[inspection typeId='unignored-venv' message='{}' file='{}' line='1' SEVERITY='ERROR']",
+            escape_teamcity(&violation_message(venv)),
+            escape_teamcity(&cfg_path(venv))
+        );
+    }
+    let _ = writeln!(
+        messages,
+        "##teamcity[buildProblem description='{}' identity='unvenv']",
+        escape_teamcity(&format!(
+            "{} Python virtual environment(s) are not ignored by Git",
+            venvs.len()
+        ))
+    );
+    messages
+}
+
+/// Escape a service message attribute value
+fn escape_teamcity(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Code Insights report, as sent to `PUT .../reports/unvenv`
+#[derive(Debug, Serialize)]
+struct InsightsReport {
+    title: &'static str,
+    details: String,
+    reporter: &'static str,
+    result: &'static str,
+}
+
+/// Code Insights annotations, as sent to `POST .../reports/unvenv/annotations`
+#[derive(Debug, Serialize)]
+struct InsightsAnnotations {
+    annotations: Vec<InsightsAnnotation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InsightsAnnotation {
+    external_id: String,
+    path: String,
+    line: u32,
+    message: String,
+    link: String,
+    severity: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+/// Write the Code Insights request bodies into `dir`
+///
+/// unvenv has no HTTP client, and the pipeline already holds the
+/// credentials, so the upload itself is left to a `curl` step.
+fn report_bitbucket(venvs: &[VenvInfo], dir: &Path) -> Result<()> {
+    let report = InsightsReport {
+        title: "unvenv",
+        details: if venvs.is_empty() {
+            "No unignored Python virtual environments found.".to_string()
+        } else {
+            format!(
+                "{} Python virtual environment(s) are not ignored by Git.",
+                venvs.len()
+            )
+        },
+        reporter: "unvenv",
+        result: if venvs.is_empty() { "PASS" } else { "FAIL" },
+    };
+    let annotations = InsightsAnnotations {
+        annotations: venvs
+            .iter()
+            .map(|venv| InsightsAnnotation {
+                external_id: format!("unvenv:{}", cfg_path(venv)),
+                path: cfg_path(venv),
+                line: 1,
+                message: violation_message(venv),
+                link: Rule::for_venv(venv).doc_url(),
+                severity: "HIGH",
+                kind: "BUG",
+            })
+            .collect(),
+    };
+
+    for (name, body) in [
+        (BITBUCKET_REPORT, serde_json::to_string_pretty(&report)?),
+        (
+            BITBUCKET_ANNOTATIONS,
+            serde_json::to_string_pretty(&annotations)?,
+        ),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    println!(
+        "Code Insights report written to {BITBUCKET_REPORT} (PUT .../reports/unvenv) and {BITBUCKET_ANNOTATIONS} (POST .../reports/unvenv/annotations)"
+    );
+    Ok(())
+}
+
+/// Write a JUnit report with one failed test case per venv
+///
+/// A clean scan gets one passing test case, so the test tab shows that the
+/// check ran.
+fn report_junit(venvs: &[VenvInfo], report_path: &Path) -> Result<()> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"unvenv\" tests=\"{}\" failures=\"{}\">",
+        venvs.len().max(1),
+        venvs.len()
+    );
+    if venvs.is_empty() {
+        xml.push_str(
+            "    <testcase classname=\"unvenv\" name=\"No unignored Python virtual environments\"/>\n",
+        );
+    }
+    for venv in venvs {
+        let rule = Rule::for_venv(venv);
+        let path = escape_xml(&cfg_path(venv));
+        let _ = writeln!(
+            xml,
+            "    <testcase classname=\"unvenv.{}\" name=\"{path}\" file=\"{path}\">\n      <failure type=\"{}\" message=\"{}\"/>\n    </testcase>",
+            rule.id,
+            rule.id,
+            escape_xml(&violation_message(venv))
+        );
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+
+    if let Some(dir) = report_path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(report_path, xml)
+        .with_context(|| format!("Failed to write {}", report_path.display()))?;
+    println!(
+        "unvenv: {} violation(s), JUnit report in {}",
+        venvs.len(),
+        report_path.display()
+    );
+    Ok(())
+}
+
+/// Escape text for an XML attribute
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape the message of an Azure Pipelines logging command
+fn escape_azure_data(value: &str) -> String {
+    value
+        .replace('%', "%AZP25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of an Azure Pipelines logging command
+fn escape_azure_property(value: &str) -> String {
+    escape_azure_data(value)
+        .replace(';', "%3B")
+        .replace(']', "%5D")
+}
+
+/// Markdown report for job summaries and PR comments
+pub fn markdown_summary(venvs: &[VenvInfo]) -> String {
+    let mut markdown = String::from("## unvenv\n\n");
+    if venvs.is_empty() {
+        markdown.push_str("No unignored Python virtual environments found.\n");
+        return markdown;
+    }
+
+    let _ = writeln!(
+        markdown,
+        "Found {} Python virtual environment(s) that Git does not ignore.\n",
+        venvs.len()
+    );
+    markdown.push_str("| Path | Git status | Python version | Rule |\n");
+    markdown.push_str("| --- | --- | --- | --- |\n");
+    for venv in venvs {
+        let rule = Rule::for_venv(venv);
+        let _ = writeln!(
+            markdown,
+            "| `{}` | {} | {} | [{}]({}) |",
+            cfg_path(venv),
+            tracking_label(venv),
+            venv.version.as_deref().unwrap_or("unknown"),
+            rule.id,
+            rule.doc_url()
+        );
+    }
+
+    markdown.push_str("\nAdd these lines to `.gitignore`:\n\n```gitignore\n");
+    for venv in venvs {
+        let _ = writeln!(markdown, "{}/", venv_dir(venv));
+    }
+    markdown.push_str("```\n");
+    markdown
+}
+
+/// Escape the message part of a workflow command
+fn escape_github_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a `key=value` property of a workflow command
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    fn venv(path: &str, tracking: Option<TrackingStatus>) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: Some("3.12.1".to_string()),
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking,
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    #[test]
+    fn test_escape_github() {
+        assert_eq!(escape_github_data("50%\nnext"), "50%25%0Anext");
+        assert_eq!(escape_github_property("a,b:c"), "a%2Cb%3Ac");
+    }
+
+    #[test]
+    fn test_markdown_summary() {
+        let summary =
+            markdown_summary(&[venv("app/.venv/pyvenv.cfg", Some(TrackingStatus::Staged))]);
+        assert!(summary.contains("| `app/.venv/pyvenv.cfg` | staged | 3.12.1 | [UV002]("));
+        assert!(summary.contains("```gitignore\napp/.venv/\n```"));
+
+        assert!(markdown_summary(&[]).contains("No unignored"));
+    }
+
+    #[test]
+    fn test_gitlab_code_quality_report() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let report_path = temp_dir.path().join(GITLAB_CODE_QUALITY_REPORT);
+
+        report_gitlab(&[venv("venv/pyvenv.cfg", None)], &report_path)?;
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path)?)?;
+        assert_eq!(report[0]["location"]["path"], "venv/pyvenv.cfg");
+        assert_eq!(report[0]["severity"], "major");
+        assert_eq!(report[0]["fingerprint"].as_str().map(str::len), Some(40));
+
+        report_gitlab(&[], &report_path)?;
+        assert_eq!(fs::read_to_string(&report_path)?, "[]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_junit_report() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let report_path = temp_dir.path().join(CIRCLECI_JUNIT_REPORT);
+
+        report_junit(&[venv("a&b/pyvenv.cfg", None)], &report_path)?;
+        let xml = fs::read_to_string(&report_path)?;
+        assert!(xml.contains("tests=\"1\" failures=\"1\""));
+        assert!(xml.contains("name=\"a&amp;b/pyvenv.cfg\""));
+        assert!(xml.contains("<failure type=\"UV001\""));
+
+        report_junit(&[], &report_path)?;
+        assert!(fs::read_to_string(&report_path)?.contains("failures=\"0\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_provider_and_base() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| (*value).to_string())
+            }
+        };
+
+        let github = env(&[("GITHUB_ACTIONS", "true"), ("GITHUB_BASE_REF", "main")]);
+        assert_eq!(CiProvider::detect(github), Some(CiProvider::Github));
+        assert_eq!(
+            CiProvider::Github.base_ref(github).as_deref(),
+            Some("origin/main")
+        );
+
+        // A push build has an empty base ref
+        let push = env(&[("GITHUB_ACTIONS", "true"), ("GITHUB_BASE_REF", "")]);
+        assert_eq!(CiProvider::Github.base_ref(push), None);
+
+        let azure = env(&[
+            ("TF_BUILD", "True"),
+            ("SYSTEM_PULLREQUEST_TARGETBRANCH", "refs/heads/release/2"),
+        ]);
+        assert_eq!(CiProvider::detect(azure), Some(CiProvider::Azure));
+        assert_eq!(
+            CiProvider::Azure.base_ref(azure).as_deref(),
+            Some("origin/release/2")
+        );
+
+        // GitLab inside a Jenkins agent is still GitLab
+        let gitlab = env(&[
+            ("JENKINS_URL", "https://ci.example.com"),
+            ("GITLAB_CI", "true"),
+            ("CI_MERGE_REQUEST_DIFF_BASE_SHA", "abc123"),
+        ]);
+        assert_eq!(CiProvider::detect(gitlab), Some(CiProvider::Gitlab));
+        assert_eq!(
+            CiProvider::Gitlab.base_ref(gitlab).as_deref(),
+            Some("abc123")
+        );
+
+        assert_eq!(
+            CiProvider::detect(env(&[("CIRCLECI", "true")])),
+            Some(CiProvider::Circleci)
+        );
+        assert_eq!(CiProvider::detect(env(&[])), None);
+    }
+
+    #[test]
+    fn test_bitbucket_insights_files() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+
+        report_bitbucket(&[venv("venv/pyvenv.cfg", None)], temp_dir.path())?;
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp_dir.path().join(BITBUCKET_REPORT))?)?;
+        assert_eq!(report["result"], "FAIL");
+        let annotations: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+            temp_dir.path().join(BITBUCKET_ANNOTATIONS),
+        )?)?;
+        assert_eq!(annotations["annotations"][0]["path"], "venv/pyvenv.cfg");
+        assert_eq!(annotations["annotations"][0]["type"], "BUG");
+        assert_eq!(
+            annotations["annotations"][0]["externalId"],
+            "unvenv:venv/pyvenv.cfg"
+        );
+        assert!(
+            annotations["annotations"][0]["link"]
+                .as_str()
+                .is_some_and(|link| link.ends_with("/UV001.md"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_azure() {
+        assert_eq!(escape_azure_data("50%\nnext"), "50%AZP25%0Anext");
+        assert_eq!(escape_azure_property("a;b]"), "a%3Bb%5D");
+    }
+
+    #[test]
+    fn test_teamcity_messages() {
+        assert_eq!(teamcity_messages(&[]), "");
+
+        let messages = teamcity_messages(&[venv("venv/pyvenv.cfg", None)]);
+        assert!(messages.starts_with("##teamcity[inspectionType id='unignored-venv'"));
+        assert!(messages.contains("file='venv/pyvenv.cfg' line='1' SEVERITY='ERROR']"));
+        assert!(messages.contains("##teamcity[buildProblem description='1 Python"));
+        assert_eq!(escape_teamcity("it's [a|b]\n"), "it|'s |[a||b|]|n");
+    }
+
+    #[test]
+    fn test_violation_message_names_directory() {
+        let message = violation_message(&venv("venv/pyvenv.cfg", None));
+        assert!(message.contains("(untracked)"));
+        assert!(message.contains("`venv/`"));
+        assert!(message.ends_with("/docs/rules/UV001.md)"));
+    }
+}
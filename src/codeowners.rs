@@ -0,0 +1,220 @@
+//! CODEOWNERS lookup for attributing violations to teams.
+//!
+//! The file is read from the locations GitHub and GitLab check
+//! (`.github/`, the repository root, `docs/`). Patterns follow the gitignore
+//! style those platforms use: a pattern without a slash matches at any
+//! depth, a leading or inner slash anchors it to the root, a pattern
+//! matching a directory covers everything inside it, and the last matching
+//! rule wins.
+
+use std::{
+    fs,
+    path::{Component, Path},
+};
+
+/// Locations searched for the CODEOWNERS file, in order
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// One `pattern owner...` line
+#[derive(Debug)]
+struct Rule {
+    /// Pattern split at `/`, with `**` for "any number of directories"
+    segments: Vec<String>,
+    owners: Vec<String>,
+}
+
+/// Parsed CODEOWNERS rules
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+impl CodeOwners {
+    /// Load the CODEOWNERS file of the repository at `root`, if it has one
+    #[must_use]
+    pub fn load(root: &Path) -> Option<Self> {
+        LOCATIONS
+            .iter()
+            .find_map(|location| fs::read_to_string(root.join(location)).ok())
+            .map(|content| Self::parse(&content))
+    }
+
+    /// Parse CODEOWNERS content; malformed lines are skipped
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            // GitLab section headers (`[Section]`) group rules but do not change them
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = fields.next()?;
+                let owners = fields
+                    .take_while(|field| !field.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some(Rule {
+                    segments: pattern_segments(pattern),
+                    owners,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of the repository-relative `path`, or `None` if nobody owns it
+    #[must_use]
+    pub fn owners(&self, path: &Path) -> Option<&[String]> {
+        let path: Vec<&str> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        let rule = self.rules.iter().rev().find(|rule| {
+            // A match on any leading part means a directory containing the path
+            (1..=path.len()).any(|len| glob_match(&rule.segments, &path[..len]))
+        })?;
+        (!rule.owners.is_empty()).then_some(rule.owners.as_slice())
+    }
+}
+
+/// Split a pattern into segments, making unanchored patterns match at any depth
+fn pattern_segments(pattern: &str) -> Vec<String> {
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let mut segments: Vec<String> = Vec::new();
+    if !anchored {
+        segments.push("**".to_string());
+    }
+    segments.extend(
+        trimmed
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string),
+    );
+    segments
+}
+
+/// Match path segments against pattern segments
+fn glob_match(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| glob_match(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| segment_match(first, name) && glob_match(rest, path)),
+    }
+}
+
+/// Match one path segment against a pattern segment with `*` and `?`
+fn segment_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*`, and the name position it was tried at
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Default owners
+*                 @org/platform
+*.py              @org/python
+/services/        @org/services   # inline comment
+docs/**/drafts    @org/docs
+/vendor/
+";
+
+    fn owners(codeowners: &CodeOwners, path: &str) -> Option<Vec<String>> {
+        codeowners.owners(Path::new(path)).map(<[String]>::to_vec)
+    }
+
+    #[test]
+    fn test_owners_last_match_wins() {
+        let codeowners = CodeOwners::parse(CODEOWNERS);
+
+        assert_eq!(
+            owners(&codeowners, "README.md"),
+            Some(vec!["@org/platform".to_string()])
+        );
+        assert_eq!(
+            owners(&codeowners, "tools/build.py"),
+            Some(vec!["@org/python".to_string()])
+        );
+        assert_eq!(
+            owners(&codeowners, "services/api/.venv/pyvenv.cfg"),
+            Some(vec!["@org/services".to_string()])
+        );
+        assert_eq!(
+            owners(&codeowners, "docs/a/b/drafts/venv/pyvenv.cfg"),
+            Some(vec!["@org/docs".to_string()])
+        );
+        // A rule without owners leaves the path unowned
+        assert_eq!(owners(&codeowners, "vendor/lib/pyvenv.cfg"), None);
+    }
+
+    #[test]
+    fn test_anchored_patterns_do_not_match_deeper() {
+        let codeowners = CodeOwners::parse("/services/ @org/services\n");
+
+        assert_eq!(owners(&codeowners, "app/services/pyvenv.cfg"), None);
+    }
+
+    #[test]
+    fn test_segment_match() {
+        assert!(segment_match("*.py", "setup.py"));
+        assert!(segment_match("py*env?", "pyvenv1"));
+        assert!(segment_match("*", ""));
+        assert!(!segment_match("*.py", "setup.pyc"));
+        assert!(!segment_match("venv", ".venv"));
+    }
+
+    #[test]
+    fn test_load_prefers_github_directory() -> std::io::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        fs::create_dir(temp_dir.path().join(".github"))?;
+        fs::write(
+            temp_dir.path().join(".github/CODEOWNERS"),
+            "* @org/github\n",
+        )?;
+        fs::write(temp_dir.path().join("CODEOWNERS"), "* @org/root\n")?;
+
+        let codeowners = CodeOwners::load(temp_dir.path()).expect("CODEOWNERS exists");
+        assert_eq!(
+            owners(&codeowners, "pyvenv.cfg"),
+            Some(vec!["@org/github".to_string()])
+        );
+        assert!(CodeOwners::load(&temp_dir.path().join("missing")).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+//! `scan --cache-key REV`: reuse the result of an earlier scan of a commit.
+//!
+//! Retried CI jobs scan the same commit again. With `--cache-key HEAD` the
+//! exit code and violations of each scan are recorded in
+//! `.git/unvenv/commit-results`, keyed by the commit, the scanned directory,
+//! and `UNVENV_SKIP`, and a later scan of the same key prints the recorded
+//! result instead of walking the tree.
+//!
+//! A recorded result only describes the working tree if it matches the
+//! commit, so the cache is bypassed when REV is not the checked-out commit or
+//! when `git status` shows changes or untracked files.
+
+use crate::{
+    cache::{FileLock, Fingerprint, write_atomic},
+    rules,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::{Repository, StatusOptions};
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// First line of the results file; bump the version when the format changes
+const RESULTS_HEADER: &str = "unvenv-commit-results 1";
+
+/// Most results kept; older commits are dropped first
+const MAX_RESULTS: usize = 64;
+
+/// Result of an earlier scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitResult {
+    /// Key of the scan
+    key: String,
+    /// Exit code the scan returned
+    pub exit_code: i32,
+    /// Reported `pyvenv.cfg` files, relative to the scanned directory
+    pub violations: Vec<PathBuf>,
+}
+
+/// Results of earlier scans, and the key of the current one
+#[derive(Debug)]
+pub struct CommitCache {
+    path: PathBuf,
+    key: String,
+    /// Abbreviated commit, for messages
+    short: String,
+    results: Vec<CommitResult>,
+}
+
+/// Parse the results file, oldest first
+fn parse(content: &str) -> Option<Vec<CommitResult>> {
+    let mut lines = content.lines();
+    if lines.next() != Some(RESULTS_HEADER) {
+        return None;
+    }
+    let mut results: Vec<CommitResult> = Vec::new();
+    for line in lines {
+        let mut fields = line.splitn(3, ' ');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("result"), Some(key), Some(code)) => results.push(CommitResult {
+                key: key.to_string(),
+                exit_code: code.parse().ok()?,
+                violations: Vec::new(),
+            }),
+            (Some("violation"), Some(key), Some(path)) => {
+                let result = results.last_mut().filter(|result| result.key == key)?;
+                result.violations.push(PathBuf::from(path));
+            }
+            _ => return None,
+        }
+    }
+    Some(results)
+}
+
+/// Whether the working tree has changes or untracked files
+fn is_dirty(repo: &Repository) -> Result<bool> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(false)
+        .recurse_untracked_dirs(false);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .context("Failed to read the Git status")?;
+    Ok(!statuses.is_empty())
+}
+
+impl CommitCache {
+    /// The cache for a scan of `workdir` at `rev`, or `None` (with a note on
+    /// stderr) when the working tree does not match the commit
+    pub fn open(workdir: &Path, rev: &str) -> Result<Option<Self>> {
+        let repo = Repository::discover(workdir).context("--cache-key needs a Git repository")?;
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("--cache-key {rev} is not a commit"))?;
+        let head = crate::incremental::head_commit(&repo);
+
+        let bypass = if head != Some(commit.id()) {
+            Some(format!("{rev} is not the checked-out commit"))
+        } else if is_dirty(&repo)? {
+            Some("the working tree has changes or untracked files".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = bypass {
+            eprintln!(
+                "{} Not using the cached result: {reason}",
+                "Note:".yellow().bold()
+            );
+            return Ok(None);
+        }
+
+        let root = repo.workdir().unwrap_or(workdir);
+        let scope = workdir.strip_prefix(root).unwrap_or(workdir);
+        let skip = env::var(rules::SKIP_VAR).unwrap_or_default();
+        let scope = Fingerprint::EMPTY
+            .add_bytes(scope.to_string_lossy().as_bytes())
+            .add_bytes(b"\0")
+            .add_bytes(skip.as_bytes());
+
+        let path = repo.path().join("unvenv").join("commit-results");
+        let results = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| parse(&content))
+            .unwrap_or_default();
+        let short = commit.id().to_string()[..12].to_string();
+        Ok(Some(Self {
+            path,
+            key: format!("{}.{:016x}", commit.id(), scope.0),
+            short,
+            results,
+        }))
+    }
+
+    /// The recorded result of this scan, if there is one
+    pub fn lookup(&self) -> Option<&CommitResult> {
+        self.results.iter().find(|result| result.key == self.key)
+    }
+
+    /// Record the result of this scan
+    pub fn record(mut self, exit_code: i32, violations: Vec<PathBuf>) -> Result<()> {
+        let _lock = FileLock::acquire(&self.path)?;
+        // Another scan may have recorded its result since this one started
+        if let Some(results) = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| parse(&content))
+        {
+            self.results = results;
+        }
+
+        self.results.retain(|result| result.key != self.key);
+        self.results.push(CommitResult {
+            key: self.key,
+            exit_code,
+            violations,
+        });
+        let excess = self.results.len().saturating_sub(MAX_RESULTS);
+        self.results.drain(..excess);
+
+        let mut content = format!("{RESULTS_HEADER}\n");
+        for result in &self.results {
+            let _ = writeln!(content, "result {} {}", result.key, result.exit_code);
+            for violation in &result.violations {
+                if let Some(violation) = violation.to_str() {
+                    let _ = writeln!(content, "violation {} {violation}", result.key);
+                }
+            }
+        }
+        write_atomic(&self.path, &content)
+    }
+
+    /// Print a recorded result in place of a scan, returning its exit code
+    pub fn replay(&self, result: &CommitResult, is_tty: bool) -> i32 {
+        if result.violations.is_empty() {
+            let message = format!(
+                "Commit {} was already scanned without violations (--cache-key); skipping the scan",
+                self.short
+            );
+            if is_tty {
+                println!("{} {message}", "OK:".green().bold());
+            } else {
+                println!("OK: {message}");
+            }
+            return result.exit_code;
+        }
+
+        let message = format!(
+            "{} virtual environment(s) not ignored by Git at commit {} (recorded by an earlier scan; run without --cache-key for details):",
+            result.violations.len(),
+            self.short
+        );
+        if is_tty {
+            println!("{} {message}", "WARNING:".yellow().bold());
+        } else {
+            println!("WARNING: {message}");
+        }
+        for violation in &result.violations {
+            let path = violation.to_string_lossy().replace('\\', "/");
+            if is_tty {
+                println!("  📁 {}", path.cyan());
+            } else {
+                println!("  {path}");
+            }
+        }
+        result.exit_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_lookup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join("README"), "hello\n")?;
+        git(dir, &["add", "README"]);
+        git(dir, &["commit", "-qm", "init"]);
+
+        let cache = CommitCache::open(dir, "HEAD")?.expect("tree is clean");
+        assert_eq!(cache.lookup(), None);
+        cache.record(2, vec![PathBuf::from("tools/venv/pyvenv.cfg")])?;
+
+        let cache = CommitCache::open(dir, "HEAD")?.expect("tree is clean");
+        let result = cache.lookup().expect("result was recorded");
+        assert_eq!(result.exit_code, 2);
+        assert_eq!(result.violations, [PathBuf::from("tools/venv/pyvenv.cfg")]);
+
+        // An untracked file may be a new venv
+        fs::write(dir.join("notes.txt"), "")?;
+        assert!(CommitCache::open(dir, "HEAD")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_format() {
+        assert_eq!(parse("unvenv-commit-results 2\n"), None);
+        assert_eq!(
+            parse(
+                "unvenv-commit-results 1\nresult abc 0\nresult def 2\nviolation def a/pyvenv.cfg\n"
+            )
+            .map(|results| results.len()),
+            Some(2)
+        );
+        assert_eq!(
+            parse("unvenv-commit-results 1\nresult abc 0\nviolation def a/pyvenv.cfg\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//! Project configuration in `.unvenv.toml`.
+//!
+//! The file is looked up in the current directory and its parents, up to the
+//! root of the repository. It holds the exit code of each outcome, the size
+//! below which a tooling directory rule lets a directory pass, whether the
+//! pre-commit hook lets a commit through when it runs out of time, and which
+//! releases `unvenv update` follows:
+//!
+//! ```toml
+//! [exit-codes]
+//! violations = 10   # instead of 2
+//! partial = 11
+//!
+//! [min-size]
+//! pip-cache = "5M"  # rule ID or name; bytes, or K/M/G (1024-based)
+//!
+//! [hook]
+//! time-budget = 2000  # ms `hook run` may take; 800 by default
+//! fail-open = true    # commit when it runs out of time
+//!
+//! [update]
+//! channel = "beta"  # or "stable"
+//! ```
+//!
+//! Each section is a flat table; strings and bare numbers or booleans are
+//! accepted alike, so `violations = 10` and `violations = "10"` mean the same.
+
+use crate::{
+    installer::Channel,
+    rules::{self, Rule},
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::Spanned;
+
+/// Name of the configuration file
+pub const FILE_NAME: &str = ".unvenv.toml";
+
+/// Every way a run can end, each with its own exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Nothing to report
+    Clean,
+    /// Venvs that Git does not ignore
+    Violations,
+    /// A limit stopped the scan before it covered the whole tree
+    Partial,
+    /// Invalid command line or configuration
+    ConfigError,
+    /// `--require-git` found no repository to scan
+    NoRepository,
+    /// Anything else that went wrong
+    InternalError,
+    /// `scan --fix` remediated every violation it found
+    Fixed,
+}
+
+impl Outcome {
+    /// All outcomes, in the order of their default codes
+    pub const ALL: [Self; 7] = [
+        Self::Clean,
+        Self::InternalError,
+        Self::Violations,
+        Self::Partial,
+        Self::ConfigError,
+        Self::NoRepository,
+        Self::Fixed,
+    ];
+
+    /// Key of the outcome in `[exit-codes]`
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::Clean => "clean",
+            Self::Violations => "violations",
+            Self::Partial => "partial",
+            Self::ConfigError => "config-error",
+            Self::NoRepository => "no-repository",
+            Self::InternalError => "internal-error",
+            Self::Fixed => "fixed",
+        }
+    }
+
+    /// Exit code used unless the configuration says otherwise
+    ///
+    /// Commands return these codes; [`ExitCodes::remap`] translates them.
+    pub const fn default_code(self) -> i32 {
+        match self {
+            Self::Clean => 0,
+            Self::InternalError => 1,
+            Self::Violations => 2,
+            Self::Partial => 3,
+            Self::ConfigError => 4,
+            Self::NoRepository => 5,
+            Self::Fixed => 6,
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            Self::Clean => 0,
+            Self::InternalError => 1,
+            Self::Violations => 2,
+            Self::Partial => 3,
+            Self::ConfigError => 4,
+            Self::NoRepository => 5,
+            Self::Fixed => 6,
+        }
+    }
+}
+
+/// Exit code of each outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitCodes([i32; 7]);
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self(Outcome::ALL.map(Outcome::default_code))
+    }
+}
+
+impl ExitCodes {
+    /// Exit code of `outcome`
+    pub const fn code(&self, outcome: Outcome) -> i32 {
+        self.0[outcome.index()]
+    }
+
+    /// Translate a default exit code returned by a command
+    ///
+    /// Codes that belong to no outcome are passed through.
+    pub fn remap(&self, code: i32) -> i32 {
+        Outcome::ALL
+            .into_iter()
+            .find(|outcome| outcome.default_code() == code)
+            .map_or(code, |outcome| self.code(outcome))
+    }
+}
+
+/// Rules about tooling directories, the only ones a size threshold applies to
+const SIZED_RULES: [Rule; 3] = [
+    rules::UNIGNORED_DIRENV,
+    rules::PYENV_INSTALL,
+    rules::PIP_CACHE,
+];
+
+/// Settings from `.unvenv.toml`
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Exit code of each outcome
+    pub exit_codes: ExitCodes,
+    /// Size in bytes a directory must reach to violate each rule
+    pub min_sizes: Vec<(Rule, u64)>,
+    /// How `hook run` deals with slow scans
+    pub hook: HookSettings,
+    /// Release channel for `unvenv update`
+    pub update: UpdateSettings,
+}
+
+/// `[hook]` settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookSettings {
+    /// Time `hook run` may scan for, instead of its default
+    pub time_budget: Option<Duration>,
+    /// Whether `hook run` lets the commit through when it runs out of time
+    pub fail_open: bool,
+}
+
+impl HookSettings {
+    /// Apply `key = value` from the `[hook]` section, or say what is wrong
+    /// with it
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "fail-open" => {
+                self.fail_open = match value {
+                    "true" => true,
+                    "false" => false,
+                    value => {
+                        return Err(format!(
+                            "`{value}` is not a boolean; expected true or false"
+                        ));
+                    }
+                };
+            }
+            "time-budget" => {
+                let millis = value.parse::<u64>().ok().filter(|&millis| millis > 0);
+                self.time_budget = Some(Duration::from_millis(millis.ok_or_else(|| {
+                    format!("`{value}` is not a time budget; expected milliseconds, e.g. 2000")
+                })?));
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section [hook]; expected time-budget or fail-open"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `[update]` settings
+///
+/// A mirror of the releases is only taken from install.sh's environment
+/// variables: the file is part of the repository, and whoever can commit to
+/// it must not be able to point `unvenv update` at their own binaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateSettings {
+    /// Channel used when `update` is not given `--channel`
+    pub channel: Option<Channel>,
+}
+
+impl UpdateSettings {
+    /// Apply `key = value` from the `[update]` section, or say what is wrong
+    /// with it
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "channel" => {
+                self.channel = Some(Channel::from_name(value).ok_or_else(|| {
+                    format!("`{value}` is not a release channel; expected stable or beta")
+                })?);
+                Ok(())
+            }
+            "api-url" | "download-url" | "releases-url" => Err(format!(
+                "`{key}` is not read from {FILE_NAME}, which anyone who can commit to the repository controls; set {} in the environment instead",
+                match key {
+                    "api-url" => "UNVENV_API_URL",
+                    "download-url" => "GITHUB_DOWNLOAD_URL",
+                    _ => "RELEASES_URL",
+                }
+            )),
+            key => Err(format!(
+                "unknown setting `{key}` in section [update]; expected channel"
+            )),
+        }
+    }
+}
+
+impl Config {
+    /// Size a directory must reach to violate `rule`, if one is configured
+    pub fn min_size(&self, rule: Rule) -> Option<u64> {
+        self.min_sizes
+            .iter()
+            .find(|(sized, _)| *sized == rule)
+            .map(|(_, bytes)| *bytes)
+    }
+}
+
+/// Bytes in a size like `512`, `64K`, `5MB`, or `1GiB`; units are
+/// 1024-based
+fn parse_size(value: &str) -> Option<u64> {
+    let digits = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let shift = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 10,
+        "m" | "mb" | "mib" => 20,
+        "g" | "gb" | "gib" => 30,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/// A configuration file that cannot be used
+#[derive(Debug, thiserror::Error)]
+#[error("{}:{line}: {message}", path.display())]
+pub struct ConfigError {
+    /// The file
+    pub path: PathBuf,
+    /// Line of the problem, counted from 1
+    pub line: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+/// Settings of one `[section]`, each with where in the file it is
+type Section = BTreeMap<Spanned<String>, Spanned<toml::Value>>;
+
+/// Line of the byte at `offset` in `content`, counted from 1
+fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// A setting's value as written, without the quotes of a string
+fn text(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Parse the contents of a configuration file read from `path`
+pub fn parse(content: &str, path: &Path) -> Result<Config, ConfigError> {
+    let error = |line, message: String| ConfigError {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let sections: BTreeMap<String, Section> = toml::from_str(content).map_err(|e| {
+        let line = e.span().map_or(1, |span| line_at(content, span.start));
+        error(line, e.message().trim().to_string())
+    })?;
+
+    let mut config = Config::default();
+    let settings = sections.iter().flat_map(|(section, settings)| {
+        settings.iter().map(move |(key, value)| {
+            (
+                line_at(content, key.span().start),
+                section.as_str(),
+                key.get_ref().as_str(),
+                text(value.get_ref()),
+            )
+        })
+    });
+    for (line, section, key, value) in settings {
+        match section {
+            "exit-codes" => {
+                let outcome = Outcome::ALL
+                    .into_iter()
+                    .find(|outcome| outcome.key() == key)
+                    .ok_or_else(|| {
+                        let keys: Vec<_> = Outcome::ALL.map(Outcome::key).into();
+                        error(
+                            line,
+                            format!(
+                                "unknown outcome `{key}`; expected one of {}",
+                                keys.join(", ")
+                            ),
+                        )
+                    })?;
+                let code = value
+                    .parse::<u8>()
+                    .map_err(|_| error(line, format!("`{value}` is not an exit code (0-255)")))?;
+                config.exit_codes.0[outcome.index()] = i32::from(code);
+            }
+            "min-size" => {
+                let rule = Rule::find(key)
+                    .filter(|rule| SIZED_RULES.contains(rule))
+                    .ok_or_else(|| {
+                        let ids: Vec<_> = SIZED_RULES.map(Rule::describe).into();
+                        error(
+                            line,
+                            format!(
+                                "`{key}` takes no size threshold; expected one of {}",
+                                ids.join(", ")
+                            ),
+                        )
+                    })?;
+                let bytes = parse_size(&value).ok_or_else(|| {
+                    error(
+                        line,
+                        format!("`{value}` is not a size (bytes, or a number with K, M, or G)"),
+                    )
+                })?;
+                config.min_sizes.retain(|(sized, _)| *sized != rule);
+                config.min_sizes.push((rule, bytes));
+            }
+            "hook" => config
+                .hook
+                .set(key, &value)
+                .map_err(|message| error(line, message))?,
+            "update" => config
+                .update
+                .set(key, &value)
+                .map_err(|message| error(line, message))?,
+            section => {
+                return Err(error(
+                    line,
+                    format!("unknown setting `{key}` in section [{section}]"),
+                ));
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Find the configuration file for `dir`: the nearest one in `dir` or a
+/// parent, not looking above the repository root
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let path = dir.join(FILE_NAME);
+        if path.is_file() {
+            return Some(path);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Load the configuration that applies to `dir`, or the defaults if there is
+/// none
+pub fn load(dir: &Path) -> Result<Config> {
+    let Some(path) = find(dir) else {
+        return Ok(Config::default());
+    };
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&content, &path).map_err(anyhow::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_exit_codes_remap() -> Result<()> {
+        let config = parse(
+            "# wrapper scripts use 2\n[exit-codes]\nviolations = 10\nconfig-error = \"64\"\n",
+            Path::new(FILE_NAME),
+        )?;
+        let codes = config.exit_codes;
+        assert_eq!(codes.remap(0), 0);
+        assert_eq!(codes.remap(1), 1);
+        assert_eq!(codes.remap(2), 10);
+        assert_eq!(codes.remap(3), 3);
+        assert_eq!(codes.remap(42), 42);
+        assert_eq!(codes.code(Outcome::ConfigError), 64);
+        assert_eq!(ExitCodes::default().remap(2), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_settings() -> Result<()> {
+        let path = Path::new(FILE_NAME);
+        let error = parse("[exit-codes]\nviolation = 10\n", path).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("unknown outcome `violation`"));
+
+        assert!(parse("[exit-codes]\npartial = 300\n", path).is_err());
+        assert!(parse("[exit-codes]\npartial = -1\n", path).is_err());
+        assert!(parse("[scan]\ndepth = 3\n", path).is_err());
+        assert!(parse("[hook]\nfail-open = yes\n", path).is_err());
+        assert!(parse("[hook]\nfail-open = true\n", path)?.hook.fail_open);
+        assert!(!parse("", path)?.hook.fail_open);
+        assert!(parse("[hook]\ntime-budget = 0\n", path).is_err());
+        assert!(parse("[hook]\ntime-budget = 2s\n", path).is_err());
+        assert_eq!(
+            parse("[hook]\ntime-budget = 2000\n", path)?
+                .hook
+                .time_budget,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(parse("", path)?.hook.time_budget, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_sizes() -> Result<()> {
+        let config = parse(
+            "[min-size]\npip-cache = \"5M\"\nUV010 = \"64KiB\"\n",
+            Path::new(FILE_NAME),
+        )?;
+        assert_eq!(config.min_size(rules::PIP_CACHE), Some(5 << 20));
+        assert_eq!(config.min_size(rules::PYENV_INSTALL), Some(64 << 10));
+        assert_eq!(config.min_size(rules::UNIGNORED_DIRENV), None);
+
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("2 GB"), Some(2 << 30));
+        assert_eq!(parse_size("1.5M"), None);
+        assert_eq!(parse_size("5 parsecs"), None);
+
+        let path = Path::new(FILE_NAME);
+        // Venvs fail at any size
+        let error = parse("[min-size]\nUV001 = \"1M\"\n", path).unwrap_err();
+        assert!(error.message.contains("takes no size threshold"));
+        assert!(parse("[min-size]\npip-cache = \"lots\"\n", path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_settings() -> Result<()> {
+        let path = Path::new(FILE_NAME);
+        let config = parse("[update]\nchannel = \"beta\"\n", path)?;
+        assert_eq!(config.update.channel, Some(Channel::Beta));
+        assert!(parse("[update]\nchannel = nightly\n", path).is_err());
+        assert!(parse("[update]\nmirror = \"https://mirror\"\n", path).is_err());
+
+        // A mirror in the repository would let a commit redirect updates
+        let error = parse("[update]\napi-url = \"https://mirror/api\"\n", path).unwrap_err();
+        assert!(error.message.contains("UNVENV_API_URL"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let error = parse(
+            "[hook]\nfail-open = true\ntime-budget = 2s\n",
+            Path::new(FILE_NAME),
+        )
+        .unwrap_err();
+        assert_eq!(error.line, 3);
+    }
+
+    #[test]
+    fn test_find_stops_at_repository_root() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git"))?;
+        fs::create_dir_all(repo.join("src/pkg"))?;
+        fs::write(temp_dir.path().join(FILE_NAME), "")?;
+
+        assert_eq!(find(&repo.join("src/pkg")), None);
+
+        fs::write(repo.join(FILE_NAME), "")?;
+        assert_eq!(find(&repo.join("src/pkg")), Some(repo.join(FILE_NAME)));
+        Ok(())
+    }
+}
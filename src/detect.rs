@@ -0,0 +1,75 @@
+//! How a scan recognizes venv roots and nested repositories.
+//!
+//! Shared by the library [`Scanner`](crate::Scanner) and the CLI walk, so
+//! both agree on what counts as a venv and where the outer repository stops.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Locate the `pyvenv.cfg` in `dir` under the name it has on disk
+///
+/// On case-insensitive filesystems `pyvenv.cfg` also opens `PyVenv.CFG`, but
+/// Git and the report need the real name. With `ignore_case`, case variants
+/// are matched on case-sensitive filesystems too.
+#[must_use]
+pub fn find_venv_cfg(dir: &Path, ignore_case: bool) -> Option<PathBuf> {
+    let exact = dir.join("pyvenv.cfg");
+    let exact_exists = fs::symlink_metadata(&exact).is_ok_and(|meta| meta.file_type().is_file());
+    if !exact_exists && !ignore_case {
+        return None;
+    }
+
+    let mut variant = None;
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(Result::ok) {
+            let is_file = entry.file_type().is_ok_and(|file_type| file_type.is_file());
+            let name = entry.file_name();
+            if !is_file
+                || !name
+                    .to_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case("pyvenv.cfg"))
+            {
+                continue;
+            }
+            // Prefer the exact name when several spellings exist side by side
+            if name == "pyvenv.cfg" {
+                return Some(exact);
+            }
+            variant.get_or_insert_with(|| entry.path());
+        }
+    }
+
+    variant.or_else(|| exact_exists.then_some(exact))
+}
+
+/// Whether `dir` is the work tree of a submodule or nested repository
+///
+/// Git reports such a directory as a single entry of the outer repository,
+/// so the status of files inside it cannot be asked there.
+#[must_use]
+pub fn is_nested_repository(dir: &Path) -> bool {
+    fs::symlink_metadata(dir.join(".git")).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_venv_cfg_case_variants() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::write(dir.join("PyVenv.CFG"), "home = /usr/bin\n")?;
+
+        assert_eq!(find_venv_cfg(dir, true), Some(dir.join("PyVenv.CFG")));
+        // Case-insensitive filesystems resolve the exact name to the variant
+        let case_insensitive = dir.join("pyvenv.cfg").exists();
+        assert_eq!(find_venv_cfg(dir, false).is_some(), case_insensitive);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! direnv's `layout python`, the usual way venvs end up in a repository.
+//!
+//! An `.envrc` with `layout python` makes direnv create a venv in
+//! `.direnv/python-X.Y/` next to it as soon as someone enters the directory.
+//! The scan marks such venvs so the report can suggest ignoring `.direnv/`
+//! rather than the versioned directory inside it, and `doctor` checks that
+//! `.direnv/` is ignored in repositories that use the layout.
+
+use git2::Repository;
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory direnv keeps its layouts in
+pub const LAYOUT_DIR: &str = ".direnv";
+
+/// File name of direnv configuration files
+const ENVRC: &str = ".envrc";
+
+/// Layouts that create a venv in `.direnv/`
+const PYTHON_LAYOUTS: [&str; 3] = ["python", "python3", "pyenv"];
+
+/// Whether an `.envrc` uses a layout that creates a Python venv
+pub fn uses_layout_python(envrc: &str) -> bool {
+    envrc.lines().any(|line| {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("layout") => words
+                .next()
+                .is_some_and(|layout| PYTHON_LAYOUTS.contains(&layout)),
+            Some(command) => command
+                .strip_prefix("layout_")
+                .is_some_and(|layout| PYTHON_LAYOUTS.contains(&layout)),
+            None => false,
+        }
+    })
+}
+
+/// The `.envrc` whose layout created the venv with its `pyvenv.cfg` at
+/// `cfg_path`, relative to the scan root like `rel_path`
+pub fn layout_envrc(cfg_path: &Path, rel_path: &Path) -> Option<PathBuf> {
+    let rel_venv = rel_path.parent()?;
+    let depth = rel_venv
+        .ancestors()
+        .position(|dir| dir.file_name() == Some(OsStr::new(LAYOUT_DIR)))?;
+    let rel_layout = rel_venv.ancestors().nth(depth)?;
+    let layout = cfg_path.parent()?.ancestors().nth(depth)?;
+
+    let content = fs::read_to_string(layout.parent()?.join(ENVRC)).ok()?;
+    uses_layout_python(&content).then(|| rel_layout.with_file_name(ENVRC))
+}
+
+/// Committed `.envrc` files and the one at the root that use a Python
+/// layout, relative to the working tree
+pub fn layout_envrcs(repo: &Repository) -> Vec<PathBuf> {
+    let Some(workdir) = repo.workdir() else {
+        return Vec::new();
+    };
+    let mut envrcs = vec![PathBuf::from(ENVRC)];
+    if let Ok(index) = repo.index() {
+        envrcs.extend(
+            index
+                .iter()
+                .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()))
+                .filter(|path| path.file_name() == Some(OsStr::new(ENVRC))),
+        );
+    }
+    envrcs.sort();
+    envrcs.dedup();
+    envrcs.retain(|envrc| {
+        fs::read_to_string(workdir.join(envrc)).is_ok_and(|content| uses_layout_python(&content))
+    });
+    envrcs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_uses_layout_python() {
+        assert!(uses_layout_python("export FOO=1\nlayout python\n"));
+        assert!(uses_layout_python("layout python3\n"));
+        assert!(uses_layout_python("  layout_pyenv 3.12.1\n"));
+        assert!(!uses_layout_python("# layout python\nlayout node\n"));
+        assert!(!uses_layout_python("use nix\n"));
+    }
+
+    #[test]
+    fn test_layout_envrc() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("svc/.direnv/python-3.12"))?;
+        fs::write(root.join("svc/.envrc"), "layout python3\n")?;
+        fs::create_dir_all(root.join("web/.direnv/python-3.12"))?;
+        fs::write(root.join("web/.envrc"), "use nix\n")?;
+
+        let rel = Path::new("svc/.direnv/python-3.12/pyvenv.cfg");
+        assert_eq!(
+            layout_envrc(&root.join(rel), rel),
+            Some(PathBuf::from("svc/.envrc"))
+        );
+        let rel = Path::new("web/.direnv/python-3.12/pyvenv.cfg");
+        assert_eq!(layout_envrc(&root.join(rel), rel), None);
+        let rel = Path::new("svc/venv/pyvenv.cfg");
+        assert_eq!(layout_envrc(&root.join(rel), rel), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Errors returned by the library API.
+
+use crate::model::ScanReport;
+use std::{io, path::PathBuf};
+
+/// Result type of the library API
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Why a library call failed
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A file could not be read
+    #[error("failed to read {}", path.display())]
+    Io {
+        /// The file
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// Git could not answer an ignore or status query
+    #[error("failed to query Git about {}", path.display())]
+    Git {
+        /// Path the query was about, relative to the repository root
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: git2::Error,
+    },
+    /// The scanner was set up with something it cannot use
+    #[error("invalid scanner configuration: {0}")]
+    Config(String),
+    /// The scan stopped before covering the whole tree; the report holds
+    /// what was found up to that point
+    #[error(
+        "scan stopped early with {} violation(s) found so far",
+        .0.violations.len()
+    )]
+    PartialScan(Box<ScanReport>),
+}
+
+impl Error {
+    /// Wrap an error from the ignore matcher, which only fails on Git queries
+    pub(crate) fn git(path: PathBuf, error: anyhow::Error) -> Self {
+        let source = error
+            .downcast::<git2::Error>()
+            .unwrap_or_else(|other| git2::Error::from_str(&format!("{other:#}")));
+        Self::Git { path, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_git_keeps_the_libgit2_error() {
+        let error = Err::<(), _>(git2::Error::from_str("bad index"))
+            .context("Failed to check Git ignore status of venv")
+            .unwrap_err();
+
+        let error = Error::git(PathBuf::from("venv"), error);
+        assert_eq!(error.to_string(), "failed to query Git about venv");
+        let Error::Git { source, .. } = error else {
+            panic!("expected a Git error");
+        };
+        assert_eq!(source.message(), "bad index");
+    }
+
+    #[test]
+    fn test_partial_scan_message() {
+        let report = ScanReport::new("1.6.0", PathBuf::from("."), Vec::new());
+        assert_eq!(
+            Error::PartialScan(Box::new(report)).to_string(),
+            "scan stopped early with 0 violation(s) found so far"
+        );
+    }
+}
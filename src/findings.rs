@@ -0,0 +1,256 @@
+//! The policies every scan result goes through before it is reported.
+//!
+//! A venv must pass or fail the same way whichever command found it, so
+//! `scan`, `report`, `hook run`, `serve`, `status`, `stats record`, `audit`,
+//! and `scan --recursive-repos` all hand the walk's outcome to [`apply`]:
+//!
+//! 1. `.unvenv-allow` markers suppress the venvs below them
+//! 2. package policies (`[tool.unvenv] policy`) warn about or drop the venvs
+//!    of a package
+//! 3. `UNVENV_SKIP` drops the venvs and tooling directories of the named
+//!    rules
+//! 4. tracked files pointing into venvs are found (not with `--no-git`), and
+//!    the skips apply to them as well
+//! 5. tooling directories below their `[min-size]` threshold pass
+//!
+//! What is left fails the scan ([`Findings::fails`]) in every command, and is
+//! what `scan --format json` prints, one [`JsonFinding`] per venv, tooling
+//! directory, and reference.
+
+use crate::{
+    ScanArgs, ScanOutcome, TrackingStatus, VenvInfo,
+    allow::{Allowances, Suppressed},
+    config, incremental, metrics,
+    packages::Packages,
+    references::{self, Reference},
+    rules::{Rule, Skips},
+};
+use anyhow::Result;
+use git2::Repository;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// What the policies made of a scan, besides the venvs and tooling
+/// directories left in the outcome
+#[derive(Debug, Default)]
+pub struct Findings {
+    /// Venvs below an allow marker
+    pub suppressed: Vec<Suppressed>,
+    /// Venvs a package policy only warns about
+    pub warned: Vec<VenvInfo>,
+    /// Tracked files pointing into a repo-local venv
+    pub references: Vec<Reference>,
+    /// How many findings `skips` dropped, for [`Skips::announce`]
+    pub skipped: usize,
+}
+
+impl Findings {
+    /// Whether the scan fails: `outcome`, already run through the policies,
+    /// still holds a venv or tooling directory, or a reference was found
+    pub fn fails(&self, outcome: &ScanOutcome) -> bool {
+        !outcome.venvs.is_empty() || !outcome.artifacts.is_empty() || !self.references.is_empty()
+    }
+
+    /// How the scan ends: failed, clean, or partial when nothing was found
+    /// but a limit left part of the tree unscanned
+    pub fn outcome(&self, outcome: &ScanOutcome) -> config::Outcome {
+        if self.fails(outcome) {
+            config::Outcome::Violations
+        } else if outcome.limit.is_some() {
+            config::Outcome::Partial
+        } else {
+            config::Outcome::Clean
+        }
+    }
+}
+
+/// Run `outcome` through every policy, leaving only the venvs and tooling
+/// directories that fail the scan in it
+pub fn apply(
+    workdir: &Path,
+    args: &ScanArgs,
+    skips: &Skips,
+    packages: &mut Packages,
+    outcome: &mut ScanOutcome,
+) -> Result<Findings> {
+    let (venvs, suppressed) = Allowances::new(workdir).apply(std::mem::take(&mut outcome.venvs));
+    let (mut venvs, warned) = packages.apply_policies(venvs);
+
+    let mut references = if args.no_git {
+        // Files are only tracked in a repository, and `--no-git` skips it
+        Vec::new()
+    } else {
+        references::find(workdir, reference_scope(workdir, args)?.as_deref())?
+    };
+    let before = venvs.len() + outcome.artifacts.len() + references.len();
+    skips.filter(&mut venvs);
+    outcome
+        .artifacts
+        .retain(|artifact| !skips.contains(artifact.kind.rule()));
+    references.retain(|reference| !skips.contains(reference.rule()));
+    let skipped = before - venvs.len() - outcome.artifacts.len() - references.len();
+    outcome.venvs = venvs;
+
+    let config = config::load(workdir)?;
+    outcome.artifacts.retain(|artifact| {
+        config
+            .min_size(artifact.kind.rule())
+            .is_none_or(|min| metrics::dir_bytes(&workdir.join(&artifact.path)) >= min)
+    });
+
+    Ok(Findings {
+        suppressed,
+        warned,
+        references,
+        skipped,
+    })
+}
+
+/// The files to look for references in, relative to `workdir`: those passed
+/// with `--pre-commit`, the staged ones with `--staged`, or `None` for every
+/// tracked file
+fn reference_scope(workdir: &Path, args: &ScanArgs) -> Result<Option<Vec<PathBuf>>> {
+    if args.pre_commit {
+        return Ok(Some(args.files.clone()));
+    }
+    if !args.staged {
+        return Ok(None);
+    }
+    let Ok(repo) = Repository::discover(workdir) else {
+        return Ok(None);
+    };
+    let Some(root) = repo.workdir() else {
+        return Ok(None);
+    };
+    let staged = incremental::staged_paths(&repo)?;
+    Ok(Some(
+        staged
+            .iter()
+            .filter_map(|path| {
+                root.join(path)
+                    .strip_prefix(workdir)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect(),
+    ))
+}
+
+/// One entry of the `scan --format json` array
+///
+/// Keys follow `pyvenv.cfg` spelling; the venv settings are absent for
+/// tooling directories and references.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct JsonFinding {
+    /// `venv`, `artifact`, or `reference`
+    pub kind: &'static str,
+    /// ID of the violated rule, e.g. `UV001`
+    pub rule: &'static str,
+    /// The `pyvenv.cfg`, the directory, or the file, relative to the scanned
+    /// directory with `/` separators
+    pub path: String,
+    /// 1-based line of a reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_system_site_packages: Option<bool>,
+    /// Whether Git ignores the path; always `false` for a finding, and kept
+    /// so consumers need not know that
+    pub ignored: bool,
+    /// `untracked`, `staged`, or `committed` for a venv in a repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking: Option<&'static str>,
+}
+
+impl JsonFinding {
+    fn new(kind: &'static str, rule: Rule, path: &Path) -> Self {
+        Self {
+            kind,
+            rule: rule.id,
+            path: path.to_string_lossy().replace('\\', "/"),
+            line: None,
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            ignored: false,
+            tracking: None,
+        }
+    }
+}
+
+/// Every finding that fails the scan, in the `scan --format json` layout
+pub fn json_findings(outcome: &ScanOutcome, references: &[Reference]) -> Vec<JsonFinding> {
+    let venvs = outcome.venvs.iter().map(|venv| JsonFinding {
+        home: venv.home.clone(),
+        version: venv.version.clone(),
+        include_system_site_packages: venv
+            .include_system_site_packages
+            .as_deref()
+            .and_then(|value| value.to_ascii_lowercase().parse().ok()),
+        tracking: venv.tracking.map(|tracking| match tracking {
+            TrackingStatus::Untracked => "untracked",
+            TrackingStatus::Staged => "staged",
+            TrackingStatus::Committed => "committed",
+        }),
+        ..JsonFinding::new("venv", Rule::for_venv(venv), &venv.path)
+    });
+    let artifacts = outcome
+        .artifacts
+        .iter()
+        .map(|artifact| JsonFinding::new("artifact", artifact.kind.rule(), &artifact.path));
+    let references = references.iter().map(|reference| JsonFinding {
+        line: Some(reference.line),
+        ..JsonFinding::new("reference", reference.rule(), &reference.file)
+    });
+    venvs.chain(artifacts).chain(references).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{allow::MARKER, find_unignored_venvs};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_apply_runs_every_policy() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        for venv in ["app/venv", "libs/core/venv", "fixtures/venv"] {
+            fs::create_dir_all(dir.join(venv))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+        fs::write(
+            dir.join("libs/core/pyproject.toml"),
+            "[project]\nname = \"core\"\n\n[tool.unvenv]\npolicy = \"warn\"\n",
+        )?;
+        fs::write(dir.join("fixtures").join(MARKER), "test data\n")?;
+        fs::create_dir_all(dir.join(".direnv"))?;
+
+        let args = ScanArgs {
+            no_git: true,
+            ..ScanArgs::default()
+        };
+        let mut outcome = find_unignored_venvs(dir, &args, false)?;
+        let (skips, _) = Skips::parse("UV009");
+        let findings = apply(dir, &args, &skips, &mut Packages::new(dir), &mut outcome)?;
+
+        let paths = |venvs: &[VenvInfo]| -> Vec<PathBuf> {
+            venvs.iter().map(|venv| venv.path.clone()).collect()
+        };
+        assert_eq!(paths(&outcome.venvs), [Path::new("app/venv/pyvenv.cfg")]);
+        assert_eq!(
+            paths(&findings.warned),
+            [Path::new("libs/core/venv/pyvenv.cfg")]
+        );
+        assert_eq!(findings.suppressed.len(), 1);
+        assert!(outcome.artifacts.is_empty());
+        assert_eq!(findings.skipped, 1);
+        Ok(())
+    }
+}
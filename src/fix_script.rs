@@ -0,0 +1,358 @@
+//! `scan --emit-script FILE`: the fix for the findings, as scripts to review.
+//!
+//! Some teams want to read every change before it touches the repository.
+//! Instead of printing instructions, `--emit-script fix.sh` writes an
+//! executable POSIX shell script, and a PowerShell script next to it
+//! (`fix.ps1`), that append the missing `.gitignore` entries and run
+//! `git rm -r --cached` for each venv already in the index. Both scripts
+//! change into the scanned directory first and are safe to run twice:
+//! entries already in `.gitignore` are not added again.
+//!
+//! `scan --fix` applies the same plan directly: it appends the entries to
+//! the scanned directory's `.gitignore` and removes the tracked venvs from
+//! the index, leaving both changes for the user to commit.
+
+use crate::{VERSION, VenvInfo, hook};
+use anyhow::{Context, Result};
+use git2::Repository;
+use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+use unvenv::Action;
+
+/// What the scripts do, derived from the findings
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Plan {
+    /// Lines to make sure `.gitignore` contains
+    ignores: BTreeSet<String>,
+    /// Venv directories to remove from the index
+    untrack: BTreeSet<String>,
+}
+
+impl Plan {
+    /// The ignore and untrack actions for `venvs`, leaving out the
+    /// alternative of deleting them
+    fn new(venvs: &[VenvInfo]) -> Self {
+        Self::from_actions(venvs.iter().flat_map(|venv| venv.to_violation().actions))
+    }
+
+    /// The ignore and untrack steps among `actions`
+    fn from_actions(actions: impl IntoIterator<Item = Action>) -> Self {
+        let mut plan = Self::default();
+        for action in actions {
+            match action {
+                Action::AddGitignoreEntry { entry, .. } => {
+                    plan.ignores.insert(entry);
+                }
+                Action::GitRmCached { path } => {
+                    plan.untrack.insert(path.to_string_lossy().into_owned());
+                }
+                _ => {}
+            }
+        }
+        plan
+    }
+}
+
+/// `value` as a single-quoted POSIX shell word
+pub fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `value` as a single-quoted PowerShell string
+fn ps_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// The POSIX shell script for `plan`, run in `workdir`
+fn shell_script(plan: &Plan, workdir: &Path) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    let _ = writeln!(
+        script,
+        "# Generated by unvenv {VERSION}. Review before running; it changes\n\
+         # .gitignore and the Git index, never the files on disk.\n\
+         set -eu\n"
+    );
+    let _ = writeln!(script, "cd -- {}\n", sh_quote(&workdir.to_string_lossy()));
+    if !plan.ignores.is_empty() {
+        script.push_str("# Finish the last line so appended entries start on their own\n");
+        script.push_str(
+            "if [ -s .gitignore ] && [ -n \"$(tail -c 1 .gitignore)\" ]; then echo >> .gitignore; fi\n",
+        );
+        for entry in &plan.ignores {
+            let entry = sh_quote(entry);
+            let _ = writeln!(
+                script,
+                "grep -qxF -- {entry} .gitignore 2>/dev/null || printf '%s\\n' {entry} >> .gitignore"
+            );
+        }
+        script.push('\n');
+    }
+    for dir in &plan.untrack {
+        let _ = writeln!(
+            script,
+            "git rm -r --cached --quiet --ignore-unmatch -- {}",
+            sh_quote(dir)
+        );
+    }
+    script.push_str("\necho 'Done. Review with `git status` and commit the result.'\n");
+    script
+}
+
+/// The PowerShell script for `plan`, run in `workdir`
+fn powershell_script(plan: &Plan, workdir: &Path) -> String {
+    let mut script = String::new();
+    let _ = writeln!(
+        script,
+        "# Generated by unvenv {VERSION}. Review before running; it changes\n\
+         # .gitignore and the Git index, never the files on disk.\n\
+         $ErrorActionPreference = 'Stop'\n"
+    );
+    let _ = writeln!(
+        script,
+        "Set-Location -LiteralPath {}\n",
+        ps_quote(&workdir.to_string_lossy())
+    );
+    for entry in &plan.ignores {
+        let entry = ps_quote(entry);
+        let _ = writeln!(
+            script,
+            "if (-not (Test-Path -LiteralPath .gitignore) -or (Get-Content -LiteralPath .gitignore) -notcontains {entry}) {{ Add-Content -LiteralPath .gitignore -Value {entry} }}"
+        );
+    }
+    if !plan.ignores.is_empty() {
+        script.push('\n');
+    }
+    for dir in &plan.untrack {
+        let _ = writeln!(
+            script,
+            "git rm -r --cached --quiet --ignore-unmatch -- {}\nif ($LASTEXITCODE -ne 0) {{ exit $LASTEXITCODE }}",
+            ps_quote(dir)
+        );
+    }
+    script.push_str("\nWrite-Output 'Done. Review with `git status` and commit the result.'\n");
+    script
+}
+
+/// Paths of the shell and PowerShell scripts for `--emit-script path`
+fn script_paths(path: &Path) -> (PathBuf, PathBuf) {
+    if path.extension().is_some_and(|extension| extension == "ps1") {
+        (path.with_extension("sh"), path.to_path_buf())
+    } else {
+        (path.to_path_buf(), path.with_extension("ps1"))
+    }
+}
+
+/// Write the scripts fixing `venvs`, found by a scan of `workdir`
+pub fn write(path: &Path, workdir: &Path, venvs: &[VenvInfo]) -> Result<()> {
+    let plan = Plan::new(venvs);
+    // The scripts may be run from anywhere, so they change into an absolute path
+    let workdir = fs::canonicalize(workdir)
+        .with_context(|| format!("Failed to resolve {}", workdir.display()))?;
+    let (shell, powershell) = script_paths(path);
+    fs::write(&shell, shell_script(&plan, &workdir))
+        .with_context(|| format!("Failed to write {}", shell.display()))?;
+    hook::make_executable(&shell)?;
+    fs::write(&powershell, powershell_script(&plan, &workdir))
+        .with_context(|| format!("Failed to write {}", powershell.display()))?;
+    eprintln!(
+        "Wrote {} and {}; review them, then run one to apply the fix",
+        shell.display(),
+        powershell.display()
+    );
+    Ok(())
+}
+
+/// What `apply` changed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Applied {
+    /// Entries appended to `.gitignore`
+    pub ignores: Vec<String>,
+    /// Venv directories removed from the index
+    pub untracked: Vec<String>,
+}
+
+/// Fix `venvs`, found by a scan of `workdir`, in place
+pub fn apply(workdir: &Path, venvs: &[VenvInfo]) -> Result<Applied> {
+    apply_plan(workdir, &Plan::new(venvs))
+}
+
+/// Apply the ignore and untrack steps among `actions` from a report of
+/// `workdir`; other actions are left to the caller
+pub fn apply_actions(workdir: &Path, actions: impl IntoIterator<Item = Action>) -> Result<Applied> {
+    apply_plan(workdir, &Plan::from_actions(actions))
+}
+
+fn apply_plan(workdir: &Path, plan: &Plan) -> Result<Applied> {
+    let mut applied = Applied::default();
+
+    let gitignore = workdir.join(".gitignore");
+    let mut content = match fs::read_to_string(&gitignore) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", gitignore.display()));
+        }
+    };
+    for entry in &plan.ignores {
+        if content.lines().any(|line| line.trim_end() == entry) {
+            continue;
+        }
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(entry);
+        content.push('\n');
+        applied.ignores.push(entry.clone());
+    }
+    if !applied.ignores.is_empty() {
+        fs::write(&gitignore, content)
+            .with_context(|| format!("Failed to write {}", gitignore.display()))?;
+    }
+
+    if !plan.untrack.is_empty() {
+        let repo = Repository::discover(workdir).context("Failed to open the repository")?;
+        let root = repo
+            .workdir()
+            .context("The repository has no working tree")?;
+        // Venv paths are relative to the scanned directory, the index to the root
+        let prefix = fs::canonicalize(workdir)?
+            .strip_prefix(fs::canonicalize(root)?)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let mut index = repo.index().context("Failed to read the Git index")?;
+        for dir in &plan.untrack {
+            index
+                .remove_dir(&prefix.join(dir), 0)
+                .with_context(|| format!("Failed to remove {dir} from the index"))?;
+            applied.untracked.push(dir.clone());
+        }
+        index.write().context("Failed to write the Git index")?;
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrackingStatus;
+    use crate::test_support::git;
+    use std::{collections::BTreeMap, process::Command};
+    use tempfile::TempDir;
+
+    fn venv(path: &str, tracking: TrackingStatus) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking: Some(tracking),
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_and_quoting() {
+        let plan = Plan::new(&[
+            venv("tools/venv/pyvenv.cfg", TrackingStatus::Untracked),
+            venv("it's/.venv/pyvenv.cfg", TrackingStatus::Committed),
+            venv("pyvenv.cfg", TrackingStatus::Untracked),
+        ]);
+        assert_eq!(
+            plan.ignores.iter().collect::<Vec<_>>(),
+            ["/it's/.venv/", "/tools/venv/"]
+        );
+        assert_eq!(plan.untrack.iter().collect::<Vec<_>>(), ["it's/.venv"]);
+
+        assert!(
+            shell_script(&plan, Path::new("/src/app"))
+                .contains("git rm -r --cached --quiet --ignore-unmatch -- 'it'\\''s/.venv'\n")
+        );
+        assert!(
+            powershell_script(&plan, Path::new("/src/app"))
+                .contains("git rm -r --cached --quiet --ignore-unmatch -- 'it''s/.venv'\n")
+        );
+        assert_eq!(
+            script_paths(Path::new("fix.ps1")),
+            (PathBuf::from("fix.sh"), PathBuf::from("fix.ps1"))
+        );
+    }
+
+    #[test]
+    fn test_apply_fixes_the_repository() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join(".gitignore"), "*.pyc")?;
+        for venv in ["app/committed", "local"] {
+            fs::create_dir_all(dir.join(venv))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+        git(dir, &["add", "app/committed"]);
+        git(dir, &["commit", "-qm", "venv"]);
+
+        // Scanned from a subdirectory, like the scan's relative paths
+        let app = dir.join("app");
+        let venvs = [venv("committed/pyvenv.cfg", TrackingStatus::Committed)];
+        let applied = apply(&app, &venvs)?;
+        assert_eq!(applied.ignores, ["/committed/"]);
+        assert_eq!(applied.untracked, ["committed"]);
+        assert_eq!(fs::read_to_string(app.join(".gitignore"))?, "/committed/\n");
+        assert_eq!(git(dir, &["ls-files", "app"]), "");
+        assert!(app.join("committed/pyvenv.cfg").is_file());
+
+        let venvs = [venv("local/pyvenv.cfg", TrackingStatus::Untracked)];
+        apply(dir, &venvs)?;
+        // A second run has nothing left to add
+        assert_eq!(apply(dir, &venvs)?, Applied::default());
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore"))?,
+            "*.pyc\n/local/\n"
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_script_fixes_the_repository() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join(".gitignore"), "*.pyc")?;
+        for venv in ["committed", "local"] {
+            fs::create_dir_all(dir.join(venv))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+        git(dir, &["add", "committed"]);
+        git(dir, &["commit", "-qm", "venv"]);
+
+        let script = dir.join("out/fix.sh");
+        fs::create_dir(dir.join("out"))?;
+        let venvs = [
+            venv("committed/pyvenv.cfg", TrackingStatus::Committed),
+            venv("local/pyvenv.cfg", TrackingStatus::Untracked),
+        ];
+        write(&script, dir, &venvs)?;
+        assert!(dir.join("out/fix.ps1").is_file());
+
+        // Running it twice must not add the entries twice
+        for _ in 0..2 {
+            let status = Command::new(&script)
+                .current_dir(dir.join("out"))
+                .status()?;
+            assert!(status.success());
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore"))?,
+            "*.pyc\n/committed/\n/local/\n"
+        );
+        assert_eq!(git(dir, &["ls-files", "committed"]), "");
+        assert!(dir.join("committed/pyvenv.cfg").is_file());
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! `unvenv dev fixture`: a realistic repository to test integrations against.
+//!
+//! Hook authors and CI maintainers need a checkout that exercises every case
+//! their wrapper has to handle, not just the one venv on their own machine.
+//! The fixture is a fresh Git repository with:
+//!
+//! - venvs in each state: covered by `.gitignore` (`ignored-N/`), untracked
+//!   (`untracked-N/`), staged (`staged-N/`), and committed (`committed-N/`)
+//! - unignored tooling directories: `.direnv/`, a `.pyenv/` root with an
+//!   installed version, `pip-wheel-metadata/`, and `.cache/pip/`
+//! - a submodule at `vendor/lib` whose own repository commits a venv
+//! - an untracked venv whose directory name is not valid UTF-8 (Unix only,
+//!   and only where the filesystem accepts such names)
+//!
+//! How many venvs of each state, and which of the extras, is configurable.
+//! Like the self-test, it uses only libgit2.
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use git2::{IndexAddOption, Repository, Signature};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// `pyvenv.cfg` written for every fixture venv
+const PYVENV_CFG: &str =
+    "home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.12.1\n";
+
+/// URL recorded for the submodule; nothing is ever fetched from it
+const SUBMODULE_URL: &str = "https://example.invalid/vendored-lib.git";
+
+/// Where the submodule is checked out
+const SUBMODULE_PATH: &str = "vendor/lib";
+
+/// What goes into the fixture repository
+#[derive(Debug, Clone)]
+pub struct Mix {
+    /// Venvs covered by `.gitignore`
+    pub ignored: usize,
+    /// Venvs Git does not know about
+    pub untracked: usize,
+    /// Venvs added to the index but not committed
+    pub staged: usize,
+    /// Venvs in the initial commit
+    pub committed: usize,
+    /// Whether to add unignored direnv, pyenv, and pip directories
+    pub caches: bool,
+    /// Whether to add a submodule that commits a venv
+    pub submodule: bool,
+    /// Whether to add a venv with a non-UTF-8 directory name
+    pub non_utf8: bool,
+}
+
+/// One path the fixture created, with what it exercises
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Created {
+    /// Path relative to the fixture root
+    pub path: String,
+    /// What it is
+    pub description: &'static str,
+}
+
+/// Write a venv with a `bin/` directory at `dir`
+fn write_venv(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir.join("bin"))
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    fs::write(dir.join("pyvenv.cfg"), PYVENV_CFG)?;
+    Ok(())
+}
+
+/// Names of the venvs for one state: `<state>-1` to `<state>-<count>`
+fn venv_names(state: &str, count: usize) -> Vec<String> {
+    (1..=count).map(|n| format!("{state}-{n}")).collect()
+}
+
+/// Commit the index of `repo` as its first commit
+fn commit_index(repo: &Repository, message: &str) -> Result<()> {
+    let signature = Signature::now("unvenv fixture", "fixture@unvenv.invalid")?;
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[])?;
+    Ok(())
+}
+
+/// Add a submodule at [`SUBMODULE_PATH`] whose only commit holds a venv
+fn add_submodule(repo: &Repository, root: &Path) -> Result<()> {
+    let mut submodule = repo
+        .submodule(SUBMODULE_URL, Path::new(SUBMODULE_PATH), true)
+        .context("Failed to add the fixture submodule")?;
+    let sub_repo = submodule.open()?;
+    write_venv(&root.join(SUBMODULE_PATH).join("venv"))?;
+    let mut index = sub_repo.index()?;
+    index.add_all(["venv"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    commit_index(&sub_repo, "Add venv")?;
+    // Records the gitlink and `.gitmodules` in the parent's index
+    submodule.add_finalize()?;
+    Ok(())
+}
+
+/// Create a venv whose directory name is not valid UTF-8, returning whether
+/// the filesystem accepted the name
+#[cfg(unix)]
+fn write_non_utf8_venv(root: &Path) -> Result<bool> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = root.join(OsStr::from_bytes(b"venv-\xff"));
+    // macOS filesystems reject names that are not UTF-8
+    if fs::create_dir(&dir).is_err() {
+        return Ok(false);
+    }
+    write_venv(&dir)?;
+    Ok(true)
+}
+
+/// Windows paths are UTF-16, so there is no such name to create
+#[cfg(not(unix))]
+fn write_non_utf8_venv(_root: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// Build the fixture repository in `root`, returning what was created
+pub fn create(root: &Path, mix: &Mix) -> Result<Vec<Created>> {
+    let repo = Repository::init(root).context("Failed to create the fixture repository")?;
+    let mut created = Vec::new();
+
+    fs::write(root.join(".gitignore"), "/ignored-*/\n")?;
+    for (state, count, description) in [
+        ("ignored", mix.ignored, "venv covered by .gitignore"),
+        ("untracked", mix.untracked, "untracked venv"),
+        ("staged", mix.staged, "staged venv"),
+        ("committed", mix.committed, "committed venv"),
+    ] {
+        for name in venv_names(state, count) {
+            write_venv(&root.join(&name))?;
+            created.push(Created {
+                path: format!("{name}/"),
+                description,
+            });
+        }
+    }
+
+    if mix.caches {
+        for (dir, description) in [
+            (".direnv/python-3.12.1/bin", "direnv state"),
+            (
+                ".pyenv/versions/3.12.1/bin",
+                "pyenv root with an installed version",
+            ),
+            ("pip-wheel-metadata/example.dist-info", "pip build metadata"),
+            (".cache/pip/http", "pip download cache"),
+        ] {
+            fs::create_dir_all(root.join(dir))?;
+            fs::write(root.join(dir).join("placeholder"), "")?;
+            let top = match dir.split('/').next() {
+                Some(".cache") => ".cache/pip",
+                Some(top) => top,
+                None => dir,
+            };
+            created.push(Created {
+                path: format!("{top}/"),
+                description,
+            });
+        }
+    }
+
+    if mix.submodule {
+        add_submodule(&repo, root)?;
+        created.push(Created {
+            path: format!("{SUBMODULE_PATH}/venv/"),
+            description: "venv committed in a submodule",
+        });
+    }
+
+    if mix.non_utf8 && write_non_utf8_venv(root)? {
+        created.push(Created {
+            path: "venv-\\xff/".to_string(),
+            description: "untracked venv with a non-UTF-8 name",
+        });
+    }
+
+    let mut index = repo.index()?;
+    // `add_finalize` wrote the submodule entries to disk
+    index.read(true)?;
+    let mut tracked = vec![".gitignore".to_string()];
+    tracked.extend(venv_names("committed", mix.committed));
+    index.add_all(
+        tracked.iter().map(String::as_str),
+        IndexAddOption::DEFAULT,
+        None,
+    )?;
+    index.write()?;
+    commit_index(&repo, "fixtures")?;
+
+    let staged = venv_names("staged", mix.staged);
+    if !staged.is_empty() {
+        index.add_all(
+            staged.iter().map(String::as_str),
+            IndexAddOption::DEFAULT,
+            None,
+        )?;
+        index.write()?;
+    }
+
+    created.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(created)
+}
+
+/// Create the fixture in `dir`, which must be missing or empty, and list
+/// what is in it
+pub fn run(dir: &Path, mix: &Mix, is_tty: bool) -> Result<i32> {
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!("{} is not empty", dir.display());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let root: PathBuf = dir.canonicalize()?;
+    let created = create(&root, mix)?;
+
+    let headline = format!("Created a fixture repository in {}", root.display());
+    if is_tty {
+        println!("{} {headline}", "OK:".green().bold());
+    } else {
+        println!("OK: {headline}");
+    }
+    println!();
+    let width = created.iter().map(|c| c.path.len()).max().unwrap_or(0);
+    for entry in &created {
+        if is_tty {
+            println!(
+                "  {}  {}",
+                format!("{:width$}", entry.path).cyan(),
+                entry.description
+            );
+        } else {
+            println!("  {:width$}  {}", entry.path, entry.description);
+        }
+    }
+    println!();
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScanArgs, TrackingStatus, find_unignored_venvs};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fixture_covers_every_state() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().canonicalize()?;
+        let mix = Mix {
+            ignored: 2,
+            untracked: 1,
+            staged: 1,
+            committed: 2,
+            caches: true,
+            submodule: true,
+            non_utf8: true,
+        };
+        let created = create(&root, &mix)?;
+        assert!(created.iter().any(|c| c.path == "vendor/lib/venv/"));
+
+        let outcome = find_unignored_venvs(&root, &ScanArgs::default(), false)?;
+        let mut found: Vec<_> = outcome
+            .venvs
+            .iter()
+            .filter(|venv| !venv.path.starts_with(SUBMODULE_PATH))
+            .filter_map(|venv| {
+                let dir = venv.path.parent()?.to_str()?;
+                Some((dir.to_string(), venv.tracking))
+            })
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            found,
+            [
+                ("committed-1".to_string(), Some(TrackingStatus::Committed)),
+                ("committed-2".to_string(), Some(TrackingStatus::Committed)),
+                ("staged-1".to_string(), Some(TrackingStatus::Staged)),
+                ("untracked-1".to_string(), Some(TrackingStatus::Untracked)),
+            ]
+        );
+        assert_eq!(outcome.artifacts.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_refuses_a_non_empty_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("README"), "")?;
+        let mix = Mix {
+            ignored: 0,
+            untracked: 1,
+            staged: 0,
+            committed: 0,
+            caches: false,
+            submodule: false,
+            non_utf8: false,
+        };
+        assert!(run(temp_dir.path(), &mix, false).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Shared machinery for fleet scans (`scan --recursive-repos`, `audit-org`).
+//!
+//! Work items are spread over a fixed number of worker threads. Network work
+//! also goes through a [`Throttle`]: requests to the same host are spaced to
+//! a maximum rate shared by all workers, and transient failures (rate
+//! limiting, server errors, dropped connections) are retried with
+//! exponential backoff, so an overnight run over hundreds of repositories
+//! neither trips abuse detection nor dies on the first hiccup.
+
+use crate::github::ApiError;
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Number of workers to use when none is configured
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, std::num::NonZero::get)
+}
+
+/// Apply `work` to every item using at most `jobs` threads
+///
+/// Results come back in the order of `items`.
+pub fn run_parallel<T, R>(items: &[T], jobs: usize, work: impl Fn(&T) -> R + Sync) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = work(item);
+                    results
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Per-host rate limit and retry policy for API requests
+#[derive(Debug)]
+pub struct Throttle {
+    /// Minimum spacing between requests to one host, if limited
+    interval: Option<Duration>,
+    /// Extra attempts after a transient failure
+    retries: u32,
+    /// Wait before the first retry; doubled for each later one
+    backoff: Duration,
+    /// Earliest time the next request to each host may start
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl Throttle {
+    /// At most `per_second` requests per host (unlimited when not positive),
+    /// retrying transient failures `retries` times
+    pub fn new(per_second: f64, retries: u32) -> Self {
+        Self {
+            interval: (per_second > 0.0)
+                .then(|| Duration::try_from_secs_f64(1.0 / per_second).ok())
+                .flatten(),
+            retries,
+            backoff: Duration::from_secs(1),
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for the next free slot on `host`
+    fn wait_for(&self, host: &str) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let now = Instant::now();
+        let slot = {
+            let mut next_slot = self
+                .next_slot
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let slot = next_slot.get(host).map_or(now, |&next| next.max(now));
+            next_slot.insert(host.to_string(), slot + interval);
+            slot
+        };
+        thread::sleep(slot.saturating_duration_since(now));
+    }
+
+    /// Run `request` against `url` within the rate limit, retrying
+    /// transient failures with exponential backoff
+    pub fn call<T>(&self, url: &str, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+        let host = host_of(url);
+        let mut attempt = 0;
+        loop {
+            self.wait_for(host);
+            match request() {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    let delay = self
+                        .backoff
+                        .saturating_mul(2_u32.saturating_pow(attempt))
+                        .min(MAX_BACKOFF);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Host part of a URL, or the whole URL if it has none
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?']).next().unwrap_or(rest)
+}
+
+/// Whether retrying might help
+fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ApiError>()
+        .is_some_and(ApiError::is_transient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(status: Option<u16>) -> anyhow::Error {
+        ApiError {
+            method: "GET".to_string(),
+            url: "https://api.github.com/orgs/acme/repos".to_string(),
+            status,
+            body: String::new(),
+        }
+        .into()
+    }
+
+    fn quick_throttle(retries: u32) -> Throttle {
+        Throttle {
+            backoff: Duration::ZERO,
+            ..Throttle::new(0.0, retries)
+        }
+    }
+
+    #[test]
+    fn test_run_parallel_keeps_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let squares = run_parallel(&items, 8, |n| n * n);
+        assert_eq!(squares, items.iter().map(|n| n * n).collect::<Vec<_>>());
+        assert!(run_parallel(&[] as &[u64], 8, |n| *n).is_empty());
+    }
+
+    #[test]
+    fn test_call_retries_transient_failures() {
+        let mut attempts = 0;
+        let result = quick_throttle(3).call("https://api.github.com/x", || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(api_error(Some(502)))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.ok(), Some(3));
+    }
+
+    #[test]
+    fn test_call_gives_up_on_permanent_failures() {
+        let mut attempts = 0;
+        let result: Result<()> = quick_throttle(3).call("https://api.github.com/x", || {
+            attempts += 1;
+            Err(api_error(Some(404)))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result: Result<()> = quick_throttle(2).call("https://api.github.com/x", || {
+            attempts += 1;
+            Err(api_error(None))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_rate_limit_spaces_requests_per_host() {
+        let throttle = Throttle::new(50.0, 0);
+        let started = Instant::now();
+        for _ in 0..3 {
+            throttle.wait_for("api.github.com");
+        }
+        throttle.wait_for("example.com");
+        // Two gaps of 20 ms on the same host; the other host is not delayed
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(
+            host_of("https://api.github.com/orgs/acme"),
+            "api.github.com"
+        );
+        assert_eq!(host_of("http://ghe.local:8080?x=1"), "ghe.local:8080");
+    }
+}
@@ -0,0 +1,313 @@
+//! GitHub API access: the sticky pull request comment for
+//! `unvenv report --github-pr` and the requests behind `unvenv audit-org`.
+//!
+//! The Markdown report is posted as a single comment tagged with a hidden
+//! marker. Later runs on the same pull request edit that comment instead of
+//! adding new ones, so the thread shows the current state only.
+//!
+//! Requests go through `curl`, like the update check. The token and request
+//! body are passed on curl's standard input rather than its command line, so
+//! they do not show up in process listings.
+
+use anyhow::{Context, Result};
+use std::{
+    env,
+    fmt::{self, Write as _},
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Hidden tag identifying the comment unvenv owns
+const MARKER: &str = "<!-- unvenv-report -->";
+
+/// A pull request to comment on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    /// `owner/name`
+    pub repo: String,
+    /// Pull request number
+    pub number: u64,
+}
+
+/// What happened to the sticky comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentAction {
+    /// No earlier comment existed
+    Created,
+    /// The earlier comment was edited
+    Updated,
+}
+
+/// An API request that did not succeed
+#[derive(Debug)]
+pub struct ApiError {
+    /// HTTP method
+    pub method: String,
+    /// Requested URL
+    pub url: String,
+    /// HTTP status, or `None` if curl could not complete the request
+    pub status: Option<u16>,
+    /// Response body
+    pub body: String,
+}
+
+impl ApiError {
+    /// Whether the same request may succeed later: rate limiting, server
+    /// errors, and failed connections
+    pub fn is_transient(&self) -> bool {
+        match self.status {
+            None | Some(429 | 500..) => true,
+            // Secondary rate limits answer 403 rather than 429
+            Some(403) => self.body.to_lowercase().contains("rate limit"),
+            Some(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            method, url, body, ..
+        } = self;
+        match self.status {
+            None => write!(f, "curl could not reach the GitHub API ({method} {url})"),
+            Some(status) => write!(
+                f,
+                "GitHub API answered {method} {url} with HTTP {status}: {body}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl PullRequest {
+    /// Use the given coordinates, filling gaps from the GitHub Actions environment
+    pub fn detect(repo: Option<String>, number: Option<u64>) -> Option<Self> {
+        let repo = repo.or_else(|| env::var("GITHUB_REPOSITORY").ok())?;
+        let number = number
+            .or_else(|| {
+                let event = fs::read_to_string(env::var_os("GITHUB_EVENT_PATH")?).ok()?;
+                pr_number_from_event(&event)
+            })
+            .or_else(|| pr_number_from_ref(&env::var("GITHUB_REF").ok()?))?;
+        Some(Self { repo, number })
+    }
+}
+
+/// Pull request number from a workflow event payload
+fn pr_number_from_event(event: &str) -> Option<u64> {
+    let event: serde_json::Value = serde_json::from_str(event).ok()?;
+    event["pull_request"]["number"].as_u64()
+}
+
+/// Pull request number from a `refs/pull/<number>/merge` ref
+fn pr_number_from_ref(git_ref: &str) -> Option<u64> {
+    git_ref
+        .strip_prefix("refs/pull/")?
+        .split('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Token from `GITHUB_TOKEN` or `GH_TOKEN`
+pub fn token_from_env() -> Option<String> {
+    ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|token| !token.is_empty()))
+}
+
+/// API base URL, from `GITHUB_API_URL` on GitHub Enterprise runners
+pub fn api_url() -> String {
+    env::var("GITHUB_API_URL").map_or_else(
+        |_| "https://api.github.com".to_string(),
+        |url| url.trim_end_matches('/').to_string(),
+    )
+}
+
+/// GET an API URL, returning the response body
+pub fn get(url: &str, token: &str) -> Result<String> {
+    request("GET", url, token, None)
+}
+
+/// Create or update the sticky comment on `pr`
+pub fn post_sticky_comment(pr: &PullRequest, token: &str, markdown: &str) -> Result<CommentAction> {
+    let api = api_url();
+    let body = serde_json::json!({ "body": format!("{MARKER}\n{markdown}") }).to_string();
+
+    // Only the first page is searched; the comment is normally posted early
+    let comments = get(
+        &format!(
+            "{api}/repos/{}/issues/{}/comments?per_page=100",
+            pr.repo, pr.number
+        ),
+        token,
+    )?;
+
+    if let Some(id) = find_comment(&comments) {
+        request(
+            "PATCH",
+            &format!("{api}/repos/{}/issues/comments/{id}", pr.repo),
+            token,
+            Some(&body),
+        )?;
+        Ok(CommentAction::Updated)
+    } else {
+        request(
+            "POST",
+            &format!("{api}/repos/{}/issues/{}/comments", pr.repo, pr.number),
+            token,
+            Some(&body),
+        )?;
+        Ok(CommentAction::Created)
+    }
+}
+
+/// Id of the comment carrying the marker in a comment list response
+fn find_comment(comments: &str) -> Option<u64> {
+    let comments: serde_json::Value = serde_json::from_str(comments).ok()?;
+    comments.as_array()?.iter().find_map(|comment| {
+        comment["body"]
+            .as_str()
+            .filter(|body| body.contains(MARKER))
+            .and_then(|_| comment["id"].as_u64())
+    })
+}
+
+/// Quote a value for a curl config file
+pub fn curl_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// curl config line sending `token` as a bearer token
+///
+/// Pass it on curl's standard input (`--config -`) to keep the token out of
+/// process listings.
+pub fn auth_config(token: &str) -> String {
+    format!(
+        "header = {}\n",
+        curl_quote(&format!("Authorization: Bearer {token}"))
+    )
+}
+
+/// curl config carrying the credentials and body of a request
+fn curl_config(token: &str, body: Option<&str>) -> String {
+    let mut config = auth_config(token);
+    config.push_str("header = \"Accept: application/vnd.github+json\"\n");
+    if let Some(body) = body {
+        config.push_str("header = \"Content-Type: application/json\"\n");
+        let _ = writeln!(config, "data-binary = {}", curl_quote(body));
+    }
+    config
+}
+
+/// Send one API request, returning the response body
+fn request(method: &str, url: &str, token: &str, body: Option<&str>) -> Result<String> {
+    let mut child = Command::new("curl")
+        .args([
+            "-sS",
+            "--max-time",
+            "30",
+            "-X",
+            method,
+            "-w",
+            "\n%{http_code}",
+        ])
+        .args(["--config", "-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run curl; it is needed to talk to the GitHub API")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(curl_config(token, body).as_bytes())
+            .context("Failed to pass the request to curl")?;
+    }
+    let output = child.wait_with_output().context("Failed to run curl")?;
+    let error = |status, body: &str| ApiError {
+        method: method.to_string(),
+        url: url.to_string(),
+        status,
+        body: body.to_string(),
+    };
+    if !output.status.success() {
+        return Err(error(None, "").into());
+    }
+
+    let response = String::from_utf8_lossy(&output.stdout);
+    let (response, status) = response
+        .rsplit_once('\n')
+        .unwrap_or_else(|| ("", response.as_ref()));
+    if !status.starts_with('2') {
+        // curl reports 000 when no response arrived
+        let status = status.parse().ok().filter(|&status| status != 0);
+        return Err(error(status, response).into());
+    }
+    Ok(response.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pr_number_detection() {
+        assert_eq!(
+            pr_number_from_event(r#"{"pull_request": {"number": 42}}"#),
+            Some(42)
+        );
+        assert_eq!(pr_number_from_event(r#"{"ref": "refs/heads/main"}"#), None);
+        assert_eq!(pr_number_from_ref("refs/pull/7/merge"), Some(7));
+        assert_eq!(pr_number_from_ref("refs/heads/main"), None);
+    }
+
+    #[test]
+    fn test_find_comment() {
+        let comments =
+            format!(r#"[{{"id": 1, "body": "LGTM"}}, {{"id": 2, "body": "{MARKER}\n## unvenv"}}]"#);
+        assert_eq!(find_comment(&comments), Some(2));
+        assert_eq!(find_comment(r#"[{"id": 1, "body": "LGTM"}]"#), None);
+    }
+
+    #[test]
+    fn test_api_error_is_transient() {
+        let error = |status, body: &str| ApiError {
+            method: "GET".to_string(),
+            url: "https://api.github.com/orgs/acme/repos".to_string(),
+            status,
+            body: body.to_string(),
+        };
+        assert!(error(None, "").is_transient());
+        assert!(error(Some(429), "").is_transient());
+        assert!(error(Some(503), "").is_transient());
+        assert!(error(Some(403), "You have exceeded a secondary rate limit").is_transient());
+        assert!(!error(Some(403), "Resource not accessible").is_transient());
+        assert!(!error(Some(404), "Not Found").is_transient());
+        assert_eq!(
+            error(Some(404), "Not Found").to_string(),
+            "GitHub API answered GET https://api.github.com/orgs/acme/repos with HTTP 404: Not Found"
+        );
+    }
+
+    #[test]
+    fn test_curl_config_quotes_values() {
+        let config = curl_config("abc", Some(r#"{"body":"a\nb \"c\""}"#));
+        assert!(config.contains("header = \"Authorization: Bearer abc\"\n"));
+        assert!(config.contains(r#"data-binary = "{\"body\":\"a\\nb \\\"c\\\"\"}""#));
+    }
+}
@@ -0,0 +1,269 @@
+//! `unvenv audit` without `--since`: one report on the repository's health.
+//!
+//! The scan, the index, and the history each see a different part of the
+//! problem. The combined audit scans the whole working tree from the
+//! repository root, walks the most recent `--max-commits` commits for added
+//! `pyvenv.cfg` files, and merges everything into one list, worst first:
+//!
+//! 1. in history: added by a commit but no longer in the tree; only a
+//!    history rewrite removes it from clones
+//! 2. committed: in HEAD, so every clone downloads it
+//! 3. staged: about to be committed
+//! 4. untracked: not ignored, one `git add .` away from being committed
+//!
+//! Allow markers and `UNVENV_SKIP` apply to the working tree findings as in
+//! `scan`.
+
+use crate::{
+    ScanArgs, TrackingStatus, VenvInfo,
+    audit::{self, AuditFinding},
+    find_unignored_venvs, findings,
+    packages::Packages,
+    rules,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::Repository;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// How bad a finding is, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Not ignored, outside the index
+    Untracked,
+    /// In the index, not yet committed
+    Staged,
+    /// In HEAD
+    Committed,
+    /// Added by a commit, gone from the tree
+    History,
+}
+
+impl Level {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Untracked => "untracked",
+            Self::Staged => "staged",
+            Self::Committed => "committed",
+            Self::History => "in history",
+        }
+    }
+
+    const fn from_tracking(tracking: Option<TrackingStatus>) -> Self {
+        match tracking {
+            None | Some(TrackingStatus::Untracked) => Self::Untracked,
+            Some(TrackingStatus::Staged) => Self::Staged,
+            Some(TrackingStatus::Committed) => Self::Committed,
+        }
+    }
+}
+
+/// One venv in the combined report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// How bad it is
+    pub level: Level,
+    /// The `pyvenv.cfg`, relative to the repository root
+    pub path: PathBuf,
+    /// Commit that added it, as `abc1234 Summary (Author <email>)`
+    pub added_by: Option<String>,
+}
+
+/// Merge the working tree findings with the commits that added venvs into
+/// one list, worst first
+pub fn merge(venvs: &[VenvInfo], history: &[AuditFinding]) -> Vec<Entry> {
+    // The walk is newest first, so the last commit seen for a path added it
+    // first
+    let mut added_by: HashMap<&Path, String> = HashMap::new();
+    for finding in history {
+        for path in &finding.paths {
+            added_by.insert(
+                path,
+                format!(
+                    "{} {} ({})",
+                    finding.commit, finding.summary, finding.author
+                ),
+            );
+        }
+    }
+
+    let mut entries: Vec<Entry> = venvs
+        .iter()
+        .map(|venv| Entry {
+            level: Level::from_tracking(venv.tracking),
+            path: venv.path.clone(),
+            added_by: added_by.remove(venv.path.as_path()),
+        })
+        .collect();
+    // What is left was committed once and is no longer in the tree
+    entries.extend(added_by.into_iter().map(|(path, commit)| Entry {
+        level: Level::History,
+        path: path.to_path_buf(),
+        added_by: Some(commit),
+    }));
+    entries.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.path.cmp(&b.path)));
+    entries
+}
+
+/// Run the combined audit of the repository containing `workdir` and print
+/// the report, returning the exit code
+pub fn run(workdir: &Path, max_commits: usize, is_tty: bool) -> Result<i32> {
+    let repo = Repository::discover(workdir)
+        .context("Not in a Git repository; audit requires commit history")?;
+    let root = repo
+        .workdir()
+        .context("A bare repository has no working tree to audit")?
+        .to_path_buf();
+
+    let args = ScanArgs::default();
+    let mut outcome = find_unignored_venvs(&root, &args, is_tty)?;
+    let skips = rules::Skips::from_env();
+    let findings = findings::apply(
+        &root,
+        &args,
+        &skips,
+        &mut Packages::new(&root),
+        &mut outcome,
+    )?;
+    skips.announce(findings.skipped);
+    let venvs = outcome.venvs;
+
+    // A repository without commits has no history yet
+    let (history, truncated) = if repo.head().is_ok() {
+        audit::walk_history(&repo, None, Some(max_commits))?
+    } else {
+        (Vec::new(), false)
+    };
+
+    let entries = merge(&venvs, &history);
+    print_report(&entries, is_tty);
+    if truncated {
+        let message = format!(
+            "History audit stopped after {max_commits} commits; use --max-commits to look further back"
+        );
+        if is_tty {
+            println!("{} {message}", "Note:".yellow().bold());
+        } else {
+            println!("Note: {message}");
+        }
+    }
+
+    Ok(if entries.is_empty() { 0 } else { 2 })
+}
+
+/// Print the entries, grouped by level
+fn print_report(entries: &[Entry], is_tty: bool) {
+    if entries.is_empty() {
+        let message =
+            "No Python virtual environments in the working tree, index, or audited history";
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+        return;
+    }
+
+    let headline = format!(
+        "{} Python virtual environment(s) found, worst first:",
+        entries.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+
+    let mut level = None;
+    for entry in entries {
+        if level != Some(entry.level) {
+            level = Some(entry.level);
+            println!();
+            if is_tty {
+                println!("{}", format!("{}:", entry.level.label()).bold());
+            } else {
+                println!("{}:", entry.level.label());
+            }
+        }
+        let path = entry.path.to_string_lossy().replace('\\', "/");
+        if is_tty {
+            println!("  📁 {}", path.cyan());
+        } else {
+            println!("  {path}");
+        }
+        if let Some(commit) = &entry.added_by {
+            if is_tty {
+                println!("     Added in: {}", commit.dimmed());
+            } else {
+                println!("    Added in: {commit}");
+            }
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn venv(dir: &Path, name: &str) -> std::io::Result<()> {
+        fs::create_dir_all(dir.join(name))?;
+        fs::write(dir.join(name).join("pyvenv.cfg"), "home = /usr/bin\n")
+    }
+
+    #[test]
+    fn test_combined_report_orders_by_severity() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+
+        venv(dir, "removed")?;
+        venv(dir, "committed")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-qm", "Add venvs"]);
+        git(dir, &["rm", "-rq", "removed"]);
+        git(dir, &["commit", "-qm", "Remove venv"]);
+        venv(dir, "staged")?;
+        git(dir, &["add", "staged"]);
+        venv(dir, "untracked")?;
+
+        let repo = Repository::open(dir)?;
+        let (history, truncated) = audit::walk_history(&repo, None, Some(10))?;
+        assert!(!truncated);
+        let venvs = find_unignored_venvs(dir, &ScanArgs::default(), false)?.venvs;
+
+        let entries = merge(&venvs, &history);
+        let levels: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.level, entry.path.to_string_lossy().into_owned()))
+            .collect();
+        assert_eq!(
+            levels,
+            [
+                (Level::History, "removed/pyvenv.cfg".to_string()),
+                (Level::Committed, "committed/pyvenv.cfg".to_string()),
+                (Level::Staged, "staged/pyvenv.cfg".to_string()),
+                (Level::Untracked, "untracked/pyvenv.cfg".to_string()),
+            ]
+        );
+        assert!(
+            entries[1]
+                .added_by
+                .as_deref()
+                .is_some_and(|commit| commit.contains("Add venvs"))
+        );
+        assert_eq!(entries[3].added_by, None);
+
+        // The bound stops the walk before the commit that added them
+        let (history, truncated) = audit::walk_history(&repo, None, Some(1))?;
+        assert!(truncated);
+        assert!(history.is_empty());
+        Ok(())
+    }
+}
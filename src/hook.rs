@@ -0,0 +1,1086 @@
+//! Git pre-commit hook management.
+//!
+//! `unvenv hook install` writes a pre-commit hook that runs `unvenv hook run`,
+//! so a venv is caught before it is committed. The
+//! unvenv part of the hook sits between marker lines, which lets a later
+//! install update it and `hook uninstall` remove it without touching
+//! anything else.
+//!
+//! The hook goes where Git runs hooks from: `core.hooksPath` when it is set
+//! (in the repository, a template, or a global config shared by every
+//! repository), else `.git/hooks`. Husky points `core.hooksPath` at wrappers
+//! it regenerates, so with Husky the block goes into its `.husky/pre-commit`
+//! script instead. Install and status say which location they used.
+//!
+//! Hooks installed by other tools are never overwritten. A plain shell hook
+//! gets the block appended; any other hook (another interpreter, or a script
+//! that may `exec` or `exit` before reaching the end) is moved aside to
+//! `pre-commit.before-unvenv` and called from a small dispatcher instead.
+//!
+//! The block records the unvenv version that wrote it. `hook status` reports
+//! that version, the `unvenv` binary the hook will run, and whether the block
+//! still matches the current template, for the repository and each of its
+//! submodules; `--update` rewrites the outdated blocks.
+//!
+//! `hook run` is the entry point the hook calls: a `scan --staged` with a
+//! time budget, no update hint, and a report that fits on one screen. Teams
+//! using a hook manager wire it in with the snippet from `hook config`.
+
+use crate::{
+    ScanArgs, TrackingStatus, VenvInfo, artifacts,
+    config::{HookSettings, Outcome},
+    find_unignored_venvs, findings,
+    fix_script::sh_quote,
+    i18n::tr,
+    logging,
+    packages::Packages,
+    references, rules, telemetry,
+};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use colored::Colorize;
+use git2::{ConfigLevel, Repository};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use unvenv::Action;
+
+/// First line of the block managed by unvenv
+const BLOCK_START: &str = "# >>> unvenv >>>";
+/// Last line of the block managed by unvenv
+const BLOCK_END: &str = "# <<< unvenv <<<";
+/// Start of the block line naming the version that wrote it
+const VERSION_LINE: &str = "# Installed by unvenv ";
+/// Name the previous hook is moved to when a dispatcher is needed
+const CHAINED_HOOK: &str = "pre-commit.before-unvenv";
+/// Hook content that is left when the block is removed from a fresh hook
+const SHEBANG: &str = "#!/bin/sh";
+/// How long `hook run` may scan by default before giving up on the commit
+const HOOK_TIME_BUDGET: Duration = Duration::from_millis(800);
+/// Venvs listed by `hook run` before the rest are summarized
+const HOOK_REPORT_LINES: usize = 10;
+
+/// Hook managers `hook config` can generate configuration for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HookManager {
+    /// Lefthook (`lefthook.yml`)
+    Lefthook,
+    /// Husky (`.husky/pre-commit`)
+    Husky,
+    /// The pre-commit framework (`.pre-commit-config.yaml`)
+    PreCommit,
+}
+
+impl HookManager {
+    /// Configuration that runs unvenv before each commit
+    fn snippet(self) -> String {
+        match self {
+            Self::Lefthook => "# lefthook.yml
+pre-commit:
+  commands:
+    unvenv:
+      run: unvenv hook run
+"
+            .to_string(),
+            Self::Husky => "# .husky/pre-commit
+unvenv hook run
+"
+            .to_string(),
+            // The hook definition in this repository runs `scan --pre-commit`
+            Self::PreCommit => format!(
+                "# .pre-commit-config.yaml
+repos:
+  - repo: https://github.com/tftio/unvenv
+    rev: v{}
+    hooks:
+      - id: unvenv
+",
+                crate::VERSION
+            ),
+        }
+    }
+}
+
+/// What `install` did to the hook file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallOutcome {
+    /// There was no pre-commit hook, so one was written
+    Created,
+    /// The block was appended to an existing shell hook
+    Appended,
+    /// The existing hook was moved aside and is run by a dispatcher
+    Chained,
+    /// The managed block was replaced with the current one
+    Updated,
+    /// The managed block was already current
+    Unchanged,
+}
+
+/// What `uninstall` did to the hook file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UninstallOutcome {
+    /// The block was removed and the rest of the hook kept
+    Removed,
+    /// The hook only contained the block, so it was deleted
+    Deleted,
+    /// The dispatcher was deleted and the previous hook moved back
+    Restored,
+    /// There was no managed block
+    NotInstalled,
+}
+
+/// State of the pre-commit hook, as shown by `hook status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // independent facts about one file
+pub struct HookStatus {
+    /// Path of the pre-commit hook
+    pub path: PathBuf,
+    /// Whether the hook file exists
+    pub exists: bool,
+    /// Whether the hook contains the managed block
+    pub installed: bool,
+    /// Whether the managed block matches this version of unvenv
+    pub current: bool,
+    /// Whether a previous hook is run through the dispatcher
+    pub chained: bool,
+    /// Whether Git can execute the hook
+    pub executable: bool,
+    /// Version of unvenv that wrote the block, if it recorded one
+    pub installed_by: Option<String>,
+}
+
+/// The hook lines unvenv owns, including the markers
+fn managed_block(chained: bool) -> String {
+    let chained_call = if chained {
+        format!("\"$(dirname \"$0\")/{CHAINED_HOOK}\" \"$@\" || exit $?\n")
+    } else {
+        String::new()
+    };
+    format!(
+        "{BLOCK_START}
+# Managed by `unvenv hook install`; changes inside this block are overwritten.
+{VERSION_LINE}{}
+{chained_call}if command -v unvenv >/dev/null 2>&1; then
+    unvenv hook run || exit $?
+else
+    echo \"unvenv: not found on PATH, skipping the Python venv check\" >&2
+fi
+{BLOCK_END}
+",
+        crate::VERSION
+    )
+}
+
+/// The block without its version line, to compare templates across versions
+fn template_of(block: &str) -> String {
+    block
+        .lines()
+        .filter(|line| !line.starts_with(VERSION_LINE))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Byte range of the managed block in `content`, including its final newline
+fn block_range(content: &str) -> Option<(usize, usize)> {
+    let start = content.find(BLOCK_START)?;
+    let end = start + content[start..].find(BLOCK_END)? + BLOCK_END.len();
+    let end = if content[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    Some((start, end))
+}
+
+/// Whether the block can simply be appended to `script`
+///
+/// That needs a shell script that runs to its end: one that may `exec`
+/// another program or `exit` early would skip the appended block.
+fn can_append(script: &str) -> bool {
+    let mut lines = script.lines();
+    let Some(interpreter) = lines.next().and_then(|shebang| shebang.strip_prefix("#!")) else {
+        return false;
+    };
+    let mut words = interpreter.split_whitespace();
+    let mut program = words.next().unwrap_or_default();
+    if program.ends_with("/env") {
+        program = words.next().unwrap_or_default();
+    }
+    let is_shell = matches!(
+        program.rsplit('/').next(),
+        Some("sh" | "bash" | "dash" | "zsh" | "ksh")
+    );
+
+    is_shell
+        && !lines.any(|line| {
+            let line = line.trim_start();
+            line.starts_with("exec ") || line == "exit" || line.starts_with("exit ")
+        })
+}
+
+/// Why hooks live where they do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HooksSource {
+    /// The repository's own `hooks` directory
+    GitDir,
+    /// `core.hooksPath` in the repository's config
+    RepoConfig,
+    /// `core.hooksPath` in the global or system config
+    SharedConfig,
+    /// `core.hooksPath` pointing into the `init.templateDir` template
+    Template,
+    /// Husky's script directory
+    Husky,
+}
+
+impl HooksSource {
+    /// How the install and status reports name the location
+    pub const fn describe(self) -> &'static str {
+        match self {
+            Self::GitDir => "the repository's hooks directory",
+            Self::RepoConfig => "core.hooksPath from the repository config",
+            Self::SharedConfig => {
+                "core.hooksPath from the global or system config, shared by every repository that uses it"
+            }
+            Self::Template => "core.hooksPath into the init.templateDir template",
+            Self::Husky => "Husky's hook scripts (core.hooksPath is managed by Husky)",
+        }
+    }
+}
+
+/// Directory the pre-commit hook goes in, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HooksLocation {
+    /// The directory
+    pub dir: PathBuf,
+    /// Where it was configured
+    pub source: HooksSource,
+}
+
+/// Where the pre-commit hook belongs, honoring `core.hooksPath` and Husky
+pub fn hooks_location(repo: &Repository) -> HooksLocation {
+    let config = repo.config().ok();
+    let configured = config.as_ref().and_then(|config| {
+        let level = config.get_entry("core.hooksPath").ok()?.level();
+        Some((config.get_path("core.hooksPath").ok()?, level))
+    });
+    let Some((path, level)) = configured else {
+        // Linked worktrees share the hooks of the main repository
+        return HooksLocation {
+            dir: repo.commondir().join("hooks"),
+            source: HooksSource::GitDir,
+        };
+    };
+    // Relative paths are resolved against the working tree root
+    let dir = if path.is_relative() {
+        repo.workdir().unwrap_or_else(|| repo.path()).join(path)
+    } else {
+        path
+    };
+
+    // Husky 9 points Git at generated wrappers in `.husky/_` that run the
+    // scripts one level up; earlier versions point at `.husky` itself
+    let husky_dir = if dir.file_name().is_some_and(|name| name == "_") {
+        dir.parent()
+            .filter(|parent| parent.file_name().is_some_and(|name| name == ".husky"))
+    } else {
+        Some(dir.as_path()).filter(|dir| dir.file_name().is_some_and(|name| name == ".husky"))
+    };
+    if let Some(husky_dir) = husky_dir {
+        return HooksLocation {
+            dir: husky_dir.to_path_buf(),
+            source: HooksSource::Husky,
+        };
+    }
+
+    let template = config
+        .as_ref()
+        .and_then(|config| config.get_path("init.templateDir").ok());
+    let source = if template.is_some_and(|template| dir.starts_with(template)) {
+        HooksSource::Template
+    } else if matches!(
+        level,
+        ConfigLevel::Local | ConfigLevel::App | ConfigLevel::Highest
+    ) {
+        HooksSource::RepoConfig
+    } else {
+        HooksSource::SharedConfig
+    };
+    HooksLocation { dir, source }
+}
+
+/// Directory Git runs hooks from, honoring `core.hooksPath`
+pub fn hooks_dir(repo: &Repository) -> PathBuf {
+    hooks_location(repo).dir
+}
+
+fn read_hook(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Install or update the pre-commit hook, returning its path
+pub fn install(repo: &Repository) -> Result<(PathBuf, InstallOutcome)> {
+    let HooksLocation { dir, source } = hooks_location(repo);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join("pre-commit");
+    let chained_path = dir.join(CHAINED_HOOK);
+
+    let (content, outcome) = match read_hook(&path)? {
+        None => (
+            format!("{SHEBANG}\n\n{}", managed_block(false)),
+            InstallOutcome::Created,
+        ),
+        Some(existing) => {
+            if let Some((start, end)) = block_range(&existing) {
+                let block = managed_block(chained_path.is_file());
+                let content = format!("{}{block}{}", &existing[..start], &existing[end..]);
+                if content == existing {
+                    return Ok((path, InstallOutcome::Unchanged));
+                }
+                (content, InstallOutcome::Updated)
+            } else if can_append(&existing)
+                // Husky runs its scripts with `sh`, so they need no shebang
+                || (source == HooksSource::Husky
+                    && !existing.starts_with("#!")
+                    && can_append(&format!("{SHEBANG}\n{existing}")))
+            {
+                let separator = if existing.ends_with('\n') {
+                    "\n"
+                } else {
+                    "\n\n"
+                };
+                (
+                    format!("{existing}{separator}{}", managed_block(false)),
+                    InstallOutcome::Appended,
+                )
+            } else {
+                if chained_path.exists() {
+                    bail!(
+                        "{} already exists; move it out of the way to chain the current pre-commit hook",
+                        chained_path.display()
+                    );
+                }
+                fs::rename(&path, &chained_path).with_context(|| {
+                    format!(
+                        "Failed to move the existing hook to {}",
+                        chained_path.display()
+                    )
+                })?;
+                (
+                    format!("{SHEBANG}\n\n{}", managed_block(true)),
+                    InstallOutcome::Chained,
+                )
+            }
+        }
+    };
+
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    make_executable(&path)?;
+    Ok((path, outcome))
+}
+
+/// Remove the managed block, leaving the rest of the hook alone
+pub fn uninstall(repo: &Repository) -> Result<(PathBuf, UninstallOutcome)> {
+    let dir = hooks_dir(repo);
+    let path = dir.join("pre-commit");
+    let chained_path = dir.join(CHAINED_HOOK);
+
+    let Some(existing) = read_hook(&path)? else {
+        return Ok((path, UninstallOutcome::NotInstalled));
+    };
+    let Some((start, end)) = block_range(&existing) else {
+        return Ok((path, UninstallOutcome::NotInstalled));
+    };
+
+    let remaining = format!("{}{}", &existing[..start], &existing[end..]);
+    if !matches!(remaining.trim(), "" | SHEBANG) {
+        fs::write(&path, remaining.trim_end().to_string() + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        return Ok((path, UninstallOutcome::Removed));
+    }
+
+    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    if chained_path.is_file() {
+        fs::rename(&chained_path, &path).with_context(|| {
+            format!(
+                "Failed to restore {} from {}",
+                path.display(),
+                chained_path.display()
+            )
+        })?;
+        return Ok((path, UninstallOutcome::Restored));
+    }
+    Ok((path, UninstallOutcome::Deleted))
+}
+
+/// Inspect the pre-commit hook without changing it
+pub fn status(repo: &Repository) -> Result<HookStatus> {
+    let dir = hooks_dir(repo);
+    let path = dir.join("pre-commit");
+    let chained = dir.join(CHAINED_HOOK).is_file();
+    let content = read_hook(&path)?;
+    let block = content
+        .as_deref()
+        .and_then(|content| block_range(content).map(|(start, end)| &content[start..end]));
+
+    Ok(HookStatus {
+        exists: content.is_some(),
+        installed: block.is_some(),
+        current: block
+            .is_some_and(|block| template_of(block) == template_of(&managed_block(chained))),
+        installed_by: block.and_then(|block| {
+            block
+                .lines()
+                .find_map(|line| line.strip_prefix(VERSION_LINE))
+                .map(|version| version.trim().to_string())
+        }),
+        chained,
+        executable: is_executable(&path),
+        path,
+    })
+}
+
+#[cfg(unix)]
+pub fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", path.display()))
+}
+
+/// Git for Windows runs hooks through its own shell, so no mode bits are needed
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+pub const fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Open the repository for `workdir`, refusing bare repositories
+fn open_repo(workdir: &Path) -> Result<Repository> {
+    let repo = Repository::discover(workdir)
+        .context("Not in a Git repository; hooks are installed per repository")?;
+    if repo.is_bare() {
+        bail!("Bare repositories have no commits to check; install the hook in a clone");
+    }
+    Ok(repo)
+}
+
+fn print_ok(message: &str, path: &Path, is_tty: bool) {
+    if is_tty {
+        println!(
+            "{} {message} {}",
+            "OK:".green().bold(),
+            path.display().to_string().cyan()
+        );
+    } else {
+        println!("OK: {message} {}", path.display());
+    }
+}
+
+/// Say which hooks directory was used and why
+fn print_location(source: HooksSource, is_tty: bool) {
+    println!("  Location: {}", source.describe());
+    if source == HooksSource::SharedConfig {
+        let note = "The hook now runs for every repository using this hooks directory";
+        if is_tty {
+            println!("  {} {note}", "Note:".yellow().bold());
+        } else {
+            println!("  Note: {note}");
+        }
+    }
+}
+
+/// Install the hook for the repository containing `workdir`
+pub fn run_install(workdir: &Path, is_tty: bool) -> Result<i32> {
+    let repo = open_repo(workdir)?;
+    let (path, outcome) = install(&repo)?;
+    let message = match outcome {
+        InstallOutcome::Created => "Installed pre-commit hook at",
+        InstallOutcome::Appended => "Added the unvenv block to the existing hook at",
+        InstallOutcome::Chained => {
+            "Moved the existing hook to pre-commit.before-unvenv; it now runs first from"
+        }
+        InstallOutcome::Updated => "Updated the unvenv block in",
+        InstallOutcome::Unchanged => "Pre-commit hook already up to date at",
+    };
+    print_ok(message, &path, is_tty);
+    print_location(hooks_location(&repo).source, is_tty);
+    Ok(0)
+}
+
+/// Remove the hook block for the repository containing `workdir`
+pub fn run_uninstall(workdir: &Path, is_tty: bool) -> Result<i32> {
+    let (path, outcome) = uninstall(&open_repo(workdir)?)?;
+    let message = match outcome {
+        UninstallOutcome::Removed => "Removed the unvenv block from",
+        UninstallOutcome::Deleted => "Removed pre-commit hook",
+        UninstallOutcome::Restored => "Restored the previous pre-commit hook at",
+        UninstallOutcome::NotInstalled => "No unvenv block to remove in",
+    };
+    print_ok(message, &path, is_tty);
+    Ok(0)
+}
+
+/// Print the configuration snippet for `manager`
+pub fn run_config(manager: HookManager) -> i32 {
+    print!("{}", manager.snippet());
+    0
+}
+
+/// Check the files staged in the repository containing `workdir`
+///
+/// A scan that runs out of the time budget (`[hook] time-budget` in
+/// `.unvenv.toml`, 800 ms by default) blocks the commit with the partial exit
+/// code, since the files it did not reach may hold a venv. With `[hook]
+/// fail-open = true` it lets the commit through with a warning instead, for
+/// repositories where a slow hook would get bypassed for good.
+pub fn run_hook(workdir: &Path, settings: &HookSettings, is_tty: bool) -> Result<i32> {
+    let budget = settings.time_budget.unwrap_or(HOOK_TIME_BUDGET);
+    let args = ScanArgs {
+        staged: true,
+        time_budget: Some(budget),
+        ..ScanArgs::default()
+    };
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    let mut outcome = find_unignored_venvs(workdir, &args, false)?;
+    // UNVENV_SKIP=UV002 is the emergency bypass for this hook
+    let skips = rules::Skips::from_env();
+    let findings = findings::apply(
+        workdir,
+        &args,
+        &skips,
+        &mut Packages::new(workdir),
+        &mut outcome,
+    )?;
+    skips.announce(findings.skipped);
+    logging::scan_result("hook", workdir, &outcome.venvs, outcome.limit.is_some());
+    telemetry::export(
+        None,
+        &telemetry::ScanTelemetry {
+            mode: "hook",
+            started: started_at,
+            duration: started.elapsed(),
+            phases: outcome.phases,
+            violations: outcome.venvs.len(),
+            partial: outcome.limit.is_some(),
+        },
+    );
+
+    if !outcome.venvs.is_empty() {
+        print_hook_report(&outcome.venvs, is_tty);
+    }
+    if !outcome.artifacts.is_empty() {
+        artifacts::print_artifacts(&outcome.artifacts, is_tty);
+    }
+    if !findings.references.is_empty() {
+        references::print_references(&findings.references, is_tty);
+    }
+    if findings.fails(&outcome) {
+        return Ok(Outcome::Violations.default_code());
+    }
+    // The time budget is the only limit the hook sets
+    if outcome.limit.is_none() {
+        return Ok(Outcome::Clean.default_code());
+    }
+    let millis = budget.as_millis().to_string();
+    if settings.fail_open {
+        eprintln!(
+            "{} {}",
+            "Warning:".yellow().bold(),
+            tr("hook-partial", &[("millis", &millis)])
+        );
+        return Ok(Outcome::Clean.default_code());
+    }
+    let message = tr("hook-partial-blocked", &[("millis", &millis)]);
+    if is_tty {
+        eprintln!("{} {message}", "unvenv:".red().bold());
+    } else {
+        eprintln!("unvenv: {message}");
+    }
+    Ok(Outcome::Partial.default_code())
+}
+
+/// Print a compact report with the commands that fix the commit
+fn print_hook_report(venvs: &[VenvInfo], is_tty: bool) {
+    let dirs: Vec<String> = venvs
+        .iter()
+        .map(|venv| {
+            let dir = venv.path.parent().unwrap_or_else(|| Path::new(""));
+            let dir = dir.to_string_lossy().replace('\\', "/");
+            if dir.is_empty() { ".".to_string() } else { dir }
+        })
+        .collect();
+
+    let heading = tr("hook-blocked", &[("count", &venvs.len().to_string())]);
+    if is_tty {
+        println!("{} {heading}", "unvenv:".red().bold());
+    } else {
+        println!("unvenv: {heading}");
+    }
+    for (dir, venv) in dirs.iter().zip(venvs).take(HOOK_REPORT_LINES) {
+        let tracking = tr(
+            &format!(
+                "tracking-{}",
+                venv.tracking.map_or("untracked", TrackingStatus::label)
+            ),
+            &[],
+        );
+        if is_tty {
+            println!("  {} ({tracking})", format!("{dir}/").cyan());
+        } else {
+            println!("  {dir}/ ({tracking})");
+        }
+    }
+    if dirs.len() > HOOK_REPORT_LINES {
+        let more = (dirs.len() - HOOK_REPORT_LINES).to_string();
+        println!("  {}", tr("hook-more", &[("count", &more)]));
+    }
+
+    let fixes: Vec<String> = venvs
+        .iter()
+        .take(HOOK_REPORT_LINES)
+        .filter_map(fix_command)
+        .collect();
+    // A venv at the repository root has no fix short of moving it
+    if !fixes.is_empty() {
+        println!("{}", tr("hook-fix", &[]));
+        for fix in fixes {
+            println!("  {fix}");
+        }
+    }
+    println!("{}", tr("hook-details", &[]));
+}
+
+/// Shell command ignoring and unstaging `venv`, built from the default
+/// actions of its violation
+fn fix_command(venv: &VenvInfo) -> Option<String> {
+    let mut commands = Vec::new();
+    for action in venv.to_violation().actions {
+        match action {
+            Action::AddGitignoreEntry { entry, .. } => {
+                commands.push(format!("echo {} >> .gitignore", sh_quote(&entry)));
+            }
+            Action::GitRmCached { path } => commands.push(format!(
+                "git rm -r --cached --quiet {}",
+                sh_quote(&path.to_string_lossy())
+            )),
+            _ => {}
+        }
+    }
+    (!commands.is_empty()).then(|| commands.join(" && "))
+}
+
+/// The repository and its checked-out submodules, named by their path
+/// relative to the repository root
+fn checkout_repos(repo: Repository) -> Vec<(String, Repository)> {
+    let mut repos = Vec::new();
+    if let Ok(submodules) = repo.submodules() {
+        for submodule in &submodules {
+            // Submodules that are not checked out have no hooks to check
+            if let Ok(sub_repo) = submodule.open() {
+                let name = submodule.path().to_string_lossy().replace('\\', "/");
+                repos.push((name, sub_repo));
+            }
+        }
+    }
+    repos.insert(0, (".".to_string(), repo));
+    repos
+}
+
+/// The `unvenv` the hook runs: the first one on `PATH`
+fn hook_binary() -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        "unvenv.exe"
+    } else {
+        "unvenv"
+    };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Print the state of one repository's hook
+fn print_status(hook: &HookStatus, refreshed: bool, source: HooksSource, is_tty: bool) {
+    let (label, detail) = if !hook.installed {
+        let detail = if hook.exists {
+            "pre-commit hook exists but does not run unvenv; run `unvenv hook install`"
+        } else {
+            "no pre-commit hook; run `unvenv hook install`"
+        };
+        ("Not installed:", detail)
+    } else if !hook.current {
+        (
+            "Outdated:",
+            "the unvenv block predates the current template; run `unvenv hook status --update`",
+        )
+    } else if !hook.executable {
+        (
+            "Not executable:",
+            "Git skips the hook until it is executable (chmod +x)",
+        )
+    } else if refreshed {
+        ("Updated:", "rewrote the outdated unvenv block")
+    } else if hook.chained {
+        (
+            "Installed:",
+            "runs pre-commit.before-unvenv first, then unvenv",
+        )
+    } else {
+        ("Installed:", "runs unvenv hook run")
+    };
+    let healthy = hook.installed && hook.current && hook.executable;
+
+    if is_tty {
+        let label = if healthy {
+            label.green().bold()
+        } else {
+            label.yellow().bold()
+        };
+        println!("{label} {}", hook.path.display().to_string().cyan());
+    } else {
+        println!("{label} {}", hook.path.display());
+    }
+    println!("  {detail}");
+    if hook.installed {
+        match &hook.installed_by {
+            Some(version) => println!("  Written by: unvenv {version}"),
+            None => println!("  Written by: an unvenv version that did not record itself"),
+        }
+    }
+    println!("  Location: {}", source.describe());
+}
+
+/// Describe the hook of the repository containing `workdir` and of its
+/// submodules, rewriting outdated blocks with `update`
+pub fn run_status(workdir: &Path, update: bool, is_tty: bool) -> Result<i32> {
+    let repos = checkout_repos(open_repo(workdir)?);
+    for (name, repo) in &repos {
+        let mut hook = status(repo)?;
+        let refreshed = update && hook.installed && !hook.current;
+        if refreshed {
+            install(repo)?;
+            hook = status(repo)?;
+        }
+        if repos.len() > 1 {
+            if is_tty {
+                println!("{}", format!("{name}:").bold());
+            } else {
+                println!("{name}:");
+            }
+        }
+        print_status(&hook, refreshed, hooks_location(repo).source, is_tty);
+        if repos.len() > 1 {
+            println!();
+        }
+    }
+
+    let this_binary = env::current_exe().and_then(fs::canonicalize).ok();
+    match hook_binary() {
+        Some(path) if fs::canonicalize(&path).ok() == this_binary => {
+            println!(
+                "Hooks run {} (this binary, unvenv {})",
+                path.display(),
+                crate::VERSION
+            );
+        }
+        Some(path) => println!(
+            "Hooks run {} (not this binary; `{} version` shows its version)",
+            path.display(),
+            path.display()
+        ),
+        None => {
+            let message = "unvenv is not on PATH, so the hooks skip the check";
+            if is_tty {
+                println!("{} {message}", "WARNING:".yellow().bold());
+            } else {
+                println!("WARNING: {message}");
+            }
+        }
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    fn init_repo(dir: &Path) -> Result<Repository> {
+        git(dir, &["init", "-q"]);
+        Ok(Repository::open(dir)?)
+    }
+
+    fn write_hook(repo: &Repository, name: &str, content: &str) -> Result<PathBuf> {
+        let path = hooks_dir(repo).join(name);
+        fs::create_dir_all(path.parent().expect("has parent"))?;
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    #[test]
+    fn test_install_creates_and_updates_hook() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+
+        let (path, outcome) = install(&repo)?;
+        assert_eq!(outcome, InstallOutcome::Created);
+        assert!(path.ends_with(".git/hooks/pre-commit"));
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with("#!/bin/sh\n"));
+        assert!(content.contains("unvenv hook run"));
+
+        assert_eq!(install(&repo)?.1, InstallOutcome::Unchanged);
+
+        // An outdated block is replaced, leaving the rest of the hook alone
+        fs::write(
+            &path,
+            format!("#!/bin/sh\n{BLOCK_START}\nold\n{BLOCK_END}\necho after\n"),
+        )?;
+        assert_eq!(install(&repo)?.1, InstallOutcome::Updated);
+        let content = fs::read_to_string(&path)?;
+        assert!(!content.contains("\nold\n"));
+        assert!(content.contains("unvenv hook run"));
+        assert!(content.ends_with(&format!("{BLOCK_END}\necho after\n")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_appends_to_shell_hook() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+        let path = write_hook(&repo, "pre-commit", "#!/usr/bin/env bash\nmake lint\n")?;
+
+        assert_eq!(install(&repo)?.1, InstallOutcome::Appended);
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with("#!/usr/bin/env bash\nmake lint\n\n"));
+        assert!(content.contains(BLOCK_START));
+
+        assert_eq!(uninstall(&repo)?.1, UninstallOutcome::Removed);
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "#!/usr/bin/env bash\nmake lint\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_chains_hook_that_execs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+        let original = "#!/bin/sh\nexec pre-commit run\n";
+        let path = write_hook(&repo, "pre-commit", original)?;
+
+        assert_eq!(install(&repo)?.1, InstallOutcome::Chained);
+        let chained = hooks_dir(&repo).join(CHAINED_HOOK);
+        assert_eq!(fs::read_to_string(&chained)?, original);
+        assert!(fs::read_to_string(&path)?.contains(CHAINED_HOOK));
+
+        let status = status(&repo)?;
+        assert!(status.installed && status.current && status.chained);
+
+        assert_eq!(uninstall(&repo)?.1, UninstallOutcome::Restored);
+        assert_eq!(fs::read_to_string(&path)?, original);
+        assert!(!chained.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_refuses_to_overwrite_chained_hook() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+        write_hook(&repo, "pre-commit", "#!/usr/bin/env python3\n")?;
+        write_hook(&repo, CHAINED_HOOK, "#!/bin/sh\n")?;
+
+        assert!(install(&repo).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall_deletes_hook_it_created() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+
+        assert_eq!(uninstall(&repo)?.1, UninstallOutcome::NotInstalled);
+        let (path, _) = install(&repo)?;
+        assert_eq!(uninstall(&repo)?.1, UninstallOutcome::Deleted);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+
+        let before = status(&repo)?;
+        assert!(!before.exists && !before.installed);
+
+        let (path, _) = install(&repo)?;
+        let after = status(&repo)?;
+        assert!(after.installed && after.current && after.executable && !after.chained);
+
+        assert_eq!(after.installed_by.as_deref(), Some(crate::VERSION));
+
+        // Another version writing the same template is still current
+        let content = fs::read_to_string(&path)?;
+        fs::write(
+            &path,
+            content.replace(
+                &format!("{VERSION_LINE}{}", crate::VERSION),
+                &format!("{VERSION_LINE}0.9.0"),
+            ),
+        )?;
+        let other_version = status(&repo)?;
+        assert!(other_version.current);
+        assert_eq!(other_version.installed_by.as_deref(), Some("0.9.0"));
+
+        fs::write(
+            &path,
+            format!("#!/bin/sh\n{BLOCK_START}\nold\n{BLOCK_END}\n"),
+        )?;
+        let outdated = status(&repo)?;
+        assert!(!outdated.current);
+        assert_eq!(outdated.installed_by, None);
+
+        run_status(temp_dir.path(), true, false)?;
+        assert!(status(&repo)?.current);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_can_append() {
+        assert!(can_append("#!/bin/sh\nmake lint\n"));
+        assert!(can_append("#!/usr/bin/env zsh\nmake lint\n"));
+        assert!(!can_append("#!/usr/bin/env python3\nprint()\n"));
+        assert!(!can_append("#!/bin/sh\nexec make lint\n"));
+        assert!(!can_append("#!/bin/sh\nmake lint\nexit 0\n"));
+        assert!(!can_append("make lint\n"));
+    }
+
+    #[test]
+    fn test_fix_command_quotes_paths() {
+        let venv = |path: &str| VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            settings: std::collections::BTreeMap::new(),
+            tracking: Some(TrackingStatus::Staged),
+            owners: Vec::new(),
+            direnv: None,
+        };
+        assert_eq!(
+            fix_command(&venv("it's/venv/pyvenv.cfg")).as_deref(),
+            Some(
+                r"echo '/it'\''s/venv/' >> .gitignore && git rm -r --cached --quiet 'it'\''s/venv'"
+            )
+        );
+        // Ignoring the repository root is no fix
+        assert_eq!(fix_command(&venv("pyvenv.cfg")), None);
+    }
+
+    #[test]
+    fn test_run_hook_checks_staged_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        init_repo(dir)?;
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        assert_eq!(run_hook(dir, &HookSettings::default(), false)?, 0);
+
+        git(dir, &["add", "venv"]);
+        assert_eq!(run_hook(dir, &HookSettings::default(), false)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manager_snippets() {
+        assert!(
+            HookManager::Lefthook
+                .snippet()
+                .contains("run: unvenv hook run")
+        );
+        assert!(HookManager::Husky.snippet().contains("unvenv hook run"));
+        assert!(
+            HookManager::PreCommit
+                .snippet()
+                .contains(&format!("rev: v{}", crate::VERSION))
+        );
+    }
+
+    #[test]
+    fn test_hooks_dir_honors_core_hooks_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+        repo.config()?.set_str("core.hooksPath", ".githooks")?;
+
+        let (path, _) = install(&repo)?;
+        assert!(path.ends_with(".githooks/pre-commit"));
+        assert!(path.is_file());
+        assert_eq!(hooks_location(&repo).source, HooksSource::RepoConfig);
+
+        let template = temp_dir.path().join("template");
+        repo.config()?
+            .set_str("init.templateDir", &template.to_string_lossy())?;
+        repo.config()?
+            .set_str("core.hooksPath", &template.join("hooks").to_string_lossy())?;
+        assert_eq!(
+            hooks_location(&repo),
+            HooksLocation {
+                dir: template.join("hooks"),
+                source: HooksSource::Template,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_into_husky_scripts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = init_repo(temp_dir.path())?;
+        // Husky 9 layout: Git runs the wrappers in `_`, which run the scripts
+        repo.config()?.set_str("core.hooksPath", ".husky/_")?;
+        fs::create_dir_all(temp_dir.path().join(".husky/_"))?;
+        fs::write(temp_dir.path().join(".husky/pre-commit"), "npm test\n")?;
+
+        assert_eq!(hooks_location(&repo).source, HooksSource::Husky);
+        let (path, outcome) = install(&repo)?;
+        assert!(path.ends_with(".husky/pre-commit"));
+        assert_eq!(outcome, InstallOutcome::Appended);
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with("npm test\n"));
+        assert!(content.contains(BLOCK_START));
+        assert!(!temp_dir.path().join(".husky/_/pre-commit").exists());
+
+        Ok(())
+    }
+}
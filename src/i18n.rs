@@ -0,0 +1,163 @@
+//! Translated user-facing messages.
+//!
+//! Messages live in Fluent files under `locales/`, one per language, and are
+//! compiled into the binary. Only the subset of Fluent the messages need is
+//! understood (`key = text` with `{ $variable }` placeholders and `#`
+//! comments), which keeps the binary free of a Fluent runtime. A message
+//! missing from a translation falls back to English.
+//!
+//! The locale comes from `UNVENV_LANG`, then the POSIX variables `LC_ALL`,
+//! `LC_MESSAGES`, and `LANG`; `de_DE.UTF-8` selects `de`, and `C`, `POSIX`,
+//! or an unknown language selects English.
+
+use std::{collections::HashMap, env, sync::OnceLock};
+
+/// Environment variable that overrides the locale for unvenv only
+pub const LANG_VAR: &str = "UNVENV_LANG";
+
+/// Messages every other locale falls back to
+const ENGLISH: &str = include_str!("../locales/en.ftl");
+
+/// Translations, by language code
+const TRANSLATIONS: [(&str, &str); 2] = [
+    ("de", include_str!("../locales/de.ftl")),
+    ("ja", include_str!("../locales/ja.ftl")),
+];
+
+/// Messages of one language, by key
+#[derive(Debug)]
+struct Catalog {
+    messages: HashMap<String, String>,
+    fallback: Option<HashMap<String, String>>,
+}
+
+/// Parse the messages of a Fluent file
+fn parse(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Language code of a locale such as `de_DE.UTF-8`, if it has a translation
+fn language(locale: &str) -> Option<&'static str> {
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()?
+        .to_ascii_lowercase();
+    TRANSLATIONS
+        .iter()
+        .map(|(code, _)| *code)
+        .find(|code| *code == language)
+}
+
+/// The translated language selected by the environment, if any
+fn detect() -> Option<&'static str> {
+    [LANG_VAR, "LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        // An empty variable does not count, as in POSIX
+        .find(|value| !value.is_empty())
+        .and_then(|locale| language(&locale))
+}
+
+impl Catalog {
+    fn load(language: Option<&str>) -> Self {
+        let english = parse(ENGLISH);
+        match TRANSLATIONS
+            .iter()
+            .find(|(code, _)| Some(*code) == language)
+        {
+            Some((_, source)) => Self {
+                messages: parse(source),
+                fallback: Some(english),
+            },
+            None => Self {
+                messages: english,
+                fallback: None,
+            },
+        }
+    }
+
+    /// The message for `key` with its placeholders filled in
+    fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let Some(message) = self
+            .messages
+            .get(key)
+            .or_else(|| self.fallback.as_ref()?.get(key))
+        else {
+            // A typo in a key should not hide the message entirely
+            return key.to_string();
+        };
+        args.iter().fold(message.clone(), |message, (name, value)| {
+            message.replace(&format!("{{ ${name} }}"), value)
+        })
+    }
+}
+
+/// The message for `key` in the user's language, with the placeholders in
+/// `args` filled in
+pub fn tr(key: &str, args: &[(&str, &str)]) -> String {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG
+        .get_or_init(|| Catalog::load(detect()))
+        .format(key, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// `{ $name }` placeholders in a message
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        message
+            .split("{ $")
+            .skip(1)
+            .filter_map(|rest| rest.split_once(" }").map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_translations_match_english() {
+        let english = parse(ENGLISH);
+        for (code, source) in TRANSLATIONS {
+            let messages = parse(source);
+            let keys: BTreeSet<_> = messages.keys().collect();
+            assert_eq!(keys, english.keys().collect(), "{code}");
+            for (key, message) in &messages {
+                assert_eq!(
+                    placeholders(message),
+                    placeholders(&english[key]),
+                    "{code}: {key}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(language("de_DE.UTF-8"), Some("de"));
+        assert_eq!(language("ja_JP"), Some("ja"));
+        assert_eq!(language("de-AT"), Some("de"));
+        assert_eq!(language("en_US.UTF-8"), None);
+        assert_eq!(language("C"), None);
+    }
+
+    #[test]
+    fn test_format_fills_placeholders_and_falls_back() {
+        let german = Catalog {
+            messages: parse("hook-more = ...und { $count } weitere\n"),
+            fallback: Some(parse(ENGLISH)),
+        };
+        assert_eq!(
+            german.format("hook-more", &[("count", "3")]),
+            "...und 3 weitere"
+        );
+        assert_eq!(german.format("tracking-staged", &[]), "staged");
+        assert_eq!(german.format("no-such-key", &[]), "no-such-key");
+    }
+}
@@ -0,0 +1,197 @@
+//! Git ignore evaluation for scans.
+//!
+//! Asking libgit2 about every file is slow on large repositories, so the
+//! scanner asks about directories instead: an ignored directory is pruned
+//! from the walk entirely, and each verdict is cached so that no directory is
+//! evaluated twice. The number of Git queries therefore scales with the
+//! number of directories rather than the number of files.
+
+use anyhow::{Context, Result};
+use git2::Repository;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Cached `.gitignore` verdicts for one repository
+pub struct IgnoreMatcher {
+    repo: Repository,
+    root: PathBuf,
+    dir_verdicts: HashMap<PathBuf, bool>,
+}
+
+impl IgnoreMatcher {
+    /// Discover the repository enclosing `workdir`, if any
+    ///
+    /// Bare repositories have no working tree to check against and are
+    /// treated as if there were no repository at all.
+    #[must_use]
+    pub fn discover(workdir: &Path) -> Option<Self> {
+        let repo = Repository::discover(workdir).ok()?;
+        let root = repo.workdir()?.to_path_buf();
+        Some(Self {
+            repo,
+            root,
+            dir_verdicts: HashMap::new(),
+        })
+    }
+
+    /// The underlying repository
+    #[must_use]
+    pub const fn repo(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// Root of the repository's working tree
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// Express `full_path` relative to the repository root, as Git expects
+    #[must_use]
+    pub fn git_path(&self, full_path: &Path) -> Option<PathBuf> {
+        repo_relative_path(&self.root, full_path)
+    }
+
+    /// Whether the directory at `full_path` is ignored
+    pub fn is_dir_ignored(&mut self, full_path: &Path) -> Result<bool> {
+        let Some(git_path) = self.git_path(full_path) else {
+            return Ok(false);
+        };
+        // The repository root itself can never be ignored
+        if git_path.as_os_str().is_empty() {
+            return Ok(false);
+        }
+
+        if let Some(&verdict) = self.dir_verdicts.get(&git_path) {
+            return Ok(verdict);
+        }
+
+        let verdict = self.repo.status_should_ignore(&git_path).with_context(|| {
+            format!(
+                "Failed to check Git ignore status of {}",
+                git_path.display()
+            )
+        })?;
+        self.dir_verdicts.insert(git_path, verdict);
+        Ok(verdict)
+    }
+
+    /// Whether the file at `full_path` is ignored
+    ///
+    /// Files inside a directory already known to be ignored are answered from
+    /// the cache without asking Git.
+    pub fn is_file_ignored(&self, full_path: &Path) -> Result<bool> {
+        let Some(git_path) = self.git_path(full_path) else {
+            return Ok(false);
+        };
+
+        if git_path
+            .parent()
+            .is_some_and(|parent| self.dir_verdicts.get(parent) == Some(&true))
+        {
+            return Ok(true);
+        }
+
+        self.repo.status_should_ignore(&git_path).with_context(|| {
+            format!(
+                "Failed to check Git ignore status of {}",
+                git_path.display()
+            )
+        })
+    }
+}
+
+/// Express `full_path` relative to the repository root
+fn repo_relative_path(repo_root: &Path, full_path: &Path) -> Option<PathBuf> {
+    if let Ok(rel) = full_path.strip_prefix(repo_root) {
+        return Some(rel.to_path_buf());
+    }
+
+    // Fall back to canonical paths when one side was reached through a
+    // symlink (e.g. /var -> /private/var on macOS)
+    let root = repo_root.canonicalize().ok()?;
+    let full = full_path.canonicalize().ok()?;
+    full.strip_prefix(root).ok().map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn init_repo(gitignore: &str) -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        git(temp_dir.path(), &["init", "-q"]);
+        fs::write(temp_dir.path().join(".gitignore"), gitignore).expect("Failed to write");
+        temp_dir
+    }
+
+    #[test]
+    fn test_discover_outside_repo() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        assert!(IgnoreMatcher::discover(temp_dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_is_dir_ignored_caches_verdict() -> Result<()> {
+        let temp_dir = init_repo("build/\n");
+        fs::create_dir(temp_dir.path().join("build"))?;
+        fs::create_dir(temp_dir.path().join("src"))?;
+
+        let mut matcher = IgnoreMatcher::discover(temp_dir.path()).expect("repo");
+
+        assert!(matcher.is_dir_ignored(&temp_dir.path().join("build"))?);
+        assert!(!matcher.is_dir_ignored(&temp_dir.path().join("src"))?);
+        assert_eq!(matcher.dir_verdicts.len(), 2);
+
+        // Second lookup is served from the cache
+        assert!(matcher.is_dir_ignored(&temp_dir.path().join("build"))?);
+        assert_eq!(matcher.dir_verdicts.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repo_root_is_never_ignored() -> Result<()> {
+        let temp_dir = init_repo("*\n");
+        let mut matcher = IgnoreMatcher::discover(temp_dir.path()).expect("repo");
+
+        assert!(!matcher.is_dir_ignored(temp_dir.path())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_file_ignored_uses_parent_verdict() -> Result<()> {
+        let temp_dir = init_repo("venv/\n");
+        let venv_dir = temp_dir.path().join("venv");
+        fs::create_dir(&venv_dir)?;
+        fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let mut matcher = IgnoreMatcher::discover(temp_dir.path()).expect("repo");
+
+        assert!(matcher.is_dir_ignored(&venv_dir)?);
+        assert!(matcher.is_file_ignored(&venv_dir.join("pyvenv.cfg"))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_file_ignored_by_file_pattern() -> Result<()> {
+        let temp_dir = init_repo("pyvenv.cfg\n");
+        let venv_dir = temp_dir.path().join("venv");
+        fs::create_dir(&venv_dir)?;
+        fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let mut matcher = IgnoreMatcher::discover(temp_dir.path()).expect("repo");
+
+        assert!(!matcher.is_dir_ignored(&venv_dir)?);
+        assert!(matcher.is_file_ignored(&venv_dir.join("pyvenv.cfg"))?);
+
+        Ok(())
+    }
+}
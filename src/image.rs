@@ -0,0 +1,523 @@
+//! `scan --image`: venvs and package caches baked into container images.
+//!
+//! The image is either a tarball (`docker save` output or an OCI image
+//! layout archive) or an image reference, which is exported with
+//! `docker save` first. Archives are read with the `tar` CLI. Each layer is
+//! listed rather than unpacked, so device files and ownership never matter;
+//! only the `pyvenv.cfg` files are read, streamed from the layer to stdout so
+//! a symlink in the image never reaches the host's files.
+//!
+//! A venv or cache deleted in a later layer is still reported: the bytes stay
+//! in the layer that added them, so the image is no smaller.
+
+use crate::{
+    config::Outcome,
+    rules::{self, Rule},
+    scratch::ScratchDir,
+};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+use unvenv::VenvMetadata;
+
+/// Package manager caches that only bloat an image
+const CACHE_DIRS: [&str; 5] = [
+    ".cache/pip",
+    ".cache/pypoetry",
+    ".cache/uv",
+    ".cache/pdm",
+    ".cache/pipenv",
+];
+
+/// Prefix of the whiteout files that delete a path from lower layers
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// Whiteout that hides the lower-layer contents of its directory
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// One filesystem layer of the image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    /// Short digest, or the archive path when there is none
+    pub label: String,
+    /// The layer archive
+    pub path: PathBuf,
+}
+
+/// A venv or cache found in a layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the layer that adds it, counted from 0
+    pub layer: usize,
+    /// The venv or cache directory inside the image
+    pub path: PathBuf,
+    /// `image-venv` or `image-cache`
+    pub rule: Rule,
+    /// Files in the cache; 0 for venvs
+    pub files: usize,
+    /// Archive entry of the venv's `pyvenv.cfg`
+    pub cfg_entry: Option<String>,
+    /// Python version from `pyvenv.cfg`
+    pub version: Option<String>,
+    /// Later layer that deletes it again
+    pub removed_in: Option<usize>,
+}
+
+/// Run `tar` with `args`
+fn tar(args: &[&OsStr]) -> Result<Output> {
+    Command::new("tar")
+        .args(args)
+        .output()
+        .context("Failed to run tar; it is needed to read image archives")
+}
+
+/// Unpack the outer image archive for `source` into `scratch`, exporting it
+/// with `docker save` if `source` is not a file
+fn unpack(source: &str, scratch: &Path) -> Result<PathBuf> {
+    let archive = if Path::new(source).is_file() {
+        PathBuf::from(source)
+    } else {
+        let archive = scratch.join("image.tar");
+        let output = Command::new("docker")
+            .args(["save", "-o"])
+            .arg(&archive)
+            .arg(source)
+            .output()
+            .context("Failed to run docker; pass a `docker save` tarball instead")?;
+        if !output.status.success() {
+            bail!(
+                "docker save {source} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        archive
+    };
+
+    let dir = scratch.join("image");
+    fs::create_dir_all(&dir)?;
+    let output = tar(&[
+        "-xf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        dir.as_os_str(),
+    ])?;
+    if !output.status.success() {
+        bail!(
+            "{} is not an image archive: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(dir)
+}
+
+/// Read a JSON file of the unpacked image
+fn read_json(path: &Path) -> Result<Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON", path.display()))
+}
+
+/// Path of the blob with `digest` in an OCI image layout
+fn blob_path(image: &Path, digest: &str) -> Option<PathBuf> {
+    let (algorithm, hex) = digest.split_once(':')?;
+    Some(image.join("blobs").join(algorithm).join(hex))
+}
+
+/// Short label for a layer archive path, from the digest in it
+fn layer_label(path: &str) -> String {
+    path.split('/')
+        .find(|part| part.len() == 64 && part.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map_or_else(|| path.to_string(), |hex| format!("sha256:{}", &hex[..12]))
+}
+
+/// The layers of an unpacked image, bottom first
+///
+/// `docker save` archives list them in `manifest.json`; OCI layouts go
+/// through `index.json` to the image manifest. Multi-image archives use the
+/// first image.
+pub fn layers(image: &Path) -> Result<Vec<Layer>> {
+    let manifest = image.join("manifest.json");
+    if manifest.is_file() {
+        let manifest = read_json(&manifest)?;
+        let layers = manifest[0]["Layers"]
+            .as_array()
+            .context("manifest.json lists no layers")?;
+        return Ok(layers
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|path| Layer {
+                label: layer_label(path),
+                path: image.join(path),
+            })
+            .collect());
+    }
+
+    let mut manifest =
+        read_json(&image.join("index.json")).context("Not a docker save or OCI image archive")?;
+    // An index may point at another index, e.g. for multi-platform images
+    while let Some(digest) = manifest["manifests"][0]["digest"].as_str() {
+        let path = blob_path(image, digest).context("Malformed digest in image index")?;
+        manifest = read_json(&path)?;
+    }
+    let layers = manifest["layers"]
+        .as_array()
+        .context("Image manifest lists no layers")?;
+    Ok(layers
+        .iter()
+        .filter_map(|layer| layer["digest"].as_str())
+        .filter_map(|digest| {
+            Some(Layer {
+                label: layer_label(&digest.replace(':', "/")),
+                path: blob_path(image, digest)?,
+            })
+        })
+        .collect())
+}
+
+/// The entries of a layer archive
+fn list(layer: &Layer) -> Result<Vec<String>> {
+    let output = tar(&["-tf".as_ref(), layer.path.as_os_str()])?;
+    if !output.status.success() {
+        bail!(
+            "Failed to list layer {}: {}",
+            layer.label,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// An archive entry as a path inside the image
+fn entry_path(entry: &str) -> &Path {
+    Path::new(entry.trim_start_matches("./").trim_end_matches('/'))
+}
+
+/// The cache directory `path` is in, if any
+fn cache_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| CACHE_DIRS.iter().any(|cache| dir.ends_with(cache)))
+        .map(Path::to_path_buf)
+}
+
+/// Paths a layer deletes from the layers below it
+fn whiteouts(entries: &[String]) -> Vec<PathBuf> {
+    entries
+        .iter()
+        .map(String::as_str)
+        .map(entry_path)
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            if name == OPAQUE_WHITEOUT {
+                Some(dir.to_path_buf())
+            } else {
+                name.strip_prefix(WHITEOUT_PREFIX)
+                    .map(|name| dir.join(name))
+            }
+        })
+        .collect()
+}
+
+/// Find venvs and caches in the listings of each layer, bottom first
+pub fn analyze(listings: &[Vec<String>]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (layer, entries) in listings.iter().enumerate() {
+        let mut caches: BTreeMap<PathBuf, usize> = BTreeMap::new();
+        for entry in entries.iter().filter(|entry| !entry.ends_with('/')) {
+            let path = entry_path(entry);
+            if path.file_name() == Some(OsStr::new("pyvenv.cfg")) {
+                findings.push(Finding {
+                    layer,
+                    path: path.parent().unwrap_or_else(|| Path::new("")).to_path_buf(),
+                    rule: rules::IMAGE_VENV,
+                    files: 0,
+                    cfg_entry: Some(entry.clone()),
+                    version: None,
+                    removed_in: None,
+                });
+            } else if let Some(cache) = cache_root(path) {
+                *caches.entry(cache).or_default() += 1;
+            }
+        }
+        findings.extend(caches.into_iter().map(|(path, files)| Finding {
+            layer,
+            path,
+            rule: rules::IMAGE_CACHE,
+            files,
+            cfg_entry: None,
+            version: None,
+            removed_in: None,
+        }));
+    }
+
+    let deleted: Vec<Vec<PathBuf>> = listings.iter().map(Vec::as_slice).map(whiteouts).collect();
+    for finding in &mut findings {
+        finding.removed_in = (finding.layer + 1..deleted.len()).find(|&later| {
+            deleted[later]
+                .iter()
+                .any(|path| finding.path.starts_with(path))
+        });
+    }
+    findings
+}
+
+/// Read the Python version of each venv from its `pyvenv.cfg`
+///
+/// A `pyvenv.cfg` that is a link instead of a file streams nothing, so its
+/// venv keeps no version.
+fn read_versions(layers: &[Layer], findings: &mut [Finding]) {
+    for finding in findings.iter_mut() {
+        let Some(entry) = &finding.cfg_entry else {
+            continue;
+        };
+        let extracted = tar(&[
+            "-xOf".as_ref(),
+            layers[finding.layer].path.as_os_str(),
+            "--".as_ref(),
+            entry.as_ref(),
+        ]);
+        if let Some(output) = extracted.ok().filter(|output| output.status.success()) {
+            finding.version =
+                VenvMetadata::from_cfg(&String::from_utf8_lossy(&output.stdout)).version;
+        }
+    }
+}
+
+/// Print the findings, grouped by layer
+fn print_report(source: &str, layers: &[Layer], findings: &[Finding], is_tty: bool) {
+    let venvs = findings
+        .iter()
+        .filter(|finding| finding.rule == rules::IMAGE_VENV)
+        .count();
+    let headline = format!(
+        "{venvs} virtual environment(s) and {} package cache(s) baked into {source}",
+        findings.len() - venvs
+    );
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+
+    let mut by_layer: BTreeMap<usize, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        by_layer.entry(finding.layer).or_default().push(finding);
+    }
+    for (layer, findings) in by_layer {
+        println!();
+        println!("Layer {} ({}):", layer + 1, layers[layer].label);
+        for finding in findings {
+            let path = format!("/{}/", finding.path.to_string_lossy().replace('\\', "/"));
+            let mut details = vec![finding.rule.describe()];
+            if let Some(version) = &finding.version {
+                details.push(format!("Python {version}"));
+            }
+            if finding.rule == rules::IMAGE_CACHE {
+                details.push(format!("{} files", finding.files));
+            }
+            if let Some(later) = finding.removed_in {
+                details.push(format!(
+                    "deleted in layer {} but still in the image",
+                    later + 1
+                ));
+            }
+            if is_tty {
+                println!("  📁 {}  {}", path.cyan(), details.join(", "));
+            } else {
+                println!("  {path}  {}", details.join(", "));
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "Copy only the venv your app runs from into the final stage of a multi-stage build, and install with `pip install --no-cache-dir` (or mount the cache with `RUN --mount=type=cache`). Deleting files in a later layer does not shrink the image."
+    );
+    for rule in [rules::IMAGE_VENV, rules::IMAGE_CACHE] {
+        if findings.iter().any(|finding| finding.rule == rule) {
+            println!("  {}: {}", rule.id, rule.doc_url());
+        }
+    }
+}
+
+/// Scan the image at `source` and report what it bakes in
+pub fn run(source: &str, is_tty: bool) -> Result<i32> {
+    let scratch = ScratchDir::new("image")?;
+    let image = unpack(source, scratch.path())?;
+    let layers = layers(&image)?;
+    let listings = layers.iter().map(list).collect::<Result<Vec<_>>>()?;
+
+    let mut findings = analyze(&listings);
+    let skips = rules::Skips::from_env();
+    let before = findings.len();
+    findings.retain(|finding| !skips.contains(finding.rule));
+    skips.announce(before - findings.len());
+    read_versions(&layers, &mut findings);
+
+    if findings.is_empty() {
+        if is_tty {
+            println!(
+                "{} No virtual environments or package caches in {source} ({} layers)",
+                "OK:".green().bold(),
+                layers.len()
+            );
+        } else {
+            println!(
+                "OK: No virtual environments or package caches in {source} ({} layers)",
+                layers.len()
+            );
+        }
+        return Ok(Outcome::Clean.default_code());
+    }
+    print_report(source, &layers, &findings, is_tty);
+    Ok(Outcome::Violations.default_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn listing(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_analyze_layers() {
+        let findings = analyze(&[
+            listing(&[
+                "opt/",
+                "opt/venv/",
+                "opt/venv/pyvenv.cfg",
+                "opt/venv/bin/python",
+                "./root/.cache/pip/http/a",
+                "./root/.cache/pip/http/b",
+                "usr/lib/python3/__init__.py",
+            ]),
+            listing(&["root/.cache/.wh.pip", "app/main.py"]),
+        ]);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].path, Path::new("opt/venv"));
+        assert_eq!(findings[0].rule, rules::IMAGE_VENV);
+        assert_eq!(
+            findings[0].cfg_entry.as_deref(),
+            Some("opt/venv/pyvenv.cfg")
+        );
+        assert_eq!(findings[0].removed_in, None);
+        assert_eq!(findings[1].path, Path::new("root/.cache/pip"));
+        assert_eq!(findings[1].rule, rules::IMAGE_CACHE);
+        assert_eq!(findings[1].files, 2);
+        assert_eq!(findings[1].removed_in, Some(1));
+    }
+
+    #[test]
+    fn test_opaque_whiteout_removes_directory_contents() {
+        let findings = analyze(&[
+            listing(&["srv/venv/pyvenv.cfg"]),
+            listing(&["srv/.wh..wh..opq", "srv/app.py"]),
+        ]);
+        assert_eq!(findings[0].removed_in, Some(1));
+    }
+
+    #[test]
+    fn test_layer_label() {
+        let hex = "a".repeat(64);
+        assert_eq!(
+            layer_label(&format!("blobs/sha256/{hex}")),
+            "sha256:aaaaaaaaaaaa"
+        );
+        assert_eq!(layer_label("layer.tar"), "layer.tar");
+    }
+
+    #[test]
+    fn test_docker_save_archive() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let rootfs = root.join("rootfs");
+        fs::create_dir_all(rootfs.join("opt/venv"))?;
+        fs::write(
+            rootfs.join("opt/venv/pyvenv.cfg"),
+            "home = /usr/local/bin\nversion = 3.12.4\n",
+        )?;
+        let image = root.join("image");
+        fs::create_dir_all(image.join("layer1"))?;
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(image.join("layer1/layer.tar"))
+            .arg("-C")
+            .arg(&rootfs)
+            .arg("opt")
+            .status()?;
+        assert!(status.success());
+        fs::write(
+            image.join("manifest.json"),
+            r#"[{"Config": "config.json", "Layers": ["layer1/layer.tar"]}]"#,
+        )?;
+        let archive = root.join("image.tar");
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&image)
+            .args(["manifest.json", "layer1"])
+            .status()?;
+        assert!(status.success());
+
+        let scratch = TempDir::new()?;
+        let unpacked = unpack(&archive.to_string_lossy(), scratch.path())?;
+        let layers = layers(&unpacked)?;
+        assert_eq!(layers.len(), 1);
+        let listings = layers.iter().map(list).collect::<Result<Vec<_>>>()?;
+        let mut findings = analyze(&listings);
+        read_versions(&layers, &mut findings);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].path, Path::new("opt/venv"));
+        assert_eq!(findings[0].version.as_deref(), Some("3.12.4"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_linked_cfg_is_not_followed() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let secret = root.join("host.cfg");
+        fs::write(&secret, "version = 9.9.9\n")?;
+        fs::create_dir_all(root.join("rootfs/opt/venv"))?;
+        std::os::unix::fs::symlink(&secret, root.join("rootfs/opt/venv/pyvenv.cfg"))?;
+        let layer = root.join("layer.tar");
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(&layer)
+            .arg("-C")
+            .arg(root.join("rootfs"))
+            .arg("opt")
+            .status()?;
+        assert!(status.success());
+
+        let layers = [Layer {
+            label: "layer.tar".to_string(),
+            path: layer,
+        }];
+        let listings = layers.iter().map(list).collect::<Result<Vec<_>>>()?;
+        let mut findings = analyze(&listings);
+        read_versions(&layers, &mut findings);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].version, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Incremental scans driven by `git status`.
+//!
+//! After each `scan --incremental`, the HEAD commit and the violations found
+//! are recorded in `.git/unvenv/last-scan`. The next incremental scan asks
+//! Git which paths are untracked, changed, or were added by commits since
+//! then, and only evaluates those (plus the previously reported violations).
+//! Without a usable record the caller falls back to a full walk.
+//!
+//! `scan --staged` uses the same path-based evaluation for the files staged
+//! in the index, which is what a pre-commit hook needs.
+
+use crate::cache::{FileLock, write_atomic};
+use anyhow::{Context, Result};
+use git2::{Delta, Oid, Repository, StatusOptions};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// First line of the record file; bump the version when the format changes
+const RECORD_HEADER: &str = "unvenv-last-scan 1";
+
+/// What the previous incremental scan saw
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScanRecord {
+    /// HEAD when the scan started, if the branch had any commits
+    pub head: Option<Oid>,
+    /// Repository-relative paths of the reported `pyvenv.cfg` files
+    pub violations: Vec<PathBuf>,
+}
+
+impl ScanRecord {
+    /// Load the record for `repo`, if a previous scan left one
+    pub fn load(repo: &Repository) -> Option<Self> {
+        let content = fs::read_to_string(record_path(repo)).ok()?;
+        let mut lines = content.lines();
+        if lines.next() != Some(RECORD_HEADER) {
+            return None;
+        }
+
+        let mut record = Self::default();
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("head", oid)) => record.head = Some(Oid::from_str(oid).ok()?),
+                Some(("violation", path)) => record.violations.push(PathBuf::from(path)),
+                _ => return None,
+            }
+        }
+        Some(record)
+    }
+
+    /// Persist the record for the next incremental scan
+    pub fn save(&self, repo: &Repository) -> Result<()> {
+        let path = record_path(repo);
+        let mut content = format!("{RECORD_HEADER}\n");
+        if let Some(head) = self.head {
+            let _ = writeln!(content, "head {head}");
+        }
+        for violation in &self.violations {
+            if let Some(violation) = violation.to_str() {
+                let _ = writeln!(content, "violation {violation}");
+            }
+        }
+
+        let _lock = FileLock::acquire(&path)?;
+        write_atomic(&path, &content)
+    }
+}
+
+/// Current HEAD commit, if the branch has any commits yet
+pub fn head_commit(repo: &Repository) -> Option<Oid> {
+    repo.head()
+        .ok()?
+        .peel_to_commit()
+        .ok()
+        .map(|commit| commit.id())
+}
+
+/// Repository-relative paths that may have changed since `record` was taken
+///
+/// Untracked directories are reported as a single directory path rather than
+/// file by file. Returns `None` when the recorded HEAD is no longer available
+/// (e.g. after a history rewrite), in which case a full walk is needed.
+pub fn changed_paths(repo: &Repository, record: &ScanRecord) -> Result<Option<Vec<PathBuf>>> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false)
+        .exclude_submodules(true);
+
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .context("Failed to read Git status")?;
+
+    let mut paths: Vec<PathBuf> = statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(PathBuf::from))
+        .collect();
+
+    // Commits made (or pulled) since the recorded scan bypass the status
+    let current_head = head_commit(repo);
+    if record.head != current_head {
+        let (Some(old), Some(new)) = (record.head, current_head) else {
+            return Ok(None);
+        };
+        let Ok(old_tree) = repo.find_commit(old).and_then(|commit| commit.tree()) else {
+            return Ok(None);
+        };
+        let new_tree = repo
+            .find_commit(new)
+            .and_then(|commit| commit.tree())
+            .context("Failed to read HEAD tree")?;
+
+        let diff = repo
+            .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)
+            .context("Failed to diff against the recorded scan")?;
+        paths.extend(
+            diff.deltas()
+                .filter(|delta| delta.status() != Delta::Deleted)
+                .filter_map(|delta| delta.new_file().path().map(Path::to_path_buf)),
+        );
+    }
+
+    // Previously reported venvs stay violations until something changes
+    paths.extend(record.violations.iter().cloned());
+
+    Ok(Some(paths))
+}
+
+/// Repository-relative paths of files staged for the next commit
+///
+/// Deletions are left out, since removing a venv is never a violation.
+pub fn staged_paths(repo: &Repository) -> Result<Vec<PathBuf>> {
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let index = repo.index().context("Failed to read the Git index")?;
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+        .context("Failed to diff the index against HEAD")?;
+
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() != Delta::Deleted)
+        .filter_map(|delta| delta.new_file().path().map(Path::to_path_buf))
+        .collect())
+}
+
+fn record_path(repo: &Repository) -> PathBuf {
+    repo.path().join("unvenv").join("last-scan")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        git(temp_dir.path(), &["init"]);
+        let repo = Repository::open(temp_dir.path())?;
+
+        assert_eq!(ScanRecord::load(&repo), None);
+
+        let record = ScanRecord {
+            head: Some(Oid::from_str("0123456789abcdef0123456789abcdef01234567")?),
+            violations: vec![PathBuf::from("venv/pyvenv.cfg")],
+        };
+        record.save(&repo)?;
+
+        assert_eq!(ScanRecord::load(&repo), Some(record));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_paths_lists_untracked_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+
+        let repo = Repository::open(dir)?;
+        let record = ScanRecord {
+            head: head_commit(&repo),
+            violations: Vec::new(),
+        };
+
+        fs::create_dir_all(dir.join("venv").join("lib"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::write(dir.join("venv/lib/site.py"), "\n")?;
+
+        let paths = changed_paths(&repo, &record)?.expect("record is usable");
+        assert_eq!(paths, vec![PathBuf::from("venv/")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_paths_includes_new_commits() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+
+        let repo = Repository::open(dir)?;
+        let record = ScanRecord {
+            head: head_commit(&repo),
+            violations: Vec::new(),
+        };
+
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "--no-verify", "-m", "Add venv"]);
+
+        let paths = changed_paths(&repo, &record)?.expect("record is usable");
+        assert_eq!(paths, vec![PathBuf::from("venv/pyvenv.cfg")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_paths_needs_full_walk_for_unknown_head() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+
+        let repo = Repository::open(dir)?;
+        let record = ScanRecord {
+            head: Some(Oid::from_str("0123456789abcdef0123456789abcdef01234567")?),
+            violations: Vec::new(),
+        };
+
+        assert_eq!(changed_paths(&repo, &record)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_staged_paths_skips_unstaged_and_deleted_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::write(dir.join("notes.txt"), "not staged\n")?;
+        git(dir, &["add", "venv"]);
+        git(dir, &["rm", "--quiet", "README.md"]);
+
+        let repo = Repository::open(dir)?;
+        assert_eq!(staged_paths(&repo)?, vec![PathBuf::from("venv/pyvenv.cfg")]);
+
+        Ok(())
+    }
+}
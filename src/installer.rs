@@ -0,0 +1,205 @@
+//! `unvenv update` through the bundled install.sh.
+//!
+//! The binary carries the copy of install.sh it was released with and runs
+//! it to update itself, so installing and updating share one release lookup,
+//! download, checksum verification, and replacement. That includes the zip
+//! archives published for Windows and moving a running `unvenv.exe` aside
+//! before copying the new one. The script needs `bash`; on Windows that is
+//! the one Git for Windows installs.
+
+use crate::scratch::ScratchDir;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{fs, path::Path, process::Command};
+
+/// install.sh as of this release
+const SCRIPT: &str = include_str!("../install.sh");
+
+/// Which releases an update follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Channel {
+    /// The latest release
+    #[default]
+    Stable,
+    /// The newest release, prereleases included
+    Beta,
+}
+
+impl Channel {
+    /// Name used by `--channel`, `[update] channel`, and install.sh
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+        }
+    }
+
+    /// The channel called `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Stable, Self::Beta]
+            .into_iter()
+            .find(|channel| channel.name() == name)
+    }
+}
+
+/// What the installer is asked to do
+#[derive(Debug, Default)]
+pub struct Options<'a> {
+    /// Release tag to install instead of the latest
+    pub version: Option<&'a str>,
+    /// Releases to pick the latest from; install.sh defaults to stable
+    pub channel: Option<Channel>,
+    /// Reinstall or downgrade when the installed version is not older
+    pub force: bool,
+    /// GitHub token for the releases API; the installer falls back to
+    /// `$GITHUB_TOKEN` and `$GH_TOKEN` itself
+    pub token: Option<&'a str>,
+    /// Proxy for every request; the installer falls back to `$HTTPS_PROXY`
+    /// and `$HTTP_PROXY` itself
+    pub proxy: Option<&'a str>,
+}
+
+/// Install a release into `install_dir`, returning the installer's exit code
+pub fn run(install_dir: &Path, options: &Options<'_>) -> Result<i32> {
+    let scratch = ScratchDir::new("update")?;
+    let script = scratch.path().join("install.sh");
+    fs::write(&script, SCRIPT).with_context(|| format!("Failed to write {}", script.display()))?;
+
+    let status = command(&script, install_dir, options)
+        .status()
+        .context("Failed to run bash; it is needed to run the installer")?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// The `bash install.sh ...` invocation for `options`
+fn command(script: &Path, install_dir: &Path, options: &Options<'_>) -> Command {
+    let mut command = Command::new("bash");
+    command.arg(script).env("INSTALL_DIR", install_dir);
+    if let Some(version) = options.version {
+        command.args(["--version", version]);
+    }
+    if let Some(channel) = options.channel {
+        command.args(["--channel", channel.name()]);
+    }
+    if options.force {
+        command.env("FORCE_INSTALL", "1");
+    }
+    // In the environment rather than as --token and --proxy, so process
+    // listings do not show the token or proxy credentials
+    if let Some(token) = options.token {
+        command.env("GITHUB_TOKEN", token);
+    }
+    if let Some(proxy) = options.proxy {
+        command.env("PROXY", proxy);
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn env<'a>(command: &'a Command, key: &str) -> Option<&'a OsStr> {
+        command
+            .get_envs()
+            .find(|(name, _)| *name == key)
+            .and_then(|(_, value)| value)
+    }
+
+    #[test]
+    fn test_command_passes_options() {
+        let script = Path::new("/tmp/install.sh");
+        let dir = Path::new("/opt/bin");
+
+        let plain = command(script, dir, &Options::default());
+        assert_eq!(plain.get_args().collect::<Vec<_>>(), [script]);
+        assert_eq!(env(&plain, "INSTALL_DIR"), Some(dir.as_os_str()));
+        assert_eq!(env(&plain, "FORCE_INSTALL"), None);
+
+        let beta = command(
+            script,
+            dir,
+            &Options {
+                channel: Some(Channel::Beta),
+                ..Options::default()
+            },
+        );
+        assert_eq!(
+            beta.get_args().collect::<Vec<_>>(),
+            [script.as_os_str(), "--channel".as_ref(), "beta".as_ref()]
+        );
+
+        let pinned = command(
+            script,
+            dir,
+            &Options {
+                version: Some("v1.2.3"),
+                channel: None,
+                force: true,
+                token: Some("secret"),
+                proxy: Some("http://proxy:3128"),
+            },
+        );
+        assert_eq!(
+            pinned.get_args().collect::<Vec<_>>(),
+            [script.as_os_str(), "--version".as_ref(), "v1.2.3".as_ref()]
+        );
+        assert_eq!(env(&pinned, "FORCE_INSTALL"), Some("1".as_ref()));
+        assert_eq!(env(&pinned, "GITHUB_TOKEN"), Some("secret".as_ref()));
+        assert!(pinned.get_args().all(|arg| arg != "secret"));
+        assert_eq!(env(&pinned, "PROXY"), Some("http://proxy:3128".as_ref()));
+    }
+
+    /// Run install.sh's `download_and_verify` for `asset` from the release
+    /// `v1.0.0` under `releases`, returning whether it passed and its log
+    fn download_and_verify(releases: &Path, asset: &str) -> Result<(bool, String)> {
+        let functions = SCRIPT
+            .trim_end()
+            .strip_suffix("main \"$@\"")
+            .context("install.sh ends by calling main")?;
+        let scratch = tempfile::TempDir::new()?;
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(format!(
+                "{functions}\ndownload_and_verify \"$RELEASES_URL/v1.0.0/$1\" \"$1\" \"$2\" v1.0.0"
+            ))
+            .args(["bash", asset])
+            .arg(scratch.path())
+            .env("RELEASES_URL", format!("file://{}", releases.display()))
+            .output()?;
+        Ok((
+            output.status.success(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_checksum_falls_back_to_sha256sums() -> Result<()> {
+        let releases = tempfile::TempDir::new()?;
+        let dir = releases.path().join("v1.0.0");
+        fs::create_dir(&dir)?;
+        let asset = "unvenv-x86_64-unknown-linux-gnu.tar.gz";
+        fs::write(dir.join(asset), "archive")?;
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("sha256sum {asset} > SHA256SUMS"))
+            .current_dir(&dir)
+            .status()?;
+        assert!(status.success());
+
+        // No per-asset .sha256, so the manifest is used, without retrying the
+        // missing file first
+        let (passed, log) = download_and_verify(releases.path(), asset)?;
+        assert!(passed, "{log}");
+        assert!(log.contains("trying SHA256SUMS manifest"), "{log}");
+        assert!(!log.contains("retrying"), "{log}");
+
+        fs::write(dir.join(asset), "tampered")?;
+        let (passed, log) = download_and_verify(releases.path(), asset)?;
+        assert!(!passed);
+        assert!(log.contains("Checksum verification failed"), "{log}");
+        Ok(())
+    }
+}
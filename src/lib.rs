@@ -0,0 +1,65 @@
+//! Library interface to unvenv: the scan result model and a scanner.
+//!
+//! `unvenv report --json` prints a [`ScanReport`]; tools that process those
+//! reports can deserialize them with the same types instead of keeping their
+//! own copy of the schema. Hosts that want to scan in-process use
+//! [`Scanner`], optionally with a [`Visitor`] that sees each candidate,
+//! violation, skipped path, and progress update as the walk reaches it, and
+//! a [`CancellationToken`] to stop it from elsewhere:
+//!
+//! ```no_run
+//! use std::ops::ControlFlow;
+//! use unvenv::{ScanEvent, Scanner};
+//!
+//! let report = Scanner::new(".").scan_with(&mut |event: ScanEvent<'_>| {
+//!     if let ScanEvent::Violation(violation) = event {
+//!         println!("{}", violation.path.display());
+//!     }
+//!     ControlFlow::Continue(())
+//! })?;
+//! # Ok::<(), unvenv::Error>(())
+//! ```
+//!
+//! With the `tokio` feature, `Scanner::violations` runs the walk on tokio's
+//! blocking pool and streams the violations to async hosts.
+//!
+//! Failures are reported as [`Error`] variants, so hosts can tell I/O and
+//! Git problems apart from a scan that was stopped early.
+//!
+//! # Stability
+//!
+//! These types follow semver together with the `unvenv` crate:
+//!
+//! - Within a major version, fields and enum variants are only added, never
+//!   renamed or removed. The structs are `#[non_exhaustive]` so that adding a
+//!   field is not a breaking change; build them with the constructors and
+//!   `Default`, then set fields.
+//! - New fields are optional in the JSON, so reports written by an older
+//!   release still deserialize. Unknown fields are ignored, so an older
+//!   reader accepts reports from a newer release.
+//! - [`SCHEMA_VERSION`] is recorded in every report and only changes with a
+//!   breaking change to the JSON layout.
+
+// Shared with the `unvenv` binary; not covered by the stability promise
+#[doc(hidden)]
+pub mod codeowners;
+#[doc(hidden)]
+pub mod detect;
+mod error;
+#[doc(hidden)]
+pub mod ignore;
+mod model;
+mod scanner;
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(test)]
+mod test_support;
+
+pub use error::{Error, Result};
+pub use model::{
+    Action, Artifact, Package, Reference, SCHEMA_VERSION, ScanReport, Tracking, VenvMetadata,
+    Violation,
+};
+pub use scanner::{CancellationToken, Progress, ScanEvent, Scanner, SkipReason, Visitor};
+#[cfg(feature = "tokio")]
+pub use stream::ViolationStream;
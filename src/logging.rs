@@ -0,0 +1,242 @@
+//! Durable logging of scan results and errors to syslog or journald.
+//!
+//! Server-side hook deployments often discard stdout and stderr, so with
+//! `--log` (or `UNVENV_LOG`, for entry points such as `hook run` that take no
+//! flags) each violation, the scan summary, and fatal errors also go to the
+//! system log. Messages are written straight to the local sockets: `/dev/log`
+//! (`/var/run/syslog` on macOS) for syslog, and the native journal socket for
+//! journald, where the venv path and tracking state become structured
+//! `UNVENV_*` fields.
+//!
+//! Logging is best effort: an unreachable socket produces one warning and
+//! never changes the exit code.
+
+use crate::VenvInfo;
+use clap::ValueEnum;
+use colored::Colorize;
+use std::{
+    env, io,
+    path::Path,
+    process,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// Environment variable selecting the backend when `--log` is not given
+const BACKEND_VAR: &str = "UNVENV_LOG";
+
+/// Identifier messages are logged under
+const IDENTIFIER: &str = "unvenv";
+
+/// Socket of the local syslog daemon
+#[cfg(not(target_os = "macos"))]
+const SYSLOG_SOCKET: &str = "/dev/log";
+#[cfg(target_os = "macos")]
+const SYSLOG_SOCKET: &str = "/var/run/syslog";
+
+/// Native protocol socket of systemd-journald
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Where log messages go
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogBackend {
+    /// The local syslog daemon, facility `user`
+    Syslog,
+    /// systemd-journald, with structured fields
+    Journald,
+}
+
+/// Syslog severity of a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The run failed
+    Error,
+    /// A violation was found
+    Warning,
+    /// Routine results
+    Info,
+}
+
+impl Severity {
+    /// Numeric severity from RFC 5424
+    const fn code(self) -> u8 {
+        match self {
+            Self::Error => 3,
+            Self::Warning => 4,
+            Self::Info => 6,
+        }
+    }
+}
+
+/// Backend chosen for this process, if any
+static BACKEND: OnceLock<LogBackend> = OnceLock::new();
+
+/// Set once the unreachable-socket warning has been printed
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Choose the backend from the flag, falling back to `UNVENV_LOG`
+pub fn init(flag: Option<LogBackend>) {
+    let backend = flag.or_else(|| {
+        let value = env::var(BACKEND_VAR)
+            .ok()
+            .filter(|value| !value.is_empty())?;
+        LogBackend::from_str(&value, true)
+            .map_err(|_| {
+                eprintln!(
+                    "{} Ignoring {BACKEND_VAR}={value}; expected syslog or journald",
+                    "Warning:".yellow().bold()
+                );
+            })
+            .ok()
+    });
+    if let Some(backend) = backend {
+        let _ = BACKEND.set(backend);
+    }
+}
+
+/// Log one message with optional structured fields (journald only)
+pub fn log(severity: Severity, message: &str, fields: &[(&str, &str)]) {
+    let Some(&backend) = BACKEND.get() else {
+        return;
+    };
+    let (socket, datagram) = match backend {
+        LogBackend::Syslog => (SYSLOG_SOCKET, syslog_datagram(severity, message)),
+        LogBackend::Journald => (JOURNALD_SOCKET, journal_entry(severity, message, fields)),
+    };
+    if let Err(e) = send(socket, &datagram) {
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "{} Could not log to {socket}: {e}",
+                "Warning:".yellow().bold()
+            );
+        }
+    }
+}
+
+/// Log the outcome of a scan of `workdir`: one entry per violation plus a summary
+pub fn scan_result(mode: &str, workdir: &Path, venvs: &[VenvInfo], partial: bool) {
+    if BACKEND.get().is_none() {
+        return;
+    }
+    let workdir = workdir.to_string_lossy();
+    for venv in venvs {
+        let path = venv.path.to_string_lossy();
+        let tracking = venv
+            .tracking
+            .map_or("untracked", crate::TrackingStatus::label);
+        log(
+            Severity::Warning,
+            &format!("{mode}: unignored virtual environment {path} ({tracking}) in {workdir}"),
+            &[
+                ("UNVENV_WORKDIR", &*workdir),
+                ("UNVENV_PATH", &*path),
+                ("UNVENV_TRACKING", tracking),
+            ],
+        );
+    }
+
+    let violations = venvs.len().to_string();
+    let (severity, summary) = match (venvs.len(), partial) {
+        (0, false) => (Severity::Info, "no unignored virtual environments"),
+        (0, true) => (Severity::Info, "no violations in the part scanned"),
+        _ => (Severity::Warning, "unignored virtual environments found"),
+    };
+    log(
+        severity,
+        &format!("{mode}: {summary} in {workdir} (violations={violations}, partial={partial})"),
+        &[
+            ("UNVENV_WORKDIR", &*workdir),
+            ("UNVENV_VIOLATIONS", violations.as_str()),
+            ("UNVENV_PARTIAL", if partial { "true" } else { "false" }),
+        ],
+    );
+}
+
+/// RFC 3164 datagram; the daemon adds the timestamp and host name
+fn syslog_datagram(severity: Severity, message: &str) -> Vec<u8> {
+    // Facility `user` is 1
+    let priority = 8 + severity.code();
+    format!("<{priority}>{IDENTIFIER}[{}]: {message}", process::id()).into_bytes()
+}
+
+/// Entry in the journal's native protocol
+fn journal_entry(severity: Severity, message: &str, fields: &[(&str, &str)]) -> Vec<u8> {
+    let priority = severity.code().to_string();
+    let mut entry = Vec::new();
+    let standard = [
+        ("PRIORITY", priority.as_str()),
+        ("SYSLOG_IDENTIFIER", IDENTIFIER),
+        ("MESSAGE", message),
+    ];
+    for &(key, value) in standard.iter().chain(fields) {
+        entry.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            // Multi-line values are length-prefixed instead of `KEY=value`
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    }
+    entry
+}
+
+#[cfg(unix)]
+fn send(socket: &str, datagram: &[u8]) -> io::Result<()> {
+    let sender = std::os::unix::net::UnixDatagram::unbound()?;
+    sender.send_to(datagram, socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &str, _datagram: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "syslog and journald are only available on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syslog_datagram() {
+        let datagram = String::from_utf8(syslog_datagram(Severity::Warning, "found venv"))
+            .expect("datagram is UTF-8");
+        assert!(datagram.starts_with("<12>unvenv["));
+        assert!(datagram.ends_with("]: found venv"));
+    }
+
+    #[test]
+    fn test_journal_entry_fields() {
+        let entry = journal_entry(
+            Severity::Error,
+            "line one\nline two",
+            &[("UNVENV_PATH", "venv/pyvenv.cfg")],
+        );
+
+        let mut expected = b"PRIORITY=3\nSYSLOG_IDENTIFIER=unvenv\nMESSAGE\n".to_vec();
+        expected.extend_from_slice(&17u64.to_le_bytes());
+        expected.extend_from_slice(b"line one\nline two\nUNVENV_PATH=venv/pyvenv.cfg\n");
+        assert_eq!(entry, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_to_datagram_socket() -> io::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("log.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path)?;
+
+        send(&path.to_string_lossy(), b"<14>unvenv[1]: hello")?;
+        let mut buffer = [0; 64];
+        let len = receiver.recv(&mut buffer)?;
+        assert_eq!(&buffer[..len], b"<14>unvenv[1]: hello");
+        Ok(())
+    }
+}
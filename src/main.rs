@@ -5,18 +5,86 @@
 //! commits of Python virtual environments.
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use git2::Repository;
+use git2::{Oid, Repository, Status};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fs,
     path::{Path, PathBuf},
     process,
+    time::{Duration, Instant, SystemTime},
 };
-use walkdir::WalkDir;
+use unvenv::{
+    codeowners::CodeOwners,
+    detect::{find_venv_cfg, is_nested_repository},
+    ignore::IgnoreMatcher,
+};
+use walkdir::{DirEntry, WalkDir};
 use workhelix_cli_common::{DoctorCheck, DoctorChecks, LicenseType, RepoInfo};
 
+mod allow;
+mod artifacts;
+mod audit;
+mod bench;
+mod build_info;
+mod cache;
+mod check_ignore;
+mod ci;
+mod commit_cache;
+mod config;
+mod direnv;
+mod doctor;
+mod findings;
+mod fix_script;
+mod fixture;
+mod fleet;
+mod github;
+mod health;
+mod hook;
+mod i18n;
+mod image;
+mod incremental;
+mod installer;
+mod logging;
+mod metrics;
+mod multi_repo;
+mod netfs;
+mod org_audit;
+mod package_manager;
+mod packages;
+mod porcelain;
+mod preflight;
+mod progress;
+mod quickstart;
+mod references;
+mod report_diff;
+mod rollback;
+mod rules;
+mod scratch;
+mod self_test;
+mod serve;
+mod signing;
+mod stats;
+mod status;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod third_party;
+mod top;
+mod trace;
+mod update_hint;
+mod venv_lint;
+
+use artifacts::ArtifactKind;
+use cache::{DirVerdict, ScanCache};
+use check_ignore::{CheckIgnore, IgnoreEngine};
+use config::Outcome;
+use incremental::ScanRecord;
+use netfs::NetworkMounts;
+use package_manager::PackageManager;
+use progress::ScanProgress;
+
 /// Application version from Cargo.toml
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -27,6 +95,92 @@ struct VenvInfo {
     home: Option<String>,
     version: Option<String>,
     include_system_site_packages: Option<String>,
+    /// Every key in the file with its value, including the three above
+    settings: BTreeMap<String, String>,
+    /// Git tracking state, if the scan ran inside a repository
+    tracking: Option<TrackingStatus>,
+    /// Owners from CODEOWNERS; empty when the venv is unowned or there is no
+    /// CODEOWNERS file
+    owners: Vec<String>,
+    /// The `.envrc` whose direnv `layout python` created the venv
+    direnv: Option<PathBuf>,
+}
+
+/// How far an unignored pyvenv.cfg has made it into Git
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackingStatus {
+    /// Only present in the working tree
+    Untracked,
+    /// Added to the index but not yet committed
+    Staged,
+    /// Already part of the committed history
+    Committed,
+}
+
+impl VenvInfo {
+    /// This venv as a violation in the public report model
+    fn to_violation(&self) -> unvenv::Violation {
+        let mut venv = unvenv::VenvMetadata::default();
+        venv.home.clone_from(&self.home);
+        venv.version.clone_from(&self.version);
+        venv.include_system_site_packages = self
+            .include_system_site_packages
+            .as_ref()
+            .and_then(|value| value.to_ascii_lowercase().parse().ok());
+        venv.settings.clone_from(&self.settings);
+
+        let mut violation = unvenv::Violation::new(self.path.clone(), venv);
+        violation.tracking = self.tracking.map(TrackingStatus::to_report);
+        violation.owners.clone_from(&self.owners);
+        let rule = rules::Rule::for_venv(self);
+        violation.rule = Some(rule.id.to_string());
+        violation.help_uri = Some(rule.doc_url());
+        violation.add_default_actions();
+        if self.direnv.is_some() {
+            // direnv recreates `.direnv/python-X.Y/` for every Python version
+            for action in &mut violation.actions {
+                if let unvenv::Action::AddGitignoreEntry { entry, .. } = action {
+                    *entry = format!("{}/", direnv::LAYOUT_DIR);
+                }
+            }
+        }
+        violation
+    }
+
+    /// How the venv was created: the `command` line Python 3.11+ records,
+    /// or the version of virtualenv
+    fn creator(&self) -> Option<String> {
+        self.settings.get("command").cloned().or_else(|| {
+            self.settings
+                .get("virtualenv")
+                .map(|version| format!("virtualenv {version}"))
+        })
+    }
+}
+
+impl TrackingStatus {
+    /// Matching state in the public report model
+    const fn to_report(self) -> unvenv::Tracking {
+        match self {
+            Self::Untracked => unvenv::Tracking::Untracked,
+            Self::Staged => unvenv::Tracking::Staged,
+            Self::Committed => unvenv::Tracking::Committed,
+        }
+    }
+
+    /// Human-readable label used in reports
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Untracked => "untracked",
+            Self::Staged => "staged",
+            Self::Committed => "committed",
+        }
+    }
+
+    /// Whether the file is in the index and must be removed with `git rm --cached`
+    const fn is_tracked(self) -> bool {
+        matches!(self, Self::Staged | Self::Committed)
+    }
 }
 
 /// Python virtual environment detector CLI
@@ -35,6 +189,10 @@ struct VenvInfo {
 #[command(about = "Python virtual environment detector CLI")]
 #[command(version = VERSION)]
 struct Cli {
+    /// Also send scan results and errors to syslog or journald (defaults to
+    /// `$UNVENV_LOG`)
+    #[arg(long, global = true, value_enum, value_name = "BACKEND")]
+    log: Option<logging::LogBackend>,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -42,33 +200,537 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Show version information
-    Version,
+    Version {
+        /// Print the version and build metadata (commit, build date, target,
+        /// features) as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Show license information
-    License,
+    License {
+        /// Print the licenses of all compiled-in dependencies instead
+        #[arg(long)]
+        third_party: bool,
+    },
     /// Scan for unignored Python virtual environments (default)
-    Scan,
+    Scan(ScanArgs),
+    /// Scan with the output of the CI system running the job, then audit the
+    /// commits since the pull request's target branch
+    Ci {
+        /// CI system to report to (detected from the environment by default)
+        #[arg(long, value_enum)]
+        provider: Option<ci::CiProvider>,
+        /// Revision the branch is merged into (detected from pull or merge
+        /// request variables by default)
+        #[arg(long, value_name = "REV")]
+        base: Option<String>,
+    },
+    /// Report venvs in the working tree, index, and recent history, worst
+    /// first; with `--since`, list the commits that added venvs instead
+    Audit {
+        /// Revision to audit from (exclusive), e.g. `origin/main` or a tag
+        #[arg(long)]
+        since: Option<String>,
+        /// Most recent commits to check in the combined report
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1000,
+            conflicts_with = "since"
+        )]
+        max_commits: usize,
+    },
+    /// Find committed Python virtual environments across a GitHub organization
+    AuditOrg {
+        /// Organization to audit
+        org: String,
+        /// API token (defaults to `$GITHUB_TOKEN` or `$GH_TOKEN`)
+        #[arg(long)]
+        token: Option<String>,
+        /// Repositories fetched at the same time
+        #[arg(long, value_name = "N", default_value_t = 4)]
+        jobs: usize,
+        /// Requests per second to each API host (0 for no limit)
+        #[arg(long, value_name = "N", default_value_t = 10.0)]
+        rate_limit: f64,
+        /// Retries, with exponential backoff, of requests that fail transiently
+        /// (rate limiting, server errors, dropped connections)
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        retries: u32,
+        /// Also audit archived repositories
+        #[arg(long)]
+        include_archived: bool,
+    },
+    /// Time the walk, ignore-checking, and parsing phases of a scan
+    Bench,
+    /// Find venvs, ignored ones included, whose base interpreter is gone or
+    /// has changed version
+    LintVenvs,
+    /// Show the largest venvs and Python caches below the current directory,
+    /// refreshed until you quit, with keys to delete or ignore them
+    Top,
+    /// Print a short repository status for shell prompts and status bars
+    Status {
+        /// Print only a stable token: `clean`, `violations:N`, `partial`, or
+        /// `no-repo`
+        #[arg(long)]
+        porcelain: bool,
+    },
+    /// Record scan results over time and show the trend
+    Stats {
+        #[command(subcommand)]
+        action: StatsCommand,
+    },
+    /// Verify this installation by scanning a throwaway repository with known
+    /// venvs
+    SelfTest,
+    /// Answer scan and quick-fix requests from an editor, one JSON line each
+    /// on stdin and stdout
+    Serve,
+    /// Print the scan results as a Markdown report, or post them to a pull request
+    #[command(args_conflicts_with_subcommands = true)]
+    Report(ReportArgs),
+    /// Manage the Git pre-commit hook
+    Hook {
+        #[command(subcommand)]
+        action: HookCommand,
+    },
     /// Generate shell completion scripts
     Completions {
         /// Shell type (bash, zsh, fish, etc.)
         shell: clap_complete::Shell,
     },
     /// Check health and configuration
-    Doctor,
+    Doctor {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// GitHub token for the releases API check (defaults to
+        /// `$GITHUB_TOKEN` or `$GH_TOKEN`)
+        #[arg(long)]
+        token: Option<String>,
+        /// Proxy for the releases API check (defaults to `$HTTPS_PROXY`)
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+    },
     /// Update to the latest version
-    Update {
-        /// Specific version to install (defaults to latest)
+    Update(UpdateArgs),
+    /// Tools for developing and testing integrations with unvenv
+    Dev {
+        #[command(subcommand)]
+        action: DevCommand,
+    },
+    /// Set up a repository step by step: .gitignore, pre-commit hook, config
+    Quickstart {
+        /// Make every change without asking
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+}
+
+impl Commands {
+    /// Whether the command runs without reading `.unvenv.toml`
+    ///
+    /// These have nothing to configure and must keep working while the file
+    /// is broken; `doctor` reports the problem as one of its checks.
+    const fn ignores_config(&self) -> bool {
+        matches!(
+            self,
+            Self::Version { .. }
+                | Self::License { .. }
+                | Self::Completions { .. }
+                | Self::Doctor { .. }
+        )
+    }
+}
+
+/// Actions of the hook subcommand
+#[derive(Subcommand)]
+enum HookCommand {
+    /// Install a pre-commit hook that runs `unvenv hook run`, keeping any
+    /// existing hook
+    Install,
+    /// Remove the unvenv block from the pre-commit hook
+    Uninstall,
+    /// Show whether the pre-commit hooks of the repository and its
+    /// submodules run unvenv, which version wrote them, and which binary
+    /// they run
+    Status {
+        /// Rewrite unvenv blocks that are older than the current template
+        #[arg(long)]
+        update: bool,
+    },
+    /// Check the staged files quickly; this is what the hook runs
+    Run,
+    /// Print the configuration that wires unvenv into a hook manager
+    Config {
+        /// Hook manager used by the repository
+        #[arg(long, value_enum)]
+        manager: hook::HookManager,
+    },
+}
+
+/// Actions of the dev subcommand
+#[derive(Subcommand)]
+enum DevCommand {
+    /// Create a Git repository with venvs in every state, tooling caches, a
+    /// submodule, and a non-UTF-8 name, for testing hooks and CI setups
+    Fixture {
+        /// Directory to create the repository in; must be missing or empty
+        dir: PathBuf,
+        /// Venvs covered by `.gitignore`
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        ignored: usize,
+        /// Venvs Git does not know about
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        untracked: usize,
+        /// Venvs added to the index but not committed
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        staged: usize,
+        /// Venvs in the initial commit
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        committed: usize,
+        /// Leave out the direnv, pyenv, and pip directories
         #[arg(long)]
-        version: Option<String>,
-        /// Force update even if already at target version
+        no_caches: bool,
+        /// Leave out the submodule
         #[arg(long)]
-        force: bool,
-        /// Custom installation directory
+        no_submodule: bool,
+        /// Leave out the venv with a non-UTF-8 name
         #[arg(long)]
-        install_dir: Option<PathBuf>,
+        no_non_utf8: bool,
     },
 }
 
-struct UnvenvTool;
+/// Actions of the stats subcommand
+#[derive(Subcommand)]
+enum StatsCommand {
+    /// Scan the current directory and append the counts and sizes to the
+    /// history
+    Record {
+        /// History file (defaults to `.git/unvenv/stats-history`)
+        #[arg(long, value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+    /// Print the recorded history with its trend
+    Show {
+        /// History file (defaults to `.git/unvenv/stats-history`)
+        #[arg(long, value_name = "FILE")]
+        file: Option<PathBuf>,
+        /// Show only the most recent N entries
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        last: usize,
+    },
+}
+
+/// Output format for commands with machine-readable output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON
+    Json,
+}
+
+/// Options for the update subcommand
+#[derive(Args, Debug)]
+struct UpdateArgs {
+    /// Specific version to install (defaults to latest)
+    #[arg(long)]
+    version: Option<String>,
+    /// Release channel to take the latest version from (defaults to
+    /// `[update] channel` in `.unvenv.toml`, else stable)
+    #[arg(long, value_enum, conflicts_with_all = ["version", "rollback"])]
+    channel: Option<installer::Channel>,
+    /// Force update even if already at target version
+    #[arg(long)]
+    force: bool,
+    /// Update in place even if a package manager installed this binary
+    #[arg(long)]
+    force_self: bool,
+    /// Restore the version that was installed before the last update
+    #[arg(long, conflicts_with_all = ["version", "force", "force_self"])]
+    rollback: bool,
+    /// Custom installation directory
+    #[arg(long)]
+    install_dir: Option<PathBuf>,
+    /// GitHub token for the releases API (defaults to `$GITHUB_TOKEN` or
+    /// `$GH_TOKEN`)
+    #[arg(long, conflicts_with = "rollback")]
+    token: Option<String>,
+    /// Proxy for the downloads (defaults to `$HTTPS_PROXY`/`$HTTP_PROXY`)
+    #[arg(long, value_name = "URL", conflicts_with = "rollback")]
+    proxy: Option<String>,
+}
+
+/// Options for the report subcommand
+#[derive(Args, Debug)]
+struct ReportArgs {
+    /// Post the report as a sticky comment on a GitHub pull request, editing
+    /// the previous one on later runs
+    #[arg(long)]
+    github_pr: bool,
+    /// Repository as `owner/name` (defaults to `$GITHUB_REPOSITORY`)
+    #[arg(long, requires = "github_pr")]
+    repo: Option<String>,
+    /// Pull request number (detected from the workflow event in GitHub Actions)
+    #[arg(long, requires = "github_pr")]
+    pr: Option<u64>,
+    /// API token (defaults to `$GITHUB_TOKEN` or `$GH_TOKEN`)
+    #[arg(long, requires = "github_pr")]
+    token: Option<String>,
+    /// Print the results as a JSON scan report instead of Markdown
+    #[arg(long, conflicts_with = "github_pr")]
+    json: bool,
+    /// Write the report to this file instead of printing it
+    #[arg(long, short, value_name = "FILE", conflicts_with = "github_pr")]
+    output: Option<PathBuf>,
+    /// Sign the JSON report with this minisign secret key, writing the
+    /// signature next to it as `<FILE>.minisig`
+    #[arg(long, value_name = "SECRET_KEY", requires_all = ["json", "output"])]
+    sign: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<ReportCommand>,
+}
+
+/// Actions of the report subcommand that work on saved reports
+#[derive(Subcommand, Debug)]
+enum ReportCommand {
+    /// Compare two JSON reports; fails only if the new one has violations the
+    /// old one did not
+    Diff {
+        /// Report of the earlier run
+        old: PathBuf,
+        /// Report of the later run
+        new: PathBuf,
+    },
+    /// Check a signed JSON report against its minisign signature
+    Verify {
+        /// The report
+        report: PathBuf,
+        /// Minisign public key file of the signer
+        #[arg(long, short = 'p', value_name = "FILE")]
+        public_key: PathBuf,
+        /// Signature file (defaults to `<REPORT>.minisig`)
+        #[arg(long, short = 'x', value_name = "FILE")]
+        signature: Option<PathBuf>,
+    },
+}
+
+/// Options for the scan subcommand
+#[derive(Args, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)] // independent CLI flags
+struct ScanArgs {
+    /// Cache per-directory results on disk so repeated scans only re-check
+    /// directories that changed
+    #[arg(long)]
+    cache: bool,
+    /// Only evaluate paths Git reports as changed since the last incremental
+    /// scan, falling back to a full walk when there is no previous scan
+    #[arg(long)]
+    incremental: bool,
+    /// Only evaluate files staged for the next commit, for pre-commit hooks
+    #[arg(long, conflicts_with = "incremental")]
+    staged: bool,
+    /// Run as a pre-commit framework hook: evaluate only the FILEs passed on
+    /// the command line, with no progress or update output
+    #[arg(long, conflicts_with_all = ["incremental", "staged"])]
+    pre_commit: bool,
+    /// Skip Git entirely and report every venv, ignored or not, e.g. in build
+    /// outputs, unpacked archives, or Docker contexts
+    #[arg(long, conflicts_with_all = ["incremental", "staged", "recursive_repos"])]
+    no_git: bool,
+    /// Fail with its own exit code (5) instead of scanning when the directory
+    /// is not inside a Git repository
+    #[arg(long, conflicts_with_all = ["no_git", "recursive_repos"])]
+    require_git: bool,
+    /// Trace every pruned directory and evaluated venv on stderr, with the
+    /// ignore rule responsible, to debug why a path was or wasn't visited
+    #[arg(long, conflicts_with = "recursive_repos")]
+    explain_scan: bool,
+    /// Record the result for commit REV (e.g. `HEAD`) and reuse it when the
+    /// same commit is scanned again, as in retried CI jobs
+    #[arg(
+        long,
+        value_name = "REV",
+        conflicts_with_all = [
+            "incremental", "staged", "pre_commit", "no_git", "recursive_repos", "max_dirs",
+            "max_files"
+        ]
+    )]
+    cache_key: Option<String>,
+    /// Print the results in a stable format for scripts instead of the
+    /// report; the layout of each VERSION never changes
+    #[arg(
+        long,
+        value_enum,
+        value_name = "VERSION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1",
+        conflicts_with_all = ["image", "recursive_repos", "cache_key", "group_by", "ci", "explain_scan"]
+    )]
+    porcelain: Option<porcelain::PorcelainVersion>,
+    /// Terminate porcelain records with NUL instead of newline
+    #[arg(short = 'z', requires = "porcelain")]
+    null_terminated: bool,
+    /// Output format; `json` prints an array of the findings (path, `home`,
+    /// `version`, `include-system-site-packages`, ignored status) and keeps
+    /// the exit codes
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with_all = [
+            "porcelain", "image", "recursive_repos", "cache_key", "group_by", "ci", "explain_scan"
+        ]
+    )]
+    format: OutputFormat,
+    /// Write the `.gitignore` edits and `git rm --cached` commands that fix
+    /// the findings to FILE, plus a PowerShell version beside it, to review
+    /// before running
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["image", "recursive_repos", "cache_key", "porcelain"]
+    )]
+    emit_script: Option<PathBuf>,
+    /// Append the missing `.gitignore` entries and remove tracked venvs from
+    /// the index, then exit 6 if that resolved every violation
+    #[arg(
+        long,
+        conflicts_with_all = ["image", "recursive_repos", "cache_key", "no_git"]
+    )]
+    fix: bool,
+    /// Where ignore verdicts come from: libgit2 in process, or `git
+    /// check-ignore` for exact parity with Git when the two disagree
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ENGINE",
+        default_value_t,
+        conflicts_with_all = ["cache", "no_git"]
+    )]
+    ignore_engine: check_ignore::IgnoreEngine,
+    /// Scan a container image instead of the working tree: a `docker save` or
+    /// OCI archive, or an image reference to export with `docker save`
+    #[arg(
+        long,
+        value_name = "TAR|REF",
+        conflicts_with_all = [
+            "cache", "incremental", "staged", "pre_commit", "no_git", "require_git",
+            "explain_scan", "recursive_repos", "group_by", "ci"
+        ]
+    )]
+    image: Option<String>,
+    /// Files to evaluate in `--pre-commit` mode, relative to the scanned directory
+    #[arg(value_name = "FILE", requires = "pre_commit")]
+    files: Vec<PathBuf>,
+    /// Do not descend into NFS, SMB, or FUSE mounts below the scanned directory
+    #[arg(long)]
+    skip_network_fs: bool,
+    /// Fail the scan on unreadable directories instead of skipping them
+    #[arg(long)]
+    fail_on_unreadable: bool,
+    /// Follow symbolic links to directories, guarding against link cycles
+    #[arg(long)]
+    follow_symlinks: bool,
+    /// Also match case variants such as `PyVenv.CFG` on case-sensitive
+    /// filesystems (case-insensitive filesystems always match them)
+    #[arg(long)]
+    ignore_case: bool,
+    /// Stop after visiting this many directories; a partial scan without
+    /// violations exits with code 3
+    #[arg(long, value_name = "N")]
+    max_dirs: Option<u64>,
+    /// Stop after seeing this many files; a partial scan without violations
+    /// exits with code 3
+    #[arg(long, value_name = "N")]
+    max_files: Option<u64>,
+    /// Scan every Git repository below DIR against its own ignore rules and
+    /// print one report grouped by repository
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["pre_commit", "group_by", "metrics_file", "ci"]
+    )]
+    recursive_repos: Option<PathBuf>,
+    /// Repositories scanned at the same time with `--recursive-repos`
+    /// (defaults to the number of CPUs)
+    #[arg(long, value_name = "N", requires = "recursive_repos")]
+    jobs: Option<usize>,
+    /// Group the violations in the report by CODEOWNERS owner or Python package
+    #[arg(long, value_enum, value_name = "KEY")]
+    group_by: Option<GroupBy>,
+    /// Write violation counts, venv sizes, and scan duration to this file in
+    /// Prometheus textfile-collector format
+    #[arg(long, value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+    /// Export scan spans and metrics to this OTLP/HTTP endpoint (defaults to
+    /// `$OTEL_EXPORTER_OTLP_ENDPOINT`; nothing is exported when neither is set)
+    #[arg(long, value_name = "URL")]
+    otlp_endpoint: Option<String>,
+    /// Also report violations in the format of this CI system
+    #[arg(long, value_enum, value_name = "PROVIDER")]
+    ci: Option<ci::CiProvider>,
+    /// Stop once the scan has run this long; set by `hook run`
+    #[arg(skip)]
+    time_budget: Option<Duration>,
+}
+
+/// How `--group-by` groups violations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    /// The owners CODEOWNERS assigns to each venv
+    Owner,
+    /// The nearest Python package (`pyproject.toml`, `setup.cfg`, or
+    /// `setup.py`) above each venv
+    Package,
+}
+
+/// A `--max-dirs`, `--max-files`, or time limit that cut a scan short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanLimit {
+    Dirs(u64),
+    Files(u64),
+    Time(Duration),
+}
+
+impl ScanLimit {
+    fn describe(self) -> String {
+        match self {
+            Self::Dirs(max) => format!("visiting {max} directories (--max-dirs)"),
+            Self::Files(max) => format!("seeing {max} files (--max-files)"),
+            Self::Time(budget) => format!("running for {} ms", budget.as_millis()),
+        }
+    }
+}
+
+/// What a scan found
+struct ScanOutcome {
+    venvs: Vec<VenvInfo>,
+    /// Tooling directories Git does not ignore, such as `.direnv/`
+    artifacts: Vec<artifacts::Artifact>,
+    /// Set when a limit stopped the scan early, so `venvs` may be incomplete
+    limit: Option<ScanLimit>,
+    /// Time spent in each phase, for telemetry
+    phases: PhaseTimes,
+}
+
+/// Cumulative time spent in the phases interleaved with the walk
+#[derive(Debug, Default, Clone, Copy)]
+struct PhaseTimes {
+    /// Classifying directories and asking Git about ignore rules
+    ignore: Duration,
+    /// Reading `pyvenv.cfg` files
+    parse: Duration,
+}
+
+/// The tool as workhelix-cli-common's doctor report sees it
+struct UnvenvTool {
+    network: doctor::NetworkOptions,
+}
 
 impl DoctorChecks for UnvenvTool {
     fn repo_info() -> RepoInfo {
@@ -80,154 +742,1296 @@ impl DoctorChecks for UnvenvTool {
     }
 
     fn tool_checks(&self) -> Vec<DoctorCheck> {
-        let mut checks = Vec::new();
-
-        // Check if in git repository
-        if let Ok(repo) = Repository::discover(".") {
-            if repo.is_bare() {
-                checks.push(DoctorCheck::fail(
-                    "Git repository check",
-                    "In bare Git repository - unvenv works best with regular repositories",
-                ));
-            } else if let Some(workdir) = repo.workdir() {
-                checks.push(DoctorCheck::pass(format!(
-                    "Git repository: {}",
-                    workdir.display()
-                )));
-            }
-        }
-
-        checks
+        doctor::checks(Path::new("."), &self.network)
+            .iter()
+            .map(doctor::Check::to_doctor_check)
+            .collect()
     }
 }
 
 fn main() {
-    let exit_code = match run() {
-        Ok(code) => code,
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        // Help and version requests are not errors; usage errors get the
+        // configured code when the configuration can be read
+        let exit_codes = if e.use_stderr() {
+            std::env::current_dir()
+                .ok()
+                .and_then(|dir| config::load(&dir).ok())
+                .map(|config| config.exit_codes)
+                .unwrap_or_default()
+        } else {
+            config::ExitCodes::default()
+        };
+        let outcome = if e.use_stderr() {
+            Outcome::ConfigError
+        } else {
+            Outcome::Clean
+        };
+        let _ = e.print();
+        process::exit(exit_codes.code(outcome));
+    });
+
+    let config = if cli.command.as_ref().is_some_and(Commands::ignores_config) {
+        Ok(config::Config::default())
+    } else {
+        std::env::current_dir()
+            .context("Failed to get current directory")
+            .and_then(|dir| config::load(&dir))
+    };
+    // A broken configuration file still gets its error reported under the
+    // default codes
+    let exit_codes = config
+        .as_ref()
+        .map(|config| config.exit_codes)
+        .unwrap_or_default();
+
+    let exit_code = match config.and_then(|config| run(cli, &config)) {
+        Ok(code) => exit_codes.remap(code),
         Err(e) => {
             eprintln!("{} {}", "Error:".red().bold(), e);
-            1
+            logging::log(logging::Severity::Error, &format!("{e:#}"), &[]);
+            exit_codes.code(if e.is::<config::ConfigError>() {
+                Outcome::ConfigError
+            } else {
+                Outcome::InternalError
+            })
         }
     };
     process::exit(exit_code);
 }
 
-fn run() -> Result<i32> {
-    let cli = Cli::parse();
+fn run(cli: Cli, config: &config::Config) -> Result<i32> {
+    logging::init(cli.log);
 
     // Check if stdout is a TTY for decoration
     let is_tty = workhelix_cli_common::output::is_tty();
 
     match cli.command {
-        Some(Commands::Version) => {
-            if is_tty {
-                println!("{} {}", "unvenv".green().bold(), VERSION);
-            } else {
-                println!("unvenv {VERSION}");
-            }
-            Ok(0)
+        Some(Commands::Version { json: true }) => build_info::print_json(),
+        Some(Commands::Version { json: false }) => Ok(print_version(is_tty)),
+        Some(Commands::License { third_party: true }) => Ok(third_party::run(is_tty)),
+        Some(Commands::License { third_party: false }) => Ok(print_license()),
+        Some(Commands::Scan(args)) => scan_for_venvs(&args, is_tty),
+        None => {
+            // Default behavior: scan for venv files
+            scan_for_venvs(&ScanArgs::default(), is_tty)
         }
-        Some(Commands::License) => {
-            println!(
-                "{}",
-                workhelix_cli_common::license::display_license("unvenv", LicenseType::MIT)
-            );
-            Ok(0)
+        Some(Commands::Ci { provider, base }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            ci::run(&workdir, provider, base)
         }
-        Some(Commands::Scan) | None => {
-            // Default behavior: scan for venv files
-            scan_for_venvs(is_tty)
+        Some(Commands::Audit { since, max_commits }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            since.map_or_else(
+                || health::run(&workdir, max_commits, is_tty),
+                |since| audit::run_audit(&workdir, &since, is_tty),
+            )
+        }
+        Some(Commands::AuditOrg {
+            org,
+            token,
+            jobs,
+            rate_limit,
+            retries,
+            include_archived,
+        }) => org_audit::run_audit_org(
+            &org,
+            org_audit::OrgAuditOptions {
+                token,
+                jobs,
+                rate_limit,
+                retries,
+                include_archived,
+            },
+            is_tty,
+        ),
+        Some(Commands::Bench) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            bench::run_bench(&workdir, is_tty)
+        }
+        Some(Commands::LintVenvs) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            venv_lint::run(&workdir, is_tty)
+        }
+        Some(Commands::Top) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            top::run(&workdir, is_tty)
+        }
+        Some(Commands::Status { porcelain }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            status::run(&workdir, porcelain, is_tty)
+        }
+        Some(Commands::Stats { action }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            run_stats(&workdir, &action, is_tty)
+        }
+        Some(Commands::SelfTest) => self_test::run(is_tty),
+        Some(Commands::Serve) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            serve::run(&workdir)
+        }
+        Some(Commands::Report(args)) => run_report(args, is_tty),
+        Some(Commands::Hook { action }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            run_hook_command(&workdir, &action, &config.hook, is_tty)
         }
         Some(Commands::Completions { shell }) => {
             workhelix_cli_common::completions::generate_completions::<Cli>(shell);
             Ok(0)
         }
-        Some(Commands::Doctor) => Ok(workhelix_cli_common::doctor::run_doctor(&UnvenvTool)),
-        Some(Commands::Update {
-            version,
-            force,
-            install_dir,
-        }) => Ok(workhelix_cli_common::update::run_update(
-            &UnvenvTool::repo_info(),
-            UnvenvTool::current_version(),
-            version.as_deref(),
-            force,
-            install_dir.as_deref(),
-        )),
+        Some(Commands::Doctor {
+            format,
+            token,
+            proxy,
+        }) => {
+            let network = doctor::NetworkOptions { token, proxy };
+            match format {
+                OutputFormat::Text => Ok(workhelix_cli_common::doctor::run_doctor(&UnvenvTool {
+                    network,
+                })),
+                OutputFormat::Json => doctor::run_json(Path::new("."), VERSION, &network),
+            }
+        }
+        Some(Commands::Update(args)) => run_update(&args, &config.update, is_tty),
+        Some(Commands::Dev { action }) => run_dev(action, is_tty),
+        Some(Commands::Quickstart { yes }) => {
+            let workdir = std::env::current_dir().context("Failed to get current directory")?;
+            quickstart::run(&workdir, yes, is_tty)
+        }
+    }
+}
+
+/// `unvenv version`
+fn print_version(is_tty: bool) -> i32 {
+    if is_tty {
+        println!("{} {}", "unvenv".green().bold(), VERSION);
+    } else {
+        println!("unvenv {VERSION}");
+    }
+    0
+}
+
+/// `unvenv license`
+fn print_license() -> i32 {
+    println!(
+        "{}",
+        workhelix_cli_common::license::display_license("unvenv", LicenseType::MIT)
+    );
+    0
+}
+
+/// `unvenv stats record|show`
+fn run_stats(workdir: &Path, action: &StatsCommand, is_tty: bool) -> Result<i32> {
+    match action {
+        StatsCommand::Record { file } => stats::run_record(workdir, file.as_deref(), is_tty),
+        StatsCommand::Show { file, last } => {
+            stats::run_show(workdir, file.as_deref(), *last, is_tty)
+        }
+    }
+}
+
+/// `unvenv hook ...`
+fn run_hook_command(
+    workdir: &Path,
+    action: &HookCommand,
+    settings: &config::HookSettings,
+    is_tty: bool,
+) -> Result<i32> {
+    match action {
+        HookCommand::Install => hook::run_install(workdir, is_tty),
+        HookCommand::Uninstall => hook::run_uninstall(workdir, is_tty),
+        HookCommand::Status { update } => hook::run_status(workdir, *update, is_tty),
+        HookCommand::Run => hook::run_hook(workdir, settings, is_tty),
+        HookCommand::Config { manager } => Ok(hook::run_config(*manager)),
+    }
+}
+
+/// `unvenv dev ...`
+fn run_dev(action: DevCommand, is_tty: bool) -> Result<i32> {
+    match action {
+        DevCommand::Fixture {
+            dir,
+            ignored,
+            untracked,
+            staged,
+            committed,
+            no_caches,
+            no_submodule,
+            no_non_utf8,
+        } => fixture::run(
+            &dir,
+            &fixture::Mix {
+                ignored,
+                untracked,
+                staged,
+                committed,
+                caches: !no_caches,
+                submodule: !no_submodule,
+                non_utf8: !no_non_utf8,
+            },
+            is_tty,
+        ),
+    }
+}
+
+/// Self-update, keeping the replaced binary around for `update --rollback`
+fn run_update(args: &UpdateArgs, settings: &config::UpdateSettings, is_tty: bool) -> Result<i32> {
+    let install_dir = args.install_dir.as_deref();
+    if args.rollback {
+        return rollback::run_rollback(install_dir, is_tty);
+    }
+
+    let binary = rollback::target_binary(install_dir)?;
+
+    // Installing into an explicit directory never touches the managed copy
+    if install_dir.is_none() && !args.force_self {
+        if let Some(manager) = package_manager::detect(&binary) {
+            print_package_manager_hint(manager, &binary, is_tty);
+            // Like a usage error: the command needs `--force-self` here
+            return Ok(Outcome::ConfigError.default_code());
+        }
+    }
+
+    // Left to itself, install.sh installs to ~/.local/bin; point it at the
+    // binary that is checked and backed up instead
+    let target_dir = binary
+        .parent()
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    preflight::check_update(&target_dir, &binary)?;
+    let staged = rollback::stage_backup(&binary)?;
+    let exit_code = installer::run(
+        &target_dir,
+        &installer::Options {
+            version: args.version.as_deref(),
+            channel: args.channel.or(settings.channel),
+            force: args.force,
+            token: args.token.as_deref(),
+            proxy: args.proxy.as_deref(),
+        },
+    )?;
+
+    if let Some(staged) = staged {
+        if exit_code == 0 {
+            if rollback::commit_backup(&binary, &staged)? {
+                println!(
+                    "Previous version kept at {}; run `unvenv update --rollback` to restore it",
+                    rollback::backup_path(&binary).display()
+                );
+            }
+        } else {
+            // A failed update leaves the existing backup alone
+            let _ = fs::remove_file(&staged);
+        }
+    }
+    // install.sh's own codes mean nothing to `[exit-codes]`; any failure
+    // is an internal error rather than, say, a violation
+    Ok(if exit_code == 0 {
+        Outcome::Clean.default_code()
+    } else {
+        Outcome::InternalError.default_code()
+    })
+}
+
+/// Scan, then print, save, or post the report; or work on saved reports
+fn run_report(args: ReportArgs, is_tty: bool) -> Result<i32> {
+    match &args.command {
+        Some(ReportCommand::Diff { old, new }) => {
+            return report_diff::run_diff(old, new, is_tty);
+        }
+        Some(ReportCommand::Verify {
+            report,
+            public_key,
+            signature,
+        }) => return signing::run_verify(report, public_key, signature.as_deref(), is_tty),
+        None => {}
     }
+
+    let workdir = std::env::current_dir().context("Failed to get current directory")?;
+    let scan_args = ScanArgs::default();
+    let mut outcome = find_unignored_venvs(&workdir, &scan_args, is_tty)?;
+    let skips = rules::Skips::from_env();
+    let mut packages = packages::Packages::new(&workdir);
+    let findings = findings::apply(&workdir, &scan_args, &skips, &mut packages, &mut outcome)?;
+    skips.announce(findings.skipped);
+    let exit_code = findings.outcome(&outcome).default_code();
+
+    let markdown = if args.json {
+        let report = scan_report(&workdir, &outcome, &findings.references);
+        format!("{}\n", serde_json::to_string_pretty(&report)?)
+    } else {
+        ci::markdown_summary(&outcome.venvs)
+            + &artifacts::markdown_section(&outcome.artifacts)
+            + &references::markdown_section(&findings.references)
+            + &allow::markdown_section(&findings.suppressed)
+    };
+
+    if let Some(output) = &args.output {
+        fs::write(output, &markdown)
+            .with_context(|| format!("Failed to write report to {}", output.display()))?;
+        if let Some(secret_key) = &args.sign {
+            let comment = format!("unvenv {VERSION} report of {}", workdir.display());
+            let signature = signing::sign(output, secret_key, &comment)?;
+            eprintln!(
+                "Signed {} (signature in {})",
+                output.display(),
+                signature.display()
+            );
+        }
+        return Ok(exit_code);
+    }
+
+    if !args.github_pr {
+        print!("{markdown}");
+        return Ok(exit_code);
+    }
+
+    let pr = github::PullRequest::detect(args.repo, args.pr).context(
+        "Could not determine the pull request; pass --repo and --pr outside GitHub Actions",
+    )?;
+    let token = args
+        .token
+        .or_else(github::token_from_env)
+        .context("No GitHub token; pass --token or set GITHUB_TOKEN")?;
+    let action = github::post_sticky_comment(&pr, &token, &markdown)?;
+    let verb = match action {
+        github::CommentAction::Created => "Posted",
+        github::CommentAction::Updated => "Updated",
+    };
+    println!("{verb} the unvenv comment on {}#{}", pr.repo, pr.number);
+    Ok(exit_code)
+}
+
+/// The scan results in the public report model, with the `references` found
+/// along with them
+///
+/// Each violation also gets its path relative to the repository root and
+/// its package, so per-package tools in a monorepo need not work out the
+/// prefixes themselves.
+fn scan_report(
+    workdir: &Path,
+    outcome: &ScanOutcome,
+    references: &[references::Reference],
+) -> unvenv::ScanReport {
+    let matcher = IgnoreMatcher::discover(workdir);
+    let repo_path = |path: &Path| {
+        matcher
+            .as_ref()
+            .and_then(|matcher| matcher.git_path(&workdir.join(path)))
+    };
+    let mut packages = packages::Packages::new(workdir);
+
+    let violations = outcome
+        .venvs
+        .iter()
+        .map(|venv| {
+            let mut violation = venv.to_violation();
+            violation.repo_path = repo_path(&venv.path);
+            violation.package = packages.package_of(&venv.path).map(|package| {
+                let path = venv.path.strip_prefix(&package.dir).unwrap_or(&venv.path);
+                let mut report_package = unvenv::Package::new(
+                    package.name.clone(),
+                    package.dir.clone(),
+                    path.to_path_buf(),
+                );
+                report_package.repo_dir = repo_path(&package.dir);
+                report_package
+            });
+            violation
+        })
+        .collect();
+    let mut report = unvenv::ScanReport::new(VERSION, workdir.to_path_buf(), violations);
+    report.artifacts = outcome
+        .artifacts
+        .iter()
+        .map(artifacts::Artifact::to_report)
+        .collect();
+    report.references = references
+        .iter()
+        .map(references::Reference::to_report)
+        .collect();
+    report.partial = outcome.limit.is_some();
+    report
 }
 
-fn scan_for_venvs(is_tty: bool) -> Result<i32> {
+/// Explain how to upgrade a package-managed install
+fn print_package_manager_hint(manager: PackageManager, binary: &Path, is_tty: bool) {
+    if is_tty {
+        println!(
+            "{} {} was installed by {}; updating it in place would bypass the package manager.",
+            "Note:".yellow().bold(),
+            binary.display().to_string().cyan(),
+            manager.name()
+        );
+        println!("To upgrade, run: {}", manager.upgrade_command().yellow());
+    } else {
+        println!(
+            "Note: {} was installed by {}; updating it in place would bypass the package manager.",
+            binary.display(),
+            manager.name()
+        );
+        println!("To upgrade, run: {}", manager.upgrade_command());
+    }
+    println!("Use `unvenv update --force-self` to update this copy anyway.");
+}
+
+fn scan_for_venvs(args: &ScanArgs, is_tty: bool) -> Result<i32> {
     let workdir = std::env::current_dir().context("Failed to get current directory")?;
-    scan_for_venvs_in_dir(&workdir, is_tty)
+    // The pre-commit framework captures the output and shows it on failure,
+    // where a spinner or update hint would only be noise; scripts reading
+    // porcelain or JSON output want nothing but the records
+    let is_tty =
+        is_tty && !args.pre_commit && args.porcelain.is_none() && args.format != OutputFormat::Json;
+    if let Some(image) = &args.image {
+        return image::run(image, is_tty);
+    }
+    // Without a repository nothing is ignored, so the scan would pass or fail
+    // for the wrong reason
+    if args.require_git && IgnoreMatcher::discover(&workdir).is_none() {
+        eprintln!(
+            "{} No Git repository found at {}; --require-git makes this a failure",
+            "Error:".red().bold(),
+            workdir.display()
+        );
+        return Ok(Outcome::NoRepository.default_code());
+    }
+    let exit_code = match &args.recursive_repos {
+        Some(root) => multi_repo::run(root, args, is_tty),
+        None => scan_for_venvs_in_dir(&workdir, args, is_tty)?,
+    };
+    update_hint::maybe_print(VERSION, is_tty);
+    quickstart::maybe_print_hint(is_tty);
+    Ok(exit_code)
 }
 
 /// Scan a specific directory for unignored Python virtual environments
-fn scan_for_venvs_in_dir(workdir: &Path, is_tty: bool) -> Result<i32> {
-    // Try to discover Git repository for ignore checking, but don't require it
-    let repo = Repository::discover(workdir).ok();
-
-    // Find all pyvenv.cfg files in the directory tree
-    let mut unignored_venvs = Vec::new();
-
-    for entry in WalkDir::new(workdir)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            // Skip .git directory
-            e.file_name().to_str() != Some(".git")
-        })
-    {
-        let entry = entry.context("Failed to read directory entry")?;
-
-        // Check if this is a pyvenv.cfg file
-        if entry.file_name() == "pyvenv.cfg" && entry.file_type().is_file() {
-            let full_path = entry.path();
-
-            // Get path relative to current workdir
-            let rel_path = full_path
-                .strip_prefix(workdir)
-                .context("Failed to create relative path")?;
-
-            // Check if file is ignored by Git (if we have a repo)
-            let is_ignored = if let Some(ref repo) = repo {
-                // Skip bare repositories
-                if repo.is_bare() {
-                    false
-                } else {
-                    repo.status_should_ignore(rel_path)
-                        .context("Failed to check Git ignore status")?
-                }
+fn scan_for_venvs_in_dir(workdir: &Path, args: &ScanArgs, is_tty: bool) -> Result<i32> {
+    let commit_cache = match &args.cache_key {
+        Some(rev) => commit_cache::CommitCache::open(workdir, rev)?,
+        None => None,
+    };
+    if let Some(cache) = &commit_cache {
+        if let Some(result) = cache.lookup() {
+            return Ok(cache.replay(result, is_tty));
+        }
+    }
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    let mut outcome = find_unignored_venvs(workdir, args, is_tty)?;
+    let duration = started.elapsed();
+    let skips = rules::Skips::from_env();
+    let mut packages = packages::Packages::new(workdir);
+    let findings = findings::apply(workdir, args, &skips, &mut packages, &mut outcome)?;
+    skips.announce(findings.skipped);
+
+    export_scan(workdir, args, &outcome, started_at, duration);
+    if let Some(limit) = outcome.limit {
+        eprintln!(
+            "{} Scan stopped after {}; results are partial",
+            "Warning:".yellow().bold(),
+            limit.describe()
+        );
+    }
+    if let Some(provider) = args.ci {
+        ci::report(provider, &outcome.venvs)?;
+    }
+    print_scan(args, &outcome, &findings, is_tty);
+
+    if findings.fails(&outcome) {
+        if let Some(path) = &args.emit_script {
+            fix_script::write(path, workdir, &outcome.venvs)?;
+        }
+        match args.group_by {
+            _ if outcome.venvs.is_empty() => {}
+            Some(GroupBy::Owner) => print_owner_groups(&outcome.venvs, is_tty),
+            Some(GroupBy::Package) => {
+                packages::print_package_groups(&mut packages, &outcome.venvs, is_tty);
+            }
+            None => {}
+        }
+    }
+    let exit_code = findings.outcome(&outcome).default_code();
+
+    let exit_code = if args.fix && !outcome.venvs.is_empty() {
+        fix_and_rescan(workdir, args, &outcome.venvs, &skips, &mut packages, is_tty)?
+            .unwrap_or(exit_code)
+    } else {
+        exit_code
+    };
+
+    // A partial result says nothing about the commit
+    if let (Some(cache), None) = (commit_cache, outcome.limit) {
+        let violations = outcome
+            .venvs
+            .iter()
+            .map(|venv| venv.path.clone())
+            .chain(
+                outcome
+                    .artifacts
+                    .iter()
+                    .map(|artifact| artifact.path.clone()),
+            )
+            .chain(
+                findings
+                    .references
+                    .iter()
+                    .map(|reference| reference.file.clone()),
+            )
+            .collect();
+        if let Err(e) = cache.record(exit_code, violations) {
+            eprintln!("{} {e:#}", "Warning:".yellow().bold());
+        }
+    }
+
+    if args.format == OutputFormat::Json {
+        let findings = findings::json_findings(&outcome, &findings.references);
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+    }
+    Ok(exit_code)
+}
+
+/// Hand the scan result to the system log, OpenTelemetry, and the
+/// Prometheus textfile, as configured
+fn export_scan(
+    workdir: &Path,
+    args: &ScanArgs,
+    outcome: &ScanOutcome,
+    started_at: SystemTime,
+    duration: Duration,
+) {
+    logging::scan_result("scan", workdir, &outcome.venvs, outcome.limit.is_some());
+    telemetry::export(
+        args.otlp_endpoint.as_deref(),
+        &telemetry::ScanTelemetry {
+            mode: "scan",
+            started: started_at,
+            duration,
+            phases: outcome.phases,
+            violations: outcome.venvs.len(),
+            partial: outcome.limit.is_some(),
+        },
+    );
+
+    if let Some(path) = &args.metrics_file {
+        let metrics = metrics::ScanMetrics::collect(
+            workdir,
+            &outcome.venvs,
+            duration,
+            outcome.limit.is_some(),
+        );
+        // Missing metrics must not hide the scan result
+        if let Err(e) = metrics::write_metrics(path, workdir, &metrics) {
+            eprintln!("{} {e:#}", "Warning:".yellow().bold());
+        }
+    }
+}
+
+/// Print the porcelain records, or the human report unless JSON was asked for
+fn print_scan(args: &ScanArgs, outcome: &ScanOutcome, findings: &findings::Findings, is_tty: bool) {
+    if let Some(version) = args.porcelain {
+        let results = porcelain::Results {
+            violations: &outcome.venvs,
+            warned: &findings.warned,
+            suppressed: findings
+                .suppressed
+                .iter()
+                .map(|finding| &finding.venv)
+                .collect(),
+            references: &findings.references,
+            artifacts: &outcome.artifacts,
+            partial: outcome.limit.is_some(),
+        };
+        print!(
+            "{}",
+            porcelain::render(version, &results, args.null_terminated)
+        );
+        return;
+    }
+    if args.format == OutputFormat::Json {
+        return;
+    }
+
+    if !findings.warned.is_empty() {
+        packages::print_warned(&findings.warned, is_tty);
+    }
+    if !findings.suppressed.is_empty() {
+        allow::print_suppressed(&findings.suppressed, is_tty);
+    }
+    if !outcome.venvs.is_empty() {
+        print_violation_report(&outcome.venvs, is_tty);
+    }
+    if !outcome.artifacts.is_empty() {
+        artifacts::print_artifacts(&outcome.artifacts, is_tty);
+    }
+    if !findings.references.is_empty() {
+        references::print_references(&findings.references, is_tty);
+    }
+}
+
+/// Apply the fix for `venvs` and rescan, returning the fixed exit code when
+/// the fresh scan finds nothing left to fail on
+fn fix_and_rescan(
+    workdir: &Path,
+    args: &ScanArgs,
+    venvs: &[VenvInfo],
+    skips: &rules::Skips,
+    packages: &mut packages::Packages,
+    is_tty: bool,
+) -> Result<Option<i32>> {
+    let applied = fix_script::apply(workdir, venvs)?;
+    // Scripts reading porcelain records or JSON only get the exit code
+    if args.porcelain.is_none() && args.format != OutputFormat::Json {
+        print_fix_summary(&applied, is_tty);
+    }
+    // The fix only counts when a fresh scan agrees
+    let mut rescan = find_unignored_venvs(workdir, args, false)?;
+    let remaining = findings::apply(workdir, args, skips, packages, &mut rescan)?;
+    let fixed = remaining.outcome(&rescan) == Outcome::Clean;
+    Ok(fixed.then(|| Outcome::Fixed.default_code()))
+}
+
+/// Collect every `pyvenv.cfg` under `workdir` that Git does not ignore
+///
+/// With `show_progress`, a spinner is drawn if the scan takes a while.
+fn find_unignored_venvs(
+    workdir: &Path,
+    args: &ScanArgs,
+    show_progress: bool,
+) -> Result<ScanOutcome> {
+    let mut scanner = Scanner::new(workdir, args, show_progress);
+    // Taken before the walk: a commit made while it runs (e.g. by a hook)
+    // must still count as changed for the next incremental scan
+    let started_head = scanner
+        .matcher
+        .as_ref()
+        .filter(|_| args.incremental)
+        .and_then(|matcher| incremental::head_commit(matcher.repo()));
+
+    let changed_paths = if args.pre_commit {
+        Some(args.files.iter().map(|file| workdir.join(file)).collect())
+    } else if args.staged {
+        scanner.staged_plan()?
+    } else if args.incremental {
+        scanner.incremental_plan()?
+    } else {
+        None
+    };
+    match changed_paths {
+        Some(paths) => {
+            // Cached verdicts are stamped with the ignore rules inherited
+            // down a walk from `workdir`; a walk starting at a changed
+            // subdirectory has not seen the `.gitignore` files above it, and
+            // saving its few verdicts would drop the rest of the cache
+            scanner.cache = None;
+            for path in paths {
+                scanner.check_changed_path(&path)?;
+            }
+        }
+        // Full walk, also the fallback when no previous scan was recorded
+        None => scanner.walk(workdir)?,
+    }
+
+    // A partial scan must not become the baseline for the next one
+    if args.incremental && scanner.limit.is_none() {
+        scanner.record_scan(started_head);
+    }
+
+    Ok(scanner.finish())
+}
+
+/// Deepest directory level visited when following symbolic links, so that a
+/// link farm cannot keep the scanner busy indefinitely
+const MAX_FOLLOW_DEPTH: usize = 256;
+
+/// What an unreadable directory does to a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unreadable {
+    /// Skip it and summarize the skipped paths after the report
+    Skip,
+    /// Fail the scan, with `--fail-on-unreadable`
+    Fail,
+}
+
+/// How the directory walks of one scan behave, from the command line
+#[derive(Debug, Clone, Copy)]
+struct WalkOptions {
+    unreadable: Unreadable,
+    follow_symlinks: bool,
+    ignore_case: bool,
+    /// Trace pruning and venv decisions, with `--explain-scan`
+    explain: bool,
+}
+
+impl WalkOptions {
+    const fn from_args(args: &ScanArgs) -> Self {
+        Self {
+            unreadable: if args.fail_on_unreadable {
+                Unreadable::Fail
+            } else {
+                Unreadable::Skip
+            },
+            follow_symlinks: args.follow_symlinks,
+            ignore_case: args.ignore_case,
+            explain: args.explain_scan,
+        }
+    }
+}
+
+/// State shared by the directory walks making up one scan
+struct Scanner<'a> {
+    workdir: &'a Path,
+    matcher: Option<IgnoreMatcher>,
+    /// Verdicts from the `git` CLI instead of libgit2, with `--ignore-engine git`
+    check_ignore: Option<CheckIgnore>,
+    codeowners: Option<CodeOwners>,
+    cache: Option<ScanCache>,
+    /// Network mounts to leave alone, with `--skip-network-fs`
+    network_mounts: Option<NetworkMounts>,
+    progress: ScanProgress,
+    venvs: Vec<VenvInfo>,
+    artifacts: Vec<artifacts::Artifact>,
+    /// Directories already evaluated, so overlapping walks report each venv once
+    visited: HashSet<PathBuf>,
+    options: WalkOptions,
+    /// (device, inode) of every directory entered while following symlinks
+    dir_ids: HashSet<(u64, u64)>,
+    /// Paths that could not be read, with the reason
+    unreadable: Vec<(PathBuf, String)>,
+    max_dirs: Option<u64>,
+    max_files: Option<u64>,
+    /// When the time budget runs out, if there is one
+    deadline: Option<(Instant, Duration)>,
+    dirs: u64,
+    files: u64,
+    /// The limit that stopped the scan, if any
+    limit: Option<ScanLimit>,
+    phases: PhaseTimes,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(workdir: &'a Path, args: &ScanArgs, show_progress: bool) -> Self {
+        // Try to discover Git repository for ignore checking, but don't require it
+        let matcher = if args.no_git {
+            None
+        } else {
+            IgnoreMatcher::discover(workdir)
+        };
+        let check_ignore = (args.ignore_engine == IgnoreEngine::Git)
+            .then(|| {
+                matcher
+                    .as_ref()
+                    .map(|matcher| CheckIgnore::new(matcher.root()))
+            })
+            .flatten();
+        let codeowners = matcher
+            .as_ref()
+            .and_then(|matcher| CodeOwners::load(matcher.root()));
+        let cache = if args.cache {
+            ScanCache::open(workdir, matcher.as_ref(), args.ignore_case)
+        } else {
+            None
+        };
+        let network_mounts = args.skip_network_fs.then(|| NetworkMounts::below(workdir));
+
+        Self {
+            workdir,
+            matcher,
+            check_ignore,
+            codeowners,
+            cache,
+            network_mounts,
+            // The spinner would garble the trace
+            progress: ScanProgress::new(show_progress && !args.explain_scan),
+            venvs: Vec::new(),
+            artifacts: Vec::new(),
+            visited: HashSet::new(),
+            options: WalkOptions::from_args(args),
+            dir_ids: HashSet::new(),
+            unreadable: Vec::new(),
+            max_dirs: args.max_dirs,
+            max_files: args.max_files,
+            deadline: args
+                .time_budget
+                .map(|budget| (Instant::now() + budget, budget)),
+            dirs: 0,
+            files: 0,
+            limit: None,
+            phases: PhaseTimes::default(),
+        }
+    }
+
+    /// Print a trace line with `--explain-scan`
+    fn explain(&self, action: &str, path: &Path, detail: impl FnOnce() -> String) {
+        if self.options.explain {
+            trace::event(self.workdir, action, path, &detail());
+        }
+    }
+
+    /// Why Git ignores `full_path`, for the trace
+    fn ignored_detail(&self, full_path: &Path) -> String {
+        // The git engine already knows the pattern
+        if let (Some(matcher), Some(check_ignore)) = (&self.matcher, &self.check_ignore) {
+            if let Some(found) = matcher
+                .git_path(full_path)
+                .and_then(|git_path| check_ignore.checked(&git_path))
+            {
+                return format!("ignored by {found}");
+            }
+        }
+        self.matcher
+            .as_ref()
+            .and_then(|matcher| Some((matcher.root(), matcher.git_path(full_path)?)))
+            .map_or_else(
+                || "ignored by Git".to_string(),
+                |(root, git_path)| trace::ignored_detail(root, &git_path),
+            )
+    }
+
+    /// Walk the tree below `root`, pruning ignored directories and venv roots
+    fn walk(&mut self, root: &Path) -> Result<()> {
+        if self.limit.is_some() {
+            return Ok(());
+        }
+
+        let mut walker = WalkDir::new(root)
+            .follow_links(self.options.follow_symlinks)
+            .max_depth(if self.options.follow_symlinks {
+                MAX_FOLLOW_DEPTH
             } else {
-                // No Git repo, so treat as not ignored
-                false
+                usize::MAX
+            })
+            .into_iter()
+            .filter_entry(|e| {
+                // Skip .git directory
+                e.file_name().to_str() != Some(".git")
+            });
+
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // A link back to an ancestor directory; everything below it
+                // has been or will be scanned through the ancestor
+                Err(e) if e.loop_ancestor().is_some() => continue,
+                // One unreadable directory (typically permission denied)
+                // should not hide violations elsewhere in the tree
+                Err(e) if self.options.unreadable == Unreadable::Skip => {
+                    let path = e.path().unwrap_or(root).to_path_buf();
+                    let reason = e
+                        .io_error()
+                        .map_or_else(|| e.to_string(), ToString::to_string);
+                    self.explain("skip", &path, || format!("unreadable: {reason}"));
+                    self.unreadable.push((path, reason));
+                    continue;
+                }
+                Err(e) => return Err(e).context("Failed to read directory entry"),
             };
 
-            if !is_ignored {
-                // Parse the pyvenv.cfg file
-                let venv_info = parse_pyvenv_cfg(full_path, rel_path)?;
-                unignored_venvs.push(venv_info);
+            let is_dir = entry.file_type().is_dir();
+            if !self.within_limits(is_dir) {
+                break;
+            }
+            if !is_dir {
+                continue;
+            }
+            self.progress.dir_visited();
+
+            // Different links can lead to the same directory; enter it only once
+            if self.options.follow_symlinks && !first_visit(&mut self.dir_ids, &entry) {
+                self.explain("prune", entry.path(), || {
+                    "already visited through another link".to_string()
+                });
+                walker.skip_current_dir();
+                continue;
+            }
+
+            if self
+                .network_mounts
+                .as_ref()
+                .is_some_and(|mounts| mounts.is_mount_point(entry.path()))
+            {
+                eprintln!(
+                    "{} Skipping network filesystem at {}",
+                    "Note:".yellow().bold(),
+                    entry.path().display()
+                );
+                self.explain("prune", entry.path(), || {
+                    "network filesystem (--skip-network-fs)".to_string()
+                });
+                walker.skip_current_dir();
+                continue;
+            }
+
+            if self.matcher.is_some() && entry.depth() > 0 && is_nested_repository(entry.path()) {
+                self.explain("prune", entry.path(), || {
+                    "submodule or nested repository; scan it on its own".to_string()
+                });
+                walker.skip_current_dir();
+                continue;
+            }
+
+            let verdict = self.classify(&entry)?;
+
+            // Nothing inside an ignored directory can be reported, and nothing
+            // below a venv root can change the verdict (venvs often hold hundreds
+            // of thousands of files), so neither is descended into
+            if verdict != DirVerdict::Plain {
+                walker.skip_current_dir();
+            }
+            match verdict {
+                DirVerdict::Ignored => {
+                    self.explain("prune", entry.path(), || self.ignored_detail(entry.path()));
+                }
+                DirVerdict::Venv => self.explain("prune", entry.path(), || {
+                    "venv root; its contents are not walked".to_string()
+                }),
+                DirVerdict::Plain => {}
+            }
+            if verdict == DirVerdict::Venv {
+                self.check_venv(entry.path())?;
+            }
+            if verdict == DirVerdict::Plain && entry.depth() > 0 {
+                if let Some(kind) = ArtifactKind::detect(entry.path()) {
+                    self.record_artifact(entry.path(), kind);
+                    if kind.prunes() {
+                        self.explain("prune", entry.path(), || {
+                            format!("{}; its contents are not walked", kind.rule().describe())
+                        });
+                        walker.skip_current_dir();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report the tooling directory at `dir`, once
+    fn record_artifact(&mut self, dir: &Path, kind: ArtifactKind) {
+        let Ok(path) = dir.strip_prefix(self.workdir) else {
+            return;
+        };
+        if self.artifacts.iter().any(|artifact| artifact.path == path) {
+            return;
+        }
+        self.explain("check", dir, || {
+            format!("reported: {} not ignored", kind.rule().describe())
+        });
+        self.artifacts.push(artifacts::Artifact {
+            path: path.to_path_buf(),
+            kind,
+        });
+    }
+
+    /// Count a walked entry, returning false once a limit has been exceeded
+    fn within_limits(&mut self, is_dir: bool) -> bool {
+        if is_dir {
+            self.dirs += 1;
+            if let Some(max) = self.max_dirs.filter(|&max| self.dirs > max) {
+                self.limit = Some(ScanLimit::Dirs(max));
+            }
+        } else {
+            self.files += 1;
+            if let Some(max) = self.max_files.filter(|&max| self.files > max) {
+                self.limit = Some(ScanLimit::Files(max));
+            }
+        }
+        self.within_deadline()
+    }
+
+    /// Check the time budget, returning false once it has run out
+    fn within_deadline(&mut self) -> bool {
+        if let Some((deadline, budget)) = self.deadline {
+            if self.limit.is_none() && Instant::now() >= deadline {
+                self.limit = Some(ScanLimit::Time(budget));
             }
         }
+        self.limit.is_none()
     }
 
-    // Handle results
-    if unignored_venvs.is_empty() {
-        // No unignored venv files found
-        Ok(0)
+    /// Whether Git ignores the file or directory at `full_path`, asking the
+    /// engine chosen with `--ignore-engine`
+    fn is_ignored(&mut self, full_path: &Path, is_dir: bool) -> Result<bool> {
+        let Some(matcher) = self.matcher.as_mut() else {
+            return Ok(false);
+        };
+        match self.check_ignore.as_mut() {
+            Some(check_ignore) => matcher
+                .git_path(full_path)
+                .map_or(Ok(false), |git_path| check_ignore.is_ignored(&git_path)),
+            None if is_dir => matcher.is_dir_ignored(full_path),
+            None => matcher.is_file_ignored(full_path),
+        }
+    }
+
+    /// Classify a directory found by the walk, consulting the cache if enabled
+    fn classify(&mut self, entry: &DirEntry) -> Result<DirVerdict> {
+        let stamp = self.cache.as_mut().and_then(|cache| {
+            let metadata = entry.metadata().ok()?;
+            cache.stamp(entry.depth(), &metadata)
+        });
+        let cached = self
+            .cache
+            .as_ref()
+            .zip(stamp)
+            .and_then(|(cache, stamp)| cache.get(entry.path(), stamp));
+        let started = Instant::now();
+        let verdict = if let Some(verdict) = cached {
+            verdict
+        } else {
+            let ignored = self.is_ignored(entry.path(), true)?;
+            classify_dir(ignored, entry.path(), self.options.ignore_case)
+        };
+        self.phases.ignore += started.elapsed();
+
+        if let (Some(cache), Some(stamp)) = (self.cache.as_mut(), stamp) {
+            cache.insert(entry.path(), stamp, verdict);
+        }
+
+        Ok(verdict)
+    }
+
+    /// Report the venv rooted at `dir` unless Git ignores its pyvenv.cfg
+    fn check_venv(&mut self, dir: &Path) -> Result<()> {
+        if !self.visited.insert(dir.to_path_buf()) {
+            return Ok(());
+        }
+
+        let cfg_path =
+            find_venv_cfg(dir, self.options.ignore_case).unwrap_or_else(|| dir.join("pyvenv.cfg"));
+
+        // Get path relative to current workdir
+        let rel_path = cfg_path
+            .strip_prefix(self.workdir)
+            .context("Failed to create relative path")?;
+
+        if self.matcher.is_none() {
+            // No Git repo, so treat as not ignored
+            self.explain("check", &cfg_path, || {
+                "reported: no repository, nothing is ignored".to_string()
+            });
+            let started = Instant::now();
+            self.venvs.push(parse_pyvenv_cfg(&cfg_path, rel_path)?);
+            self.phases.parse += started.elapsed();
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let ignored = self.is_ignored(&cfg_path, false)?;
+        self.phases.ignore += started.elapsed();
+        if ignored {
+            self.explain("check", &cfg_path, || self.ignored_detail(&cfg_path));
+        } else if let Some(matcher) = self.matcher.as_ref() {
+            // Parse the pyvenv.cfg file
+            let started = Instant::now();
+            let mut venv_info = parse_pyvenv_cfg(&cfg_path, rel_path)?;
+            self.phases.parse += started.elapsed();
+            let git_path = matcher
+                .git_path(&cfg_path)
+                .unwrap_or_else(|| rel_path.to_path_buf());
+            let tracking = tracking_status(matcher.repo(), &git_path)?;
+            venv_info.tracking = Some(tracking);
+            if let Some(owners) = self
+                .codeowners
+                .as_ref()
+                .and_then(|codeowners| codeowners.owners(&git_path))
+            {
+                venv_info.owners = owners.to_vec();
+            }
+            self.venvs.push(venv_info);
+            self.explain("check", &cfg_path, || {
+                format!("reported: not ignored ({})", tracking.label())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Paths to evaluate for an incremental scan, or `None` for a full walk
+    fn incremental_plan(&self) -> Result<Option<Vec<PathBuf>>> {
+        let Some(matcher) = &self.matcher else {
+            return Ok(None);
+        };
+        let Some(record) = ScanRecord::load(matcher.repo()) else {
+            return Ok(None);
+        };
+
+        let paths = incremental::changed_paths(matcher.repo(), &record)?;
+        Ok(paths.map(|paths| {
+            paths
+                .iter()
+                .map(|path| matcher.root().join(path))
+                .filter(|path| path.starts_with(self.workdir))
+                .collect()
+        }))
+    }
+
+    /// Staged paths to evaluate, or `None` for a full walk outside a repository
+    fn staged_plan(&self) -> Result<Option<Vec<PathBuf>>> {
+        let Some(matcher) = &self.matcher else {
+            return Ok(None);
+        };
+
+        let paths = incremental::staged_paths(matcher.repo())?;
+        Ok(Some(
+            paths
+                .iter()
+                .map(|path| matcher.root().join(path))
+                .filter(|path| path.starts_with(self.workdir))
+                .collect(),
+        ))
+    }
+
+    /// Evaluate one path reported by Git as changed
+    fn check_changed_path(&mut self, path: &Path) -> Result<()> {
+        if !self.within_deadline() {
+            return Ok(());
+        }
+
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir()) {
+            return self.walk(path);
+        }
+
+        // A changed file may sit anywhere inside a venv, so look for the
+        // nearest enclosing venv root
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(self.workdir) || !self.visited.insert(dir.to_path_buf()) {
+                break;
+            }
+            if dir != self.workdir {
+                if let Some(kind) = ArtifactKind::detect(dir) {
+                    if !self.is_ignored(dir, true)? {
+                        self.record_artifact(dir, kind);
+                    }
+                }
+            }
+            if find_venv_cfg(dir, self.options.ignore_case).is_some() {
+                // check_venv dedupes by directory, so forget this visit first
+                self.visited.remove(dir);
+                self.check_venv(dir)?;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remember HEAD as of the start of the scan and the violations found
+    /// for the next incremental scan
+    fn record_scan(&self, head: Option<Oid>) {
+        let Some(matcher) = &self.matcher else {
+            return;
+        };
+
+        let record = ScanRecord {
+            head,
+            violations: self
+                .venvs
+                .iter()
+                .filter_map(|venv| matcher.git_path(&self.workdir.join(&venv.path)))
+                .collect(),
+        };
+
+        // A missing record only costs a full walk next time
+        if let Err(e) = record.save(matcher.repo()) {
+            eprintln!("{} {e:#}", "Warning:".yellow().bold());
+        }
+    }
+
+    /// Save the cache and hand back what the scan found
+    fn finish(mut self) -> ScanOutcome {
+        self.progress.clear();
+
+        if !self.unreadable.is_empty() {
+            eprintln!(
+                "{} Skipped {} unreadable path(s); use --fail-on-unreadable to make this an error:",
+                "Warning:".yellow().bold(),
+                self.unreadable.len()
+            );
+            for (path, reason) in &self.unreadable {
+                let rel_path = path.strip_prefix(self.workdir).unwrap_or(path);
+                eprintln!("  {}: {reason}", rel_path.display());
+            }
+        }
+
+        if let Some(cache) = self.cache {
+            // A stale or unwritable cache only costs speed, never correctness
+            if let Err(e) = cache.save() {
+                eprintln!("{} {e:#}", "Warning:".yellow().bold());
+            }
+        }
+
+        // The venv finding for `layout python` already covers its `.direnv/`
+        let venvs = &self.venvs;
+        self.artifacts.retain(|artifact| {
+            artifact.kind != ArtifactKind::Direnv
+                || !venvs
+                    .iter()
+                    .any(|venv| venv.path.starts_with(&artifact.path))
+        });
+
+        ScanOutcome {
+            venvs: self.venvs,
+            artifacts: self.artifacts,
+            limit: self.limit,
+            phases: self.phases,
+        }
+    }
+}
+
+/// Record the directory's identity, returning whether it was new
+#[cfg(unix)]
+fn first_visit(dir_ids: &mut HashSet<(u64, u64)>, entry: &DirEntry) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    entry.metadata().map_or(true, |metadata| {
+        dir_ids.insert((metadata.dev(), metadata.ino()))
+    })
+}
+
+/// Without stable inode numbers, rely on the walk's ancestor loop detection
+/// and [`MAX_FOLLOW_DEPTH`]
+#[cfg(not(unix))]
+const fn first_visit(_dir_ids: &mut HashSet<(u64, u64)>, _entry: &DirEntry) -> bool {
+    true
+}
+
+/// Decide whether a directory Git does or does not ignore is a venv root
+fn classify_dir(ignored: bool, dir: &Path, ignore_case: bool) -> DirVerdict {
+    if ignored {
+        return DirVerdict::Ignored;
+    }
+
+    // A directory with pyvenv.cfg at its root is a venv
+    let exact = dir.join("pyvenv.cfg");
+    let is_venv = fs::symlink_metadata(&exact).is_ok_and(|meta| meta.file_type().is_file())
+        || (ignore_case && find_venv_cfg(dir, true).is_some());
+    if is_venv {
+        DirVerdict::Venv
     } else {
-        // Found unignored venv files - print helpful output and exit with error
-        print_violation_report(&unignored_venvs, is_tty);
-        Ok(2)
+        DirVerdict::Plain
     }
 }
 
+/// Determine how far a `pyvenv.cfg` has made it into Git
+fn tracking_status(repo: &Repository, git_path: &Path) -> Result<TrackingStatus> {
+    let status = repo
+        .status_file(git_path)
+        .with_context(|| format!("Failed to check Git status of {}", git_path.display()))?;
+
+    Ok(if status.contains(Status::WT_NEW) {
+        TrackingStatus::Untracked
+    } else if status.contains(Status::INDEX_NEW) {
+        TrackingStatus::Staged
+    } else {
+        TrackingStatus::Committed
+    })
+}
+
 /// Parse a pyvenv.cfg file to extract useful metadata
 fn parse_pyvenv_cfg(full_path: &Path, rel_path: &Path) -> Result<VenvInfo> {
     let content = fs::read_to_string(full_path)
         .with_context(|| format!("Failed to read {}", rel_path.display()))?;
 
-    let mut fields = HashMap::new();
+    let mut fields = BTreeMap::new();
 
     for line in content.lines() {
         let line = line.trim();
@@ -247,11 +2051,46 @@ fn parse_pyvenv_cfg(full_path: &Path, rel_path: &Path) -> Result<VenvInfo> {
         home: fields.get("home").cloned(),
         version: fields.get("version").cloned(),
         include_system_site_packages: fields.get("include-system-site-packages").cloned(),
+        settings: fields,
+        tracking: None,
+        owners: Vec::new(),
+        direnv: direnv::layout_envrc(full_path, rel_path),
     })
 }
 
+/// List the violations per CODEOWNERS owner, so cleanup can be assigned
+///
+/// A venv with several owners is listed under each of them.
+fn print_owner_groups(venvs: &[VenvInfo], is_tty: bool) {
+    let mut groups: BTreeMap<&str, Vec<&VenvInfo>> = BTreeMap::new();
+    for venv in venvs {
+        if venv.owners.is_empty() {
+            groups.entry("(no owner)").or_default().push(venv);
+        }
+        for owner in &venv.owners {
+            groups.entry(owner).or_default().push(venv);
+        }
+    }
+
+    if is_tty {
+        println!("{}", "Violations by owner:".bold());
+    } else {
+        println!("Violations by owner:");
+    }
+    for (owner, venvs) in groups {
+        if is_tty {
+            println!("  {} ({})", owner.cyan(), venvs.len());
+        } else {
+            println!("  {owner} ({})", venvs.len());
+        }
+        for venv in venvs {
+            println!("    {}", venv.path.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    println!();
+}
+
 /// Print a helpful report about policy violations
-#[allow(clippy::too_many_lines)]
 fn print_violation_report(venvs: &[VenvInfo], is_tty: bool) {
     if is_tty {
         println!(
@@ -279,52 +2118,31 @@ fn print_violation_report(venvs: &[VenvInfo], is_tty: bool) {
             if let Some(version) = &venv.version {
                 println!("     Python version: {version}");
             }
-            if let Some(include_sys) = &venv.include_system_site_packages {
-                println!("     Include system packages: {include_sys}");
-            }
-            println!();
-        }
-
-        // Suggest gitignore entries
-        let mut suggested_ignores = std::collections::HashSet::new();
-        for venv in venvs {
-            if let Some(parent) = venv.path.parent() {
-                if let Some(dir_name) = parent.file_name() {
-                    if let Some(dir_str) = dir_name.to_str() {
-                        suggested_ignores.insert(format!("{dir_str}/"));
-                    }
-                }
+            if let Some(include_sys) = &venv.include_system_site_packages {
+                println!("     Include system packages: {include_sys}");
             }
-        }
-
-        if !suggested_ignores.is_empty() {
-            println!("{}", "Suggested .gitignore entries:".bold());
-            println!();
-            for ignore_entry in suggested_ignores {
-                println!("  {}", ignore_entry.green());
+            if let Some(executable) = venv.settings.get("executable") {
+                println!("     Interpreter: {executable}");
             }
-            println!();
-        }
-
-        println!("To fix this issue:");
-        println!("1. Add the virtual environment directories to your .gitignore file");
-        println!("2. If already committed, remove them from the index:");
-        for venv in venvs {
-            if let Some(parent) = venv.path.parent() {
-                println!(
-                    "   {}",
-                    format!("git rm -r --cached {}", parent.display()).yellow()
-                );
+            if let Some(creator) = venv.creator() {
+                println!("     Created with: {creator}");
             }
-        }
-        println!("2. If already committed, remove them from the index:");
-        for venv in venvs {
-            if let Some(parent) = venv.path.parent() {
+            if let Some(tracking) = venv.tracking {
+                println!("     Git status: {}", tracking.label());
+            }
+            let rule = rules::Rule::for_venv(venv);
+            println!("     Rule: {}", rule.describe());
+            println!("     Docs: {}", rule.doc_url());
+            if !venv.owners.is_empty() {
+                println!("     Owners: {}", venv.owners.join(" "));
+            }
+            if let Some(envrc) = &venv.direnv {
                 println!(
-                    "   {}",
-                    format!("git rm -r --cached {}", parent.display()).yellow()
+                    "     Created by: direnv `layout python` in {}",
+                    envrc.to_string_lossy().replace('\\', "/")
                 );
             }
+            println!();
         }
     } else {
         // Non-TTY output: plain text without colors or decorations
@@ -346,44 +2164,154 @@ fn print_violation_report(venvs: &[VenvInfo], is_tty: bool) {
             if let Some(include_sys) = &venv.include_system_site_packages {
                 println!("    Include system packages: {include_sys}");
             }
+            if let Some(executable) = venv.settings.get("executable") {
+                println!("    Interpreter: {executable}");
+            }
+            if let Some(creator) = venv.creator() {
+                println!("    Created with: {creator}");
+            }
+            if let Some(tracking) = venv.tracking {
+                println!("    Git status: {}", tracking.label());
+            }
+            let rule = rules::Rule::for_venv(venv);
+            println!("    Rule: {}", rule.describe());
+            println!("    Docs: {}", rule.doc_url());
+            if !venv.owners.is_empty() {
+                println!("    Owners: {}", venv.owners.join(" "));
+            }
+            if let Some(envrc) = &venv.direnv {
+                println!(
+                    "    Created by: direnv `layout python` in {}",
+                    envrc.to_string_lossy().replace('\\', "/")
+                );
+            }
         }
         println!();
+    }
 
-        // Suggest gitignore entries
-        let mut suggested_ignores = std::collections::HashSet::new();
-        for venv in venvs {
-            if let Some(parent) = venv.path.parent() {
-                if let Some(dir_name) = parent.file_name() {
-                    if let Some(dir_str) = dir_name.to_str() {
-                        suggested_ignores.insert(format!("{dir_str}/"));
-                    }
-                }
+    print_remediation_steps(venvs, is_tty);
+}
+
+/// Say what `scan --fix` changed and what is left to do
+fn print_fix_summary(applied: &fix_script::Applied, is_tty: bool) {
+    let fixed = if is_tty {
+        "Fixed:".green().bold().to_string()
+    } else {
+        "Fixed:".to_string()
+    };
+    for entry in &applied.ignores {
+        println!("{fixed} added {entry} to .gitignore");
+    }
+    for dir in &applied.untracked {
+        println!("{fixed} removed {dir}/ from the index (the files stay on disk)");
+    }
+    if !applied.ignores.is_empty() || !applied.untracked.is_empty() {
+        println!("Review with `git status` and commit .gitignore and the removals.");
+    }
+}
+
+/// Print fix-up steps tailored to how far each venv has made it into Git
+///
+/// Untracked venvs only need a `.gitignore` entry; staged or committed ones
+/// must also be removed from the index.
+fn print_remediation_steps(venvs: &[VenvInfo], is_tty: bool) {
+    let tracked: Vec<&VenvInfo> = venvs
+        .iter()
+        .filter(|venv| venv.tracking.is_some_and(TrackingStatus::is_tracked))
+        .collect();
+    // The same entries and commands the JSON report's actions hold
+    let mut entries = BTreeSet::new();
+    let mut untrack = BTreeSet::new();
+    for action in venvs.iter().flat_map(|venv| venv.to_violation().actions) {
+        match action {
+            unvenv::Action::AddGitignoreEntry { entry, .. } => {
+                entries.insert(entry);
             }
+            unvenv::Action::GitRmCached { path } => {
+                untrack.insert(format!(
+                    "git rm -r --cached -- {}",
+                    fix_script::sh_quote(&path.to_string_lossy())
+                ));
+            }
+            _ => {}
         }
+    }
 
-        if !suggested_ignores.is_empty() {
-            println!("Suggested .gitignore entries:");
-            for ignore_entry in suggested_ignores {
-                println!("  {ignore_entry}");
-            }
-            println!();
+    println!("To fix this issue:");
+    println!("1. Add the virtual environment directories to your .gitignore file:");
+    for entry in entries {
+        if is_tty {
+            println!("   {}", entry.yellow());
+        } else {
+            println!("   {entry}");
         }
+    }
+    // `venv/` does not cover `Venv/` unless Git is configured to ignore case
+    if venvs.iter().any(|venv| {
+        venv.path
+            .to_string_lossy()
+            .chars()
+            .any(|c| c.is_ascii_uppercase())
+    }) {
+        println!(
+            "   (Git matches these patterns case-sensitively unless core.ignorecase is set, so keep the casing shown)"
+        );
+    }
 
-        println!("To fix this issue:");
-        println!("1. Add the virtual environment directories to your .gitignore file");
-        println!("2. If already committed, remove them from the index:");
-        for venv in venvs {
-            if let Some(parent) = venv.path.parent() {
-                println!("   git rm -r --cached {}", parent.display());
-            }
+    if tracked.is_empty() {
+        println!("2. Commit the .gitignore changes");
+        print_recreate_commands(venvs, is_tty);
+        return;
+    }
+
+    println!("2. Remove them from the index (the files stay on disk):");
+    for command in untrack {
+        if is_tty {
+            println!("   {}", command.yellow());
+        } else {
+            println!("   {command}");
+        }
+    }
+
+    if tracked
+        .iter()
+        .any(|venv| venv.tracking == Some(TrackingStatus::Committed))
+    {
+        println!("3. Commit the .gitignore changes together with the removals");
+    } else {
+        println!("3. Commit the .gitignore changes");
+    }
+    print_recreate_commands(venvs, is_tty);
+}
+
+/// Show how to build each venv again with the interpreter it recorded, so
+/// one deleted instead of ignored can be rebuilt
+fn print_recreate_commands(venvs: &[VenvInfo], is_tty: bool) {
+    let commands: BTreeSet<String> = venvs
+        .iter()
+        .flat_map(|venv| venv.to_violation().actions)
+        .filter_map(|action| match action {
+            unvenv::Action::DeleteDir { recreate, .. } => recreate,
+            _ => None,
+        })
+        .collect();
+    if commands.is_empty() {
+        return;
+    }
+    println!("To rebuild a venv after deleting it:");
+    for command in commands {
+        if is_tty {
+            println!("   {}", command.yellow());
+        } else {
+            println!("   {command}");
         }
     }
-    println!("3. Commit the .gitignore changes");
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::git;
     use std::fs;
     use tempfile::TempDir;
 
@@ -416,6 +2344,37 @@ version = 3.9.7
         Ok(())
     }
 
+    #[test]
+    fn test_parse_pyvenv_cfg_keeps_every_key() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let pyvenv_path = temp_dir.path().join("pyvenv.cfg");
+        fs::write(
+            &pyvenv_path,
+            "home = /usr/bin\nimplementation = CPython\nvirtualenv = 20.25.0\nprompt = app\n",
+        )?;
+
+        let info = parse_pyvenv_cfg(&pyvenv_path, Path::new("test/pyvenv.cfg"))?;
+        assert_eq!(info.settings.len(), 4);
+        assert_eq!(
+            info.settings.get("implementation").map(String::as_str),
+            Some("CPython")
+        );
+        assert_eq!(info.creator().as_deref(), Some("virtualenv 20.25.0"));
+
+        // The `venv` module's own record wins over the virtualenv version
+        fs::write(
+            &pyvenv_path,
+            "virtualenv = 20.25.0\ncommand = /usr/bin/python3 -m venv /src/app/.venv\n",
+        )?;
+        let info = parse_pyvenv_cfg(&pyvenv_path, Path::new("test/pyvenv.cfg"))?;
+        assert_eq!(
+            info.creator().as_deref(),
+            Some("/usr/bin/python3 -m venv /src/app/.venv")
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_empty_pyvenv_cfg() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -478,6 +2437,10 @@ version = 3.10.1
             home: Some("/usr/bin".to_string()),
             version: Some("3.9.0".to_string()),
             include_system_site_packages: Some("false".to_string()),
+            settings: BTreeMap::new(),
+            tracking: Some(TrackingStatus::Committed),
+            owners: vec!["@org/python".to_string()],
+            direnv: None,
         }];
 
         // Should not panic
@@ -491,6 +2454,10 @@ version = 3.10.1
             home: Some("/usr/bin".to_string()),
             version: Some("3.9.0".to_string()),
             include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking: None,
+            owners: Vec::new(),
+            direnv: None,
         }];
 
         // Should not panic
@@ -505,12 +2472,20 @@ version = 3.10.1
                 home: Some("/usr/bin".to_string()),
                 version: Some("3.9.0".to_string()),
                 include_system_site_packages: Some("true".to_string()),
+                settings: BTreeMap::new(),
+                tracking: Some(TrackingStatus::Staged),
+                owners: Vec::new(),
+                direnv: None,
             },
             VenvInfo {
                 path: PathBuf::from("venv2/pyvenv.cfg"),
                 home: None,
                 version: None,
                 include_system_site_packages: None,
+                settings: BTreeMap::new(),
+                tracking: None,
+                owners: Vec::new(),
+                direnv: None,
             },
         ];
 
@@ -526,6 +2501,10 @@ version = 3.10.1
             home: Some("/usr/bin".to_string()),
             version: Some("3.9.0".to_string()),
             include_system_site_packages: Some("false".to_string()),
+            settings: BTreeMap::new(),
+            tracking: None,
+            owners: Vec::new(),
+            direnv: None,
         };
 
         assert_eq!(venv.path, PathBuf::from("test/pyvenv.cfg"));
@@ -560,11 +2539,7 @@ another malformed line
     fn test_scan_for_venvs_no_violations() -> Result<()> {
         let temp_dir = TempDir::new()?;
 
-        // Initialize git repo
-        std::process::Command::new("git")
-            .args(["init"])
-            .current_dir(temp_dir.path())
-            .output()?;
+        git(temp_dir.path(), &["init", "-q"]);
 
         // Create .gitignore
         fs::write(temp_dir.path().join(".gitignore"), "venv/\n")?;
@@ -575,7 +2550,7 @@ another malformed line
         fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
 
         // Scan should return 0 (no violations)
-        let result = scan_for_venvs_in_dir(temp_dir.path(), false)?;
+        let result = scan_for_venvs_in_dir(temp_dir.path(), &ScanArgs::default(), false)?;
         assert_eq!(result, 0, "Should return 0 when all venvs are ignored");
 
         Ok(())
@@ -585,11 +2560,7 @@ another malformed line
     fn test_scan_for_venvs_with_violations() -> Result<()> {
         let temp_dir = TempDir::new()?;
 
-        // Initialize git repo
-        std::process::Command::new("git")
-            .args(["init"])
-            .current_dir(temp_dir.path())
-            .output()?;
+        git(temp_dir.path(), &["init", "-q"]);
 
         // Create unignored venv
         let venv_dir = temp_dir.path().join("venv");
@@ -597,12 +2568,548 @@ another malformed line
         fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
 
         // Scan should return 2 (policy violation)
-        let result = scan_for_venvs_in_dir(temp_dir.path(), false)?;
+        let result = scan_for_venvs_in_dir(temp_dir.path(), &ScanArgs::default(), false)?;
         assert_eq!(result, 2, "Should return 2 when unignored venvs found");
 
         Ok(())
     }
 
+    #[test]
+    fn test_find_unignored_venvs_skips_venv_contents() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let venv_dir = temp_dir.path().join("venv");
+        let nested_dir = venv_dir.join("lib").join("nested");
+        fs::create_dir_all(&nested_dir)?;
+        fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::write(nested_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let venvs = find_unignored_venvs(temp_dir.path(), &ScanArgs::default(), false)?.venvs;
+
+        assert_eq!(
+            venvs.len(),
+            1,
+            "Contents of a detected venv should not be walked"
+        );
+        assert_eq!(venvs[0].path, PathBuf::from("venv/pyvenv.cfg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_ignores_pyvenv_cfg_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir_all(temp_dir.path().join("data").join("pyvenv.cfg"))?;
+
+        let venvs = find_unignored_venvs(temp_dir.path(), &ScanArgs::default(), false)?.venvs;
+
+        assert!(
+            venvs.is_empty(),
+            "A directory named pyvenv.cfg is not a venv marker"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_no_git_reports_ignored_venvs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join(".gitignore"), "dist/\n")?;
+        fs::create_dir_all(dir.join("dist/app/venv"))?;
+        fs::write(dir.join("dist/app/venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let venvs = find_unignored_venvs(dir, &ScanArgs::default(), false)?.venvs;
+        assert!(venvs.is_empty());
+
+        let args = ScanArgs {
+            no_git: true,
+            ..ScanArgs::default()
+        };
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("dist/app/venv/pyvenv.cfg"));
+        assert_eq!(venvs[0].tracking, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_with_cache() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join(".gitignore"), "build/\n")?;
+        fs::create_dir_all(dir.join("build").join("venv"))?;
+        fs::write(dir.join("build/venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let args = ScanArgs {
+            cache: true,
+            ..ScanArgs::default()
+        };
+
+        // Cold and warm runs must agree
+        for _ in 0..2 {
+            let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+            assert_eq!(venvs.len(), 1);
+            assert_eq!(venvs[0].path, PathBuf::from("venv/pyvenv.cfg"));
+        }
+        assert!(dir.join(".git/unvenv/scan-cache").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_path_scans_leave_cache_alone() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::create_dir_all(dir.join("app/venv"))?;
+        fs::write(dir.join("app/venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        git(dir, &["add", "app"]);
+        let cache = dir.join(".git/unvenv/scan-cache");
+        fs::create_dir_all(dir.join(".git/unvenv"))?;
+        let content = "unvenv-scan-cache 1\n1 0000000000000001 P lib\n";
+        fs::write(&cache, content)?;
+
+        let args = ScanArgs {
+            cache: true,
+            staged: true,
+            ..ScanArgs::default()
+        };
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(fs::read_to_string(&cache)?, content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_incremental() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("README.md"), "# Test\n")?;
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "Initial commit"]);
+
+        let args = ScanArgs {
+            incremental: true,
+            ..ScanArgs::default()
+        };
+
+        // Cold: full walk, which records the scan
+        assert!(find_unignored_venvs(dir, &args, false)?.venvs.is_empty());
+        assert!(dir.join(".git/unvenv/last-scan").is_file());
+
+        // Warm: the new untracked venv is picked up from git status
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("venv/pyvenv.cfg"));
+
+        // Once committed it no longer shows in git status, but stays reported
+        git(dir, &["add", "venv"]);
+        git(dir, &["commit", "--no-verify", "-m", "Add venv"]);
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].tracking, Some(TrackingStatus::Committed));
+
+        // Ignoring it resolves the violation
+        fs::write(dir.join(".gitignore"), "venv/\n")?;
+        assert!(find_unignored_venvs(dir, &args, false)?.venvs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_incremental_finds_staged_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+
+        let args = ScanArgs {
+            incremental: true,
+            ..ScanArgs::default()
+        };
+        assert!(find_unignored_venvs(dir, &args, false)?.venvs.is_empty());
+
+        let lib_dir = dir.join("venv").join("lib");
+        fs::create_dir_all(&lib_dir)?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::write(lib_dir.join("site.py"), "\n")?;
+        git(dir, &["add", "venv/lib/site.py"]);
+
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("venv/pyvenv.cfg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_staged() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        for venv in ["staged", "untracked"] {
+            fs::create_dir_all(dir.join(venv).join("lib"))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+            fs::write(dir.join(venv).join("lib/site.py"), "\n")?;
+        }
+
+        let args = ScanArgs {
+            staged: true,
+            ..ScanArgs::default()
+        };
+        assert!(find_unignored_venvs(dir, &args, false)?.venvs.is_empty());
+
+        // Staging any file inside a venv reports the venv; unstaged ones are left alone
+        git(dir, &["add", "staged/lib/site.py"]);
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("staged/pyvenv.cfg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_attributes_owners() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::write(dir.join("CODEOWNERS"), "* @org/platform\n/ml/ @org/ml\n")?;
+        for venv in ["ml/venv", "web/venv"] {
+            fs::create_dir_all(dir.join(venv))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+
+        let mut venvs = find_unignored_venvs(dir, &ScanArgs::default(), false)?.venvs;
+        venvs.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(venvs[0].owners, vec!["@org/ml".to_string()]);
+        assert_eq!(venvs[1].owners, vec!["@org/platform".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_marks_direnv_layouts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::create_dir_all(dir.join("api/.direnv/python-3.12"))?;
+        fs::write(dir.join("api/.envrc"), "layout python3\n")?;
+        fs::write(
+            dir.join("api/.direnv/python-3.12/pyvenv.cfg"),
+            "home = /usr/bin\n",
+        )?;
+
+        let venvs = find_unignored_venvs(dir, &ScanArgs::default(), false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].direnv, Some(PathBuf::from("api/.envrc")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_reports_tooling_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        fs::create_dir_all(dir.join("web/.direnv/bin"))?;
+        fs::create_dir_all(dir.join("tools/.pyenv/versions/3.12.1/bin"))?;
+        // A venv made by pyenv-virtualenv is part of the pyenv finding
+        fs::write(
+            dir.join("tools/.pyenv/versions/3.12.1/pyvenv.cfg"),
+            "home = /usr/bin\n",
+        )?;
+        fs::create_dir_all(dir.join("api/.direnv/python-3.12"))?;
+        fs::write(dir.join("api/.envrc"), "layout python3\n")?;
+        fs::write(
+            dir.join("api/.direnv/python-3.12/pyvenv.cfg"),
+            "home = /usr/bin\n",
+        )?;
+        fs::create_dir_all(dir.join("ignored/.direnv"))?;
+        fs::write(dir.join(".gitignore"), "/ignored/\n")?;
+
+        let outcome = find_unignored_venvs(dir, &ScanArgs::default(), false)?;
+        assert_eq!(outcome.venvs.len(), 1);
+        assert_eq!(
+            outcome.venvs[0].path,
+            PathBuf::from("api/.direnv/python-3.12/pyvenv.cfg")
+        );
+        let mut artifacts: Vec<_> = outcome
+            .artifacts
+            .iter()
+            .map(|artifact| (artifact.path.clone(), artifact.kind))
+            .collect();
+        artifacts.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            artifacts,
+            [
+                (PathBuf::from("tools/.pyenv"), ArtifactKind::Pyenv),
+                (PathBuf::from("web/.direnv"), ArtifactKind::Direnv),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_pre_commit_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+        for venv in ["listed", "unlisted"] {
+            fs::create_dir_all(dir.join(venv).join("lib"))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+            fs::write(dir.join(venv).join("lib/site.py"), "\n")?;
+        }
+
+        let args = ScanArgs {
+            pre_commit: true,
+            files: vec![PathBuf::from("listed/lib/site.py")],
+            ..ScanArgs::default()
+        };
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("listed/pyvenv.cfg"));
+
+        // No files passed means nothing to check, not a full walk
+        let args = ScanArgs {
+            pre_commit: true,
+            ..ScanArgs::default()
+        };
+        assert!(find_unignored_venvs(dir, &args, false)?.venvs.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_unignored_venvs_skips_unreadable_dirs() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        let locked = dir.join("locked");
+        fs::create_dir(&locked)?;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+
+        // Privileged users can read the directory anyway
+        let readable = fs::read_dir(&locked).is_ok();
+
+        let venvs = find_unignored_venvs(dir, &ScanArgs::default(), false);
+        let strict = find_unignored_venvs(
+            dir,
+            &ScanArgs {
+                fail_on_unreadable: true,
+                ..ScanArgs::default()
+            },
+            false,
+        );
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+
+        assert_eq!(venvs?.venvs.len(), 1);
+        if !readable {
+            assert!(strict.is_err());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_unignored_venvs_follow_symlinks_breaks_cycles() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        let outside = TempDir::new()?;
+
+        // venv/lib64 -> lib, plus a link back to the root
+        fs::create_dir_all(dir.join("venv/lib"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        symlink("lib", dir.join("venv/lib64"))?;
+        fs::create_dir(dir.join("src"))?;
+        symlink("..", dir.join("src/up"))?;
+        // Two links to the same external venv
+        fs::create_dir(outside.path().join("env"))?;
+        fs::write(outside.path().join("env/pyvenv.cfg"), "home = /usr/bin\n")?;
+        symlink(outside.path(), dir.join("ext1"))?;
+        symlink(outside.path(), dir.join("ext2"))?;
+
+        assert_eq!(
+            find_unignored_venvs(dir, &ScanArgs::default(), false)?
+                .venvs
+                .len(),
+            1
+        );
+
+        let args = ScanArgs {
+            follow_symlinks: true,
+            ..ScanArgs::default()
+        };
+        let mut venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+        venvs.sort_by(|a, b| a.path.cmp(&b.path));
+
+        // The external venv is reported through whichever link came first
+        assert_eq!(venvs.len(), 2);
+        assert!(venvs[0].path.ends_with("env/pyvenv.cfg"));
+        assert!(venvs[0].path.starts_with("ext1") || venvs[0].path.starts_with("ext2"));
+        assert_eq!(venvs[1].path, PathBuf::from("venv/pyvenv.cfg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_ignore_case() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("Venv"))?;
+        fs::write(dir.join("Venv/PYVENV.cfg"), "home = /usr/bin\n")?;
+
+        let args = ScanArgs {
+            ignore_case: true,
+            ..ScanArgs::default()
+        };
+        let venvs = find_unignored_venvs(dir, &args, false)?.venvs;
+
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].path, PathBuf::from("Venv/PYVENV.cfg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_max_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        for name in ["a", "b", "c"] {
+            fs::create_dir(dir.join(name))?;
+            fs::write(dir.join(name).join("file.txt"), "")?;
+        }
+
+        let limited = |max_dirs, max_files| ScanArgs {
+            max_dirs,
+            max_files,
+            ..ScanArgs::default()
+        };
+
+        let outcome = find_unignored_venvs(dir, &limited(Some(2), None), false)?;
+        assert_eq!(outcome.limit, Some(ScanLimit::Dirs(2)));
+
+        let outcome = find_unignored_venvs(dir, &limited(None, Some(1)), false)?;
+        assert_eq!(outcome.limit, Some(ScanLimit::Files(1)));
+
+        // root plus three subdirectories, three files
+        let outcome = find_unignored_venvs(dir, &limited(Some(4), Some(3)), false)?;
+        assert_eq!(outcome.limit, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_tracking_status() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+
+        for name in ["committed", "staged", "untracked"] {
+            fs::create_dir(dir.join(name))?;
+            fs::write(dir.join(name).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+        git(dir, &["add", "committed"]);
+        git(dir, &["commit", "--no-verify", "-m", "Add venv"]);
+        git(dir, &["add", "staged"]);
+
+        let mut venvs = find_unignored_venvs(dir, &ScanArgs::default(), false)?.venvs;
+        venvs.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let statuses: Vec<_> = venvs.iter().map(|venv| venv.tracking).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some(TrackingStatus::Committed),
+                Some(TrackingStatus::Staged),
+                Some(TrackingStatus::Untracked),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_unignored_venvs_outside_repo_has_no_tracking() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir(temp_dir.path().join("venv"))?;
+        fs::write(temp_dir.path().join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let venvs = find_unignored_venvs(temp_dir.path(), &ScanArgs::default(), false)?.venvs;
+
+        assert_eq!(venvs.len(), 1);
+        assert_eq!(venvs[0].tracking, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_from_subdirectory_uses_repo_relative_ignores() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init"]);
+
+        // Anchored pattern only matches when checked relative to the repo root
+        fs::write(dir.join(".gitignore"), "/sub/venv/\n")?;
+        let venv_dir = dir.join("sub").join("venv");
+        fs::create_dir_all(&venv_dir)?;
+        fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let result = scan_for_venvs_in_dir(&dir.join("sub"), &ScanArgs::default(), false)?;
+        assert_eq!(
+            result, 0,
+            "Anchored ignore should apply when scanning a subdirectory"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_report_has_repo_and_package_paths() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().canonicalize()?;
+        git(&dir, &["init"]);
+        let package = dir.join("services").join("api");
+        fs::create_dir_all(package.join(".venv"))?;
+        fs::write(
+            package.join("pyproject.toml"),
+            "[project]\nname = \"api\"\n",
+        )?;
+        fs::write(package.join(".venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let workdir = dir.join("services");
+        let outcome = find_unignored_venvs(&workdir, &ScanArgs::default(), false)?;
+        let report = scan_report(&workdir, &outcome, &[]);
+        let violation = &report.violations[0];
+        assert_eq!(violation.path, Path::new("api/.venv/pyvenv.cfg"));
+        assert_eq!(
+            violation.repo_path.as_deref(),
+            Some(Path::new("services/api/.venv/pyvenv.cfg"))
+        );
+        let package = violation.package.as_ref().expect("venv is in a package");
+        assert_eq!(package.name, "api");
+        assert_eq!(package.dir, Path::new("api"));
+        assert_eq!(package.repo_dir.as_deref(), Some(Path::new("services/api")));
+        assert_eq!(package.path, Path::new(".venv/pyvenv.cfg"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_pyvenv_cfg_missing_file() {
         let result = parse_pyvenv_cfg(Path::new("/nonexistent/pyvenv.cfg"), Path::new("test.cfg"));
@@ -668,6 +3175,10 @@ another malformed line
             home: None,
             version: None,
             include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking: None,
+            owners: Vec::new(),
+            direnv: None,
         };
 
         assert_eq!(venv.path, PathBuf::from("test/pyvenv.cfg"));
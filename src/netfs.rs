@@ -0,0 +1,233 @@
+//! Detection of network filesystem mount points.
+//!
+//! Walking a mounted NFS/SMB share or a FUSE filesystem can take minutes, or
+//! hang outright when the server is unreachable. With `--skip-network-fs`
+//! the scanner looks up the mount table once and does not descend into any
+//! such mount below the scanned directory.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Filesystem types backed by a remote server
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+];
+
+/// Network mount points below a directory
+#[derive(Debug, Default)]
+pub struct NetworkMounts {
+    mount_points: HashSet<PathBuf>,
+}
+
+impl NetworkMounts {
+    /// Read the mount table and keep the network mounts strictly below `root`
+    ///
+    /// Platforms without a readable mount table yield no mount points, so the
+    /// scan then behaves as if the option were not given.
+    pub fn below(root: &Path) -> Self {
+        let mounts = read_mount_table().unwrap_or_default();
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+
+        Self {
+            mount_points: mounts
+                .into_iter()
+                .filter(|(_, fs_type)| is_network_fs(fs_type))
+                .map(|(mount_point, _)| mount_point)
+                .filter(|mount_point| mount_point.starts_with(&root) && *mount_point != root)
+                .collect(),
+        }
+    }
+
+    /// Whether `dir` is the mount point of a network filesystem
+    pub fn is_mount_point(&self, dir: &Path) -> bool {
+        !self.mount_points.is_empty()
+            && (self.mount_points.contains(dir)
+                || dir
+                    .canonicalize()
+                    .is_ok_and(|dir| self.mount_points.contains(&dir)))
+    }
+}
+
+/// Whether a filesystem type names a network or FUSE filesystem
+fn is_network_fs(fs_type: &str) -> bool {
+    // FUSE types appear as `fuse.sshfs`, `fuse.rclone`, `fuseblk`, ...
+    fs_type.starts_with("fuse") || NETWORK_FS_TYPES.contains(&fs_type)
+}
+
+/// Mount points and their filesystem types
+fn read_mount_table() -> Option<Vec<(PathBuf, String)>> {
+    if let Ok(content) = fs::read_to_string("/proc/self/mounts") {
+        return Some(parse_proc_mounts(&content));
+    }
+
+    // BSDs and macOS have no /proc; `mount` prints the table instead
+    let output = Command::new("mount").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_mount_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `/proc/self/mounts`: `device mount_point fs_type options 0 0`
+fn parse_proc_mounts(content: &str) -> Vec<(PathBuf, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            Some((unescape_mount_path(mount_point), fs_type.to_string()))
+        })
+        .collect()
+}
+
+/// Parse BSD/macOS `mount` output: `device on /mount/point (fs_type, options)`
+fn parse_mount_output(content: &str) -> Vec<(PathBuf, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(" on ")?;
+            let (mount_point, details) = rest.rsplit_once(" (")?;
+            let fs_type = details.split([',', ')']).next()?.trim();
+            Some((PathBuf::from(mount_point), fs_type.to_string()))
+        })
+        .collect()
+}
+
+/// Undo the octal escapes (`\040` for a space) used in `/proc/self/mounts`
+///
+/// Each escape stands for one byte of the path, which may be part of a
+/// multi-byte character.
+fn unescape_mount_path(path: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&first, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..3)
+            .filter(|digits| {
+                first == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+            })
+            .and_then(|digits| {
+                digits.iter().try_fold(0u8, |byte, digit| {
+                    byte.checked_mul(8)?.checked_add(digit - b'0')
+                })
+            });
+        if let Some(byte) = escaped {
+            bytes.push(byte);
+            rest = &tail[3..];
+        } else {
+            bytes.push(first);
+            rest = tail;
+        }
+    }
+    path_from_bytes(&bytes)
+}
+
+/// The path made of `bytes`, whatever their encoding
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+/// Paths are Unicode elsewhere, and only Linux has `/proc/self/mounts`
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_mounts() {
+        let content = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+server:/export /home/user/repo/data nfs4 rw,relatime 0 0
+//nas/share /home/user/repo/my\\040share cifs rw 0 0
+sshfs#host: /home/user/repo/remote fuse.sshfs rw 0 0
+";
+
+        let mounts = parse_proc_mounts(content);
+
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(
+            mounts[2],
+            (
+                PathBuf::from("/home/user/repo/my share"),
+                "cifs".to_string()
+            )
+        );
+        let network = mounts
+            .iter()
+            .filter(|(_, fs_type)| is_network_fs(fs_type))
+            .count();
+        assert_eq!(network, 3);
+    }
+
+    #[test]
+    fn test_unescape_mount_path() {
+        assert_eq!(
+            unescape_mount_path("/mnt/M\\303\\274ller\\040Akten"),
+            PathBuf::from("/mnt/Müller Akten")
+        );
+        // Not an escape: kept as written
+        assert_eq!(
+            unescape_mount_path("/mnt/a\\9b\\"),
+            PathBuf::from("/mnt/a\\9b\\")
+        );
+    }
+
+    #[test]
+    fn test_parse_mount_output() {
+        let content = "\
+/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)
+//user@nas/share on /Users/me/repo/share (smbfs, nodev, nosuid, mounted by me)
+";
+
+        let mounts = parse_mount_output(content);
+
+        assert_eq!(
+            mounts,
+            vec![
+                (PathBuf::from("/"), "apfs".to_string()),
+                (PathBuf::from("/Users/me/repo/share"), "smbfs".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_network_fs() {
+        assert!(is_network_fs("nfs"));
+        assert!(is_network_fs("fuse.sshfs"));
+        assert!(!is_network_fs("ext4"));
+        assert!(!is_network_fs("apfs"));
+    }
+
+    #[test]
+    fn test_is_mount_point() {
+        let mounts = NetworkMounts {
+            mount_points: HashSet::from([PathBuf::from("/repo/share")]),
+        };
+
+        assert!(mounts.is_mount_point(Path::new("/repo/share")));
+        assert!(!mounts.is_mount_point(Path::new("/repo/src")));
+    }
+}
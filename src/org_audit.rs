@@ -0,0 +1,288 @@
+//! `unvenv audit-org`: find committed venvs across a GitHub organization.
+//!
+//! Repositories are listed through the GitHub API and the tree of each
+//! default branch is fetched with one recursive request, so nothing is
+//! cloned. Any `pyvenv.cfg` in that tree is a venv that made it into the
+//! branch, whatever the ignore rules say today. Requests go through the
+//! fleet worker pool and throttle, so at most `--jobs` are in flight, each
+//! host sees at most `--rate-limit` per second, and transient failures are
+//! retried.
+
+use crate::{
+    fleet::{self, Throttle},
+    github,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::fmt::Write as _;
+
+/// One repository of the organization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrgRepo {
+    /// Repository name without the owner
+    pub name: String,
+    /// Branch whose tree is audited
+    pub default_branch: String,
+}
+
+/// What the tree of one repository holds
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TreeFindings {
+    /// Paths of committed `pyvenv.cfg` files
+    pub venvs: Vec<String>,
+    /// Whether GitHub cut the tree listing short, so venvs may be missing
+    pub truncated: bool,
+}
+
+/// Repositories of `org` from one page of the repository list
+fn parse_repos(page: &str, include_archived: bool) -> Result<Vec<OrgRepo>> {
+    let page: Value = serde_json::from_str(page).context("Unexpected repository list")?;
+    let repos = page
+        .as_array()
+        .context("Unexpected repository list")?
+        .iter()
+        .filter(|repo| include_archived || !repo["archived"].as_bool().unwrap_or(false))
+        // Empty repositories have no tree to fetch
+        .filter(|repo| repo["size"].as_u64() != Some(0))
+        .filter_map(|repo| {
+            Some(OrgRepo {
+                name: repo["name"].as_str()?.to_string(),
+                default_branch: repo["default_branch"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Ok(repos)
+}
+
+/// List the repositories of `org`, following pagination
+pub fn list_repos(
+    org: &str,
+    token: &str,
+    include_archived: bool,
+    throttle: &Throttle,
+) -> Result<Vec<OrgRepo>> {
+    let api = github::api_url();
+    let mut repos = Vec::new();
+    for page in 1.. {
+        let url = format!("{api}/orgs/{org}/repos?type=all&per_page=100&page={page}");
+        let body = throttle.call(&url, || github::get(&url, token))?;
+        // Archived and empty repositories are filtered out, so an empty page
+        // rather than a short one marks the end
+        if serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|page| page.as_array().map(Vec::is_empty))
+            .unwrap_or(true)
+        {
+            break;
+        }
+        repos.extend(parse_repos(&body, include_archived)?);
+    }
+    Ok(repos)
+}
+
+/// The committed venvs in a recursive tree listing
+fn tree_findings(tree: &str) -> Result<TreeFindings> {
+    let tree: Value = serde_json::from_str(tree).context("Unexpected tree listing")?;
+    let venvs = tree["tree"]
+        .as_array()
+        .context("Unexpected tree listing")?
+        .iter()
+        .filter(|entry| entry["type"] == "blob")
+        .filter_map(|entry| entry["path"].as_str())
+        .filter(|path| *path == "pyvenv.cfg" || path.ends_with("/pyvenv.cfg"))
+        .map(str::to_string)
+        .collect();
+    Ok(TreeFindings {
+        venvs,
+        truncated: tree["truncated"].as_bool().unwrap_or(false),
+    })
+}
+
+/// Percent-encode a branch name for use in a URL path
+fn encode_ref(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Fetch the default-branch tree of one repository
+fn audit_repo(org: &str, repo: &OrgRepo, token: &str, throttle: &Throttle) -> Result<TreeFindings> {
+    let url = format!(
+        "{}/repos/{org}/{}/git/trees/{}?recursive=1",
+        github::api_url(),
+        repo.name,
+        encode_ref(&repo.default_branch)
+    );
+    tree_findings(&throttle.call(&url, || github::get(&url, token))?)
+}
+
+/// How to run the organization audit
+#[derive(Debug)]
+pub struct OrgAuditOptions {
+    /// API token, falling back to the environment
+    pub token: Option<String>,
+    /// Repositories fetched at the same time
+    pub jobs: usize,
+    /// Requests per second to each API host; 0 for no limit
+    pub rate_limit: f64,
+    /// Retries of a request that failed transiently
+    pub retries: u32,
+    /// Whether archived repositories are audited too
+    pub include_archived: bool,
+}
+
+/// Run the organization audit and print the report, returning the exit code
+pub fn run_audit_org(org: &str, options: OrgAuditOptions, is_tty: bool) -> Result<i32> {
+    let token = options
+        .token
+        .or_else(github::token_from_env)
+        .context("No GitHub token; pass --token or set GITHUB_TOKEN")?;
+    let throttle = Throttle::new(options.rate_limit, options.retries);
+    let repos = list_repos(org, &token, options.include_archived, &throttle)?;
+    let results = fleet::run_parallel(&repos, options.jobs, |repo| {
+        audit_repo(org, repo, &token, &throttle)
+    });
+
+    let mut findings = Vec::new();
+    let mut partial = false;
+    for (repo, result) in repos.iter().zip(results) {
+        let full_name = format!("{org}/{}", repo.name);
+        match result {
+            Ok(found) => {
+                if found.truncated {
+                    eprintln!(
+                        "{} The tree of {full_name} is too large for one listing; some venvs may be missing",
+                        "Warning:".yellow().bold()
+                    );
+                    partial = true;
+                }
+                if !found.venvs.is_empty() {
+                    findings.push((full_name, repo, found.venvs));
+                }
+            }
+            // One unreadable repository should not hide the others
+            Err(e) => {
+                eprintln!(
+                    "{} Could not audit {full_name}: {e:#}",
+                    "Warning:".yellow().bold()
+                );
+                partial = true;
+            }
+        }
+    }
+
+    print_report(org, repos.len(), &findings, is_tty);
+    Ok(if !findings.is_empty() {
+        2
+    } else if partial {
+        3
+    } else {
+        0
+    })
+}
+
+/// Print the repositories with committed venvs
+fn print_report(
+    org: &str,
+    audited: usize,
+    findings: &[(String, &OrgRepo, Vec<String>)],
+    is_tty: bool,
+) {
+    if findings.is_empty() {
+        let message =
+            format!("No committed Python virtual environments in {audited} repositories of {org}");
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+        return;
+    }
+
+    let total: usize = findings.iter().map(|(_, _, venvs)| venvs.len()).sum();
+    let headline = format!(
+        "Found {total} committed Python virtual environment(s) in {} of {audited} repositories of {org}!",
+        findings.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+    println!();
+
+    for (full_name, repo, venvs) in findings {
+        if is_tty {
+            println!(
+                "  {} {} ({})",
+                full_name.cyan().bold(),
+                format!("[{}]", repo.default_branch).dimmed(),
+                venvs.len()
+            );
+        } else {
+            println!("  {full_name} [{}] ({})", repo.default_branch, venvs.len());
+        }
+        for path in venvs {
+            if is_tty {
+                println!("     📁 {}", path.cyan());
+            } else {
+                println!("    {path}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repos_skips_archived_and_empty() -> Result<()> {
+        let page = r#"[
+            {"name": "api", "default_branch": "main", "archived": false, "size": 120},
+            {"name": "old", "default_branch": "master", "archived": true, "size": 50},
+            {"name": "empty", "default_branch": "main", "archived": false, "size": 0}
+        ]"#;
+
+        let names = |repos: Vec<OrgRepo>| -> Vec<String> {
+            repos.into_iter().map(|repo| repo.name).collect()
+        };
+        assert_eq!(names(parse_repos(page, false)?), ["api"]);
+        assert_eq!(names(parse_repos(page, true)?), ["api", "old"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_findings() -> Result<()> {
+        let tree = r#"{
+            "truncated": true,
+            "tree": [
+                {"path": "pyvenv.cfg", "type": "blob"},
+                {"path": "services/api/.venv/pyvenv.cfg", "type": "blob"},
+                {"path": "docs/pyvenv.cfg.md", "type": "blob"},
+                {"path": "tools/pyvenv.cfg", "type": "tree"}
+            ]
+        }"#;
+
+        let findings = tree_findings(tree)?;
+        assert_eq!(
+            findings.venvs,
+            ["pyvenv.cfg", "services/api/.venv/pyvenv.cfg"]
+        );
+        assert!(findings.truncated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_ref() {
+        assert_eq!(encode_ref("release/1.2"), "release/1.2");
+        assert_eq!(encode_ref("feat#1 x"), "feat%231%20x");
+    }
+}
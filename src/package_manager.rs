@@ -0,0 +1,132 @@
+//! Detection of package-manager installs.
+//!
+//! A binary installed by Homebrew, `cargo install`, or a distribution package
+//! is owned by that package manager: overwriting it in place confuses the
+//! manager's bookkeeping and is undone by its next upgrade. `update` therefore
+//! points such users at the right upgrade command instead.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Package manager that owns an installed binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    /// Homebrew / Linuxbrew
+    Homebrew,
+    /// `cargo install`
+    Cargo,
+    /// A distribution package (apt, dnf, pacman, ...)
+    System,
+}
+
+impl PackageManager {
+    /// Human-readable name
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Homebrew => "Homebrew",
+            Self::Cargo => "cargo install",
+            Self::System => "your system package manager",
+        }
+    }
+
+    /// Command that upgrades unvenv through this package manager
+    pub const fn upgrade_command(self) -> &'static str {
+        match self {
+            Self::Homebrew => "brew upgrade unvenv",
+            Self::Cargo => "cargo install --locked unvenv",
+            Self::System => "upgrade the unvenv package with your distribution's package manager",
+        }
+    }
+}
+
+/// Package manager owning `binary`, if any
+pub fn detect(binary: &Path) -> Option<PackageManager> {
+    // Homebrew links bin/unvenv to the real file inside its Cellar
+    let binary = binary
+        .canonicalize()
+        .unwrap_or_else(|_| binary.to_path_buf());
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")));
+    detect_in(&binary, cargo_home.as_deref())
+}
+
+fn detect_in(binary: &Path, cargo_home: Option<&Path>) -> Option<PackageManager> {
+    let is_homebrew = binary
+        .components()
+        .any(|component| component.as_os_str() == "Cellar")
+        || binary.starts_with("/opt/homebrew")
+        || binary.starts_with("/home/linuxbrew/.linuxbrew");
+    if is_homebrew {
+        return Some(PackageManager::Homebrew);
+    }
+
+    if cargo_home.is_some_and(|cargo_home| binary.starts_with(cargo_home.join("bin"))) {
+        return Some(PackageManager::Cargo);
+    }
+
+    // /usr/local belongs to the administrator, not the distribution
+    let is_system = [
+        "/usr/bin",
+        "/usr/sbin",
+        "/bin",
+        "/sbin",
+        "/usr/lib",
+        "/usr/libexec",
+    ]
+    .iter()
+    .any(|dir| binary.starts_with(dir));
+    is_system.then_some(PackageManager::System)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_homebrew() {
+        assert_eq!(
+            detect_in(
+                Path::new("/opt/homebrew/Cellar/unvenv/1.6.0/bin/unvenv"),
+                None
+            ),
+            Some(PackageManager::Homebrew)
+        );
+        assert_eq!(
+            detect_in(Path::new("/usr/local/Cellar/unvenv/1.6.0/bin/unvenv"), None),
+            Some(PackageManager::Homebrew)
+        );
+    }
+
+    #[test]
+    fn test_detect_cargo() {
+        let cargo_home = Path::new("/home/user/.cargo");
+        assert_eq!(
+            detect_in(Path::new("/home/user/.cargo/bin/unvenv"), Some(cargo_home)),
+            Some(PackageManager::Cargo)
+        );
+    }
+
+    #[test]
+    fn test_detect_system() {
+        assert_eq!(
+            detect_in(Path::new("/usr/bin/unvenv"), None),
+            Some(PackageManager::System)
+        );
+    }
+
+    #[test]
+    fn test_detect_self_managed() {
+        let cargo_home = Path::new("/home/user/.cargo");
+        assert_eq!(
+            detect_in(Path::new("/usr/local/bin/unvenv"), Some(cargo_home)),
+            None
+        );
+        assert_eq!(
+            detect_in(Path::new("/home/user/.local/bin/unvenv"), Some(cargo_home)),
+            None
+        );
+    }
+}
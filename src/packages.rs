@@ -0,0 +1,376 @@
+//! Python packages inside a monorepo, and their venv policies.
+//!
+//! A package is a directory with a `pyproject.toml`, `setup.cfg`, or
+//! `setup.py`; each venv belongs to the nearest one above it. `--group-by
+//! package` lists violations per package, and a package can relax the policy
+//! for venvs inside it:
+//!
+//! ```toml
+//! # pyproject.toml
+//! [tool.unvenv]
+//! policy = "warn"   # "deny" (default), "warn", or "allow"
+//! ```
+//!
+//! or `policy = warn` in a `[tool:unvenv]` section of `setup.cfg`. Venvs in a
+//! `warn` package are reported without failing the scan; venvs in an `allow`
+//! package are not reported at all. A `pyproject.toml` that is not valid TOML
+//! names no package and sets no policy.
+
+use crate::VenvInfo;
+use colored::Colorize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Files that mark the root of a Python package
+const MARKERS: [&str; 3] = ["pyproject.toml", "setup.cfg", "setup.py"];
+
+/// What happens to venvs inside a package
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Report them and fail the scan
+    #[default]
+    Deny,
+    /// Report them without failing the scan
+    Warn,
+    /// Do not report them
+    Allow,
+}
+
+impl Policy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "deny" => Some(Self::Deny),
+            "warn" => Some(Self::Warn),
+            "allow" => Some(Self::Allow),
+            _ => None,
+        }
+    }
+}
+
+/// One Python package of the workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// Package directory relative to the scan root; empty for the root
+    pub dir: PathBuf,
+    /// Project name from the metadata, or the directory
+    pub name: String,
+    /// Policy for venvs inside the package
+    pub policy: Policy,
+}
+
+/// Read the package whose marker files are in `dir`, if any
+fn read_package(workdir: &Path, dir: &Path) -> Option<Package> {
+    let rel_dir = dir.strip_prefix(workdir).unwrap_or(dir).to_path_buf();
+    let mut name = None;
+    let mut policy = None;
+    let mut is_package = false;
+
+    for marker in MARKERS {
+        let path = dir.join(marker);
+        if !path.is_file() {
+            continue;
+        }
+        is_package = true;
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let lookup: fn(&str, &str, &str) -> Option<String> = match marker {
+            "pyproject.toml" => toml_value,
+            "setup.cfg" => ini_value,
+            _ => continue,
+        };
+        let (name_sections, policy_section): (&[&str], &str) = match marker {
+            "pyproject.toml" => (&["project", "tool.poetry"], "tool.unvenv"),
+            _ => (&["metadata"], "tool:unvenv"),
+        };
+        if name.is_none() {
+            name = name_sections
+                .iter()
+                .find_map(|section| lookup(&content, section, "name"));
+        }
+        if policy.is_none() {
+            if let Some(value) = lookup(&content, policy_section, "policy") {
+                policy = Policy::parse(&value);
+                if policy.is_none() {
+                    eprintln!(
+                        "{} Unknown policy {value:?} in {}; expected deny, warn, or allow",
+                        "Warning:".yellow().bold(),
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+
+    is_package.then(|| Package {
+        name: name.unwrap_or_else(|| display_dir(&rel_dir)),
+        dir: rel_dir,
+        policy: policy.unwrap_or_default(),
+    })
+}
+
+/// String `key` in the table at the dotted `section` of a TOML file
+fn toml_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let table: toml::Table = toml::from_str(content).ok()?;
+    section
+        .split('.')
+        .try_fold(&table, |table, name| table.get(name)?.as_table())?
+        .get(key)?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Value of `key` in `[section]` of an INI file such as `setup.cfg`
+fn ini_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let mut current = "";
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[') {
+            current = name.strip_suffix(']').unwrap_or(name).trim();
+        } else if current == section && !line.starts_with(['#', ';']) {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            if name.trim() == key {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+/// `dir` for display, with `.` for the scan root
+fn display_dir(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        dir.to_string_lossy().replace('\\', "/")
+    }
+}
+
+/// Finds the package of each venv, reading each directory once
+#[derive(Debug)]
+pub struct Packages<'a> {
+    workdir: &'a Path,
+    by_dir: HashMap<PathBuf, Option<Package>>,
+}
+
+impl<'a> Packages<'a> {
+    /// Package lookup for venvs found under `workdir`
+    pub fn new(workdir: &'a Path) -> Self {
+        Self {
+            workdir,
+            by_dir: HashMap::new(),
+        }
+    }
+
+    /// The nearest package above the venv whose `pyvenv.cfg` is at
+    /// `cfg_path`, relative to the scan root
+    pub fn package_of(&mut self, cfg_path: &Path) -> Option<&Package> {
+        // The venv directory itself is never the package
+        let venv_dir = cfg_path.parent()?;
+        let workdir = self.workdir;
+        let mut found = None;
+        for rel_dir in venv_dir.ancestors().skip(1) {
+            let package = self
+                .by_dir
+                .entry(rel_dir.to_path_buf())
+                .or_insert_with(|| read_package(workdir, &workdir.join(rel_dir)));
+            if package.is_some() {
+                found = Some(rel_dir.to_path_buf());
+                break;
+            }
+        }
+        self.by_dir.get(&found?)?.as_ref()
+    }
+
+    /// Split `venvs` by the policy of their package into the ones that fail
+    /// the scan and the ones that are only reported; allowed ones are dropped
+    pub fn apply_policies(&mut self, venvs: Vec<VenvInfo>) -> (Vec<VenvInfo>, Vec<VenvInfo>) {
+        let mut denied = Vec::new();
+        let mut warned = Vec::new();
+        for venv in venvs {
+            match self.package_of(&venv.path).map(|package| package.policy) {
+                None | Some(Policy::Deny) => denied.push(venv),
+                Some(Policy::Warn) => warned.push(venv),
+                Some(Policy::Allow) => {}
+            }
+        }
+        (denied, warned)
+    }
+}
+
+/// List the violations per package
+pub fn print_package_groups(packages: &mut Packages<'_>, venvs: &[VenvInfo], is_tty: bool) {
+    let mut groups: BTreeMap<String, (String, Vec<&VenvInfo>)> = BTreeMap::new();
+    for venv in venvs {
+        let (key, label) = packages.package_of(&venv.path).map_or_else(
+            || ("~".to_string(), "(no package)".to_string()),
+            |package| {
+                let dir = display_dir(&package.dir);
+                let label = if package.name == dir {
+                    dir.clone()
+                } else {
+                    format!("{} ({dir})", package.name)
+                };
+                (dir, label)
+            },
+        );
+        groups
+            .entry(key)
+            .or_insert_with(|| (label, Vec::new()))
+            .1
+            .push(venv);
+    }
+
+    if is_tty {
+        println!("{}", "Violations by package:".bold());
+    } else {
+        println!("Violations by package:");
+    }
+    for (label, venvs) in groups.values() {
+        if is_tty {
+            println!("  {} ({})", label.cyan(), venvs.len());
+        } else {
+            println!("  {label} ({})", venvs.len());
+        }
+        for venv in venvs {
+            println!("    {}", venv.path.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    println!();
+}
+
+/// Print the venvs a `warn` policy keeps from failing the scan
+pub fn print_warned(venvs: &[VenvInfo], is_tty: bool) {
+    let headline = format!(
+        "{} virtual environment(s) not ignored by Git in packages with `policy = \"warn\"`:",
+        venvs.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "Note:".yellow().bold());
+    } else {
+        println!("Note: {headline}");
+    }
+    for venv in venvs {
+        let path = venv.path.to_string_lossy().replace('\\', "/");
+        if is_tty {
+            println!("     📁 {}", path.cyan());
+        } else {
+            println!("    {path}");
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_marker_values() {
+        let pyproject = "[project]\nname = \"a\"\n[tool.unvenv]\n# policy = \"allow\"\npolicy = 'warn'  # for now\n";
+        assert_eq!(
+            toml_value(pyproject, "tool.unvenv", "policy").as_deref(),
+            Some("warn")
+        );
+        assert_eq!(
+            toml_value(pyproject, "project", "name").as_deref(),
+            Some("a")
+        );
+        assert_eq!(toml_value(pyproject, "tool.poetry", "name"), None);
+        assert_eq!(toml_value("[project\nname = a\n", "project", "name"), None);
+
+        let setup_cfg = "[metadata]\nname = b\n\n[tool:unvenv]\n; policy = allow\npolicy = warn\n";
+        assert_eq!(
+            ini_value(setup_cfg, "metadata", "name").as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            ini_value(setup_cfg, "tool:unvenv", "policy").as_deref(),
+            Some("warn")
+        );
+        assert_eq!(ini_value(setup_cfg, "options", "name"), None);
+    }
+
+    fn venv(path: &str) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking: None,
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    fn workspace() -> std::io::Result<TempDir> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::write(root.join("pyproject.toml"), "[project]\nname = \"mono\"\n")?;
+        fs::create_dir_all(root.join("libs/core/venv"))?;
+        fs::write(
+            root.join("libs/core/pyproject.toml"),
+            "[project]\nname = \"core\"\n\n[tool.unvenv]\npolicy = \"warn\"  # being migrated\n",
+        )?;
+        fs::create_dir_all(root.join("tools/scripts/.venv"))?;
+        fs::write(
+            root.join("tools/scripts/setup.cfg"),
+            "[metadata]\nname = scripts\n\n[tool:unvenv]\npolicy = allow\n",
+        )?;
+        fs::create_dir_all(root.join("apps/web/env"))?;
+        fs::write(root.join("apps/web/setup.py"), "")?;
+        Ok(temp_dir)
+    }
+
+    #[test]
+    fn test_package_of_finds_nearest_package() -> std::io::Result<()> {
+        let temp_dir = workspace()?;
+        let mut packages = Packages::new(temp_dir.path());
+
+        let package = |packages: &mut Packages<'_>, path: &str| {
+            packages
+                .package_of(Path::new(path))
+                .map(|package| (package.name.clone(), package.policy))
+        };
+        assert_eq!(
+            package(&mut packages, "libs/core/venv/pyvenv.cfg"),
+            Some(("core".to_string(), Policy::Warn))
+        );
+        assert_eq!(
+            package(&mut packages, "tools/scripts/.venv/pyvenv.cfg"),
+            Some(("scripts".to_string(), Policy::Allow))
+        );
+        assert_eq!(
+            package(&mut packages, "apps/web/env/pyvenv.cfg"),
+            Some(("apps/web".to_string(), Policy::Deny))
+        );
+        assert_eq!(
+            package(&mut packages, "venv/pyvenv.cfg"),
+            Some(("mono".to_string(), Policy::Deny))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_policies() -> std::io::Result<()> {
+        let temp_dir = workspace()?;
+        let mut packages = Packages::new(temp_dir.path());
+
+        let (denied, warned) = packages.apply_policies(vec![
+            venv("libs/core/venv/pyvenv.cfg"),
+            venv("tools/scripts/.venv/pyvenv.cfg"),
+            venv("apps/web/env/pyvenv.cfg"),
+        ]);
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].path, Path::new("apps/web/env/pyvenv.cfg"));
+        assert_eq!(warned.len(), 1);
+        assert_eq!(warned[0].path, Path::new("libs/core/venv/pyvenv.cfg"));
+        Ok(())
+    }
+}
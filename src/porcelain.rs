@@ -0,0 +1,218 @@
+//! `scan --porcelain=v1`: scan results in a frozen, script-friendly layout.
+//!
+//! The human report is free to change between releases; the porcelain
+//! format is not. Each version's layout is fixed once released, and a new
+//! layout gets a new version, as with `git status --porcelain=v2`. Version
+//! 1 prints one record per line (or per NUL with `-z`), with
+//! space-separated fields:
+//!
+//! ```text
+//! violation UV001 untracked tools/venv/pyvenv.cfg
+//! warned UV002 staged libs/core/.venv/pyvenv.cfg
+//! suppressed UV001 untracked tests/fixtures/venv/pyvenv.cfg
+//! artifact UV010 tools/.pyenv
+//! reference UV006 12 scripts/run.py
+//! partial
+//! ```
+//!
+//! - `violation <rule> <tracking> <path>`: a venv that fails the scan
+//! - `warned <rule> <tracking> <path>`: a venv in a package with `policy =
+//!   "warn"`, reported without failing
+//! - `suppressed <rule> <tracking> <path>`: a venv below a `.unvenv-allow`
+//!   marker
+//! - `artifact <rule> <path>`: a tooling directory such as `.direnv/` that
+//!   Git does not ignore
+//! - `reference <rule> <line> <path>`: a tracked file naming an interpreter
+//!   that only exists on this machine on 1-based line `<line>`
+//! - `partial`: a limit stopped the scan, so the records may be incomplete
+//!
+//! `<tracking>` is `untracked`, `staged`, `committed`, or `none` outside a
+//! repository. `<path>` is the `pyvenv.cfg`, the directory, or the file,
+//! relative to the scanned directory with `/` separators, always the last
+//! field so it may contain spaces. Without `-z`, a path containing a double
+//! quote, backslash, tab, or newline is written in double quotes with those
+//! characters escaped as `\"`, `\\`, `\t`, and `\n`; with `-z` paths are
+//! never quoted. A clean scan prints nothing. Records appear in the order
+//! above; scripts should skip record types they do not know, which later
+//! releases may add to v1.
+
+use crate::{TrackingStatus, VenvInfo, artifacts::Artifact, references::Reference, rules::Rule};
+use clap::ValueEnum;
+use std::fmt::Write as _;
+
+/// Layout versions of the porcelain format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PorcelainVersion {
+    /// Version 1
+    V1,
+}
+
+/// Scan results to print
+#[derive(Debug, Default)]
+pub struct Results<'a> {
+    /// Venvs that fail the scan
+    pub violations: &'a [VenvInfo],
+    /// Venvs a package policy only warns about
+    pub warned: &'a [VenvInfo],
+    /// Venvs below an allow marker
+    pub suppressed: Vec<&'a VenvInfo>,
+    /// Tooling directories Git does not ignore
+    pub artifacts: &'a [Artifact],
+    /// Tracked files pointing into a repo-local venv
+    pub references: &'a [Reference],
+    /// Whether a limit stopped the scan early
+    pub partial: bool,
+}
+
+/// The `<tracking>` field, spelled out here so that relabeling the human
+/// report cannot change it
+const fn tracking(venv: &VenvInfo) -> &'static str {
+    match venv.tracking {
+        None => "none",
+        Some(TrackingStatus::Untracked) => "untracked",
+        Some(TrackingStatus::Staged) => "staged",
+        Some(TrackingStatus::Committed) => "committed",
+    }
+}
+
+/// Quote `path` if a line-based parser would misread it
+fn quote(path: &str) -> String {
+    if !path.contains(['"', '\\', '\t', '\n']) {
+        return path.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for ch in path.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The results in the porcelain layout `version`, records terminated by NUL
+/// with `nul` and by newline otherwise
+pub fn render(version: PorcelainVersion, results: &Results<'_>, nul: bool) -> String {
+    let PorcelainVersion::V1 = version;
+    let terminator = if nul { '\0' } else { '\n' };
+    let mut output = String::new();
+    let groups = [
+        ("violation", results.violations.iter().collect::<Vec<_>>()),
+        ("warned", results.warned.iter().collect()),
+        ("suppressed", results.suppressed.clone()),
+    ];
+    for (kind, venvs) in groups {
+        for venv in venvs {
+            let path = venv.path.to_string_lossy().replace('\\', "/");
+            let _ = write!(
+                output,
+                "{kind} {} {} {}{terminator}",
+                Rule::for_venv(venv).id,
+                tracking(venv),
+                if nul { path } else { quote(&path) }
+            );
+        }
+    }
+    for artifact in results.artifacts {
+        let path = artifact.path.to_string_lossy().replace('\\', "/");
+        let _ = write!(
+            output,
+            "artifact {} {}{terminator}",
+            artifact.kind.rule().id,
+            if nul { path } else { quote(&path) }
+        );
+    }
+    for reference in results.references {
+        let path = reference.file.to_string_lossy().replace('\\', "/");
+        let _ = write!(
+            output,
+            "reference {} {} {}{terminator}",
+            reference.rule().id,
+            reference.line,
+            if nul { path } else { quote(&path) }
+        );
+    }
+    if results.partial {
+        let _ = write!(output, "partial{terminator}");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{artifacts::ArtifactKind, references::Kind};
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    fn venv(path: &str, tracking: Option<TrackingStatus>) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: Some("3.12.1".to_string()),
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking,
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    #[test]
+    fn test_render_v1() {
+        let violations = [
+            venv("tools/venv/pyvenv.cfg", Some(TrackingStatus::Untracked)),
+            venv("my env/pyvenv.cfg", Some(TrackingStatus::Committed)),
+        ];
+        let warned = [venv(
+            "libs/core/.venv/pyvenv.cfg",
+            Some(TrackingStatus::Staged),
+        )];
+        let allowed = venv("tests/fixtures/venv/pyvenv.cfg", None);
+        let results = Results {
+            violations: &violations,
+            warned: &warned,
+            suppressed: vec![&allowed],
+            artifacts: &[Artifact {
+                path: PathBuf::from("tools/.pyenv"),
+                kind: ArtifactKind::Pyenv,
+            }],
+            references: &[Reference {
+                file: PathBuf::from("scripts/run.py"),
+                line: 12,
+                target: "/src/app/.venv/bin/python".to_string(),
+                kind: Kind::Path,
+            }],
+            partial: true,
+        };
+
+        // Frozen: changing this output breaks scripts
+        assert_eq!(
+            render(PorcelainVersion::V1, &results, false),
+            "violation UV001 untracked tools/venv/pyvenv.cfg\n\
+             violation UV003 committed my env/pyvenv.cfg\n\
+             warned UV002 staged libs/core/.venv/pyvenv.cfg\n\
+             suppressed UV001 none tests/fixtures/venv/pyvenv.cfg\n\
+             artifact UV010 tools/.pyenv\n\
+             reference UV006 12 scripts/run.py\n\
+             partial\n"
+        );
+        assert!(
+            render(PorcelainVersion::V1, &results, true)
+                .starts_with("violation UV001 untracked tools/venv/pyvenv.cfg\0violation")
+        );
+        assert_eq!(render(PorcelainVersion::V1, &Results::default(), false), "");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a b/pyvenv.cfg"), "a b/pyvenv.cfg");
+        assert_eq!(
+            quote("odd\t\"dir\"\n/pyvenv.cfg"),
+            "\"odd\\t\\\"dir\\\"\\n/pyvenv.cfg\""
+        );
+    }
+}
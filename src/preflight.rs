@@ -0,0 +1,117 @@
+//! Checks run before `update` downloads anything.
+//!
+//! A release archive is tens of megabytes; finding out after the download
+//! that the install directory is read-only or the disk is full wastes the
+//! download and leaves a confusing error. These checks fail early instead,
+//! with a message that says what to do.
+
+use anyhow::{Result, bail};
+use std::{env, fs, path::Path, process::Command};
+
+/// Space assumed necessary when the binary's size is unknown
+const FALLBACK_REQUIRED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Verify the temp directory and `install_dir`, where install.sh is told to
+/// put the new binary, can take the update of `binary`
+pub fn check_update(install_dir: &Path, binary: &Path) -> Result<()> {
+    check_writable(install_dir)?;
+
+    // The archive and extracted binary land in the temp dir; the new binary
+    // and the backup kept for rollback land next to the old one
+    let required = fs::metadata(binary).map_or(FALLBACK_REQUIRED_BYTES, |metadata| {
+        metadata.len().saturating_mul(2)
+    });
+    check_free_space(&env::temp_dir(), required)?;
+    check_free_space(install_dir, required)
+}
+
+/// Fail unless a file can be created in `dir`
+pub fn check_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(format!(".unvenv-write-test-{}", std::process::id()));
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => bail!(
+            "Cannot write to {} ({e}); re-run with sufficient permissions or pass --install-dir with a directory you own",
+            dir.display()
+        ),
+    }
+}
+
+/// Fail if `dir` is known to have less than `required` bytes free
+///
+/// When the free space cannot be determined the check passes, so an
+/// unusual platform never blocks updates.
+fn check_free_space(dir: &Path, required: u64) -> Result<()> {
+    let Some(available) = free_space(dir) else {
+        return Ok(());
+    };
+    if available < required {
+        bail!(
+            "Not enough disk space in {}: {} MB free, {} MB needed",
+            dir.display(),
+            available / (1024 * 1024),
+            required.div_ceil(1024 * 1024)
+        );
+    }
+    Ok(())
+}
+
+/// Free bytes on the filesystem holding `dir`, as reported by POSIX `df`
+fn free_space(dir: &Path) -> Option<u64> {
+    if cfg!(windows) {
+        return None;
+    }
+    let output = Command::new("df").arg("-Pk").arg(dir).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `df -Pk`: a header line, then `fs blocks used available capacity mount`
+fn parse_df_output(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available_kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_df_output() {
+        let output = "\
+Filesystem     1024-blocks      Used Available Capacity Mounted on
+/dev/sda1        102400000  51200000  51200000      50% /
+";
+        assert_eq!(parse_df_output(output), Some(51_200_000 * 1024));
+        assert_eq!(parse_df_output("Filesystem\n"), None);
+    }
+
+    #[test]
+    fn test_check_writable() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        check_writable(temp_dir.path())?;
+        assert!(check_writable(&temp_dir.path().join("missing")).is_err());
+        // The probe file is cleaned up
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_free_space_rejects_impossible_requirement() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+        assert!(check_free_space(temp_dir.path(), 0).is_ok());
+        if free_space(temp_dir.path()).is_some() {
+            assert!(check_free_space(temp_dir.path(), u64::MAX).is_err());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Progress indication for long scans.
+//!
+//! Most scans finish well within a second, where a spinner would only
+//! flicker. The spinner therefore stays hidden until the scan has run for
+//! [`SHOW_AFTER`], and is erased again before the report is printed.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::time::{Duration, Instant};
+
+/// How long a scan runs before the spinner appears
+const SHOW_AFTER: Duration = Duration::from_secs(1);
+
+/// Only look at the clock every this many directories
+const CLOCK_INTERVAL: u64 = 64;
+
+/// Spinner with a count of directories visited
+pub struct ScanProgress {
+    enabled: bool,
+    started: Instant,
+    dirs: u64,
+    bar: Option<ProgressBar>,
+}
+
+impl ScanProgress {
+    /// Start tracking a scan; nothing is ever drawn unless `enabled`
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            started: Instant::now(),
+            dirs: 0,
+            bar: None,
+        }
+    }
+
+    /// Record one more visited directory
+    pub fn dir_visited(&mut self) {
+        self.dirs += 1;
+
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.dirs);
+        } else if self.enabled
+            && self.dirs % CLOCK_INTERVAL == 0
+            && self.started.elapsed() >= SHOW_AFTER
+        {
+            self.bar = Some(spinner(self.dirs));
+        }
+    }
+
+    /// Erase the spinner so that it does not interleave with the report
+    pub fn clear(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+impl Drop for ScanProgress {
+    fn drop(&mut self) {
+        // Also erase the spinner when the scan bails out with an error
+        self.clear();
+    }
+}
+
+fn spinner(dirs: u64) -> ProgressBar {
+    let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stdout());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} Scanning... {pos} directories visited ({elapsed})")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+    );
+    bar.set_position(dirs);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_progress_never_shows() {
+        let mut progress = ScanProgress::new(false);
+        progress.started -= SHOW_AFTER;
+
+        for _ in 0..CLOCK_INTERVAL * 2 {
+            progress.dir_visited();
+        }
+
+        assert!(progress.bar.is_none());
+    }
+
+    #[test]
+    fn test_progress_hidden_for_short_scans() {
+        let mut progress = ScanProgress::new(true);
+
+        for _ in 0..CLOCK_INTERVAL * 2 {
+            progress.dir_visited();
+        }
+
+        assert!(progress.bar.is_none());
+    }
+
+    #[test]
+    fn test_progress_shows_for_long_scans_and_clears() {
+        let mut progress = ScanProgress::new(true);
+        progress.started -= SHOW_AFTER;
+
+        for _ in 0..CLOCK_INTERVAL {
+            progress.dir_visited();
+        }
+        assert!(progress.bar.is_some());
+
+        progress.clear();
+        assert!(progress.bar.is_none());
+    }
+}
@@ -0,0 +1,291 @@
+//! `unvenv quickstart`: guided setup for a repository.
+//!
+//! One flow for people who have never used unvenv: check that there is a
+//! repository, then offer the `.gitignore` entries it is missing, the
+//! pre-commit hook, and a starter `.unvenv.toml`. Every change is asked for
+//! first; `--yes` accepts them all, and without a terminal on stdin nothing is
+//! changed. Interactive scans mention the command once, on the first run.
+
+use crate::{
+    ScanArgs, cache::user_cache_dir, config, config::Outcome, doctor, find_unignored_venvs, hook,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::Repository;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+/// Commented configuration written by the quickstart
+const STARTER_CONFIG: &str = "\
+# unvenv configuration; see `unvenv --help` for what each setting does.
+
+# Exit codes of each outcome, for wrapper scripts that give the defaults
+# another meaning.
+[exit-codes]
+# clean = 0
+# internal-error = 1
+# violations = 2
+# partial = 3
+# config-error = 4
+# no-repository = 5
+";
+
+/// Marker recording that the first-run hint has been shown
+const HINT_MARKER: &str = "quickstart-hint-shown";
+
+/// Asks yes/no questions, or answers them without asking
+struct Prompter {
+    assume_yes: bool,
+    interactive: bool,
+}
+
+impl Prompter {
+    /// Ask `question`, defaulting to yes
+    fn confirm(&self, question: &str) -> Result<bool> {
+        if self.assume_yes {
+            println!("{question} yes");
+            return Ok(true);
+        }
+        if !self.interactive {
+            println!("{question} skipped (not interactive; rerun with --yes)");
+            return Ok(false);
+        }
+
+        print!("{question} [Y/n] ");
+        io::stdout()
+            .flush()
+            .context("Failed to write the question")?;
+        let mut answer = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .context("Failed to read the answer")?;
+        let answer = answer.trim().to_ascii_lowercase();
+        Ok(answer.is_empty() || answer == "y" || answer == "yes")
+    }
+}
+
+/// Entries `.gitignore` needs: the standard patterns it lacks, then the
+/// directories of venvs it does not ignore yet
+fn gitignore_additions(repo: &Repository, root: &Path) -> Result<Vec<String>> {
+    let mut additions: Vec<String> = doctor::missing_patterns(repo)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    for venv in find_unignored_venvs(root, &ScanArgs::default(), false)?.venvs {
+        let Some(dir) = venv.path.parent() else {
+            continue;
+        };
+        // A standard pattern being added, e.g. `.venv/`, already covers it
+        let name = dir
+            .file_name()
+            .map(|name| format!("{}/", name.to_string_lossy()));
+        if name.is_some_and(|name| additions.contains(&name)) {
+            continue;
+        }
+        let entry = format!("/{}/", dir.to_string_lossy().replace('\\', "/"));
+        if !additions.contains(&entry) {
+            additions.push(entry);
+        }
+    }
+    Ok(additions)
+}
+
+/// Append `entries` to the `.gitignore` at `path`, creating it if needed
+fn append_gitignore(path: &Path, entries: &[String]) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let mut text = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str("\n# Python virtual environments (added by unvenv quickstart)\n");
+    for entry in entries {
+        text.push_str(entry);
+        text.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .with_context(|| format!("Failed to update {}", path.display()))
+}
+
+/// Print a step result
+fn print_step(ok: bool, message: &str, is_tty: bool) {
+    match (ok, is_tty) {
+        (true, true) => println!("{} {message}", "OK:".green().bold()),
+        (true, false) => println!("OK: {message}"),
+        (false, true) => println!("{} {message}", "TODO:".yellow().bold()),
+        (false, false) => println!("TODO: {message}"),
+    }
+}
+
+/// Run the guided setup for the repository containing `workdir`
+pub fn run(workdir: &Path, assume_yes: bool, is_tty: bool) -> Result<i32> {
+    let prompter = Prompter {
+        assume_yes,
+        interactive: io::stdin().is_terminal(),
+    };
+    let repo = match Repository::discover(workdir) {
+        Ok(repo) if !repo.is_bare() => repo,
+        _ => {
+            print_step(
+                false,
+                "Not inside a Git repository; run `git init` or change into a clone, then rerun `unvenv quickstart`",
+                is_tty,
+            );
+            return Ok(Outcome::NoRepository.default_code());
+        }
+    };
+    let root = repo
+        .workdir()
+        .context("Repository has no working tree")?
+        .to_path_buf();
+    print_step(
+        true,
+        &format!("Git repository at {}", root.display()),
+        is_tty,
+    );
+
+    let additions = gitignore_additions(&repo, &root)?;
+    if additions.is_empty() {
+        print_step(
+            true,
+            ".gitignore already covers Python virtual environments",
+            is_tty,
+        );
+    } else {
+        println!();
+        println!(".gitignore is missing:");
+        for entry in &additions {
+            println!("  {entry}");
+        }
+        if prompter.confirm("Add these entries to .gitignore?")? {
+            append_gitignore(&root.join(".gitignore"), &additions)?;
+            print_step(true, "Updated .gitignore", is_tty);
+            println!("  Venvs that are already committed still need `git rm -r --cached <dir>`.");
+        }
+    }
+
+    println!();
+    let status = hook::status(&repo)?;
+    if status.installed && status.current {
+        print_step(true, "Pre-commit hook runs unvenv", is_tty);
+    } else if prompter
+        .confirm("Install the pre-commit hook, so venvs are caught before they are committed?")?
+    {
+        let (path, _) = hook::install(&repo)?;
+        print_step(
+            true,
+            &format!("Pre-commit hook installed at {}", path.display()),
+            is_tty,
+        );
+    }
+
+    println!();
+    let config_path = root.join(config::FILE_NAME);
+    if config_path.exists() {
+        print_step(true, &format!("{} exists", config::FILE_NAME), is_tty);
+    } else if prompter.confirm(&format!("Write a starter {}?", config::FILE_NAME))? {
+        fs::write(&config_path, STARTER_CONFIG)
+            .with_context(|| format!("Failed to write {}", config_path.display()))?;
+        print_step(true, &format!("Wrote {}", config_path.display()), is_tty);
+    }
+
+    println!();
+    println!("Run `unvenv scan` any time, or `unvenv doctor` to check the setup.");
+    Ok(0)
+}
+
+/// Point first-time users at the quickstart, once per user
+///
+/// Like the update hint, this only ever appears in a terminal.
+pub fn maybe_print_hint(is_tty: bool) {
+    if !is_tty {
+        return;
+    }
+    let Some(marker) = user_cache_dir().map(|dir| dir.join(HINT_MARKER)) else {
+        return;
+    };
+    if marker.exists() {
+        return;
+    }
+    if let Some(parent) = marker.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    // Without a writable marker the hint would repeat on every scan
+    if fs::write(&marker, "").is_ok() {
+        eprintln!(
+            "{} New to unvenv? `unvenv quickstart` sets up .gitignore, the pre-commit hook, and a config file in one go",
+            "Hint:".cyan().bold()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    fn init_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        git(temp_dir.path(), &["init", "-q"]);
+        temp_dir
+    }
+
+    #[test]
+    fn test_gitignore_additions() -> Result<()> {
+        let temp_dir = init_repo();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "__pycache__/\n")?;
+        for dir in [".venv", "services/api/env"] {
+            fs::create_dir_all(root.join(dir))?;
+            fs::write(root.join(dir).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+        }
+
+        let repo = Repository::open(root)?;
+        assert_eq!(
+            gitignore_additions(&repo, root)?,
+            [".venv/", "venv/", "/services/api/env/"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_with_yes_sets_everything_up() -> Result<()> {
+        let temp_dir = init_repo();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "*.pyc")?;
+        fs::create_dir(root.join("env"))?;
+        fs::write(root.join("env/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        assert_eq!(run(root, true, false)?, 0);
+
+        let gitignore = fs::read_to_string(root.join(".gitignore"))?;
+        assert!(gitignore.starts_with("*.pyc\n\n# Python virtual environments"));
+        assert!(gitignore.contains("\n/env/\n"));
+        let repo = Repository::open(root)?;
+        assert!(hook::status(&repo)?.installed);
+        let config = fs::read_to_string(root.join(config::FILE_NAME))?;
+        assert!(config::parse(&config, Path::new(config::FILE_NAME)).is_ok());
+
+        // Nothing is left to do on a second run
+        assert!(gitignore_additions(&repo, root)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_outside_repository() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        assert_eq!(
+            run(temp_dir.path(), true, false)?,
+            Outcome::NoRepository.default_code()
+        );
+        Ok(())
+    }
+}
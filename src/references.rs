@@ -0,0 +1,561 @@
+//! Tracked files that point at a venv only this machine has.
+//!
+//! Ignoring a venv keeps it out of Git, but a committed script whose shebang
+//! is `#!/home/alice/src/app/.venv/bin/python`, or a Makefile or launch
+//! configuration naming that interpreter by absolute path, still only works
+//! on the machine it was written on. The scan reads every tracked text file
+//! below the scanned directory, looks for absolute paths under the
+//! repository root, and reports those that lead into a directory holding a
+//! `pyvenv.cfg` as rule UV006 `venv-reference`. Files inside a venv are left
+//! to the venv's own finding.
+//!
+//! Editor settings leak interpreters from outside the repository too:
+//! `.vscode/settings.json` with a `python.defaultInterpreterPath` of
+//! `/Users/bob/.virtualenvs/app/bin/python`, or a JetBrains SDK entry in
+//! `.idea/misc.xml`. In those files (`.vscode/*.json`, `*.code-workspace`,
+//! `.idea/**/*.xml`, `.idea/**/*.iml`, `pyrightconfig.json`) any absolute
+//! path to a `bin/python*` or `Scripts\python*.exe` interpreter under a home
+//! directory (`/home/`, `/Users/`, `/root/`, `~/`, `C:\Users\`) is rule UV007
+//! `interpreter-path`.
+//!
+//! A tracked activation script (`activate`, `activate.fish`, `Activate.ps1`,
+//! and the other shells' variants) that sets `VIRTUAL_ENV` outside any venv
+//! is rule UV008 `activation-script`: someone copied part of a venv into the
+//! tree, and the script activates a directory other clones do not have.
+
+use crate::rules::{ACTIVATION_SCRIPT, INTERPRETER_PATH, Rule, VENV_REFERENCE};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::Repository;
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Files larger than this are not read
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// File names of the activation scripts venv and virtualenv write, compared
+/// case-insensitively (`Activate.ps1`)
+const ACTIVATION_SCRIPTS: [&str; 8] = [
+    "activate",
+    "activate.bat",
+    "activate.csh",
+    "activate.fish",
+    "activate.nu",
+    "activate.ps1",
+    "activate.xsh",
+    "activate_this.py",
+];
+
+/// Bytes inspected for a NUL to tell binary files apart, as Git does
+const BINARY_PROBE: usize = 8000;
+
+/// How a tracked file names a venv
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// The shebang interpreter is inside a repo-local venv
+    Shebang,
+    /// Another path inside a repo-local venv
+    Path,
+    /// An editor setting names an interpreter in a home directory
+    Interpreter,
+    /// A venv activation script outside a venv
+    Activation,
+}
+
+/// A tracked file naming a venv path that only exists on this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// File containing the path, relative to the scanned directory
+    pub file: PathBuf,
+    /// 1-based line of the path
+    pub line: usize,
+    /// The path as written
+    pub target: String,
+    /// What kind of path it is
+    pub kind: Kind,
+}
+
+impl Reference {
+    /// The rule the reference violates
+    pub const fn rule(&self) -> Rule {
+        match self.kind {
+            Kind::Shebang | Kind::Path => VENV_REFERENCE,
+            Kind::Interpreter => INTERPRETER_PATH,
+            Kind::Activation => ACTIVATION_SCRIPT,
+        }
+    }
+
+    /// This reference in the public report model
+    pub fn to_report(&self) -> unvenv::Reference {
+        let rule = self.rule();
+        let mut reference = unvenv::Reference::new(
+            self.file.clone(),
+            self.line,
+            self.target.clone(),
+            rule.id.to_string(),
+        );
+        reference.help_uri = Some(rule.doc_url());
+        reference
+    }
+}
+
+/// The venv directory (relative to `root`) that `rel` lies in, if any
+fn venv_of(root: &Path, rel: &Path) -> Option<PathBuf> {
+    rel.ancestors()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .find(|dir| root.join(dir).join("pyvenv.cfg").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Whether `ch` ends a path written in a script or config file
+fn ends_path(ch: char) -> bool {
+    ch.is_whitespace()
+        || matches!(
+            ch,
+            '"' | '\'' | '`' | ',' | ';' | ')' | ']' | '}' | '<' | '>'
+        )
+}
+
+/// The paths starting with one of `prefixes` written in `text`, with their
+/// 1-based line
+fn paths_below<'a>(text: &'a str, prefixes: &[String]) -> Vec<(usize, &'a str)> {
+    let mut found = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        // Byte ranges already taken, since one prefix may contain another
+        // (`/tmp/app/` inside `/private/tmp/app/`)
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for prefix in prefixes {
+            for (start, _) in line.match_indices(prefix.as_str()) {
+                let end = line[start..]
+                    .find(ends_path)
+                    .map_or(line.len(), |end| start + end);
+                if !spans.iter().any(|&(from, to)| from <= start && end <= to) {
+                    spans.push((start, end));
+                }
+            }
+        }
+        spans.sort_unstable();
+        found.extend(
+            spans
+                .into_iter()
+                .map(|(start, end)| (index + 1, &line[start..end])),
+        );
+    }
+    found
+}
+
+/// Whether `rel` is an editor or type checker setting that names an
+/// interpreter
+fn is_editor_config(rel: &Path) -> bool {
+    let name = rel.file_name().unwrap_or_default().to_string_lossy();
+    let extension = rel.extension().unwrap_or_default();
+    let in_dir = |dir: &str| rel.components().any(|part| part.as_os_str() == dir);
+    name.ends_with(".code-workspace")
+        || name == "pyrightconfig.json"
+        || (in_dir(".vscode") && extension == "json")
+        || (in_dir(".idea") && (extension == "xml" || extension == "iml"))
+}
+
+/// The line of an activation script that sets `VIRTUAL_ENV`, if `rel` is one
+///
+/// A file merely named `activate` is left alone; every venv and virtualenv
+/// activation script sets the variable.
+fn activation_line(rel: &Path, text: &str) -> Option<(usize, String)> {
+    let name = rel.file_name()?.to_string_lossy().to_ascii_lowercase();
+    if !ACTIVATION_SCRIPTS.contains(&name.as_str()) {
+        return None;
+    }
+    text.lines()
+        .enumerate()
+        .find(|(_, line)| line.contains("VIRTUAL_ENV"))
+        .map(|(index, line)| (index + 1, line.trim().to_string()))
+}
+
+/// Whether `path`, with `/` separators, starts in a user's home directory
+fn in_home(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    ["/home/", "/users/", "/root/", "~/"]
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+        || lower
+            .get(1..)
+            .is_some_and(|rest| rest.starts_with(":/users/"))
+}
+
+/// Whether `name` is a Python interpreter binary (`python`, `python3.12`,
+/// `pythonw.exe`)
+fn is_interpreter(name: &str) -> bool {
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    name.strip_prefix("pythonw")
+        .or_else(|| name.strip_prefix("python"))
+        .is_some_and(|version| version.chars().all(|ch| ch.is_ascii_digit() || ch == '.'))
+}
+
+/// The interpreter paths in home directories written in `text`, with their
+/// 1-based line
+fn interpreters_in_home(text: &str) -> Vec<(usize, String)> {
+    let mut found = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for token in line.split(ends_path).filter(|token| !token.is_empty()) {
+            // JSON doubles the separators of Windows paths
+            let path = token.replace("\\\\", "/").replace('\\', "/");
+            let mut parts = path.rsplit('/');
+            let (Some(name), Some(dir)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            if is_interpreter(name)
+                && (dir == "bin" || dir.eq_ignore_ascii_case("scripts"))
+                && in_home(&path)
+            {
+                found.push((index + 1, token.to_string()));
+            }
+        }
+    }
+    found
+}
+
+/// The contents of `path` if it is a small text file
+fn read_text(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    file.take(MAX_FILE_SIZE).read_to_end(&mut bytes).ok()?;
+    if bytes.iter().take(BINARY_PROBE).any(|&byte| byte == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Find the references in the tracked files below `workdir`, or only in
+/// `files` (relative to `workdir`) when given
+///
+/// Returns nothing outside a repository.
+pub fn find(workdir: &Path, files: Option<&[PathBuf]>) -> Result<Vec<Reference>> {
+    let Ok(repo) = Repository::discover(workdir) else {
+        return Ok(Vec::new());
+    };
+    let Some(root) = repo.workdir().map(Path::to_path_buf) else {
+        return Ok(Vec::new());
+    };
+    let scope = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
+    let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+    let Ok(scope) = scope.strip_prefix(&canonical_root).map(Path::to_path_buf) else {
+        return Ok(Vec::new());
+    };
+
+    // The root as written in files, with and without symlinks resolved
+    let mut prefixes = Vec::new();
+    for dir in [&root, &canonical_root] {
+        let prefix = format!("{}/", dir.to_string_lossy().trim_end_matches('/'));
+        if !prefixes.contains(&prefix) {
+            prefixes.push(prefix);
+        }
+    }
+
+    let tracked: Vec<PathBuf> = if let Some(files) = files {
+        files.iter().map(|file| scope.join(file)).collect()
+    } else {
+        let index = repo.index().context("Failed to read the Git index")?;
+        index
+            .iter()
+            .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()))
+            .filter(|path| path.starts_with(&scope))
+            .collect()
+    };
+
+    let mut references = Vec::new();
+    for rel in tracked {
+        if venv_of(&canonical_root, &rel).is_some() {
+            continue;
+        }
+        let Some(text) = read_text(&canonical_root.join(&rel)) else {
+            continue;
+        };
+        // The paths in a stray activation script are part of the one finding
+        if let Some((line, target)) = activation_line(&rel, &text) {
+            references.push(Reference {
+                file: rel.strip_prefix(&scope).unwrap_or(&rel).to_path_buf(),
+                line,
+                target,
+                kind: Kind::Activation,
+            });
+            continue;
+        }
+        for (line, target) in paths_below(&text, &prefixes) {
+            let Some(prefix) = prefixes.iter().find(|prefix| target.starts_with(*prefix)) else {
+                continue;
+            };
+            if venv_of(&canonical_root, Path::new(&target[prefix.len()..])).is_none() {
+                continue;
+            }
+            references.push(Reference {
+                file: rel.strip_prefix(&scope).unwrap_or(&rel).to_path_buf(),
+                line,
+                target: target.to_string(),
+                kind: if line == 1 && text.starts_with("#!") {
+                    Kind::Shebang
+                } else {
+                    Kind::Path
+                },
+            });
+        }
+        if !is_editor_config(&rel) {
+            continue;
+        }
+        for (line, target) in interpreters_in_home(&text) {
+            // A venv in this repository was already reported above
+            if references
+                .iter()
+                .any(|reference: &Reference| reference.line == line && reference.target == target)
+            {
+                continue;
+            }
+            references.push(Reference {
+                file: rel.strip_prefix(&scope).unwrap_or(&rel).to_path_buf(),
+                line,
+                target,
+                kind: Kind::Interpreter,
+            });
+        }
+    }
+    Ok(references)
+}
+
+/// Print the references after the venv report
+pub fn print_references(references: &[Reference], is_tty: bool) {
+    let headline = "Tracked files name Python interpreters that only exist on this machine:";
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+    println!();
+    for reference in references {
+        let location = format!(
+            "{}:{}",
+            reference.file.to_string_lossy().replace('\\', "/"),
+            reference.line
+        );
+        let kind = match reference.kind {
+            Kind::Shebang => "Shebang",
+            Kind::Path => "Path",
+            Kind::Interpreter => "Interpreter",
+            Kind::Activation => "Activation script",
+        };
+        let rule = reference.rule();
+        if is_tty {
+            println!("  📄 {}", location.cyan());
+            println!("     {kind}: {}", reference.target);
+            println!("     Rule: {}", rule.describe());
+            println!("     Docs: {}", rule.doc_url());
+        } else {
+            println!("  {location}");
+            println!("    {kind}: {}", reference.target);
+            println!("    Rule: {}", rule.describe());
+            println!("    Docs: {}", rule.doc_url());
+        }
+    }
+    println!();
+    println!("Every other clone breaks on these paths. Use `#!/usr/bin/env python3`, a path");
+    println!("relative to the project, or a setting each developer keeps out of Git.");
+    println!();
+}
+
+/// Markdown section on `references` for the job summary and PR comment
+pub fn markdown_section(references: &[Reference]) -> String {
+    let mut markdown = String::new();
+    if references.is_empty() {
+        return markdown;
+    }
+    let _ = writeln!(
+        markdown,
+        "\n### Machine-specific paths\n\n{} line(s) in tracked files name Python interpreters that only exist on this machine.\n",
+        references.len()
+    );
+    markdown.push_str("| File | Path | Rule |\n");
+    markdown.push_str("| --- | --- | --- |\n");
+    for reference in references {
+        let rule = reference.rule();
+        let _ = writeln!(
+            markdown,
+            "| `{}:{}` | `{}` | [{}]({}) |",
+            reference.file.to_string_lossy().replace('\\', "/"),
+            reference.line,
+            reference.target.replace('|', "\\|"),
+            rule.id,
+            rule.doc_url()
+        );
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_paths_below() {
+        let text = "run:\n\t\"/src/app/.venv/bin/python\" -m app, /src/app/x\n/src/other\n";
+        assert_eq!(
+            paths_below(text, &["/src/app/".to_string()]),
+            [(2, "/src/app/.venv/bin/python"), (2, "/src/app/x")]
+        );
+        let prefixes = ["/private/tmp/app/".to_string(), "/tmp/app/".to_string()];
+        assert_eq!(
+            paths_below("/private/tmp/app/.venv /tmp/app/.venv", &prefixes),
+            [(1, "/private/tmp/app/.venv"), (1, "/tmp/app/.venv")]
+        );
+    }
+
+    #[test]
+    fn test_interpreters_in_home() {
+        let settings = r#"{
+  "python.defaultInterpreterPath": "/Users/bob/.virtualenvs/app/bin/python3.12",
+  "python.analysis.extraPaths": ["/home/bob/lib"],
+  "python.pythonPath": "C:\\Users\\bob\\app\\.venv\\Scripts\\python.exe",
+  "python.condaPath": "/usr/local/bin/python3"
+}"#;
+        assert_eq!(
+            interpreters_in_home(settings),
+            [
+                (2, "/Users/bob/.virtualenvs/app/bin/python3.12".to_string()),
+                (
+                    4,
+                    r"C:\\Users\\bob\\app\\.venv\\Scripts\\python.exe".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            interpreters_in_home(r#"<homePath value="~/venvs/app/bin/pythonw" />"#),
+            [(1, "~/venvs/app/bin/pythonw".to_string())]
+        );
+        assert!(interpreters_in_home("/home/bob/bin/python-config").is_empty());
+    }
+
+    #[test]
+    fn test_activation_line() {
+        let script = "# This file must be used with \"source bin/activate\"\n\
+                      VIRTUAL_ENV='/home/bob/app/.venv'\nexport VIRTUAL_ENV\n";
+        assert_eq!(
+            activation_line(Path::new("tools/activate"), script),
+            Some((2, "VIRTUAL_ENV='/home/bob/app/.venv'".to_string()))
+        );
+        assert_eq!(
+            activation_line(Path::new("Activate.ps1"), "$env:VIRTUAL_ENV = $VenvDir"),
+            Some((1, "$env:VIRTUAL_ENV = $VenvDir".to_string()))
+        );
+        // Same name, but not a venv script
+        assert_eq!(
+            activation_line(Path::new("bin/activate"), "#!/bin/sh\nexec app"),
+            None
+        );
+        assert_eq!(activation_line(Path::new("activate.sh"), script), None);
+    }
+
+    #[test]
+    fn test_is_editor_config() {
+        for path in [
+            ".vscode/settings.json",
+            "app.code-workspace",
+            ".idea/misc.xml",
+            "sub/.idea/app.iml",
+            "pyrightconfig.json",
+        ] {
+            assert!(is_editor_config(Path::new(path)), "{path}");
+        }
+        for path in ["README.md", ".vscode/notes.txt", "idea/misc.xml"] {
+            assert!(!is_editor_config(Path::new(path)), "{path}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_references_into_venvs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().canonicalize()?;
+        git(&root, &["init", "-q"]);
+        fs::create_dir_all(root.join(".venv/bin"))?;
+        fs::write(root.join(".venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        let python = root.join(".venv/bin/python");
+        let python = python.to_string_lossy();
+        fs::create_dir(root.join("scripts"))?;
+        fs::write(
+            root.join("scripts/run.py"),
+            format!("#!{python}\nprint('hi')\n"),
+        )?;
+        fs::write(
+            root.join("Makefile"),
+            format!(
+                "test:\n\t{python} -m pytest\n\tcat {}/README\n",
+                root.display()
+            ),
+        )?;
+        fs::write(root.join("untracked.sh"), format!("#!{python}\n"))?;
+        fs::write(
+            root.join("scripts/activate.fish"),
+            format!("set -gx VIRTUAL_ENV {}/.venv\n", root.display()),
+        )?;
+        fs::create_dir(root.join(".vscode"))?;
+        fs::write(
+            root.join(".vscode/settings.json"),
+            format!(
+                "{{\n  \"python.defaultInterpreterPath\": \"{python}\",\n  \
+                 \"python.venvPath\": \"/home/bob/.venvs/app/bin/python\"\n}}\n"
+            ),
+        )?;
+        git(&root, &["add", "scripts", "Makefile", ".vscode"]);
+
+        let references = find(&root, None)?;
+        assert_eq!(
+            references,
+            [
+                Reference {
+                    file: PathBuf::from(".vscode/settings.json"),
+                    line: 2,
+                    target: python.to_string(),
+                    kind: Kind::Path,
+                },
+                Reference {
+                    file: PathBuf::from(".vscode/settings.json"),
+                    line: 3,
+                    target: "/home/bob/.venvs/app/bin/python".to_string(),
+                    kind: Kind::Interpreter,
+                },
+                Reference {
+                    file: PathBuf::from("Makefile"),
+                    line: 2,
+                    target: python.to_string(),
+                    kind: Kind::Path,
+                },
+                Reference {
+                    file: PathBuf::from("scripts/activate.fish"),
+                    line: 1,
+                    target: format!("set -gx VIRTUAL_ENV {}/.venv", root.display()),
+                    kind: Kind::Activation,
+                },
+                Reference {
+                    file: PathBuf::from("scripts/run.py"),
+                    line: 1,
+                    target: python.to_string(),
+                    kind: Kind::Shebang,
+                },
+            ]
+        );
+
+        // Scanning a subdirectory reports paths relative to it
+        let references = find(&root.join("scripts"), None)?;
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[1].file, PathBuf::from("run.py"));
+        Ok(())
+    }
+}
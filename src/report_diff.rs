@@ -0,0 +1,201 @@
+//! `unvenv report diff`: compare two JSON scan reports.
+//!
+//! Pipelines that archive the `report --json` output of every run can use
+//! this to ratchet: violations are matched by their path relative to the scan
+//! root, so the two reports may come from checkouts in different
+//! directories. Only violations missing from the old report count against
+//! the new one; resolved ones are listed for the record.
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::{collections::HashSet, fs, path::Path};
+use unvenv::{SCHEMA_VERSION, ScanReport, Violation};
+
+/// Violations that differ between two reports
+#[derive(Debug, Default)]
+pub struct ReportDiff<'a> {
+    /// In the new report but not the old one
+    pub added: Vec<&'a Violation>,
+    /// In the old report but not the new one
+    pub resolved: Vec<&'a Violation>,
+}
+
+/// Read a report written by `unvenv report --json`
+pub fn load(path: &Path) -> Result<ScanReport> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read report {}", path.display()))?;
+    let report: ScanReport = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not an unvenv JSON report", path.display()))?;
+    if report.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{} uses report schema {}, but this unvenv only understands up to {SCHEMA_VERSION}",
+            path.display(),
+            report.schema_version
+        );
+    }
+    Ok(report)
+}
+
+/// Violations added and resolved between `old` and `new`
+pub fn diff<'a>(old: &'a ScanReport, new: &'a ScanReport) -> ReportDiff<'a> {
+    let old_paths: HashSet<&Path> = old.violations.iter().map(|v| v.path.as_path()).collect();
+    let new_paths: HashSet<&Path> = new.violations.iter().map(|v| v.path.as_path()).collect();
+    ReportDiff {
+        added: new
+            .violations
+            .iter()
+            .filter(|v| !old_paths.contains(v.path.as_path()))
+            .collect(),
+        resolved: old
+            .violations
+            .iter()
+            .filter(|v| !new_paths.contains(v.path.as_path()))
+            .collect(),
+    }
+}
+
+/// Compare the reports at `old` and `new` and print the differences,
+/// returning the exit code
+pub fn run_diff(old: &Path, new: &Path, is_tty: bool) -> Result<i32> {
+    let old_report = load(old)?;
+    let new_report = load(new)?;
+    if new_report.partial {
+        eprintln!(
+            "{} {} is from a scan that stopped early; violations it did not reach show as resolved",
+            "Warning:".yellow().bold(),
+            new.display()
+        );
+    } else if old_report.partial {
+        eprintln!(
+            "{} {} is from a scan that stopped early; violations it did not reach show as new",
+            "Warning:".yellow().bold(),
+            old.display()
+        );
+    }
+
+    let changes = diff(&old_report, &new_report);
+    print_diff(&changes, &old.display().to_string(), is_tty);
+    Ok(if changes.added.is_empty() { 0 } else { 2 })
+}
+
+/// Print the added and resolved violations
+fn print_diff(changes: &ReportDiff<'_>, old: &str, is_tty: bool) {
+    if changes.added.is_empty() {
+        let message = format!("No new unignored Python virtual environments since {old}");
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+    } else {
+        let headline = format!(
+            "{} new unignored Python virtual environment(s) since {old}!",
+            changes.added.len()
+        );
+        if is_tty {
+            println!("{} {headline}", "WARNING:".yellow().bold());
+        } else {
+            println!("WARNING: {headline}");
+        }
+        println!();
+        for violation in &changes.added {
+            print_violation('+', violation, is_tty);
+        }
+    }
+
+    if !changes.resolved.is_empty() {
+        println!();
+        println!("Resolved ({}):", changes.resolved.len());
+        for violation in &changes.resolved {
+            print_violation('-', violation, is_tty);
+        }
+    }
+}
+
+/// Print one violation line with its owners
+fn print_violation(marker: char, violation: &Violation, is_tty: bool) {
+    let path = violation.path.to_string_lossy().replace('\\', "/");
+    let owners = if violation.owners.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", violation.owners.join(", "))
+    };
+    if is_tty {
+        let marker = if marker == '+' {
+            marker.to_string().red().bold()
+        } else {
+            marker.to_string().green().bold()
+        };
+        println!("  {marker} {}{}", path.cyan(), owners.dimmed());
+    } else {
+        println!("  {marker} {path}{owners}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use unvenv::VenvMetadata;
+
+    fn report(root: &str, paths: &[&str]) -> ScanReport {
+        let violations = paths
+            .iter()
+            .map(|path| Violation::new(PathBuf::from(path), VenvMetadata::default()))
+            .collect();
+        ScanReport::new("1.6.0", PathBuf::from(root), violations)
+    }
+
+    #[test]
+    fn test_diff_matches_by_relative_path() {
+        let old = report("/runner/a", &["venv/pyvenv.cfg", "tools/.venv/pyvenv.cfg"]);
+        let new = report("/runner/b", &["venv/pyvenv.cfg", "api/env/pyvenv.cfg"]);
+
+        let changes = diff(&old, &new);
+        let paths = |violations: &[&Violation]| -> Vec<PathBuf> {
+            violations.iter().map(|v| v.path.clone()).collect()
+        };
+        assert_eq!(paths(&changes.added), [PathBuf::from("api/env/pyvenv.cfg")]);
+        assert_eq!(
+            paths(&changes.resolved),
+            [PathBuf::from("tools/.venv/pyvenv.cfg")]
+        );
+    }
+
+    #[test]
+    fn test_run_diff_fails_only_on_new_violations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let write = |name: &str, report: &ScanReport| -> Result<PathBuf> {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, serde_json::to_string(report)?)?;
+            Ok(path)
+        };
+        let before = write(
+            "before.json",
+            &report(".", &["a/pyvenv.cfg", "b/pyvenv.cfg"]),
+        )?;
+        let fixed = write("fixed.json", &report(".", &["a/pyvenv.cfg"]))?;
+        let worse = write(
+            "worse.json",
+            &report(".", &["a/pyvenv.cfg", "c/pyvenv.cfg"]),
+        )?;
+
+        assert_eq!(run_diff(&before, &fixed, false)?, 0);
+        assert_eq!(run_diff(&before, &before, false)?, 0);
+        assert_eq!(run_diff(&fixed, &worse, false)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rejects_newer_schema() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("future.json");
+        fs::write(
+            &path,
+            r#"{"schema_version": 99, "tool_version": "9.0.0", "root": "."}"#,
+        )?;
+        assert!(load(&path).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! Backup of the previous binary for `update --rollback`.
+//!
+//! Before updating, the running binary is copied aside. If the update
+//! actually replaced it, the copy is kept as `unvenv.bak` next to the binary
+//! (`unvenv.exe.bak` on Windows); `unvenv update --rollback` moves it back,
+//! so a bad release can be backed out without a network round trip.
+
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Binary an update or rollback applies to
+pub fn target_binary(install_dir: Option<&Path>) -> Result<PathBuf> {
+    let current = env::current_exe().context("Failed to locate the running binary")?;
+    Ok(match install_dir {
+        Some(dir) => dir.join(current.file_name().unwrap_or_else(|| "unvenv".as_ref())),
+        None => current,
+    })
+}
+
+/// Where the previous version of `binary` is kept
+pub fn backup_path(binary: &Path) -> PathBuf {
+    with_suffix(binary, ".bak")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Copy `binary` aside before an update, returning the staged copy
+///
+/// Returns `None` when there is nothing to back up (e.g. a first install
+/// into `--install-dir`).
+pub fn stage_backup(binary: &Path) -> Result<Option<PathBuf>> {
+    if !binary.is_file() {
+        return Ok(None);
+    }
+    let staged = with_suffix(binary, ".bak.new");
+    fs::copy(binary, &staged).with_context(|| format!("Failed to back up {}", binary.display()))?;
+    Ok(Some(staged))
+}
+
+/// Keep the staged copy as the backup if the update replaced `binary`
+///
+/// An update that found nothing newer leaves the binary untouched; keeping
+/// the copy then would overwrite the real previous version with the current
+/// one, so it is discarded instead.
+pub fn commit_backup(binary: &Path, staged: &Path) -> Result<bool> {
+    let replaced = fs::read(binary).ok() != fs::read(staged).ok();
+    if replaced {
+        fs::rename(staged, backup_path(binary))
+            .with_context(|| format!("Failed to keep backup of {}", binary.display()))?;
+    } else {
+        fs::remove_file(staged)
+            .with_context(|| format!("Failed to remove {}", staged.display()))?;
+    }
+    Ok(replaced)
+}
+
+/// Restore the previous version of `binary` from its backup
+pub fn rollback(binary: &Path) -> Result<()> {
+    let backup = backup_path(binary);
+    if !backup.is_file() {
+        bail!(
+            "No previous version to roll back to ({} does not exist)",
+            backup.display()
+        );
+    }
+
+    // Windows cannot overwrite a running executable but can rename it
+    if cfg!(windows) && binary.exists() {
+        let old = with_suffix(binary, ".old");
+        let _ = fs::remove_file(&old);
+        fs::rename(binary, &old)
+            .with_context(|| format!("Failed to move {} aside", binary.display()))?;
+    }
+
+    fs::rename(&backup, binary).with_context(|| format!("Failed to restore {}", binary.display()))
+}
+
+/// Run `update --rollback` and print the outcome, returning the exit code
+pub fn run_rollback(install_dir: Option<&Path>, is_tty: bool) -> Result<i32> {
+    let binary = target_binary(install_dir)?;
+    rollback(&binary)?;
+
+    if is_tty {
+        println!(
+            "{} Restored the previous version of {}",
+            "OK:".green().bold(),
+            binary.display().to_string().cyan()
+        );
+    } else {
+        println!("OK: Restored the previous version of {}", binary.display());
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backup_kept_only_when_binary_replaced() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let binary = temp_dir.path().join("unvenv");
+        fs::write(&binary, "v1")?;
+
+        // No-op update: the staged copy is discarded
+        let staged = stage_backup(&binary)?.expect("binary exists");
+        assert!(!commit_backup(&binary, &staged)?);
+        assert!(!staged.exists());
+        assert!(!backup_path(&binary).exists());
+
+        // Real update: the old binary becomes the backup
+        let staged = stage_backup(&binary)?.expect("binary exists");
+        fs::write(&binary, "v2")?;
+        assert!(commit_backup(&binary, &staged)?);
+        assert_eq!(fs::read_to_string(backup_path(&binary))?, "v1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback_restores_backup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let binary = temp_dir.path().join("unvenv");
+        fs::write(&binary, "v2")?;
+        fs::write(backup_path(&binary), "v1")?;
+
+        rollback(&binary)?;
+
+        assert_eq!(fs::read_to_string(&binary)?, "v1");
+        assert!(!backup_path(&binary).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback_without_backup_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let binary = temp_dir.path().join("unvenv");
+
+        assert!(rollback(&binary).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Rule identifiers for the checks unvenv runs.
+//!
+//! Every finding belongs to one rule with a stable ID. The ID is shown next
+//! to each violation, and `UNVENV_SKIP=UV002,...` disables rules for one
+//! invocation, mirroring pre-commit's `SKIP`: an emergency commit can get
+//! past one specific check while the bypass stays visible in the output and
+//! the system log.
+
+use crate::{TrackingStatus, VenvInfo, logging};
+use colored::Colorize;
+use std::env;
+
+/// Where the explanation and remediation page of each rule lives
+const DOCS_URL: &str = "https://github.com/tftio/unvenv/blob/main/docs/rules";
+
+/// Environment variable listing the rules to skip
+pub const SKIP_VAR: &str = "UNVENV_SKIP";
+
+/// One check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Stable identifier, e.g. `UV001`
+    pub id: &'static str,
+    /// Short name
+    pub name: &'static str,
+}
+
+/// A venv Git does not ignore, outside the index
+pub const UNIGNORED_VENV: Rule = Rule {
+    id: "UV001",
+    name: "unignored-venv",
+};
+
+/// A venv staged for the next commit
+pub const STAGED_VENV: Rule = Rule {
+    id: "UV002",
+    name: "staged-venv",
+};
+
+/// A venv already in the committed history
+pub const COMMITTED_VENV: Rule = Rule {
+    id: "UV003",
+    name: "committed-venv",
+};
+
+/// A venv baked into a container image layer
+pub const IMAGE_VENV: Rule = Rule {
+    id: "UV004",
+    name: "image-venv",
+};
+
+/// A package manager cache baked into a container image layer
+pub const IMAGE_CACHE: Rule = Rule {
+    id: "UV005",
+    name: "image-cache",
+};
+
+/// A tracked file naming a path inside a repo-local venv
+pub const VENV_REFERENCE: Rule = Rule {
+    id: "UV006",
+    name: "venv-reference",
+};
+
+/// An editor setting naming an interpreter in someone's home directory
+pub const INTERPRETER_PATH: Rule = Rule {
+    id: "UV007",
+    name: "interpreter-path",
+};
+
+/// A venv activation script committed outside a venv
+pub const ACTIVATION_SCRIPT: Rule = Rule {
+    id: "UV008",
+    name: "activation-script",
+};
+
+/// A `.direnv/` directory Git does not ignore
+pub const UNIGNORED_DIRENV: Rule = Rule {
+    id: "UV009",
+    name: "unignored-direnv",
+};
+
+/// A pyenv root with installed Python versions inside the repository
+pub const PYENV_INSTALL: Rule = Rule {
+    id: "UV010",
+    name: "pyenv-install",
+};
+
+/// pip build metadata or a pip download cache Git does not ignore
+pub const PIP_CACHE: Rule = Rule {
+    id: "UV011",
+    name: "pip-cache",
+};
+
+/// Every rule, by ID
+pub const ALL: [Rule; 11] = [
+    UNIGNORED_VENV,
+    STAGED_VENV,
+    COMMITTED_VENV,
+    IMAGE_VENV,
+    IMAGE_CACHE,
+    VENV_REFERENCE,
+    INTERPRETER_PATH,
+    ACTIVATION_SCRIPT,
+    UNIGNORED_DIRENV,
+    PYENV_INSTALL,
+    PIP_CACHE,
+];
+
+impl Rule {
+    /// The rule with this ID (any case) or name
+    pub fn find(entry: &str) -> Option<Self> {
+        ALL.into_iter()
+            .find(|rule| rule.id.eq_ignore_ascii_case(entry) || rule.name == entry)
+    }
+
+    /// The rule a venv violates
+    pub const fn for_venv(venv: &VenvInfo) -> Self {
+        match venv.tracking {
+            None | Some(TrackingStatus::Untracked) => UNIGNORED_VENV,
+            Some(TrackingStatus::Staged) => STAGED_VENV,
+            Some(TrackingStatus::Committed) => COMMITTED_VENV,
+        }
+    }
+
+    /// `UV001 (unignored-venv)`
+    pub fn describe(self) -> String {
+        format!("{} ({})", self.id, self.name)
+    }
+
+    /// Stable link to the rule's page in `docs/rules/`
+    pub fn doc_url(self) -> String {
+        format!("{DOCS_URL}/{}.md", self.id)
+    }
+}
+
+/// Rules disabled for this invocation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Skips(Vec<Rule>);
+
+impl Skips {
+    /// Parse a comma-separated list of rule IDs or names, returning the
+    /// entries that name no rule alongside
+    pub fn parse(value: &str) -> (Self, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut unknown = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match Rule::find(entry) {
+                Some(rule) if !rules.contains(&rule) => rules.push(rule),
+                Some(_) => {}
+                None => unknown.push(entry.to_string()),
+            }
+        }
+        (Self(rules), unknown)
+    }
+
+    /// The rules listed in `UNVENV_SKIP`, warning about unknown entries
+    pub fn from_env() -> Self {
+        let Ok(value) = env::var(SKIP_VAR) else {
+            return Self::default();
+        };
+        let (skips, unknown) = Self::parse(&value);
+        for entry in unknown {
+            eprintln!(
+                "{} {SKIP_VAR} names unknown rule {entry:?}",
+                "Warning:".yellow().bold()
+            );
+        }
+        skips
+    }
+
+    /// Whether `rule` is skipped
+    pub fn contains(&self, rule: Rule) -> bool {
+        self.0.contains(&rule)
+    }
+
+    /// Drop the venvs whose rule is skipped, returning how many were dropped
+    pub fn filter(&self, venvs: &mut Vec<VenvInfo>) -> usize {
+        let before = venvs.len();
+        venvs.retain(|venv| !self.contains(Rule::for_venv(venv)));
+        before - venvs.len()
+    }
+
+    /// Say which rules were skipped, on stderr and in the system log, so the
+    /// bypass shows up in CI logs
+    pub fn announce(&self, suppressed: usize) {
+        if self.0.is_empty() {
+            return;
+        }
+        let rules: Vec<String> = self.0.iter().copied().map(Rule::describe).collect();
+        let message = format!(
+            "Skipping {} via {SKIP_VAR}; {suppressed} finding(s) suppressed",
+            rules.join(", ")
+        );
+        eprintln!("{} {message}", "Warning:".yellow().bold());
+        logging::log(logging::Severity::Warning, &message, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    fn venv(path: &str, tracking: Option<TrackingStatus>) -> VenvInfo {
+        VenvInfo {
+            path: PathBuf::from(path),
+            home: None,
+            version: None,
+            include_system_site_packages: None,
+            settings: BTreeMap::new(),
+            tracking,
+            owners: Vec::new(),
+            direnv: None,
+        }
+    }
+
+    #[test]
+    fn test_doc_urls_have_pages() {
+        for rule in ALL {
+            let url = rule.doc_url();
+            let page = url.rsplit('/').next().expect("URL has a file name");
+            assert!(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("docs/rules")
+                    .join(page)
+                    .is_file(),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_skips() {
+        let (skips, unknown) = Skips::parse("UV002, uv003,committed-venv,,UV999");
+        assert_eq!(skips, Skips(vec![STAGED_VENV, COMMITTED_VENV]));
+        assert_eq!(unknown, ["UV999"]);
+    }
+
+    #[test]
+    fn test_filter_by_rule() {
+        let mut venvs = vec![
+            venv("a/pyvenv.cfg", None),
+            venv("b/pyvenv.cfg", Some(TrackingStatus::Untracked)),
+            venv("c/pyvenv.cfg", Some(TrackingStatus::Staged)),
+        ];
+        let (skips, _) = Skips::parse("UV002");
+
+        assert_eq!(skips.filter(&mut venvs), 1);
+        let paths: Vec<_> = venvs.iter().map(|venv| venv.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [PathBuf::from("a/pyvenv.cfg"), PathBuf::from("b/pyvenv.cfg")]
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Scanner for embedding unvenv in other tools.
+//!
+//! [`Scanner`] is a simpler walk than `unvenv scan`. It recognizes venvs and
+//! nested repositories the same way, but has none of the CLI's options:
+//! no case-variant matching, caching, limits, symlink following, or
+//! incremental plans. Ignored directories are pruned, venv contents are
+//! skipped, and every `pyvenv.cfg` Git does not ignore becomes a
+//! [`Violation`]. [`Scanner::scan_with`] hands each step to
+//! a [`Visitor`] as it happens, so hosts can drive their own progress UI and
+//! stop the scan early. A [`CancellationToken`] stops it from another thread.
+
+use crate::{
+    codeowners::CodeOwners,
+    detect::{find_venv_cfg, is_nested_repository},
+    error::{Error, Result},
+    ignore::IgnoreMatcher,
+    model::{ScanReport, Tracking, VenvMetadata, Violation},
+};
+use git2::{Repository, Status};
+use std::{
+    fs,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use walkdir::WalkDir;
+
+/// Library version, recorded in reports
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Something the scan came across
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ScanEvent<'a> {
+    /// A `pyvenv.cfg` about to be checked against the ignore rules
+    Candidate(&'a Path),
+    /// A `pyvenv.cfg` Git does not ignore
+    Violation(&'a Violation),
+    /// A path the scan did not look inside
+    Skipped {
+        /// The skipped path
+        path: &'a Path,
+        /// Why it was skipped
+        reason: SkipReason,
+    },
+    /// The walk entered another directory
+    Progress(Progress),
+}
+
+/// How far the walk has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// Directories entered so far
+    pub dirs: u64,
+    /// Files seen so far; files inside venvs and ignored directories are
+    /// never visited
+    pub files: u64,
+}
+
+/// Cooperative cancellation for a running scan
+///
+/// Clones share the same flag: hand one to [`Scanner::with_cancellation`]
+/// and call [`cancel`](Self::cancel) on another, from any thread. The scan
+/// checks the flag before each entry and stops with
+/// [`Error::PartialScan`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that has not been cancelled
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask scans using this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a path was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// Git ignores the directory, so nothing inside it can be a violation
+    Ignored,
+    /// The path could not be read, with the error message
+    Unreadable(String),
+    /// A submodule or nested repository; it has its own index and ignore
+    /// rules and is scanned on its own
+    NestedRepository,
+}
+
+/// Receiver of scan events
+///
+/// Closures taking a [`ScanEvent`] and returning a [`ControlFlow`] are
+/// visitors too.
+pub trait Visitor {
+    /// Handle one event; returning [`ControlFlow::Break`] stops the scan
+    fn visit(&mut self, event: ScanEvent<'_>) -> ControlFlow<()>;
+}
+
+impl<F> Visitor for F
+where
+    F: FnMut(ScanEvent<'_>) -> ControlFlow<()>,
+{
+    fn visit(&mut self, event: ScanEvent<'_>) -> ControlFlow<()> {
+        self(event)
+    }
+}
+
+/// Scanner for one directory tree
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    root: PathBuf,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Scanner {
+    /// Scanner for the tree at `root`, checked against the repository
+    /// containing it (if any)
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            cancellation: None,
+        }
+    }
+
+    /// Stop the scan once `token` is cancelled
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Scan the whole tree
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Config`] if the root is not a directory, [`Error::Git`] if Git
+    /// cannot answer an ignore or status query, and [`Error::Io`] if a
+    /// `pyvenv.cfg` cannot be read.
+    pub fn scan(&self) -> Result<ScanReport> {
+        self.scan_with(&mut |_: ScanEvent<'_>| ControlFlow::Continue(()))
+    }
+
+    /// Scan the tree, reporting each event to `visitor`
+    ///
+    /// Unreadable paths are reported as skipped rather than failing the scan.
+    ///
+    /// # Errors
+    ///
+    /// As for [`scan`](Self::scan), plus [`Error::PartialScan`] with the
+    /// violations found so far when the visitor breaks or the scan is
+    /// cancelled.
+    pub fn scan_with(&self, visitor: &mut impl Visitor) -> Result<ScanReport> {
+        if !self.root.is_dir() {
+            return Err(Error::Config(format!(
+                "{} is not a directory",
+                self.root.display()
+            )));
+        }
+
+        let mut matcher = IgnoreMatcher::discover(&self.root);
+        let codeowners = matcher
+            .as_ref()
+            .and_then(|matcher| CodeOwners::load(matcher.root()));
+        let mut report = ScanReport::new(VERSION, self.root.clone(), Vec::new());
+        let mut progress = Progress::default();
+
+        let mut walker = WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git");
+        while let Some(entry) = walker.next() {
+            if self
+                .cancellation
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+            {
+                return Err(partial(report));
+            }
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(&self.root).to_path_buf();
+                    let reason = e
+                        .io_error()
+                        .map_or_else(|| e.to_string(), ToString::to_string);
+                    let event = ScanEvent::Skipped {
+                        path: &path,
+                        reason: SkipReason::Unreadable(reason),
+                    };
+                    if visitor.visit(event).is_break() {
+                        return Err(partial(report));
+                    }
+                    continue;
+                }
+            };
+            if !entry.file_type().is_dir() {
+                progress.files += 1;
+                continue;
+            }
+            progress.dirs += 1;
+            if visitor.visit(ScanEvent::Progress(progress)).is_break() {
+                return Err(partial(report));
+            }
+
+            let dir = entry.path();
+            if matcher.is_some() && entry.depth() > 0 && is_nested_repository(dir) {
+                walker.skip_current_dir();
+                let event = ScanEvent::Skipped {
+                    path: dir,
+                    reason: SkipReason::NestedRepository,
+                };
+                if visitor.visit(event).is_break() {
+                    return Err(partial(report));
+                }
+                continue;
+            }
+            if let Some(matcher) = matcher.as_mut() {
+                let ignored = matcher
+                    .is_dir_ignored(dir)
+                    .map_err(|e| Error::git(dir.to_path_buf(), e))?;
+                if ignored {
+                    walker.skip_current_dir();
+                    let event = ScanEvent::Skipped {
+                        path: dir,
+                        reason: SkipReason::Ignored,
+                    };
+                    if visitor.visit(event).is_break() {
+                        return Err(partial(report));
+                    }
+                    continue;
+                }
+            }
+
+            let Some(cfg_path) = find_venv_cfg(dir, false) else {
+                continue;
+            };
+            // Nothing below a venv root can change the verdict
+            walker.skip_current_dir();
+            if visitor.visit(ScanEvent::Candidate(&cfg_path)).is_break() {
+                return Err(partial(report));
+            }
+
+            let violation = self.check_venv(matcher.as_mut(), codeowners.as_ref(), &cfg_path)?;
+            if let Some(violation) = violation {
+                let flow = visitor.visit(ScanEvent::Violation(&violation));
+                report.violations.push(violation);
+                if flow.is_break() {
+                    return Err(partial(report));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// The violation for `cfg_path`, or `None` if Git ignores it
+    fn check_venv(
+        &self,
+        matcher: Option<&mut IgnoreMatcher>,
+        codeowners: Option<&CodeOwners>,
+        cfg_path: &Path,
+    ) -> Result<Option<Violation>> {
+        let rel_path = cfg_path.strip_prefix(&self.root).unwrap_or(cfg_path);
+
+        // Outside a repository nothing is ignored and nothing is tracked
+        let mut state = None;
+        let mut repo_path = None;
+        let mut owners = Vec::new();
+        if let Some(matcher) = matcher {
+            let ignored = matcher
+                .is_file_ignored(cfg_path)
+                .map_err(|e| Error::git(rel_path.to_path_buf(), e))?;
+            if ignored {
+                return Ok(None);
+            }
+            let git_path = matcher
+                .git_path(cfg_path)
+                .unwrap_or_else(|| rel_path.to_path_buf());
+            state = Some(tracking(matcher.repo(), &git_path)?);
+            if let Some(found) = codeowners.and_then(|codeowners| codeowners.owners(&git_path)) {
+                owners = found.to_vec();
+            }
+            repo_path = Some(git_path);
+        }
+
+        let content = fs::read_to_string(cfg_path).map_err(|source| Error::Io {
+            path: cfg_path.to_path_buf(),
+            source,
+        })?;
+        let mut violation =
+            Violation::new(rel_path.to_path_buf(), VenvMetadata::from_cfg(&content));
+        violation.tracking = state;
+        violation.owners = owners;
+        violation.repo_path = repo_path;
+        violation.add_default_actions();
+        Ok(Some(violation))
+    }
+}
+
+/// Error for a scan the visitor stopped
+fn partial(mut report: ScanReport) -> Error {
+    report.partial = true;
+    Error::PartialScan(Box::new(report))
+}
+
+/// Determine how far a `pyvenv.cfg` has made it into Git
+fn tracking(repo: &Repository, git_path: &Path) -> Result<Tracking> {
+    let status = repo.status_file(git_path).map_err(|source| Error::Git {
+        path: git_path.to_path_buf(),
+        source,
+    })?;
+
+    Ok(if status.contains(Status::WT_NEW) {
+        Tracking::Untracked
+    } else if status.contains(Status::INDEX_NEW) {
+        Tracking::Staged
+    } else {
+        Tracking::Committed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn repo_with_venvs() -> Result<TempDir> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join(".gitignore"), "ignored/\n")?;
+        for dir in ["a/venv", "b/venv", "ignored/venv"] {
+            fs::create_dir_all(root.join(dir).join("lib"))?;
+            fs::write(
+                root.join(dir).join("pyvenv.cfg"),
+                "home = /usr/bin\nversion = 3.12.1\n",
+            )?;
+        }
+        git(root, &["add", "a/venv/pyvenv.cfg"]);
+        Ok(temp_dir)
+    }
+
+    #[test]
+    fn test_scan_with_reports_events() -> Result<()> {
+        let temp_dir = repo_with_venvs()?;
+        let mut candidates = 0;
+        let mut skipped = Vec::new();
+        let mut violations = Vec::new();
+
+        let report = Scanner::new(temp_dir.path()).scan_with(&mut |event: ScanEvent<'_>| {
+            match event {
+                ScanEvent::Candidate(_) => candidates += 1,
+                ScanEvent::Violation(violation) => violations.push(violation.path.clone()),
+                ScanEvent::Skipped { path, reason } => {
+                    skipped.push((path.file_name().map(ToOwned::to_owned), reason));
+                }
+                ScanEvent::Progress(_) => {}
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        assert_eq!(candidates, 2);
+        assert_eq!(skipped, vec![(Some("ignored".into()), SkipReason::Ignored)]);
+        violations.sort();
+        assert_eq!(
+            violations,
+            [
+                PathBuf::from("a/venv/pyvenv.cfg"),
+                PathBuf::from("b/venv/pyvenv.cfg")
+            ]
+        );
+        assert!(!report.partial);
+        let tracking = |path: &str| {
+            report
+                .violations
+                .iter()
+                .find(|violation| violation.path == Path::new(path))
+                .and_then(|violation| violation.tracking)
+        };
+        assert_eq!(tracking("a/venv/pyvenv.cfg"), Some(Tracking::Staged));
+        assert_eq!(tracking("b/venv/pyvenv.cfg"), Some(Tracking::Untracked));
+        assert_eq!(report.violations[0].venv.version.as_deref(), Some("3.12.1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_with_stops_on_break() -> Result<()> {
+        let temp_dir = repo_with_venvs()?;
+
+        let result = Scanner::new(temp_dir.path()).scan_with(&mut |event: ScanEvent<'_>| {
+            if matches!(event, ScanEvent::Violation(_)) {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        let Err(Error::PartialScan(report)) = result else {
+            panic!("expected a partial scan, got {result:?}");
+        };
+        assert!(report.partial);
+        assert_eq!(report.violations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_outside_repository() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir(temp_dir.path().join("venv"))?;
+        fs::write(temp_dir.path().join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let report = Scanner::new(temp_dir.path()).scan()?;
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].tracking, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_progress_counts_dirs_and_files() -> Result<()> {
+        let temp_dir = repo_with_venvs()?;
+        let mut last = Progress::default();
+
+        Scanner::new(temp_dir.path()).scan_with(&mut |event: ScanEvent<'_>| {
+            if let ScanEvent::Progress(progress) = event {
+                assert!(progress.dirs > last.dirs);
+                last = progress;
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        // root, a, a/venv, b, b/venv, ignored; venv contents are not entered
+        assert_eq!(last.dirs, 6);
+        // Only .gitignore: nothing inside a venv or ignored directory is visited
+        assert_eq!(last.files, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancellation_stops_the_scan() -> Result<()> {
+        let temp_dir = repo_with_venvs()?;
+        let token = CancellationToken::new();
+        let scanner = Scanner::new(temp_dir.path()).with_cancellation(token.clone());
+
+        let result = scanner.scan_with(&mut |event: ScanEvent<'_>| {
+            if matches!(event, ScanEvent::Candidate(_)) {
+                token.cancel();
+            }
+            ControlFlow::Continue(())
+        });
+
+        let Err(Error::PartialScan(report)) = result else {
+            panic!("expected a partial scan, got {result:?}");
+        };
+        // The venv being checked when the token fired is still reported
+        assert_eq!(report.violations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_missing_root_is_a_config_error() {
+        let result = Scanner::new("/nonexistent/unvenv-root").scan();
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,34 @@
+//! Temporary working directories for commands that unpack or build files.
+
+use anyhow::{Context, Result};
+use std::{env, path::Path};
+use tempfile::TempDir;
+
+/// Directory under the system temp dir, removed with its contents on drop
+///
+/// The name ends in random characters and the directory is created
+/// exclusively, so another user cannot plant it or a link in its place
+/// beforehand.
+#[derive(Debug)]
+pub struct ScratchDir(TempDir);
+
+impl ScratchDir {
+    /// Create an empty, private `unvenv-<purpose>-<random>` directory
+    pub fn new(purpose: &str) -> Result<Self> {
+        tempfile::Builder::new()
+            .prefix(&format!("unvenv-{purpose}-"))
+            .tempdir()
+            .map(Self)
+            .with_context(|| {
+                format!(
+                    "Failed to create a directory in {}",
+                    env::temp_dir().display()
+                )
+            })
+    }
+
+    /// The directory
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
@@ -0,0 +1,177 @@
+//! `unvenv self-test`: end-to-end check of an installed binary.
+//!
+//! Packagers and IT need to know that a build (particularly a cross-compiled
+//! one) actually works on the machine it landed on. The self-test builds a
+//! throwaway repository with known venvs in every state (ignored, untracked,
+//! staged, committed), runs the real scanner over it in each mode, and
+//! compares the findings with what they must be. Only libgit2 is used, so the
+//! `git` CLI does not have to be installed.
+
+use crate::{
+    ScanArgs, TrackingStatus, VenvInfo, config::Outcome, find_unignored_venvs, scratch::ScratchDir,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::{IndexAddOption, Repository, Signature};
+use std::{fs, path::Path};
+
+/// `pyvenv.cfg` written for every fixture venv
+const PYVENV_CFG: &str =
+    "home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.12.1\n";
+
+/// Build the fixture repository in `root`
+fn create_fixtures(root: &Path) -> Result<()> {
+    let repo = Repository::init(root).context("Failed to create the fixture repository")?;
+    fs::write(root.join(".gitignore"), ".venv/\n")?;
+    for venv in [".venv", "committed", "staged", "local"] {
+        fs::create_dir_all(root.join(venv).join("bin"))?;
+        fs::write(root.join(venv).join("pyvenv.cfg"), PYVENV_CFG)?;
+    }
+
+    let signature = Signature::now("unvenv self-test", "self-test@unvenv.invalid")?;
+    let mut index = repo.index()?;
+    index.add_all([".gitignore", "committed"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    repo.commit(Some("HEAD"), &signature, &signature, "fixtures", &tree, &[])?;
+
+    index.add_all(["staged"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    Ok(())
+}
+
+/// Reported venv directories and their tracking status, sorted
+fn findings(venvs: &[VenvInfo]) -> Vec<(String, Option<TrackingStatus>)> {
+    let mut findings: Vec<_> = venvs
+        .iter()
+        .map(|venv| {
+            let dir = venv.path.parent().unwrap_or(&venv.path);
+            (dir.to_string_lossy().replace('\\', "/"), venv.tracking)
+        })
+        .collect();
+    findings.sort_by(|a, b| a.0.cmp(&b.0));
+    findings
+}
+
+/// Compare a scan of `root` with `args` against the expected findings
+fn check_scan(
+    root: &Path,
+    args: &ScanArgs,
+    expected: &[(&str, Option<TrackingStatus>)],
+) -> Result<(), String> {
+    let outcome = find_unignored_venvs(root, args, false).map_err(|e| format!("{e:#}"))?;
+    let found = findings(&outcome.venvs);
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|(dir, tracking)| ((*dir).to_string(), *tracking))
+        .collect();
+    if found != expected {
+        return Err(format!("expected {expected:?}, found {found:?}"));
+    }
+    if let Some(venv) = outcome
+        .venvs
+        .iter()
+        .find(|venv| venv.version.as_deref() != Some("3.12.1"))
+    {
+        return Err(format!(
+            "{} parsed as version {:?}",
+            venv.path.display(),
+            venv.version
+        ));
+    }
+    Ok(())
+}
+
+/// Every check, with its outcome
+fn checks(root: &Path) -> Vec<(&'static str, Result<(), String>)> {
+    use TrackingStatus::{Committed, Staged, Untracked};
+
+    vec![
+        (
+            "Scan reports unignored venvs with their Git status",
+            check_scan(
+                root,
+                &ScanArgs::default(),
+                &[
+                    ("committed", Some(Committed)),
+                    ("local", Some(Untracked)),
+                    ("staged", Some(Staged)),
+                ],
+            ),
+        ),
+        (
+            "Staged scan checks only the index",
+            check_scan(
+                root,
+                &ScanArgs {
+                    staged: true,
+                    ..ScanArgs::default()
+                },
+                &[("staged", Some(Staged))],
+            ),
+        ),
+        (
+            "Scan without Git reports every venv",
+            check_scan(
+                root,
+                &ScanArgs {
+                    no_git: true,
+                    ..ScanArgs::default()
+                },
+                &[
+                    (".venv", None),
+                    ("committed", None),
+                    ("local", None),
+                    ("staged", None),
+                ],
+            ),
+        ),
+    ]
+}
+
+/// Run the self-test, returning 0 if every check passes
+pub fn run(is_tty: bool) -> Result<i32> {
+    let scratch = ScratchDir::new("self-test")?;
+    create_fixtures(scratch.path())?;
+
+    let results = checks(scratch.path());
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    for (name, result) in &results {
+        match (result, is_tty) {
+            (Ok(()), true) => println!("{} {name}", "PASS".green().bold()),
+            (Ok(()), false) => println!("PASS {name}"),
+            (Err(reason), true) => println!("{} {name}: {reason}", "FAIL".red().bold()),
+            (Err(reason), false) => println!("FAIL {name}: {reason}"),
+        }
+    }
+
+    println!();
+    if failed == 0 {
+        println!(
+            "Self-test passed: {} checks ({} {})",
+            results.len(),
+            crate::VERSION,
+            env!("UNVENV_BUILD_TARGET")
+        );
+        Ok(Outcome::Clean.default_code())
+    } else {
+        println!("Self-test failed: {failed} of {} checks", results.len());
+        Ok(Outcome::InternalError.default_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_checks_pass() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        create_fixtures(temp_dir.path())?;
+
+        for (name, result) in checks(temp_dir.path()) {
+            assert_eq!(result, Ok(()), "{name}");
+        }
+        Ok(())
+    }
+}
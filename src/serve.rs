@@ -0,0 +1,191 @@
+//! `unvenv serve`: scan results and quick fixes for editor extensions.
+//!
+//! The server reads one JSON request per line on stdin and writes exactly one
+//! JSON response line per request on stdout, echoing the request's `id`:
+//!
+//! - `{"id": 1, "method": "scan"}` scans the directory the server was started
+//!   in and returns the report (the `report --json` layout) as `result`
+//! - `{"id": 2, "method": "applyFix", "params": {"path": "venv/pyvenv.cfg",
+//!   "action": "add_gitignore_entry"}}` performs the named entry of that
+//!   violation's `actions` and returns the report of a fresh scan, so a code
+//!   action can update its diagnostics from the reply
+//!
+//! A request that fails gets `{"id": ..., "error": {"message": ...}}` and the
+//! server keeps running; it exits when stdin is closed.
+
+use crate::{
+    ScanArgs, find_unignored_venvs, findings, fix_script, packages::Packages, rules, scan_report,
+};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+use unvenv::{Action, ScanReport};
+
+/// One line of input
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Echoed in the response; any JSON value
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Option<FixParams>,
+}
+
+/// Parameters of `applyFix`
+#[derive(Debug, Deserialize)]
+struct FixParams {
+    /// `path` of the violation in the report
+    path: PathBuf,
+    /// `action` of the entry in the violation's `actions`
+    action: String,
+}
+
+/// The name an action has in the report
+const fn action_name(action: &Action) -> &'static str {
+    match action {
+        Action::AddGitignoreEntry { .. } => "add_gitignore_entry",
+        Action::GitRmCached { .. } => "git_rm_cached",
+        Action::DeleteDir { .. } => "delete_dir",
+        _ => "unknown",
+    }
+}
+
+/// Scan `workdir` the way `report --json` does
+fn scan(workdir: &Path) -> Result<ScanReport> {
+    let args = ScanArgs::default();
+    let mut outcome = find_unignored_venvs(workdir, &args, false)?;
+    let skips = rules::Skips::from_env();
+    let findings = findings::apply(
+        workdir,
+        &args,
+        &skips,
+        &mut Packages::new(workdir),
+        &mut outcome,
+    )?;
+    skips.announce(findings.skipped);
+    Ok(scan_report(workdir, &outcome, &findings.references))
+}
+
+/// Perform `params` against the current state of `workdir`
+fn apply_fix(workdir: &Path, params: &FixParams) -> Result<ScanReport> {
+    let report = scan(workdir)?;
+    let Some(violation) = report
+        .violations
+        .iter()
+        .find(|violation| violation.path == params.path)
+    else {
+        bail!("{} is not a violation", params.path.display());
+    };
+    let Some(action) = violation
+        .actions
+        .iter()
+        .find(|action| action_name(action) == params.action)
+    else {
+        bail!("{} has no {} action", params.path.display(), params.action);
+    };
+
+    if let Action::DeleteDir { path, .. } = action {
+        let dir = workdir.join(path);
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))?;
+    } else {
+        fix_script::apply_actions(workdir, [action.clone()])?;
+    }
+    scan(workdir)
+}
+
+/// Run the method `request` names
+fn dispatch(workdir: &Path, request: &Request) -> Result<ScanReport> {
+    match (request.method.as_str(), &request.params) {
+        ("scan", _) => scan(workdir),
+        ("applyFix", Some(params)) => apply_fix(workdir, params),
+        ("applyFix", None) => bail!("applyFix needs `path` and `action` params"),
+        (method, _) => bail!("Unknown method {method}"),
+    }
+}
+
+/// The response to one request line
+fn handle(workdir: &Path, line: &str) -> Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return json!({ "id": null, "error": { "message": format!("Invalid request: {e}") } });
+        }
+    };
+
+    let result = dispatch(workdir, &request)
+        .and_then(|report| serde_json::to_value(report).context("Failed to encode the report"));
+    match result {
+        Ok(report) => json!({ "id": request.id, "result": report }),
+        Err(e) => json!({ "id": request.id, "error": { "message": format!("{e:#}") } }),
+    }
+}
+
+/// Answer requests from stdin until it is closed
+pub fn run(workdir: &Path) -> Result<i32> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.context("Failed to read a request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle(workdir, &line);
+        writeln!(stdout, "{response}").context("Failed to write a response")?;
+        stdout.flush().context("Failed to write a response")?;
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_apply_fix_updates_the_scan() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::create_dir(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let response = handle(dir, r#"{"id": 1, "method": "scan"}"#);
+        assert_eq!(response["id"], 1);
+        assert_eq!(
+            response["result"]["violations"][0]["path"],
+            "venv/pyvenv.cfg"
+        );
+
+        let response = handle(
+            dir,
+            r#"{"id": "fix", "method": "applyFix", "params": {"path": "venv/pyvenv.cfg", "action": "add_gitignore_entry"}}"#,
+        );
+        assert_eq!(response["id"], "fix");
+        assert_eq!(response["result"]["violations"], json!([]));
+        assert_eq!(fs::read_to_string(dir.join(".gitignore"))?, "/venv/\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_keep_the_request_id() {
+        let temp_dir = TempDir::new().expect("temp dir");
+        let response = handle(
+            temp_dir.path(),
+            r#"{"id": 7, "method": "applyFix", "params": {"path": "venv/pyvenv.cfg", "action": "delete_dir"}}"#,
+        );
+        assert_eq!(response["id"], 7);
+        assert_eq!(
+            response["error"]["message"],
+            "venv/pyvenv.cfg is not a violation"
+        );
+
+        let response = handle(temp_dir.path(), "not json");
+        assert!(response["error"]["message"].is_string());
+    }
+}
@@ -0,0 +1,145 @@
+//! Signed JSON reports.
+//!
+//! Reports are signed and verified with the `minisign` CLI (Ed25519), so the
+//! binary carries no crypto code and the keys are ordinary minisign keys
+//! that compliance teams can check with minisign alone. The signature sits
+//! next to the report as `<report>.minisig`, and its trusted comment records
+//! the unvenv version and scan root, so both are covered by the signature
+//! too.
+
+use crate::config::Outcome;
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+/// Default location of the signature for `report`
+pub fn signature_path(report: &Path) -> PathBuf {
+    let mut path = OsString::from(report.as_os_str());
+    path.push(".minisig");
+    PathBuf::from(path)
+}
+
+/// Run minisign, returning its output
+///
+/// Stdin is inherited so minisign can prompt for the password of an
+/// encrypted secret key.
+fn minisign(args: &[&OsStr]) -> Result<Output> {
+    Command::new("minisign")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .context("Failed to run minisign; it is needed to sign and verify reports")
+}
+
+/// Sign `report` with `secret_key`, returning the signature path
+pub fn sign(report: &Path, secret_key: &Path, trusted_comment: &str) -> Result<PathBuf> {
+    let signature = signature_path(report);
+    let output = minisign(&[
+        "-S".as_ref(),
+        "-s".as_ref(),
+        secret_key.as_os_str(),
+        "-m".as_ref(),
+        report.as_os_str(),
+        "-x".as_ref(),
+        signature.as_os_str(),
+        "-t".as_ref(),
+        trusted_comment.as_ref(),
+    ])?;
+    if !output.status.success() {
+        bail!(
+            "Failed to sign {}: {}",
+            report.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(signature)
+}
+
+/// The trusted comment minisign printed for a verified signature
+fn trusted_comment(stdout: &str) -> Option<&str> {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Trusted comment: "))
+        .map(str::trim)
+}
+
+/// Check the signature of `report` and print the verdict, returning the
+/// exit code: 0 if it is valid, 2 if it is not
+pub fn run_verify(
+    report: &Path,
+    public_key: &Path,
+    signature: Option<&Path>,
+    is_tty: bool,
+) -> Result<i32> {
+    let signature = signature.map_or_else(|| signature_path(report), Path::to_path_buf);
+    for file in [report, public_key, signature.as_path()] {
+        if !file.is_file() {
+            bail!("{} does not exist", file.display());
+        }
+    }
+
+    let output = minisign(&[
+        "-V".as_ref(),
+        "-p".as_ref(),
+        public_key.as_os_str(),
+        "-m".as_ref(),
+        report.as_os_str(),
+        "-x".as_ref(),
+        signature.as_os_str(),
+    ])?;
+
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = format!("{} has a valid signature", report.display());
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+        if let Some(comment) = trusted_comment(&stdout) {
+            println!("  Signed: {comment}");
+        }
+        return Ok(0);
+    }
+
+    let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let message = format!(
+        "{} does not match its signature {}: {reason}",
+        report.display(),
+        signature.display()
+    );
+    if is_tty {
+        println!("{} {message}", "FAILED:".red().bold());
+    } else {
+        println!("FAILED: {message}");
+    }
+    // A report that was changed after signing fails CI like a violation
+    Ok(Outcome::Violations.default_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_path() {
+        assert_eq!(
+            signature_path(Path::new("out/unvenv.json")),
+            Path::new("out/unvenv.json.minisig")
+        );
+    }
+
+    #[test]
+    fn test_trusted_comment() {
+        let stdout = "Signature and comment signature verified\nTrusted comment: unvenv 1.6.0 report of /src/app\n";
+        assert_eq!(
+            trusted_comment(stdout),
+            Some("unvenv 1.6.0 report of /src/app")
+        );
+        assert_eq!(trusted_comment("Signature verified\n"), None);
+    }
+}
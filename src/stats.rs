@@ -0,0 +1,401 @@
+//! `unvenv stats`: a local history of scan results and its trend.
+//!
+//! `stats record` scans the current directory like `scan` (allow markers,
+//! `UNVENV_SKIP`, and package policies included) and appends one line to a
+//! history file: when, at which revision, how many venvs, and how large they
+//! are. `stats show` prints the history with a bar per entry and the change
+//! since the first one, so a team running `stats record` in each release
+//! pipeline can see whether its repository is getting cleaner.
+//!
+//! The history lives in `.git/unvenv/stats-history` unless `--file` points
+//! somewhere else, e.g. a file kept as a CI artifact or committed.
+
+use crate::{
+    ScanArgs, config::Outcome, find_unignored_venvs, findings, metrics::ScanMetrics,
+    packages::Packages, rules,
+};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use git2::{DescribeOptions, Repository};
+use std::{
+    cmp::Ordering,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// First line of the history file; bump the version when the format changes
+const HISTORY_HEADER: &str = "unvenv-stats 1";
+
+/// Width of the longest bar in `stats show`
+const BAR_WIDTH: usize = 30;
+
+/// One recorded scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// When the scan ran, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Revision that was scanned, from `git describe`
+    pub label: String,
+    /// Venvs Git does not ignore
+    pub violations: usize,
+    /// Combined size of the files in those venvs
+    pub bytes: u64,
+    /// Whether a limit stopped the scan early
+    pub partial: bool,
+}
+
+impl Entry {
+    /// The entry as a line of the history file
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.timestamp,
+            self.label.replace(['\t', '\n'], " "),
+            self.violations,
+            self.bytes,
+            u8::from(self.partial)
+        )
+    }
+
+    /// Parse a line of the history file
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let entry = Self {
+            timestamp: fields.next()?.parse().ok()?,
+            label: fields.next()?.to_string(),
+            violations: fields.next()?.parse().ok()?,
+            bytes: fields.next()?.parse().ok()?,
+            partial: match fields.next()? {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            },
+        };
+        fields.next().is_none().then_some(entry)
+    }
+}
+
+/// Parse a history file, oldest entry first
+fn parse_history(content: &str, path: &Path) -> Result<Vec<Entry>> {
+    let mut lines = content.lines();
+    if lines.next() != Some(HISTORY_HEADER) {
+        bail!(
+            "{} is not an unvenv stats history (expected `{HISTORY_HEADER}` on the first line)",
+            path.display()
+        );
+    }
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            Entry::parse(line)
+                .with_context(|| format!("{}:{}: malformed entry", path.display(), index + 2))
+        })
+        .collect()
+}
+
+/// The history file to use: `file`, or the default one in the repository
+fn history_path(workdir: &Path, file: Option<&Path>) -> Result<PathBuf> {
+    if let Some(file) = file {
+        return Ok(file.to_path_buf());
+    }
+    let repo = Repository::discover(workdir)
+        .context("Not inside a Git repository; pass --file to choose where the history lives")?;
+    Ok(repo.path().join("unvenv").join("stats-history"))
+}
+
+/// `git describe --tags --always` for the checked-out commit
+fn describe(workdir: &Path) -> Option<String> {
+    let repo = Repository::discover(workdir).ok()?;
+    let description = repo
+        .describe(
+            DescribeOptions::new()
+                .describe_tags()
+                .show_commit_oid_as_fallback(true),
+        )
+        .ok()?;
+    description.format(None).ok()
+}
+
+/// Scan `workdir` and append the result to the history
+pub fn run_record(workdir: &Path, file: Option<&Path>, is_tty: bool) -> Result<i32> {
+    let path = history_path(workdir, file)?;
+
+    let started = Instant::now();
+    let args = ScanArgs::default();
+    let mut outcome = find_unignored_venvs(workdir, &args, is_tty)?;
+    let duration = started.elapsed();
+    let skips = rules::Skips::from_env();
+    let findings = findings::apply(
+        workdir,
+        &args,
+        &skips,
+        &mut Packages::new(workdir),
+        &mut outcome,
+    )?;
+    skips.announce(findings.skipped);
+    let metrics = ScanMetrics::collect(workdir, &outcome.venvs, duration, outcome.limit.is_some());
+
+    let entry = Entry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        label: describe(workdir).unwrap_or_else(|| "-".to_string()),
+        violations: metrics.violations,
+        bytes: metrics.violation_bytes,
+        partial: metrics.partial,
+    };
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let is_new = !fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0);
+    if !is_new {
+        // Refuse to append to something else
+        parse_history(&fs::read_to_string(&path)?, &path)?;
+    }
+    let mut history = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut lines = String::new();
+    if is_new {
+        let _ = writeln!(lines, "{HISTORY_HEADER}");
+    }
+    let _ = writeln!(lines, "{}", entry.to_line());
+    history
+        .write_all(lines.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let message = format!(
+        "Recorded {} violation(s) ({}) at {} in {}",
+        entry.violations,
+        format_bytes(entry.bytes),
+        entry.label,
+        path.display()
+    );
+    if is_tty {
+        println!("{} {message}", "OK:".green().bold());
+    } else {
+        println!("OK: {message}");
+    }
+    Ok(Outcome::Clean.default_code())
+}
+
+/// Print the last `last` entries of the history with their trend
+pub fn run_show(workdir: &Path, file: Option<&Path>, last: usize, is_tty: bool) -> Result<i32> {
+    let path = history_path(workdir, file)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!(
+                "No stats recorded yet in {}; run `unvenv stats record`, e.g. in each release pipeline",
+                path.display()
+            );
+            return Ok(Outcome::Clean.default_code());
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let entries = parse_history(&content, &path)?;
+    let shown = &entries[entries.len().saturating_sub(last)..];
+    print!("{}", render(shown, is_tty));
+    Ok(Outcome::Clean.default_code())
+}
+
+/// The history as a table with one bar per entry, and a summary line
+fn render(entries: &[Entry], is_tty: bool) -> String {
+    let mut text = String::new();
+    let (Some(first), Some(latest)) = (entries.first(), entries.last()) else {
+        return text;
+    };
+    let max = entries
+        .iter()
+        .map(|entry| entry.violations)
+        .max()
+        .unwrap_or(0);
+    let label_width = entries
+        .iter()
+        .map(|entry| entry.label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Revision".len());
+
+    let _ = writeln!(
+        text,
+        "{:<10}  {:<label_width$}  {:>10}  {:>10}",
+        "Date", "Revision", "Violations", "Size"
+    );
+    for entry in entries {
+        let bar = "█".repeat(if max == 0 {
+            0
+        } else {
+            (entry.violations * BAR_WIDTH).div_ceil(max)
+        });
+        let bar = if is_tty {
+            if entry.violations == 0 {
+                bar.green().to_string()
+            } else {
+                bar.yellow().to_string()
+            }
+        } else {
+            bar
+        };
+        let partial = if entry.partial { " (partial)" } else { "" };
+        let _ = writeln!(
+            text,
+            "{}  {:<label_width$}  {:>10}  {:>10}  {bar}{partial}",
+            format_date(entry.timestamp),
+            entry.label,
+            entry.violations,
+            format_bytes(entry.bytes)
+        );
+    }
+
+    if entries.len() > 1 {
+        let trend = match latest.violations.cmp(&first.violations) {
+            Ordering::Less => "down",
+            Ordering::Equal => "unchanged",
+            Ordering::Greater => "up",
+        };
+        let _ = writeln!(
+            text,
+            "\nViolations {trend} from {} to {} ({} to {}) since {} ({})",
+            first.violations,
+            latest.violations,
+            format_bytes(first.bytes),
+            format_bytes(latest.bytes),
+            first.label,
+            format_date(first.timestamp)
+        );
+    }
+    text
+}
+
+/// `1.5 MiB` and friends
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)] // display only
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// `YYYY-MM-DD` (UTC) of a Unix timestamp
+fn format_date(timestamp: u64) -> String {
+    // Howard Hinnant's civil_from_days
+    let days = i64::try_from(timestamp / 86_400).unwrap_or(i64::MAX) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(timestamp: u64, label: &str, violations: usize, bytes: u64) -> Entry {
+        Entry {
+            timestamp,
+            label: label.to_string(),
+            violations,
+            bytes,
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn test_history_round_trip() -> Result<()> {
+        let entries = vec![
+            entry(1_700_000_000, "v1.0.0", 4, 52_428_800),
+            Entry {
+                partial: true,
+                ..entry(1_710_000_000, "v1.1.0-3-gabc1234", 1, 2048)
+            },
+        ];
+        let mut content = format!("{HISTORY_HEADER}\n");
+        for entry in &entries {
+            content.push_str(&entry.to_line());
+            content.push('\n');
+        }
+        assert_eq!(parse_history(&content, Path::new("history"))?, entries);
+
+        assert!(parse_history("something else\n", Path::new("history")).is_err());
+        assert!(
+            parse_history(
+                &format!("{HISTORY_HEADER}\n1\tv1\t2\n"),
+                Path::new("history")
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_trend() {
+        let text = render(
+            &[
+                entry(1_700_000_000, "v1.0.0", 4, 52_428_800),
+                entry(1_710_000_000, "v1.1.0", 2, 1536),
+                entry(1_720_000_000, "v1.2.0", 0, 0),
+            ],
+            false,
+        );
+        assert!(text.contains(&format!(
+            "2023-11-14  v1.0.0             4    50.0 MiB  {}",
+            "█".repeat(BAR_WIDTH)
+        )));
+        assert!(text.contains(&format!("1.5 KiB  {}\n", "█".repeat(BAR_WIDTH / 2))));
+        assert!(
+            text.contains(
+                "Violations down from 4 to 0 (50.0 MiB to 0 B) since v1.0.0 (2023-11-14)"
+            )
+        );
+        assert_eq!(render(&[], false), "");
+    }
+
+    #[test]
+    fn test_record_appends() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("venv"))?;
+        fs::write(dir.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+        let history = dir.join("stats/history");
+
+        run_record(dir, Some(&history), false)?;
+        fs::remove_dir_all(dir.join("venv"))?;
+        run_record(dir, Some(&history), false)?;
+
+        let entries = parse_history(&fs::read_to_string(&history)?, &history)?;
+        let counts: Vec<_> = entries.iter().map(|entry| entry.violations).collect();
+        assert_eq!(counts, [1, 0]);
+        assert!(entries[0].bytes > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! `unvenv status`: a one-word answer for shell prompts and status bars.
+//!
+//! Prompts redraw after every command, so the status scans the whole
+//! repository with `--cache` (only changed directories are re-checked) under
+//! a short time budget, and never walks anything outside a repository. With
+//! `--porcelain` the output is a single stable token:
+//!
+//! - `clean`: no findings
+//! - `violations:N`: N venvs Git does not ignore
+//! - `partial`: the time budget ran out before any violation was found
+//! - `no-repo`: not inside a Git working tree
+//!
+//! `.unvenv-allow` markers, rules skipped with `UNVENV_SKIP`, and package
+//! policies apply as in `scan`.
+
+use crate::{ScanArgs, config::Outcome, find_unignored_venvs, findings, packages::Packages, rules};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use git2::Repository;
+use std::{fmt, path::Path, time::Duration};
+
+/// Longest a status scan may take; later calls pick up where the cache left off
+const STATUS_TIME_BUDGET: Duration = Duration::from_millis(300);
+
+/// Result of a status check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing to report
+    Clean,
+    /// This many venvs are not ignored
+    Violations(usize),
+    /// Ran out of time without finding a violation
+    Partial,
+    /// Not inside a Git working tree
+    NoRepository,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clean => f.write_str("clean"),
+            Self::Violations(count) => write!(f, "violations:{count}"),
+            Self::Partial => f.write_str("partial"),
+            Self::NoRepository => f.write_str("no-repo"),
+        }
+    }
+}
+
+impl Status {
+    /// Exit code of the status
+    const fn outcome(self) -> Outcome {
+        match self {
+            Self::Clean => Outcome::Clean,
+            Self::Violations(_) => Outcome::Violations,
+            Self::Partial => Outcome::Partial,
+            Self::NoRepository => Outcome::NoRepository,
+        }
+    }
+}
+
+/// Check the repository containing `workdir`
+pub fn check(workdir: &Path) -> Result<Status> {
+    let root = match Repository::discover(workdir) {
+        Ok(repo) => match repo.workdir() {
+            Some(root) => root.to_path_buf(),
+            None => return Ok(Status::NoRepository),
+        },
+        Err(_) => return Ok(Status::NoRepository),
+    };
+
+    let args = ScanArgs {
+        cache: true,
+        time_budget: Some(STATUS_TIME_BUDGET),
+        ..ScanArgs::default()
+    };
+    let mut outcome = find_unignored_venvs(&root, &args, false)
+        .with_context(|| format!("Failed to scan {}", root.display()))?;
+    let findings = findings::apply(
+        &root,
+        &args,
+        &rules::Skips::from_env(),
+        &mut Packages::new(&root),
+        &mut outcome,
+    )?;
+    let count = outcome.venvs.len() + outcome.artifacts.len() + findings.references.len();
+
+    Ok(match (count, outcome.limit) {
+        (0, Some(_)) => Status::Partial,
+        (0, None) => Status::Clean,
+        (count, _) => Status::Violations(count),
+    })
+}
+
+/// Print the status of the repository containing `workdir`
+pub fn run(workdir: &Path, porcelain: bool, is_tty: bool) -> Result<i32> {
+    let status = check(workdir)?;
+    if porcelain {
+        println!("{status}");
+        return Ok(status.outcome().default_code());
+    }
+
+    let (label, message) = match status {
+        Status::Clean => ("OK:", "no unignored virtual environments".to_string()),
+        Status::Violations(count) => (
+            "WARNING:",
+            format!(
+                "{count} virtual environment(s) not ignored by Git; run `unvenv scan` for details"
+            ),
+        ),
+        Status::Partial => (
+            "Note:",
+            "no violations found so far; run `unvenv scan` for a complete check".to_string(),
+        ),
+        Status::NoRepository => ("Note:", "not inside a Git repository".to_string()),
+    };
+    if is_tty {
+        let label = if status == Status::Clean {
+            label.green()
+        } else {
+            label.yellow()
+        };
+        println!("{} {message}", label.bold());
+    } else {
+        println!("{label} {message}");
+    }
+    Ok(status.outcome().default_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_status_tokens() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        assert_eq!(check(temp_dir.path())?, Status::NoRepository);
+
+        git(temp_dir.path(), &["init", "-q"]);
+        fs::create_dir_all(temp_dir.path().join("src"))?;
+        assert_eq!(check(temp_dir.path())?, Status::Clean);
+
+        for dir in ["venv", "tools/.venv"] {
+            fs::create_dir_all(temp_dir.path().join(dir))?;
+            fs::write(
+                temp_dir.path().join(dir).join("pyvenv.cfg"),
+                "home = /usr/bin\n",
+            )?;
+        }
+        // The whole repository counts, wherever the prompt is
+        let status = check(&temp_dir.path().join("src"))?;
+        assert_eq!(status, Status::Violations(2));
+        assert_eq!(status.to_string(), "violations:2");
+        Ok(())
+    }
+}
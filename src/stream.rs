@@ -0,0 +1,150 @@
+//! Async scanning for tokio hosts (the `tokio` feature).
+//!
+//! A walk of a large tree takes minutes and blocks the thread doing it.
+//! [`Scanner::violations`] runs it on tokio's blocking pool instead and
+//! hands the violations back as a [`Stream`], so an async daemon can scan
+//! without stalling its runtime:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use std::{future, pin::Pin};
+//! use futures_core::Stream;
+//! use unvenv::Scanner;
+//!
+//! let mut violations = Scanner::new(".").violations();
+//! while let Some(item) =
+//!     future::poll_fn(|cx| Pin::new(&mut violations).poll_next(cx)).await
+//! {
+//!     match item {
+//!         Ok(violation) => println!("{}", violation.path.display()),
+//!         Err(e) => eprintln!("{e}"),
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! Dropping the stream stops the walk at the next directory.
+
+use crate::{
+    error::Result,
+    model::Violation,
+    scanner::{ScanEvent, Scanner},
+};
+use futures_core::Stream;
+use std::{
+    ops::ControlFlow,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// Violations buffered ahead of a consumer that is slow to poll
+const BUFFER: usize = 64;
+
+/// Violations of a running scan, see [`Scanner::violations`]
+///
+/// Yields each violation as the walk finds it. A scan that fails ends the
+/// stream with its [`Error`](crate::Error); one stopped by a
+/// [`CancellationToken`](crate::CancellationToken) ends it with
+/// [`Error::PartialScan`](crate::Error::PartialScan).
+#[derive(Debug)]
+pub struct ViolationStream(mpsc::Receiver<Result<Violation>>);
+
+impl Stream for ViolationStream {
+    type Item = Result<Violation>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+impl Scanner {
+    /// Scan the tree on tokio's blocking pool, streaming the violations
+    ///
+    /// # Panics
+    ///
+    /// If called outside a tokio runtime.
+    #[must_use]
+    pub fn violations(&self) -> ViolationStream {
+        let (tx, rx) = mpsc::channel(BUFFER);
+        let scanner = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = scanner.scan_with(&mut |event: ScanEvent<'_>| match event {
+                ScanEvent::Violation(violation) => match tx.blocking_send(Ok(violation.clone())) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                },
+                // Nobody is listening any more
+                _ if tx.is_closed() => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            });
+            if let Err(e) = result {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        ViolationStream(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CancellationToken, Error};
+    use anyhow::Result;
+    use std::{fs, future, path::Path};
+    use tempfile::TempDir;
+
+    async fn collect(mut stream: ViolationStream) -> Vec<crate::Result<Violation>> {
+        let mut items = Vec::new();
+        while let Some(item) = future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            items.push(item);
+        }
+        items
+    }
+
+    fn runtime() -> Result<tokio::runtime::Runtime> {
+        Ok(tokio::runtime::Builder::new_current_thread().build()?)
+    }
+
+    #[test]
+    fn test_violations_streams_each_venv() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for dir in ["a/venv", "b/venv"] {
+            fs::create_dir_all(temp_dir.path().join(dir))?;
+            fs::write(
+                temp_dir.path().join(dir).join("pyvenv.cfg"),
+                "home = /usr/bin\n",
+            )?;
+        }
+
+        let scanner = Scanner::new(temp_dir.path());
+        let items = runtime()?.block_on(async { collect(scanner.violations()).await });
+
+        let mut paths = items
+            .into_iter()
+            .map(|item| item.map(|violation| violation.path))
+            .collect::<crate::Result<Vec<_>>>()?;
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                Path::new("a/venv/pyvenv.cfg"),
+                Path::new("b/venv/pyvenv.cfg")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_violations_ends_with_the_scan_error() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let scanner = Scanner::new(temp_dir.path()).with_cancellation(token);
+        let items = runtime()?.block_on(async { collect(scanner.violations()).await });
+
+        assert!(matches!(items.as_slice(), [Err(Error::PartialScan(_))]));
+        Ok(())
+    }
+}
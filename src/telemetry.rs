@@ -0,0 +1,292 @@
+//! OpenTelemetry export of scan telemetry over OTLP/HTTP.
+//!
+//! When an endpoint is configured (`--otlp-endpoint`, or the standard
+//! `OTEL_EXPORTER_OTLP_ENDPOINT`), each scan sends one trace and a few gauges
+//! to the collector using the OTLP JSON encoding. The trace has a root span
+//! for the scan and one child span per phase. The walk, ignore checks, and
+//! parsing are interleaved, so each phase span covers the cumulative time
+//! spent in that phase, all starting with the scan.
+//!
+//! Export goes through `curl` with a short timeout and never affects the
+//! scan result; `OTEL_EXPORTER_OTLP_HEADERS` is honored for authentication.
+
+use crate::{PhaseTimes, VERSION, github};
+use colored::Colorize;
+use serde_json::{Value, json};
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    fmt::Write as _,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    process::{Command, Stdio},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// What one scan reports
+#[derive(Debug)]
+pub struct ScanTelemetry<'a> {
+    /// How the scan was started, e.g. `scan` or `hook`
+    pub mode: &'a str,
+    /// When the scan started
+    pub started: SystemTime,
+    /// Wall-clock time of the scan
+    pub duration: Duration,
+    /// Time spent in the interleaved phases
+    pub phases: PhaseTimes,
+    /// Unignored venvs found
+    pub violations: usize,
+    /// Whether a limit stopped the scan early
+    pub partial: bool,
+}
+
+/// Endpoint to export to, from the flag or the standard environment variable
+pub fn endpoint(flag: Option<&str>) -> Option<String> {
+    flag.map(str::to_string)
+        .or_else(|| env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+}
+
+/// Send the trace and metrics for one scan, if an endpoint is configured
+///
+/// Never fails: telemetry problems are reported as a warning at most.
+pub fn export(flag: Option<&str>, telemetry: &ScanTelemetry) {
+    let Some(endpoint) = endpoint(flag) else {
+        return;
+    };
+    let service = env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "unvenv".to_string());
+
+    let resource =
+        json!({ "attributes": [attribute("service.name", &json!({ "stringValue": service }))] });
+    let traces = json!({
+        "resourceSpans": [{
+            "resource": resource,
+            "scopeSpans": [{ "scope": scope(), "spans": spans(telemetry) }],
+        }],
+    });
+    let metrics = json!({
+        "resourceMetrics": [{
+            "resource": resource,
+            "scopeMetrics": [{ "scope": scope(), "metrics": metrics(telemetry) }],
+        }],
+    });
+
+    for (path, body) in [("v1/traces", traces), ("v1/metrics", metrics)] {
+        if let Err(message) = post(&format!("{endpoint}/{path}"), &body.to_string()) {
+            eprintln!(
+                "{} OTLP export to {endpoint} failed: {message}",
+                "Warning:".yellow().bold()
+            );
+            return;
+        }
+    }
+}
+
+fn scope() -> Value {
+    json!({ "name": "unvenv", "version": VERSION })
+}
+
+fn attribute(key: &str, value: &Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// Random hex id of `bytes` bytes, as OTLP JSON expects for trace and span ids
+fn random_id(bytes: usize) -> String {
+    let mut id = String::with_capacity(bytes * 2);
+    while id.len() < bytes * 2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(id.len());
+        let _ = write!(id, "{:016x}", hasher.finish());
+    }
+    id.truncate(bytes * 2);
+    id
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos())
+        .to_string()
+}
+
+/// Root span for the scan plus one span per phase
+fn spans(telemetry: &ScanTelemetry) -> Vec<Value> {
+    let trace_id = random_id(16);
+    let root_id = random_id(8);
+    let start = unix_nanos(telemetry.started);
+    let span = |name: &str, span_id: &str, parent: Option<&str>, duration: Duration| {
+        let mut span = json!({
+            "traceId": trace_id,
+            "spanId": span_id,
+            "name": name,
+            "kind": 1,
+            "startTimeUnixNano": start,
+            "endTimeUnixNano": unix_nanos(telemetry.started + duration),
+        });
+        if let Some(parent) = parent {
+            span["parentSpanId"] = json!(parent);
+        }
+        span
+    };
+
+    let mut root = span("unvenv.scan", &root_id, None, telemetry.duration);
+    root["attributes"] = json!([
+        attribute("unvenv.mode", &json!({ "stringValue": telemetry.mode })),
+        attribute(
+            "unvenv.violations",
+            &json!({ "intValue": telemetry.violations.to_string() })
+        ),
+        attribute("unvenv.partial", &json!({ "boolValue": telemetry.partial })),
+    ]);
+
+    let phases = telemetry.phases.ignore + telemetry.phases.parse;
+    vec![
+        root,
+        span(
+            "walk",
+            &random_id(8),
+            Some(&root_id),
+            telemetry.duration.saturating_sub(phases),
+        ),
+        span(
+            "ignore-check",
+            &random_id(8),
+            Some(&root_id),
+            telemetry.phases.ignore,
+        ),
+        span(
+            "parse",
+            &random_id(8),
+            Some(&root_id),
+            telemetry.phases.parse,
+        ),
+    ]
+}
+
+/// Gauges for the violation count and scan duration
+fn metrics(telemetry: &ScanTelemetry) -> Vec<Value> {
+    let time = unix_nanos(telemetry.started + telemetry.duration);
+    let attributes = json!([attribute(
+        "unvenv.mode",
+        &json!({ "stringValue": telemetry.mode })
+    )]);
+    vec![
+        json!({
+            "name": "unvenv.violations",
+            "unit": "{venv}",
+            "gauge": { "dataPoints": [{
+                "asInt": telemetry.violations.to_string(),
+                "timeUnixNano": time,
+                "attributes": attributes,
+            }]},
+        }),
+        json!({
+            "name": "unvenv.scan.duration",
+            "unit": "s",
+            "gauge": { "dataPoints": [{
+                "asDouble": telemetry.duration.as_secs_f64(),
+                "timeUnixNano": time,
+                "attributes": attributes,
+            }]},
+        }),
+    ]
+}
+
+/// curl config with the content type, any `OTEL_EXPORTER_OTLP_HEADERS`, and
+/// the request body, which stays off the command line
+fn curl_config(headers: Option<&str>, body: &str) -> String {
+    let mut config = String::from("header = \"Content-Type: application/json\"\n");
+    for pair in headers.unwrap_or_default().split(',') {
+        if let Some((key, value)) = pair.split_once('=') {
+            let header = format!("{}: {}", key.trim(), value.trim());
+            let _ = writeln!(config, "header = {}", github::curl_quote(&header));
+        }
+    }
+    let _ = writeln!(config, "data-binary = {}", github::curl_quote(body));
+    config
+}
+
+/// POST one OTLP request, returning a description of any failure
+fn post(url: &str, body: &str) -> Result<(), String> {
+    let headers = env::var("OTEL_EXPORTER_OTLP_HEADERS").ok();
+    let mut child = Command::new("curl")
+        .args(["-sS", "-f", "--max-time", "2", "--config", "-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run curl: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(curl_config(headers.as_deref(), body).as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry() -> ScanTelemetry<'static> {
+        ScanTelemetry {
+            mode: "scan",
+            started: UNIX_EPOCH + Duration::from_secs(1),
+            duration: Duration::from_millis(100),
+            phases: PhaseTimes {
+                ignore: Duration::from_millis(30),
+                parse: Duration::from_millis(10),
+            },
+            violations: 2,
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn test_spans_cover_phases() {
+        let spans = spans(&telemetry());
+        let names: Vec<&str> = spans
+            .iter()
+            .filter_map(|span| span["name"].as_str())
+            .collect();
+        assert_eq!(names, ["unvenv.scan", "walk", "ignore-check", "parse"]);
+
+        assert_eq!(spans[0]["startTimeUnixNano"], "1000000000");
+        assert_eq!(spans[0]["endTimeUnixNano"], "1100000000");
+        // The walk gets whatever the other phases did not use
+        assert_eq!(spans[1]["endTimeUnixNano"], "1060000000");
+        assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+        assert_eq!(spans[1]["traceId"].as_str().map(str::len), Some(32));
+        assert_eq!(spans[1]["spanId"].as_str().map(str::len), Some(16));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = metrics(&telemetry());
+        assert_eq!(metrics[0]["name"], "unvenv.violations");
+        assert_eq!(metrics[0]["gauge"]["dataPoints"][0]["asInt"], "2");
+        assert_eq!(metrics[1]["gauge"]["dataPoints"][0]["asDouble"], 0.1);
+    }
+
+    #[test]
+    fn test_curl_config_headers() {
+        let config = curl_config(Some("x-api-key=abc, tenant = a\"b"), "{}");
+        assert!(config.contains("header = \"x-api-key: abc\"\n"));
+        assert!(config.contains("header = \"tenant: a\\\"b\"\n"));
+        assert_eq!(
+            curl_config(None, r#"{"a":"b"}"#),
+            "header = \"Content-Type: application/json\"\ndata-binary = \"{\\\"a\\\":\\\"b\\\"}\"\n"
+        );
+    }
+
+    #[test]
+    fn test_endpoint_flag_wins() {
+        assert_eq!(
+            endpoint(Some("http://collector:4318/")),
+            Some("http://collector:4318".to_string())
+        );
+    }
+}
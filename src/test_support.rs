@@ -0,0 +1,17 @@
+//! Helpers shared by the unit tests.
+
+use std::{path::Path, process::Command};
+
+/// Run `git args` in `dir` as a fixed test identity, returning its output
+///
+/// Fails the test if Git cannot be run or exits with an error.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    assert!(output.status.success(), "git {args:?} failed");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
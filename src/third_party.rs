@@ -0,0 +1,87 @@
+//! `license --third-party`: licenses of the compiled-in dependencies.
+//!
+//! `build.rs` collects the license expression and license files of every
+//! crate linked into the binary; this prints them, one section per crate.
+
+use colored::Colorize;
+use serde::Deserialize;
+
+/// Dependency licenses generated at build time
+const DATA: &str = include_str!(concat!(env!("OUT_DIR"), "/third_party_licenses.json"));
+
+/// One license file shipped with a dependency
+#[derive(Debug, Deserialize)]
+struct LicenseText {
+    file: String,
+    text: String,
+}
+
+/// One compiled-in dependency
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    name: String,
+    version: String,
+    license: Option<String>,
+    texts: Vec<LicenseText>,
+}
+
+/// Print the licenses of all compiled-in dependencies
+pub fn run(is_tty: bool) -> i32 {
+    let dependencies: Vec<Dependency> = serde_json::from_str(DATA).unwrap_or_default();
+    if dependencies.is_empty() {
+        eprintln!(
+            "{} This build carries no third-party license data; `cargo metadata` was unavailable when it was built",
+            "Error:".red().bold()
+        );
+        return 1;
+    }
+
+    println!(
+        "unvenv includes the following {} third-party crates:",
+        dependencies.len()
+    );
+    for dependency in &dependencies {
+        let license = dependency.license.as_deref().unwrap_or("see license files");
+        println!("  {} {} ({license})", dependency.name, dependency.version);
+    }
+
+    for dependency in &dependencies {
+        let heading = format!("{} {}", dependency.name, dependency.version);
+        println!();
+        if is_tty {
+            println!("{}", format!("=== {heading} ===").bold());
+        } else {
+            println!("=== {heading} ===");
+        }
+        if let Some(license) = &dependency.license {
+            println!("License: {license}");
+        }
+        if dependency.texts.is_empty() {
+            println!("(no license file in the published crate)");
+        }
+        for text in &dependency.texts {
+            println!();
+            println!("--- {} ---", text.file);
+            println!("{}", text.text.trim_end());
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_data_lists_direct_dependencies() {
+        let dependencies: Vec<Dependency> =
+            serde_json::from_str(DATA).expect("build script writes valid JSON");
+        // Offline builds without `cargo metadata` embed nothing
+        if dependencies.is_empty() {
+            return;
+        }
+        let names: Vec<&str> = dependencies.iter().map(|dep| dep.name.as_str()).collect();
+        assert!(names.contains(&"clap"));
+        assert!(names.contains(&"git2"));
+    }
+}
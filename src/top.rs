@@ -0,0 +1,532 @@
+//! `unvenv top`: where the disk space under the work tree went.
+//!
+//! Venvs and Python caches pile up unnoticed on laptops, often tens of
+//! gigabytes across old checkouts. `top` lists the largest ones below the
+//! current directory, ignored by Git or not, and re-measures them every few
+//! seconds on a background thread, so keys never wait for a walk. In a
+//! terminal it takes over the screen until you quit:
+//!
+//! - the arrow keys (or `j`/`k`, Home/End, `g`/`G`) move the selection
+//! - `d` (or Delete) deletes the selected entry after a `y` to confirm
+//! - `i` adds the selected entry to the repository's top-level `.gitignore`
+//!   (the current directory's outside a repository)
+//! - `r` refreshes now, `q` (or Esc, Ctrl-C) quits
+//!
+//! The terminal is put into non-canonical mode with `stty`, which keeps the
+//! crate free of a terminal UI dependency. When stdout is not a terminal the
+//! list is printed once.
+
+use crate::{ScanArgs, find_unignored_venvs, fix_script, metrics, stats};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use git2::Repository;
+use std::{
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use unvenv::{Action, ignore::IgnoreMatcher};
+
+/// How often the list is redrawn without input
+const REFRESH: Duration = Duration::from_secs(5);
+
+/// Entries shown
+const ROWS: usize = 20;
+
+/// One venv or cache directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Directory relative to the current directory
+    path: PathBuf,
+    /// `venv`, or the rule name of a tooling directory such as `pip-cache`
+    kind: &'static str,
+    bytes: u64,
+    /// Whether Git ignores it
+    ignored: bool,
+}
+
+/// A key pressed while the list is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    First,
+    Last,
+    /// Delete the selected entry
+    Delete,
+    /// Add the selected entry to `.gitignore`
+    Ignore,
+    /// Confirm a deletion
+    Yes,
+    Refresh,
+    Quit,
+    /// Any key without a meaning, which cancels a deletion
+    Other,
+}
+
+impl Key {
+    /// The key whose escape sequence or character `bytes` is
+    fn parse(bytes: &[u8]) -> Self {
+        match bytes {
+            b"\x1b[A" | b"\x1bOA" | b"k" => Self::Up,
+            b"\x1b[B" | b"\x1bOB" | b"j" => Self::Down,
+            b"\x1b[H" | b"\x1bOH" | b"\x1b[1~" | b"g" => Self::First,
+            b"\x1b[F" | b"\x1bOF" | b"\x1b[4~" | b"G" => Self::Last,
+            b"\x1b[3~" | b"d" => Self::Delete,
+            b"i" => Self::Ignore,
+            b"y" | b"Y" => Self::Yes,
+            b"r" => Self::Refresh,
+            // Esc on its own, and Ctrl-C since signals are off
+            b"\x1b" | b"\x03" | b"q" => Self::Quit,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The largest venvs and caches below `workdir`, biggest first
+fn collect(workdir: &Path) -> Result<Vec<Entry>> {
+    // Ignored directories take up space just the same
+    let args = ScanArgs {
+        no_git: true,
+        ..ScanArgs::default()
+    };
+    let outcome = find_unignored_venvs(workdir, &args, false)?;
+    let mut matcher = IgnoreMatcher::discover(workdir);
+
+    let venvs = outcome
+        .venvs
+        .iter()
+        .filter_map(|venv| Some((venv.path.parent()?.to_path_buf(), "venv")));
+    let artifacts = outcome
+        .artifacts
+        .iter()
+        .map(|artifact| (artifact.path.clone(), artifact.kind.rule().name));
+    let mut entries: Vec<Entry> = venvs
+        .chain(artifacts)
+        // The current directory itself is never offered for deletion
+        .filter(|(path, _)| !path.as_os_str().is_empty())
+        .map(|(path, kind)| {
+            let full_path = workdir.join(&path);
+            let ignored = matcher
+                .as_mut()
+                .is_some_and(|matcher| matcher.is_dir_ignored(&full_path).unwrap_or(false));
+            Entry {
+                bytes: metrics::dir_bytes(&full_path),
+                path,
+                kind,
+                ignored,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    entries.truncate(ROWS);
+    Ok(entries)
+}
+
+/// The screen for `entries`, with `selected` highlighted and the result of
+/// the last key below
+fn render(
+    workdir: &Path,
+    entries: &[Entry],
+    selected: Option<usize>,
+    message: &str,
+    is_tty: bool,
+) -> String {
+    let total: u64 = entries.iter().map(|entry| entry.bytes).sum();
+    let mut screen = String::new();
+    let headline = format!(
+        "Largest Python environments and caches in {} ({} total)",
+        workdir.display(),
+        stats::format_bytes(total)
+    );
+    if is_tty {
+        let _ = writeln!(screen, "{}", headline.bold());
+    } else {
+        let _ = writeln!(screen, "{headline}");
+    }
+    screen.push('\n');
+
+    if entries.is_empty() {
+        screen.push_str("  No virtual environments or caches found\n");
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        let status = if entry.ignored { "ignored" } else { "" };
+        let row = format!(
+            "  {:>3}  {:>10}  {:<16}  {:<7}  {}/",
+            index + 1,
+            stats::format_bytes(entry.bytes),
+            entry.kind,
+            status,
+            entry.path.to_string_lossy().replace('\\', "/")
+        );
+        if selected == Some(index) {
+            let _ = writeln!(screen, "{}", row.reversed());
+        } else {
+            let _ = writeln!(screen, "{row}");
+        }
+    }
+
+    if selected.is_some() {
+        screen.push('\n');
+        if !message.is_empty() {
+            let _ = writeln!(screen, "{message}");
+        }
+        screen.push_str(
+            "Up/Down: select   d: delete   i: add to .gitignore   r: refresh   q: quit\n",
+        );
+    }
+    screen
+}
+
+/// Delete `entry`, returning what happened
+fn delete(workdir: &Path, entry: &Entry) -> Result<String> {
+    let dir = workdir.join(&entry.path);
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))?;
+    Ok(format!(
+        "Deleted {}/ ({})",
+        entry.path.to_string_lossy().replace('\\', "/"),
+        stats::format_bytes(entry.bytes)
+    ))
+}
+
+/// Add `entry` to the `.gitignore` at the top of the repository containing
+/// `workdir`, or in `workdir` outside one, returning what happened
+fn ignore(workdir: &Path, entry: &Entry) -> Result<String> {
+    let path = entry.path.to_string_lossy().replace('\\', "/");
+    if entry.ignored {
+        return Ok(format!("{path}/ is already ignored"));
+    }
+    let root = Repository::discover(workdir)
+        .ok()
+        .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+        .unwrap_or_else(|| workdir.to_path_buf());
+    // Entries are relative to the current directory, the file to the root
+    let prefix = fs::canonicalize(workdir)?
+        .strip_prefix(fs::canonicalize(&root)?)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let pattern = format!(
+        "/{}/",
+        prefix
+            .join(&entry.path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    );
+    let applied = fix_script::apply_actions(
+        &root,
+        [Action::AddGitignoreEntry {
+            file: PathBuf::from(".gitignore"),
+            entry: pattern.clone(),
+        }],
+    )?;
+    let file = root.join(".gitignore");
+    Ok(if applied.ignores.is_empty() {
+        format!("{pattern} is already in {}", file.display())
+    } else {
+        format!("Added {pattern} to {}", file.display())
+    })
+}
+
+/// What the dashboard shows between keys
+#[derive(Debug, Default)]
+struct State {
+    entries: Vec<Entry>,
+    selected: usize,
+    /// Set after `d`, until the next key confirms or cancels the deletion
+    confirming: bool,
+    message: String,
+    /// Bumped by every reload, so a background measurement started before
+    /// one is known to be stale
+    generation: u64,
+}
+
+impl State {
+    /// Re-measure the entries now
+    fn reload(&mut self, workdir: &Path) -> Result<()> {
+        let entries = collect(workdir)?;
+        self.show(entries);
+        Ok(())
+    }
+
+    /// Replace the entries, keeping the selection on screen
+    fn show(&mut self, entries: Vec<Entry>) {
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.generation += 1;
+    }
+
+    /// React to `key`; `Ok(false)` means quit
+    fn handle(&mut self, workdir: &Path, key: Key) -> Result<bool> {
+        if std::mem::take(&mut self.confirming) {
+            self.message = match (key, self.entries.get(self.selected)) {
+                (Key::Yes, Some(entry)) => {
+                    let message =
+                        delete(workdir, entry).unwrap_or_else(|e| format!("Error: {e:#}"));
+                    self.reload(workdir)?;
+                    message
+                }
+                _ => "Not deleted".to_string(),
+            };
+            return Ok(true);
+        }
+
+        self.message.clear();
+        let last = self.entries.len().saturating_sub(1);
+        match key {
+            Key::Quit => return Ok(false),
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(last),
+            Key::First => self.selected = 0,
+            Key::Last => self.selected = last,
+            Key::Refresh => self.reload(workdir)?,
+            Key::Delete => {
+                if let Some(entry) = self.entries.get(self.selected) {
+                    self.message = format!(
+                        "Delete {}/ ({})? [y/N]",
+                        entry.path.to_string_lossy().replace('\\', "/"),
+                        stats::format_bytes(entry.bytes)
+                    );
+                    self.confirming = true;
+                }
+            }
+            Key::Ignore => {
+                if let Some(entry) = self.entries.get(self.selected) {
+                    self.message =
+                        ignore(workdir, entry).unwrap_or_else(|e| format!("Error: {e:#}"));
+                    self.reload(workdir)?;
+                }
+            }
+            Key::Yes | Key::Other => {}
+        }
+        Ok(true)
+    }
+}
+
+/// The controlling terminal, in non-canonical mode and on the alternate
+/// screen until dropped
+struct Terminal {
+    tty: File,
+    /// `stty -g` settings to restore
+    saved: String,
+}
+
+impl Terminal {
+    fn open() -> Result<Self> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .context("unvenv top needs a terminal")?;
+        let saved = stty(&tty, &["-g"])?;
+        // Reads return each key at once, or nothing after a tenth of a second
+        stty(
+            &tty,
+            &["-icanon", "-echo", "-isig", "min", "0", "time", "1"],
+        )?;
+        let mut terminal = Self {
+            tty,
+            saved: saved.trim().to_string(),
+        };
+        // Switch to the alternate screen and hide the cursor
+        terminal.tty.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        Ok(terminal)
+    }
+
+    fn draw(&mut self, screen: &str) -> Result<()> {
+        // Move the cursor home and clear the screen
+        write!(self.tty, "\x1b[H\x1b[2J{screen}")?;
+        self.tty.flush()?;
+        Ok(())
+    }
+
+    /// The next key, or `None` if none was pressed in time
+    fn key(&mut self) -> Result<Option<Key>> {
+        let mut buf = [0; 8];
+        let n = self.tty.read(&mut buf)?;
+        // Keys typed faster than they are read arrive together; only escape
+        // sequences are longer than a byte
+        let len = if buf[0] == 0x1b { n } else { n.min(1) };
+        Ok((n > 0).then(|| Key::parse(&buf[..len])))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stty(&self.tty, &[&self.saved]);
+    }
+}
+
+/// Run `stty` on `tty`, returning what it printed
+fn stty(tty: &File, args: &[&str]) -> Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to run stty; unvenv top needs a Unix terminal")?;
+    if !output.status.success() {
+        bail!("stty {} failed", args.join(" "));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Show the dashboard for `workdir` until the user quits
+pub fn run(workdir: &Path, is_tty: bool) -> Result<i32> {
+    let mut state = State::default();
+    state.reload(workdir)?;
+    if !is_tty {
+        print!("{}", render(workdir, &state.entries, None, "", false));
+        return Ok(0);
+    }
+
+    let mut terminal = Terminal::open()?;
+    let (tx, rx) = mpsc::channel();
+    let mut measured = Instant::now();
+    // Generation of the state a background measurement started from
+    let mut measuring = None;
+    let mut redraw = true;
+    loop {
+        if redraw {
+            terminal.draw(&render(
+                workdir,
+                &state.entries,
+                Some(state.selected),
+                &state.message,
+                true,
+            ))?;
+        }
+        let Some(key) = terminal.key()? else {
+            redraw = false;
+            if measuring.is_none() && measured.elapsed() >= REFRESH {
+                measuring = Some(state.generation);
+                let (tx, workdir) = (tx.clone(), workdir.to_path_buf());
+                thread::spawn(move || {
+                    let _ = tx.send(collect(&workdir));
+                });
+            }
+            if let Ok(entries) = rx.try_recv() {
+                measured = Instant::now();
+                // Leave the list alone while a deletion waits for an answer,
+                // and drop a result a key has already reloaded past
+                let fresh = measuring.take() == Some(state.generation);
+                if fresh && !state.confirming {
+                    state.show(entries?);
+                    redraw = true;
+                }
+            }
+            continue;
+        };
+        if !state.handle(workdir, key)? {
+            return Ok(0);
+        }
+        redraw = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_key_parse() {
+        assert_eq!(Key::parse(b"\x1b[A"), Key::Up);
+        assert_eq!(Key::parse(b"j"), Key::Down);
+        assert_eq!(Key::parse(b"\x1b[4~"), Key::Last);
+        assert_eq!(Key::parse(b"\x1b[3~"), Key::Delete);
+        assert_eq!(Key::parse(b"\x1b"), Key::Quit);
+        assert_eq!(Key::parse(b"\x03"), Key::Quit);
+        assert_eq!(Key::parse(b"x"), Key::Other);
+        assert_eq!(Key::parse(b"jj"), Key::Other);
+    }
+
+    #[test]
+    fn test_largest_first_then_delete_and_ignore() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        for (venv, size) in [("small", 10), ("big", 5000)] {
+            fs::create_dir_all(dir.join(venv).join("lib"))?;
+            fs::write(dir.join(venv).join("pyvenv.cfg"), "home = /usr/bin\n")?;
+            fs::write(dir.join(venv).join("lib/blob"), vec![0; size])?;
+        }
+        fs::create_dir_all(dir.join(".cache/pip/http"))?;
+
+        let mut state = State::default();
+        state.reload(dir)?;
+        let listed: Vec<_> = state
+            .entries
+            .iter()
+            .map(|entry| (entry.path.to_string_lossy().into_owned(), entry.kind))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("big".to_string(), "venv"),
+                ("small".to_string(), "venv"),
+                (".cache/pip".to_string(), "pip-cache"),
+            ]
+        );
+        assert!(render(dir, &state.entries, None, "", false).contains("  1     4.9 KiB  venv"));
+
+        // The selection stops at either end
+        state.handle(dir, Key::Up)?;
+        assert_eq!(state.selected, 0);
+        for _ in 0..5 {
+            state.handle(dir, Key::Down)?;
+        }
+        assert_eq!(state.selected, 2);
+
+        state.handle(dir, Key::First)?;
+        state.handle(dir, Key::Down)?;
+        state.handle(dir, Key::Ignore)?;
+        assert_eq!(
+            state.message,
+            format!("Added /small/ to {}", dir.join(".gitignore").display())
+        );
+        assert_eq!(fs::read_to_string(dir.join(".gitignore"))?, "/small/\n");
+
+        // A deletion only happens once confirmed
+        state.handle(dir, Key::First)?;
+        state.handle(dir, Key::Delete)?;
+        state.handle(dir, Key::Down)?;
+        assert_eq!(state.message, "Not deleted");
+        assert!(dir.join("small").exists());
+        state.handle(dir, Key::First)?;
+        state.handle(dir, Key::Delete)?;
+        state.handle(dir, Key::Yes)?;
+        assert!(!dir.join("big").exists());
+        assert_eq!(state.entries.len(), 2);
+
+        assert!(!state.handle(dir, Key::Quit)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_writes_the_top_level_gitignore() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        git(root, &["init", "-q"]);
+        let app = root.join("services/app");
+        fs::create_dir_all(app.join("venv"))?;
+        fs::write(app.join("venv/pyvenv.cfg"), "home = /usr/bin\n")?;
+
+        let mut state = State::default();
+        state.reload(&app)?;
+        state.handle(&app, Key::Ignore)?;
+
+        assert_eq!(
+            fs::read_to_string(root.join(".gitignore"))?,
+            "/services/app/venv/\n"
+        );
+        assert!(!app.join(".gitignore").exists());
+        assert!(state.entries[0].ignored);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! `scan --explain-scan`: a trace of what the walk did and why.
+//!
+//! Each pruned directory and each evaluated `pyvenv.cfg` gets one line on
+//! stderr, so the report on stdout stays machine-readable. For paths Git
+//! ignores, the matching pattern and where it comes from (`.gitignore`,
+//! `.git/info/exclude`, `core.excludesFile`) are looked up with
+//! `git check-ignore -v`. That costs a process per ignored path, which is
+//! fine for a debugging aid but is why the normal scan never does it.
+
+use colored::Colorize;
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Print one trace line for `path`, shown relative to `workdir`
+pub fn event(workdir: &Path, action: &str, path: &Path, detail: &str) {
+    let rel = path.strip_prefix(workdir).unwrap_or(path);
+    let rel = if rel.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        rel.to_string_lossy().replace('\\', "/")
+    };
+    eprintln!(
+        "{} {action:<7} {rel}  {}",
+        "trace:".dimmed(),
+        detail.dimmed()
+    );
+}
+
+/// The ignore pattern that matches `git_path`, as `source:line:pattern`
+///
+/// Returns `None` when Git cannot say, e.g. because the `git` CLI is missing.
+fn ignore_source(repo_root: &Path, git_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["check-ignore", "-v", "--no-index", "--"])
+        .arg(git_path)
+        .current_dir(repo_root)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_check_ignore(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `source:line:pattern` from one line of `git check-ignore -v` output
+fn parse_check_ignore(stdout: &str) -> Option<String> {
+    let (source, _path) = stdout.lines().next()?.split_once('\t')?;
+    Some(source.to_string())
+}
+
+/// Why Git ignores `git_path`, for a trace line
+pub fn ignored_detail(repo_root: &Path, git_path: &Path) -> String {
+    ignore_source(repo_root, git_path).map_or_else(
+        || "ignored by Git".to_string(),
+        |source| format!("ignored by {source}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_check_ignore() {
+        assert_eq!(
+            parse_check_ignore(".gitignore:3:build/\tbuild\n").as_deref(),
+            Some(".gitignore:3:build/")
+        );
+        assert_eq!(parse_check_ignore(""), None);
+    }
+
+    #[test]
+    fn test_ignore_source_names_the_rule() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        git(temp_dir.path(), &["init", "-q"]);
+        fs::write(temp_dir.path().join(".gitignore"), "# comment\n.venv/\n")?;
+        fs::create_dir(temp_dir.path().join(".venv"))?;
+
+        assert_eq!(
+            ignore_source(temp_dir.path(), Path::new(".venv")).as_deref(),
+            Some(".gitignore:2:.venv/")
+        );
+        assert_eq!(ignore_source(temp_dir.path(), Path::new("src")), None);
+        Ok(())
+    }
+}
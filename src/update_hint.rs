@@ -0,0 +1,164 @@
+//! "New version available" hint after interactive scans.
+//!
+//! Most users never run `doctor`, so after a scan in a terminal unvenv
+//! checks for a newer release and prints a one-line hint. The result is
+//! cached in the user cache directory so the releases API is asked at most
+//! once a day; set `UNVENV_NO_UPDATE_CHECK` to turn the check off entirely.
+
+use crate::cache::user_cache_dir;
+use colored::Colorize;
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Environment variable that disables the check
+const DISABLE_VAR: &str = "UNVENV_NO_UPDATE_CHECK";
+
+/// How long a cached result stays valid
+const CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Releases API endpoint for the latest stable release
+pub const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/tftio/unvenv/releases/latest";
+
+/// Print the hint if a newer release than `current` exists
+///
+/// Never fails: an unreachable network or unwritable cache only means no hint.
+pub fn maybe_print(current: &str, is_tty: bool) {
+    // Hints are for people; keep CI logs and pipelines clean
+    if !is_tty || env::var_os(DISABLE_VAR).is_some() {
+        return;
+    }
+
+    let Some(latest) = latest_version() else {
+        return;
+    };
+    if is_newer(&latest, current) {
+        eprintln!(
+            "{} unvenv {} is available (you have {current}); run `unvenv update` or set {DISABLE_VAR} to silence this",
+            "Hint:".cyan().bold(),
+            latest.trim_start_matches('v').green()
+        );
+    }
+}
+
+/// Latest released version, from the daily cache or the releases API
+fn latest_version() -> Option<String> {
+    let cache_file = user_cache_dir()?.join("latest-version");
+    let now = now_secs();
+
+    if let Some((checked_at, version)) = read_cache(&cache_file) {
+        if now.saturating_sub(checked_at) < CHECK_INTERVAL_SECS {
+            return (!version.is_empty()).then_some(version);
+        }
+    }
+
+    // Failures are cached too, so an offline machine is not slowed down by
+    // a timed-out request on every scan
+    let version = fetch_latest_version().unwrap_or_default();
+    if let Some(parent) = cache_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(&cache_file, format!("{now} {version}\n"));
+
+    (!version.is_empty()).then_some(version)
+}
+
+/// Read `<checked_at_secs> <version>` from the cache file
+fn read_cache(path: &Path) -> Option<(u64, String)> {
+    let content = fs::read_to_string(path).ok()?;
+    let (checked_at, version) = content
+        .trim_end()
+        .split_once(' ')
+        .unwrap_or_else(|| (content.trim_end(), ""));
+    Some((checked_at.parse().ok()?, version.to_string()))
+}
+
+/// Ask the releases API for the latest tag, giving up quickly
+fn fetch_latest_version() -> Option<String> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "--max-time", "2", LATEST_RELEASE_URL])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_tag_name(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extract `"tag_name": "<tag>"` from a releases API response
+fn parse_tag_name(json: &str) -> Option<String> {
+    let rest = &json[json.find("\"tag_name\"")? + "\"tag_name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    let tag = &rest[..rest.find('"')?];
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Whether `candidate` is a newer version than `current` (leading `v` optional)
+///
+/// Pre-releases are never offered as updates.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+    match (parse(candidate), parse(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_tag_name() {
+        let json = r#"{"url": "x", "tag_name" : "v1.7.0", "name": "v1.7.0"}"#;
+        assert_eq!(parse_tag_name(json), Some("v1.7.0".to_string()));
+        assert_eq!(parse_tag_name(r#"{"message": "rate limited"}"#), None);
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v1.7.0", "1.6.0"));
+        assert!(is_newer("1.10.0", "1.9.3"));
+        assert!(!is_newer("v1.6.0", "1.6.0"));
+        assert!(!is_newer("v1.5.9", "1.6.0"));
+        assert!(!is_newer("v1.7.0-beta.1", "1.6.0"));
+    }
+
+    #[test]
+    fn test_read_cache() -> std::io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("latest-version");
+
+        fs::write(&path, "1700000000 v1.7.0\n")?;
+        assert_eq!(
+            read_cache(&path),
+            Some((1_700_000_000, "v1.7.0".to_string()))
+        );
+
+        // A failed check is cached without a version
+        fs::write(&path, "1700000000 \n")?;
+        assert_eq!(read_cache(&path), Some((1_700_000_000, String::new())));
+
+        fs::write(&path, "garbage")?;
+        assert_eq!(read_cache(&path), None);
+
+        Ok(())
+    }
+}
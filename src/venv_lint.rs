@@ -0,0 +1,350 @@
+//! `unvenv lint-venvs`: find venvs that no longer work.
+//!
+//! A venv reaches its base interpreter through `home` in `pyvenv.cfg`, and
+//! its own `bin/python` is usually a symlink into that directory. Upgrading
+//! the OS Python or removing a pyenv version breaks the venv in ways that
+//! only show when a tool fails to start. This check looks at every venv
+//! below the current directory, ignored or not, and reports:
+//!
+//! - `home` missing, or pointing at a directory that no longer exists
+//! - no `python` interpreter left in `home`
+//! - the venv's own interpreter (`bin/python`, `Scripts\python.exe`) dangling
+//! - a base interpreter of a different minor version than the recorded
+//!   `version`, so the venv's `site-packages` belongs to another Python
+//!
+//! `pyvenv.cfg` is part of the checkout, so nothing it names is ever run:
+//! the version is read from the `pythonX.Y` file names in `home`, and a
+//! relative `home` or one inside the scanned tree is reported instead of
+//! looked at.
+
+use crate::{ScanArgs, VenvInfo, config::Outcome, find_unignored_venvs, fix_script::sh_quote};
+use anyhow::Result;
+use colored::Colorize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Why a venv does not work
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// `pyvenv.cfg` has no `home`
+    NoHome,
+    /// `home` is a relative path, which Python does not resolve
+    RelativeHome(String),
+    /// `home` is inside the scanned tree rather than an installed Python
+    HomeInTree(String),
+    /// `home` names a directory that does not exist
+    HomeMissing(String),
+    /// `home` exists but has no Python interpreter in it
+    NoInterpreter(String),
+    /// The venv's interpreter is a link to a file that is gone
+    DanglingInterpreter(PathBuf),
+    /// The base interpreter is now a different Python than the venv was
+    /// built for
+    VersionMismatch {
+        /// `version` from `pyvenv.cfg`
+        recorded: String,
+        /// Newest `pythonX.Y` in `home`
+        actual: String,
+    },
+}
+
+impl Problem {
+    /// One-line explanation for the report
+    fn describe(&self) -> String {
+        match self {
+            Self::NoHome => {
+                "pyvenv.cfg has no `home`, so Python cannot find the base install".into()
+            }
+            Self::RelativeHome(home) => format!("home {home} is not an absolute path"),
+            Self::HomeInTree(home) => {
+                format!("home {home} is inside the scanned tree, not an installed Python")
+            }
+            Self::HomeMissing(home) => format!("home {home} no longer exists"),
+            Self::NoInterpreter(home) => format!("no python interpreter left in home {home}"),
+            Self::DanglingInterpreter(link) => format!(
+                "{} points at a file that no longer exists",
+                link.to_string_lossy().replace('\\', "/")
+            ),
+            Self::VersionMismatch { recorded, actual } => format!(
+                "built for Python {recorded}, but home now has Python {actual} (the OS Python was likely upgraded)"
+            ),
+        }
+    }
+}
+
+/// `X.Y` of a version string like `3.12.1`
+fn minor_version(version: &str) -> &str {
+    let mut dots = version.match_indices('.').map(|(index, _)| index);
+    match (dots.next(), dots.next()) {
+        (Some(_), Some(end)) => &version[..end],
+        _ => version,
+    }
+}
+
+/// The interpreter in `home`, preferring the one matching `version`
+fn base_interpreter(home: &Path, version: Option<&str>) -> Option<PathBuf> {
+    let mut names = Vec::new();
+    if let Some(version) = version {
+        names.push(format!("python{}", minor_version(version)));
+    }
+    names.extend(["python3", "python", "python.exe"].map(String::from));
+    names
+        .into_iter()
+        .map(|name| home.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Newest `X.Y` among the `pythonX.Y` files in `home`
+fn installed_version(home: &Path) -> Option<String> {
+    fs::read_dir(home)
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let (major, minor) = name.strip_prefix("python")?.split_once('.')?;
+            Some((major.parse::<u32>().ok()?, minor.parse::<u32>().ok()?))
+        })
+        .max()
+        .map(|(major, minor)| format!("{major}.{minor}"))
+}
+
+/// Whether `path` is `root` or below it, following symlinks where they exist
+fn is_within(path: &Path, root: &Path) -> bool {
+    let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    resolve(path).starts_with(resolve(root))
+}
+
+/// Whether the base interpreter `home` names is gone, checked without
+/// running anything
+pub fn home_is_gone(venv: &VenvInfo) -> bool {
+    venv.home
+        .as_deref()
+        .is_some_and(|home| base_interpreter(Path::new(home), venv.version.as_deref()).is_none())
+}
+
+/// Everything wrong with the venv in `dir` described by `venv`, found
+/// while scanning `root`
+pub fn check(root: &Path, dir: &Path, venv: &VenvInfo) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for link in [Path::new("bin/python"), Path::new("Scripts/python.exe")] {
+        // Present as a link, but the target is gone
+        if fs::symlink_metadata(dir.join(link)).is_ok() && fs::metadata(dir.join(link)).is_err() {
+            problems.push(Problem::DanglingInterpreter(link.to_path_buf()));
+        }
+    }
+
+    let Some(home) = &venv.home else {
+        problems.push(Problem::NoHome);
+        return problems;
+    };
+    if !Path::new(home).is_absolute() {
+        problems.push(Problem::RelativeHome(home.clone()));
+        return problems;
+    }
+    if is_within(Path::new(home), root) {
+        problems.push(Problem::HomeInTree(home.clone()));
+        return problems;
+    }
+    if !Path::new(home).is_dir() {
+        problems.push(Problem::HomeMissing(home.clone()));
+        return problems;
+    }
+    if base_interpreter(Path::new(home), venv.version.as_deref()).is_none() {
+        problems.push(Problem::NoInterpreter(home.clone()));
+        return problems;
+    }
+
+    let installed = installed_version(Path::new(home));
+    if let (Some(recorded), Some(actual)) = (&venv.version, installed) {
+        if !Path::new(home)
+            .join(format!("python{}", minor_version(recorded)))
+            .is_file()
+        {
+            problems.push(Problem::VersionMismatch {
+                recorded: recorded.clone(),
+                actual,
+            });
+        }
+    }
+    problems
+}
+
+/// Check every venv below `workdir` and print the broken ones, returning
+/// the exit code
+pub fn run(workdir: &Path, is_tty: bool) -> Result<i32> {
+    // Ignored venvs break just the same
+    let args = ScanArgs {
+        no_git: true,
+        ..ScanArgs::default()
+    };
+    let outcome = find_unignored_venvs(workdir, &args, is_tty)?;
+
+    let broken: Vec<(&VenvInfo, Vec<Problem>)> = outcome
+        .venvs
+        .iter()
+        .filter_map(|venv| {
+            let dir = workdir.join(venv.path.parent()?);
+            let problems = check(workdir, &dir, venv);
+            (!problems.is_empty()).then_some((venv, problems))
+        })
+        .collect();
+
+    if broken.is_empty() {
+        let message = format!(
+            "All {} Python virtual environment(s) have a working interpreter",
+            outcome.venvs.len()
+        );
+        if is_tty {
+            println!("{} {message}", "OK:".green().bold());
+        } else {
+            println!("OK: {message}");
+        }
+        return Ok(0);
+    }
+
+    let headline = format!(
+        "{} of {} Python virtual environment(s) are broken:",
+        broken.len(),
+        outcome.venvs.len()
+    );
+    if is_tty {
+        println!("{} {headline}", "WARNING:".yellow().bold());
+    } else {
+        println!("WARNING: {headline}");
+    }
+    println!();
+    for (venv, problems) in &broken {
+        let dir = venv
+            .path
+            .parent()
+            .unwrap_or(&venv.path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if is_tty {
+            println!("  📁 {}", format!("{dir}/").cyan());
+        } else {
+            println!("  {dir}/");
+        }
+        for problem in problems {
+            println!("    - {}", problem.describe());
+        }
+        // Only a dangling link leaves `home` usable; otherwise the same
+        // Python from `PATH` is the best bet
+        let mut metadata = venv.to_violation().venv;
+        if problems
+            .iter()
+            .any(|problem| !matches!(problem, Problem::DanglingInterpreter(_)))
+        {
+            metadata.home = None;
+        }
+        // Never suggest removing the directory the check ran in
+        let command = metadata.recreate_command(&dir).filter(|_| !dir.is_empty());
+        if let Some(command) = command {
+            println!("    Recreate it: rm -rf {} && {command}", sh_quote(&dir));
+        } else {
+            println!("    Delete it and create it again with the Python you use now");
+        }
+        println!();
+    }
+    Ok(Outcome::Violations.default_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn venv(cfg: &str) -> VenvInfo {
+        let temp_dir = TempDir::new().expect("temp dir");
+        let path = temp_dir.path().join("pyvenv.cfg");
+        fs::write(&path, cfg).expect("write pyvenv.cfg");
+        crate::parse_pyvenv_cfg(&path, Path::new("venv/pyvenv.cfg")).expect("pyvenv.cfg parses")
+    }
+
+    #[test]
+    fn test_minor_version() {
+        assert_eq!(minor_version("3.12.1"), "3.12");
+        assert_eq!(minor_version("3.12"), "3.12");
+        assert_eq!(minor_version("3"), "3");
+    }
+
+    #[test]
+    fn test_missing_home() -> Result<()> {
+        let (tree, install) = (TempDir::new()?, TempDir::new()?);
+        let home = install.path().join("gone");
+        let info = venv(&format!("home = {}\nversion = 3.11.4\n", home.display()));
+        assert_eq!(
+            check(tree.path(), tree.path(), &info),
+            [Problem::HomeMissing(home.display().to_string())]
+        );
+
+        assert_eq!(
+            check(tree.path(), tree.path(), &venv("version = 3.11.4\n")),
+            [Problem::NoHome]
+        );
+
+        fs::create_dir(&home)?;
+        assert_eq!(
+            check(tree.path(), tree.path(), &info),
+            [Problem::NoInterpreter(home.display().to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_untrusted_home() -> Result<()> {
+        let tree = TempDir::new()?;
+        assert_eq!(
+            check(tree.path(), tree.path(), &venv("home = bin\n")),
+            [Problem::RelativeHome("bin".to_string())]
+        );
+
+        // A committed pyvenv.cfg must not send the check into the checkout
+        let home = tree.path().join("tools/bin");
+        fs::create_dir_all(&home)?;
+        fs::write(home.join("python3"), "")?;
+        let info = venv(&format!("home = {}\n", home.display()));
+        assert_eq!(
+            check(tree.path(), tree.path(), &info),
+            [Problem::HomeInTree(home.display().to_string())]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_upgraded_base_interpreter() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let (tree, install) = (TempDir::new()?, TempDir::new()?);
+        let home = install.path().join("bin");
+        fs::create_dir(&home)?;
+        // Never run, so it does not need to be executable
+        for name in ["python3", "python3.9", "python3.12"] {
+            fs::write(home.join(name), "")?;
+        }
+
+        let dir = tree.path().join("venv");
+        fs::create_dir_all(dir.join("bin"))?;
+        symlink(home.join("python3.11"), dir.join("bin/python"))?;
+
+        let info = venv(&format!("home = {}\nversion = 3.11.4\n", home.display()));
+        assert_eq!(
+            check(tree.path(), &dir, &info),
+            [
+                Problem::DanglingInterpreter(PathBuf::from("bin/python")),
+                Problem::VersionMismatch {
+                    recorded: "3.11.4".to_string(),
+                    actual: "3.12".to_string(),
+                },
+            ]
+        );
+
+        // A patch release of the same minor version is fine
+        let info = venv(&format!("home = {}\nversion = 3.12.0\n", home.display()));
+        assert!(check(tree.path(), tree.path(), &info).is_empty());
+        Ok(())
+    }
+}
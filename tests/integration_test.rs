@@ -35,6 +35,31 @@ fn test_version_command() {
     assert!(stdout.contains(env!("CARGO_PKG_VERSION")));
 }
 
+/// Test version --json build metadata
+#[test]
+fn test_version_json() {
+    let output = Command::new(get_binary_path())
+        .args(["version", "--json"])
+        .output()
+        .expect("Failed to execute binary");
+
+    assert!(output.status.success());
+    let info: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("version output is JSON");
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert!(
+        info["target"]
+            .as_str()
+            .is_some_and(|target| !target.is_empty())
+    );
+    assert!(
+        info["build_date"]
+            .as_str()
+            .is_some_and(|date| date.ends_with('Z'))
+    );
+    assert!(info["features"].is_array());
+}
+
 /// Test built-in help flag
 #[test]
 fn test_help_flag() {
@@ -232,6 +257,48 @@ fn test_multiple_venvs() {
     assert!(stdout.contains("myenv/pyvenv.cfg"));
 }
 
+/// Test that a venv inside a submodule does not abort the scan
+#[test]
+fn test_venv_in_submodule_does_not_abort_scan() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let git = |dir: &str, args: &[&str]| {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(["-c", "protocol.file.allow=always"])
+            .args(args)
+            .current_dir(temp_dir.path().join(dir))
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success(), "git {args:?} failed");
+    };
+
+    // The submodule's own repository commits a venv
+    let lib_venv = temp_dir.path().join("lib/venv");
+    fs::create_dir_all(&lib_venv).expect("Failed to create venv directory");
+    fs::write(lib_venv.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+    git("lib", &["init"]);
+    git("lib", &["add", "."]);
+    git("lib", &["commit", "-m", "Commit a venv"]);
+
+    fs::create_dir(temp_dir.path().join("app")).expect("Failed to create app directory");
+    git("app", &["init"]);
+    git("app", &["submodule", "add", "../lib", "vendor/lib"]);
+    git("app", &["commit", "-m", "Add submodule"]);
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--explain-scan"])
+        .current_dir(temp_dir.path().join("app"))
+        .output()
+        .expect("Failed to execute binary");
+
+    // The submodule has its own index; the outer scan prunes it
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Failed to check Git status"));
+    assert!(stderr.contains("vendor/lib  submodule or nested repository"));
+}
+
 /// Test no issues when no venv files exist
 #[test]
 fn test_no_venv_files() {
@@ -261,3 +328,691 @@ fn test_no_venv_files() {
     // Should exit with code 0 (no issues)
     assert!(output.status.success());
 }
+
+/// Test audit reports commits that added venvs since a revision
+#[test]
+fn test_audit_since_reports_bypassed_commits() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success());
+    };
+
+    git(&["init"]);
+    fs::write(temp_dir.path().join("README.md"), "# Test Project\n")
+        .expect("Failed to write README.md");
+    git(&["add", "."]);
+    git(&["commit", "-m", "Initial commit"]);
+    git(&["tag", "base"]);
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+    git(&["add", "."]);
+    git(&["commit", "--no-verify", "-m", "Add local venv"]);
+
+    let binary_path = get_binary_path();
+    let output = Command::new(binary_path)
+        .args(["audit", "--since", "base"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    // Should exit with code 2 (policy violation in history)
+    assert_eq!(output.status.code(), Some(2));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Add local venv"));
+    assert!(stdout.contains("venv/pyvenv.cfg"));
+    assert!(stdout.contains("Dev <dev@example.com>"));
+}
+
+/// Test remediation steps depend on whether the venv is tracked by Git
+#[test]
+fn test_remediation_tailored_to_tracking_status() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success());
+    };
+
+    git(&["init"]);
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let binary_path = get_binary_path();
+
+    // Untracked: a .gitignore entry is all that is needed
+    let output = Command::new(&binary_path)
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Git status: untracked"));
+    assert_eq!(stdout.matches("/venv/").count(), 1);
+    assert!(!stdout.contains("git rm -r --cached"));
+
+    // Committed: the venv must also be removed from the index
+    git(&["add", "venv"]);
+    git(&["commit", "--no-verify", "-m", "Add venv"]);
+    let output = Command::new(&binary_path)
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Git status: committed"));
+    assert!(stdout.contains("git rm -r --cached -- 'venv'"));
+}
+
+/// Test repeated cached scans keep reporting violations
+#[test]
+fn test_scan_with_cache() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let init_output = Command::new("git")
+        .args(["init"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to initialize git repo");
+    assert!(init_output.status.success());
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let binary_path = get_binary_path();
+    for _ in 0..2 {
+        let output = Command::new(&binary_path)
+            .args(["scan", "--cache"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("venv/pyvenv.cfg"));
+    }
+
+    assert!(temp_dir.path().join(".git/unvenv/scan-cache").is_file());
+}
+
+#[test]
+fn test_scan_incremental() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let init_output = Command::new("git")
+        .args(["init"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to initialize git repo");
+    assert!(init_output.status.success());
+
+    let binary_path = get_binary_path();
+    let run_scan = || {
+        Command::new(&binary_path)
+            .args(["scan", "--incremental"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary")
+    };
+
+    // First run has no record and walks the whole tree
+    assert_eq!(run_scan().status.code(), Some(0));
+    assert!(temp_dir.path().join(".git/unvenv/last-scan").is_file());
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    // The new venv is found through git status, and stays reported
+    for _ in 0..2 {
+        let output = run_scan();
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("venv/pyvenv.cfg"));
+    }
+}
+
+#[test]
+fn test_bench_prints_phase_breakdown() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let output = Command::new(get_binary_path())
+        .arg("bench")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for phase in ["walk", "ignore", "parse", "scan"] {
+        assert!(stdout.contains(phase), "missing {phase} in {stdout}");
+    }
+}
+
+#[test]
+fn test_scan_skip_network_fs_still_scans_local_dirs() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--skip-network-fs"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("venv/pyvenv.cfg"));
+}
+
+#[test]
+fn test_scan_max_dirs_exits_partial() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    for name in ["a", "b", "c"] {
+        fs::create_dir(temp_dir.path().join(name)).expect("Failed to create directory");
+    }
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--max-dirs", "2"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--max-dirs"));
+}
+
+#[test]
+fn test_doctor_format_json() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let init_output = Command::new("git")
+        .args(["init"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to initialize git repo");
+    assert!(init_output.status.success());
+
+    let output = Command::new(get_binary_path())
+        .args(["doctor", "--format", "json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("doctor output is JSON");
+    assert_eq!(report["tool"], "unvenv");
+    assert_eq!(report["checks"][0]["status"], "pass");
+    // Other checks depend on the machine (e.g. another unvenv on PATH)
+    let healthy = report["healthy"].as_bool().expect("healthy is a boolean");
+    assert_eq!(output.status.code(), Some(i32::from(!healthy)));
+}
+
+#[test]
+fn test_hook_install_then_scan_staged() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let init_output = Command::new("git")
+        .args(["init"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to initialize git repo");
+    assert!(init_output.status.success());
+
+    let output = Command::new(get_binary_path())
+        .args(["hook", "install"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(0));
+    let hook = fs::read_to_string(temp_dir.path().join(".git/hooks/pre-commit"))
+        .expect("Failed to read hook");
+    assert!(hook.contains("unvenv hook run"));
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let run_scan = || {
+        Command::new(get_binary_path())
+            .args(["scan", "--staged"])
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary")
+    };
+
+    // Only staged files count
+    assert_eq!(run_scan().status.code(), Some(0));
+    let add_output = Command::new("git")
+        .args(["add", "venv"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to stage venv");
+    assert!(add_output.status.success());
+    assert_eq!(run_scan().status.code(), Some(2));
+}
+
+#[test]
+fn test_scan_ci_github_annotations_and_summary() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+    let summary_path = temp_dir.path().join("summary.md");
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--ci", "github"])
+        .env("GITHUB_STEP_SUMMARY", &summary_path)
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("::error file=venv/pyvenv.cfg,"));
+    let summary = fs::read_to_string(&summary_path).expect("Failed to read summary");
+    assert!(summary.contains("`venv/pyvenv.cfg`"));
+}
+
+#[test]
+fn test_report_json_deserializes_into_library_model() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(
+        venv_dir.join("pyvenv.cfg"),
+        "home = /usr/bin\nversion = 3.12.1\ninclude-system-site-packages = False\n",
+    )
+    .expect("Failed to write pyvenv.cfg");
+
+    let output = Command::new(get_binary_path())
+        .args(["report", "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+    let report: unvenv::ScanReport =
+        serde_json::from_slice(&output.stdout).expect("Failed to parse the report");
+    assert_eq!(report.schema_version, unvenv::SCHEMA_VERSION);
+    assert_eq!(report.violations.len(), 1);
+    let violation = &report.violations[0];
+    assert_eq!(violation.path, std::path::Path::new("venv/pyvenv.cfg"));
+    assert_eq!(violation.venv.version.as_deref(), Some("3.12.1"));
+    assert_eq!(violation.venv.include_system_site_packages, Some(false));
+}
+
+/// Test that report fails on tooling directories and references, and lists
+/// them in the JSON and Markdown reports
+#[test]
+fn test_report_covers_tooling_directories_and_references() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success(), "git {args:?} failed");
+    };
+    git(&["init"]);
+    fs::create_dir(temp_dir.path().join(".pip-cache")).expect("Failed to create pip cache");
+    fs::create_dir(temp_dir.path().join("scripts")).expect("Failed to create scripts");
+    fs::write(
+        temp_dir.path().join("scripts/activate"),
+        "VIRTUAL_ENV=\"/home/dev/app/venv\"\nexport VIRTUAL_ENV\n",
+    )
+    .expect("Failed to write activation script");
+    git(&["add", "scripts"]);
+
+    let output = Command::new(get_binary_path())
+        .args(["report", "--json"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    let report: unvenv::ScanReport =
+        serde_json::from_slice(&output.stdout).expect("Failed to parse the report");
+    assert!(report.violations.is_empty());
+    assert_eq!(report.artifacts.len(), 1);
+    assert_eq!(report.artifacts[0].path, std::path::Path::new(".pip-cache"));
+    assert_eq!(report.artifacts[0].rule, "UV011");
+    assert_eq!(report.references.len(), 1);
+    assert_eq!(
+        report.references[0].file,
+        std::path::Path::new("scripts/activate")
+    );
+    assert_eq!(report.references[0].rule, "UV008");
+
+    let output = Command::new(get_binary_path())
+        .arg("report")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("| `.pip-cache/` |"));
+    assert!(stdout.contains("| `scripts/activate:1` |"));
+}
+
+/// Test that report diff fails on new violations but not on resolved ones
+#[test]
+fn test_report_diff_exit_codes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let report = |paths: &[&str]| {
+        let violations: Vec<_> = paths
+            .iter()
+            .map(|path| serde_json::json!({ "path": path }))
+            .collect();
+        serde_json::json!({
+            "schema_version": 1,
+            "tool_version": "1.6.0",
+            "root": ".",
+            "violations": violations
+        })
+        .to_string()
+    };
+    let old = temp_dir.path().join("old.json");
+    let new = temp_dir.path().join("new.json");
+    fs::write(&old, report(&["venv/pyvenv.cfg"])).expect("Failed to write old report");
+    fs::write(&new, report(&["api/.venv/pyvenv.cfg"])).expect("Failed to write new report");
+
+    let output = Command::new(get_binary_path())
+        .args(["report", "diff"])
+        .args([&old, &new])
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("+ api/.venv/pyvenv.cfg"));
+    assert!(stdout.contains("- venv/pyvenv.cfg"));
+
+    let output = Command::new(get_binary_path())
+        .args(["report", "diff"])
+        .args([&new, &old])
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+
+    fs::write(&new, report(&[])).expect("Failed to write new report");
+    let output = Command::new(get_binary_path())
+        .args(["report", "diff"])
+        .args([&old, &new])
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// Test that report --output writes the report to a file
+#[test]
+fn test_report_output_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let output_path = temp_dir.path().join("unvenv.json");
+
+    let output = Command::new(get_binary_path())
+        .args(["report", "--json", "--output"])
+        .arg(&output_path)
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    let content = fs::read_to_string(&output_path).expect("Failed to read the report");
+    let report: unvenv::ScanReport =
+        serde_json::from_str(&content).expect("Failed to parse the report");
+    assert!(report.violations.is_empty());
+}
+
+/// Test that .unvenv.toml remaps exit codes
+#[test]
+fn test_config_remaps_exit_codes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+    fs::write(
+        temp_dir.path().join(".unvenv.toml"),
+        "[exit-codes]\nviolations = 10\nconfig-error = 64\n",
+    )
+    .expect("Failed to write config");
+
+    let output = Command::new(get_binary_path())
+        .arg("scan")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(10));
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--no-such-flag"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(64));
+
+    fs::write(
+        temp_dir.path().join(".unvenv.toml"),
+        "[exit-codes]\nviolation = 10\n",
+    )
+    .expect("Failed to write config");
+    let output = Command::new(get_binary_path())
+        .arg("scan")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(".unvenv.toml:2"));
+
+    // Commands that have nothing to configure still run
+    for args in [&["--help"][..], &["version"]] {
+        let output = Command::new(get_binary_path())
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary");
+        assert_eq!(output.status.code(), Some(0), "{args:?}");
+    }
+}
+
+/// Test that --require-git fails outside a repository
+#[test]
+fn test_require_git_outside_repository() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--require-git"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No Git repository"));
+
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(temp_dir.path())
+        .status()
+        .expect("Failed to run git init");
+    assert!(status.success());
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--require-git"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// Test that `UNVENV_SKIP` suppresses a rule and says so
+#[test]
+fn test_unvenv_skip_suppresses_rule() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let output = Command::new(get_binary_path())
+        .arg("scan")
+        .env("UNVENV_SKIP", "UV001")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipping UV001 (unignored-venv) via UNVENV_SKIP; 1 finding(s) suppressed")
+    );
+
+    let output = Command::new(get_binary_path())
+        .arg("scan")
+        .env("UNVENV_SKIP", "UV003")
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Rule: UV001 (unignored-venv)"));
+}
+
+/// Test that --explain-scan names the ignore rule that pruned a directory
+#[test]
+fn test_explain_scan_trace() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(temp_dir.path())
+        .status()
+        .expect("Failed to run git init");
+    assert!(status.success());
+    fs::write(temp_dir.path().join(".gitignore"), "build/\n").expect("Failed to write .gitignore");
+    fs::create_dir_all(temp_dir.path().join("build/venv")).expect("Failed to create build dir");
+    fs::create_dir(temp_dir.path().join("venv")).expect("Failed to create venv directory");
+    fs::write(temp_dir.path().join("venv/pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--explain-scan"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("prune   build  ignored by .gitignore:1:build/"));
+    assert!(stderr.contains("check   venv/pyvenv.cfg  reported: not ignored (untracked)"));
+}
+
+/// Test that --porcelain prints the frozen v1 records and nothing else
+#[test]
+fn test_scan_porcelain_v1() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(venv_dir.join("pyvenv.cfg"), "home = /usr/bin\n")
+        .expect("Failed to write pyvenv.cfg");
+
+    for args in [&["scan", "--porcelain"][..], &["scan", "--porcelain=v1"]] {
+        let output = Command::new(get_binary_path())
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary");
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "violation UV001 none venv/pyvenv.cfg\n"
+        );
+    }
+
+    let output = Command::new(get_binary_path())
+        .args(["scan", "--porcelain=v2"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to execute binary");
+    assert!(!output.status.success());
+}
+
+/// Test that --format json prints every finding that fails the scan
+#[test]
+fn test_scan_json_format_keeps_exit_codes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let init_output = Command::new("git")
+        .args(["init"])
+        .current_dir(temp_dir.path())
+        .output()
+        .expect("Failed to initialize git repo");
+    assert!(init_output.status.success());
+
+    let scan = |args: &[&str]| {
+        let output = Command::new(get_binary_path())
+            .args(["scan", "--format", "json"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .output()
+            .expect("Failed to execute binary");
+        let findings: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("stdout is JSON");
+        (output.status.code(), findings)
+    };
+
+    assert_eq!(scan(&[]), (Some(0), serde_json::json!([])));
+
+    // A tooling directory alone fails the scan, so it is listed too
+    fs::create_dir(temp_dir.path().join(".direnv")).expect("Failed to create .direnv");
+    let (code, findings) = scan(&[]);
+    assert_eq!(code, Some(2));
+    assert_eq!(
+        findings,
+        serde_json::json!([
+            { "kind": "artifact", "rule": "UV009", "path": ".direnv", "ignored": false },
+        ])
+    );
+    fs::write(temp_dir.path().join(".gitignore"), "/.direnv/\n").expect("Failed to write");
+
+    let venv_dir = temp_dir.path().join("venv");
+    fs::create_dir(&venv_dir).expect("Failed to create venv directory");
+    fs::write(
+        venv_dir.join("pyvenv.cfg"),
+        "home = /usr/bin\nversion = 3.12.1\ninclude-system-site-packages = false\n",
+    )
+    .expect("Failed to write pyvenv.cfg");
+    let (code, findings) = scan(&[]);
+    assert_eq!(code, Some(2));
+    assert_eq!(
+        findings,
+        serde_json::json!([{
+            "kind": "venv",
+            "rule": "UV001",
+            "path": "venv/pyvenv.cfg",
+            "home": "/usr/bin",
+            "version": "3.12.1",
+            "include-system-site-packages": false,
+            "ignored": false,
+            "tracking": "untracked",
+        }])
+    );
+
+    let (code, findings) = scan(&["--fix"]);
+    assert_eq!(code, Some(6));
+    assert_eq!(findings[0]["path"], "venv/pyvenv.cfg");
+    assert_eq!(scan(&[]), (Some(0), serde_json::json!([])));
+}